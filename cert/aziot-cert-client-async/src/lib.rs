@@ -4,32 +4,32 @@
 	clippy::unnested_or_patterns, // TODO: Remove when https://github.com/rust-lang/rust-clippy/issues/5704 is fixed
 )]
 
-pub struct Client<C> {
-	inner: hyper::Client<C, hyper::Body>,
+pub struct Client {
+	inner: hyper::Client<http_common::Connector, hyper::Body>,
 }
 
-impl<C> Client<C> where C: hyper::client::connect::Connect + Clone {
-	pub fn new(connect: C) -> Self {
-		let inner = hyper::Client::builder().build(connect);
+impl Client {
+	pub fn new(connector: http_common::Connector) -> Self {
+		let inner = hyper::Client::builder().build(connector);
 		Client {
 			inner,
 		}
 	}
 }
 
-impl<C> std::fmt::Debug for Client<C> {
+impl std::fmt::Debug for Client {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("Client").finish()
 	}
 }
 
-impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync + 'static {
+impl Client {
 	pub async fn create_cert(
 		&self,
 		id: &str,
 		csr: &[u8],
 		issuer: Option<(&str, &aziot_key_common::KeyHandle)>,
-	) -> Result<Vec<u8>, std::io::Error> {
+	) -> Result<Vec<u8>, Error> {
 		let body = aziot_cert_common_http::create_cert::Request {
 			cert_id: id.to_owned(),
 			csr: aziot_cert_common_http::Pem(csr.to_owned()),
@@ -52,7 +52,7 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 		&self,
 		id: &str,
 		pem: &[u8],
-	) -> Result<Vec<u8>, std::io::Error> {
+	) -> Result<Vec<u8>, Error> {
 		let uri = format!("/certificates/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
 
 		let body = aziot_cert_common_http::import_cert::Request {
@@ -71,7 +71,7 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 	pub async fn get_cert(
 		&self,
 		id: &str,
-	) -> Result<Vec<u8>, std::io::Error> {
+	) -> Result<Vec<u8>, Error> {
 		let uri = format!("/certificates/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
 
 		let res: aziot_cert_common_http::get_cert::Response = request::<_, (), _>(
@@ -86,7 +86,7 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 	pub async fn delete_cert(
 		&self,
 		id: &str,
-	) -> Result<(), std::io::Error> {
+	) -> Result<(), Error> {
 		let uri = format!("/certificates/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
 
 		let () = request_no_content::<_, ()>(
@@ -99,12 +99,61 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 	}
 }
 
+pub use aziot_cert_common_http::ErrorCode;
+
+/// An error returned by the [`Client`].
+#[derive(Debug)]
+pub enum Error {
+	/// The request could not be sent to aziot-certd, or its response could not be read.
+	Io(std::io::Error),
+
+	/// aziot-certd failed the request.
+	Api {
+		code: ErrorCode,
+		message: String,
+	},
+}
+
+impl Error {
+	/// The code of the error returned by aziot-certd, or `None` if the request did not get a response.
+	pub fn code(&self) -> Option<ErrorCode> {
+		match self {
+			Error::Io(_) => None,
+			Error::Api { code, .. } => Some(*code),
+		}
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Error::Io(_) => f.write_str("could not communicate with aziot-certd"),
+			Error::Api { code, message } => write!(f, "aziot-certd returned an error ({:?}): {}", code, message),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io(err) => Some(err),
+			Error::Api { .. } => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Self {
+		Error::Io(err)
+	}
+}
+
 async fn request<TConnect, TRequest, TResponse>(
 	client: &hyper::Client<TConnect, hyper::Body>,
 	method: http::Method,
 	uri: &str,
 	body: Option<&TRequest>,
-) -> std::io::Result<TResponse>
+) -> Result<TResponse, Error>
 where
 	TConnect: hyper::client::connect::Connect + Clone + Send + Sync + 'static,
 	TRequest: serde::Serialize,
@@ -143,7 +192,7 @@ where
 	}
 
 	if !is_json {
-		return Err(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"));
+		return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response")));
 	}
 
 	let body = hyper::body::to_bytes(body).await.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
@@ -156,10 +205,10 @@ where
 
 		res_status_code if res_status_code.is_client_error() || res_status_code.is_server_error() => {
 			let res: aziot_cert_common_http::Error = serde_json::from_slice(&body).map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
-			return Err(std::io::Error::new(std::io::ErrorKind::Other, res.message));
+			return Err(Error::Api { code: res.code, message: res.message.into_owned() });
 		},
 
-		_ => return Err(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response")),
+		_ => return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"))),
 	};
 	Ok(res)
 }
@@ -169,7 +218,7 @@ async fn request_no_content<TConnect, TRequest>(
 	method: http::Method,
 	uri: &str,
 	body: Option<&TRequest>,
-) -> std::io::Result<()>
+) -> Result<(), Error>
 where
 	TConnect: hyper::client::connect::Connect + Clone + Send + Sync + 'static,
 	TRequest: serde::Serialize,
@@ -213,13 +262,13 @@ where
 			}
 
 			if !is_json {
-				return Err(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"));
+				return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response")));
 			}
 
 			let res: aziot_cert_common_http::Error = serde_json::from_slice(&body).map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
-			Err(std::io::Error::new(std::io::ErrorKind::Other, res.message))
+			Err(Error::Api { code: res.code, message: res.message.into_owned() })
 		},
 
-		_ => Err(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response")),
+		_ => Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"))),
 	}
 }
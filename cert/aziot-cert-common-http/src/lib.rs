@@ -38,9 +38,39 @@ impl serde::Serialize for Pem {
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct Error {
+	/// Servers that predate error codes do not send this field, in which case it deserializes as `ErrorCode::Unknown`.
+	#[serde(default)]
+	pub code: ErrorCode,
+
 	pub message: std::borrow::Cow<'static, str>,
 }
 
+/// A stable identifier of the kind of error in an [`Error`] response. Unlike the message, clients can match on it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum ErrorCode {
+	/// The request could not be parsed, or used the wrong path or method.
+	InvalidRequest,
+
+	/// A parameter of the request has an invalid value.
+	InvalidParameter,
+
+	/// The cert with the requested ID does not exist.
+	CertNotFound,
+
+	/// The server encountered an internal error.
+	Internal,
+
+	/// An error code that this version of the crate does not recognize.
+	#[serde(other)]
+	Unknown,
+}
+
+impl Default for ErrorCode {
+	fn default() -> Self {
+		ErrorCode::Unknown
+	}
+}
+
 pub mod create_cert {
 	#[derive(Debug, serde::Deserialize, serde::Serialize)]
 	pub struct Request {
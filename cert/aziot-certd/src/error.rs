@@ -1,5 +1,6 @@
 #[derive(Debug)]
 pub enum Error {
+	CertNotFound,
 	Internal(InternalError),
 	InvalidParameter(&'static str, Box<dyn std::error::Error + Send + Sync>),
 }
@@ -13,6 +14,7 @@ impl Error {
 impl std::fmt::Display for Error {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
+			Error::CertNotFound => f.write_str("cert not found"),
 			Error::Internal(_) => f.write_str("internal error"),
 			Error::InvalidParameter(name, _) => write!(f, "parameter {:?} has an invalid value", name),
 		}
@@ -22,6 +24,7 @@ impl std::fmt::Display for Error {
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
+			Error::CertNotFound => None,
 			Error::Internal(err) => Some(err),
 			Error::InvalidParameter(_, err) => Some(&**err),
 		}
@@ -35,6 +38,7 @@ pub enum InternalError {
 	DeleteFile(std::io::Error),
 	GetPath(openssl::error::ErrorStack),
 	InvalidConfig(String),
+	InvalidHomedir(std::io::Error),
 	LoadKeyOpenslEngine(openssl2::Error),
 	ReadFile(std::io::Error),
 }
@@ -47,6 +51,7 @@ impl std::fmt::Display for InternalError {
 			InternalError::DeleteFile(_) => f.write_str("could not delete cert file"),
 			InternalError::GetPath(_) => f.write_str("could not get file path corresponding to cert ID"),
 			InternalError::InvalidConfig(err) => write!(f, "invalid config: {}", err),
+			InternalError::InvalidHomedir(_) => f.write_str("homedir cannot be used to store certs"),
 			InternalError::LoadKeyOpenslEngine(_) => f.write_str("could not load aziot-key-openssl-engine"),
 			InternalError::ReadFile(_) => f.write_str("could not read cert file"),
 		}
@@ -61,6 +66,7 @@ impl std::error::Error for InternalError {
 			InternalError::DeleteFile(err) => Some(err),
 			InternalError::GetPath(err) => Some(err),
 			InternalError::InvalidConfig(_) => None,
+			InternalError::InvalidHomedir(err) => Some(err),
 			InternalError::LoadKeyOpenslEngine(err) => Some(err),
 			InternalError::ReadFile(err) => Some(err),
 		}
@@ -14,6 +14,7 @@ pub(super) fn handle(
 			return Ok(super::err_response(
 				hyper::StatusCode::METHOD_NOT_ALLOWED,
 				Some((hyper::header::ALLOW, "POST")),
+				aziot_cert_common_http::ErrorCode::InvalidRequest,
 				"method not allowed".into(),
 			));
 		}
@@ -22,6 +23,7 @@ pub(super) fn handle(
 			return Ok(super::err_response(
 				hyper::StatusCode::UNSUPPORTED_MEDIA_TYPE,
 				None,
+				aziot_cert_common_http::ErrorCode::InvalidRequest,
 				"request body must be application/json".into(),
 			));
 		}
@@ -31,6 +33,7 @@ pub(super) fn handle(
 			Err(err) => return Ok(super::err_response(
 				hyper::StatusCode::BAD_REQUEST,
 				None,
+				aziot_cert_common_http::ErrorCode::InvalidRequest,
 				super::error_to_message(&err).into(),
 			)),
 		};
@@ -39,6 +42,7 @@ pub(super) fn handle(
 			Err(err) => return Ok(super::err_response(
 				hyper::StatusCode::UNPROCESSABLE_ENTITY,
 				None,
+				aziot_cert_common_http::ErrorCode::InvalidRequest,
 				super::error_to_message(&err).into(),
 			)),
 		};
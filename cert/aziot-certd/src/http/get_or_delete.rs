@@ -21,6 +21,7 @@ pub(super) fn handle(
 			Err(err) => return Ok(super::err_response(
 				hyper::StatusCode::BAD_REQUEST,
 				None,
+				aziot_cert_common_http::ErrorCode::InvalidRequest,
 				super::error_to_message(&err).into(),
 			)),
 		};
@@ -59,6 +60,7 @@ pub(super) fn handle(
 			_ => Ok(super::err_response(
 				hyper::StatusCode::METHOD_NOT_ALLOWED,
 				Some((hyper::header::ALLOW, "GET")),
+				aziot_cert_common_http::ErrorCode::InvalidRequest,
 				"method not allowed".into(),
 			)),
 		}
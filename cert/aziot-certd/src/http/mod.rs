@@ -46,6 +46,7 @@ impl hyper::service::Service<hyper::Request<hyper::Body>> for Server {
 			let res = res.unwrap_or_else(|| err_response(
 				hyper::StatusCode::NOT_FOUND,
 				None,
+				aziot_cert_common_http::ErrorCode::InvalidRequest,
 				"not found".into(),
 			));
 
@@ -87,9 +88,11 @@ fn json_response(status_code: hyper::StatusCode, body: &impl serde::Serialize) -
 fn err_response(
 	status_code: hyper::StatusCode,
 	extra_header: Option<(hyper::header::HeaderName, &'static str)>,
+	code: aziot_cert_common_http::ErrorCode,
 	message: std::borrow::Cow<'static, str>,
 ) -> hyper::Response<hyper::Body> {
 	let body = aziot_cert_common_http::Error {
+		code,
 		message,
 	};
 
@@ -112,12 +115,21 @@ impl ToHttpResponse for aziot_certd::Error {
 			aziot_certd::Error::Internal(_) => err_response(
 				hyper::StatusCode::INTERNAL_SERVER_ERROR,
 				None,
+				aziot_cert_common_http::ErrorCode::Internal,
 				self.to_string().into(), // Do not use error_to_message for Error::Internal because we don't want to leak internal errors
 			),
 
+			err @ aziot_certd::Error::CertNotFound => err_response(
+				hyper::StatusCode::NOT_FOUND,
+				None,
+				aziot_cert_common_http::ErrorCode::CertNotFound,
+				error_to_message(err).into(),
+			),
+
 			err @ aziot_certd::Error::InvalidParameter(_, _) => err_response(
 				hyper::StatusCode::BAD_REQUEST,
 				None,
+				aziot_cert_common_http::ErrorCode::InvalidParameter,
 				error_to_message(err).into(),
 			),
 		}
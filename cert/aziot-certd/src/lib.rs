@@ -16,6 +16,8 @@ impl Server {
 		homedir_path: std::path::PathBuf,
 		key_client: std::sync::Arc<aziot_key_client::Client>,
 	) -> Result<Self, Error> {
+		fs_common::check_dir(&homedir_path).map_err(|err| Error::Internal(InternalError::InvalidHomedir(err)))?;
+
 		let key_engine = aziot_key_openssl_engine::load(key_client).map_err(|err| Error::Internal(InternalError::LoadKeyOpenslEngine(err)))?;
 		let key_engine = std::sync::Arc::new(std::sync::Mutex::new(key_engine));
 
@@ -72,7 +74,7 @@ impl Server {
 
 			let x509 =
 				if issuer_id == id {
-					x509.sign(&issuer_private_key, openssl::hash::MessageDigest::sha256()).map_err(|err| Error::Internal(InternalError::CreateCert(Box::new(err))))?;
+					x509.sign(&issuer_private_key, openssl2::signature_message_digest(&issuer_private_key)).map_err(|err| Error::Internal(InternalError::CreateCert(Box::new(err))))?;
 
 					let x509 = x509.build();
 
@@ -90,7 +92,7 @@ impl Server {
 
 					x509.set_issuer_name(issuer_x509.subject_name()).map_err(|err| Error::Internal(InternalError::CreateCert(Box::new(err))))?;
 
-					x509.sign(&issuer_private_key, openssl::hash::MessageDigest::sha256()).map_err(|err| Error::Internal(InternalError::CreateCert(Box::new(err))))?;
+					x509.sign(&issuer_private_key, openssl2::signature_message_digest(&issuer_private_key)).map_err(|err| Error::Internal(InternalError::CreateCert(Box::new(err))))?;
 
 					let x509 = x509.build();
 
@@ -102,7 +104,7 @@ impl Server {
 				};
 
 			let path = get_path(&self.homedir_path, id)?;
-			std::fs::write(path, &x509).map_err(|err| Error::Internal(InternalError::CreateCert(Box::new(err))))?;
+			fs_common::write_file(&path, &x509).map_err(|err| Error::Internal(InternalError::CreateCert(Box::new(err))))?;
 
 			Ok(x509)
 		}
@@ -127,7 +129,7 @@ impl Server {
 		id: &str,
 	) -> Result<Vec<u8>, Error> {
 		let path = get_path(&self.homedir_path, id)?;
-		let bytes = load_inner(&path)?.ok_or_else(|| Error::invalid_parameter("id", "not found"))?;
+		let bytes = load_inner(&path)?.ok_or(Error::CertNotFound)?;
 		Ok(bytes)
 	}
 
@@ -165,6 +167,6 @@ fn load_inner(path: &std::path::Path) -> Result<Option<Vec<u8>>, Error> {
 }
 
 fn create_inner(path: &std::path::Path, bytes: &[u8]) -> Result<(), Error> {
-	std::fs::write(path, bytes).map_err(|err| Error::Internal(InternalError::CreateFile(err)))?;
+	fs_common::write_file(path, bytes).map_err(|err| Error::Internal(InternalError::CreateFile(err)))?;
 	Ok(())
 }
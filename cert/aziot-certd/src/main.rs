@@ -15,16 +15,10 @@ async fn main() -> Result<(), Error> {
 		.ok_or_else(|| aziot_certd::Error::Internal(aziot_certd::InternalError::InvalidConfig("HOMEDIR_PATH not set".to_owned())))?.into();
 
 	let key_client = {
-		struct Connector;
+		let key_service_uri = get_uri_from_env("AZIOT_KEYD_URI", "unix:///var/run/aziot/keyd.sock")?;
+		let key_connector = http_common::Connector::new(&key_service_uri)?;
 
-		impl aziot_key_client::Connector for Connector {
-			fn connect(&self) -> std::io::Result<Box<dyn aziot_key_client::Stream>> {
-				let stream = std::net::TcpStream::connect(("localhost", 8888))?;
-				Ok(Box::new(stream))
-			}
-		}
-
-		let key_client = aziot_key_client::Client::new(Box::new(Connector));
+		let key_client = aziot_key_client::Client::new(key_connector);
 		let key_client = std::sync::Arc::new(key_client);
 		key_client
 	};
@@ -44,9 +38,11 @@ async fn main() -> Result<(), Error> {
 
 	let server = std::sync::Arc::new(server);
 
+	let listen_uri = get_uri_from_env("AZIOT_CERTD_URI", "unix:///var/run/aziot/certd.sock")?;
+
 	eprintln!("Starting server...");
 
-	let incoming = hyper::server::conn::AddrIncoming::bind(&"0.0.0.0:8889".parse()?)?;
+	let incoming = http_common::Connector::new(&listen_uri)?.incoming().await?;
 
 	let server =
 		hyper::Server::builder(incoming)
@@ -61,6 +57,12 @@ async fn main() -> Result<(), Error> {
 	Ok(())
 }
 
+fn get_uri_from_env(name: &str, default: &str) -> Result<url::Url, Error> {
+	let value = std::env::var(name).unwrap_or_else(|_| default.to_owned());
+	let value = value.parse().map_err(|err| format!("{} has an invalid value: {}", name, err))?;
+	Ok(value)
+}
+
 struct Error(Box<dyn std::error::Error>, backtrace::Backtrace);
 
 impl std::fmt::Debug for Error {
@@ -120,3 +120,20 @@ softhsm2-util --init-token --free --label "$TOKEN" --so-pin "$SO_PIN" --pin "$US
     --ca-cert "$PWD/ca.pem" --ca-key "pkcs11:token=$TOKEN;object=$LABEL_1?pin-value=$USER_PIN" \
     --out-file "$PWD/client.pem"
 [ -f "$PWD/client.pem" ]
+
+case "$KEY_TYPE" in
+    rsa-*)
+        "$PWD/target/debug/pkcs11-test" encrypt-decrypt \
+            --key "pkcs11:token=$TOKEN;object=$LABEL_2?pin-value=$USER_PIN" \
+            --plaintext 'Hello, world!'
+        ;;
+esac
+
+"$PWD/target/debug/pkcs11-test" list-objects \
+    --token "pkcs11:token=$TOKEN?pin-value=$USER_PIN"
+
+"$PWD/target/debug/pkcs11-test" delete-object \
+    --object "pkcs11:token=$TOKEN;object=$LABEL_3;type=public?pin-value=$USER_PIN"
+"$PWD/target/debug/pkcs11-test" delete-object \
+    --object "pkcs11:token=$TOKEN;object=$LABEL_3;type=private?pin-value=$USER_PIN"
+[ -z "$("$PWD/target/debug/pkcs11-test" list-objects --token "pkcs11:token=$TOKEN;object=$LABEL_3?pin-value=$USER_PIN" | grep '^class = ')" ]
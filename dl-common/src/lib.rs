@@ -1,15 +1,27 @@
+#![deny(rust_2018_idioms, warnings)]
+#![allow(
+)]
+
+//! Loading of dynamic libraries with `dlopen`, shared by the crates that load PKCS#11 and libaziot-keys implementations.
+
 /// Represents a dynamically loaded library.
-pub(crate) struct Library {
+pub struct Library {
 	handle: *mut std::ffi::c_void,
 }
 
 impl Library {
 	/// Load the library at the specified path.
-	pub(crate) unsafe fn load(path: &std::path::Path) -> Result<Self, String> {
+	///
+	/// `flags` are passed to `dlopen`, such as `libc::RTLD_LAZY | libc::RTLD_LOCAL`.
+	///
+	/// # Safety
+	///
+	/// Loading a library runs its initializers, so the library must be trusted.
+	pub unsafe fn load(path: &std::path::Path, flags: std::os::raw::c_int) -> Result<Self, String> {
 		let path = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_owned();
 		let path = std::ffi::CString::new(path).map_err(|err| err.to_string())?;
 
-		let handle = libc::dlopen(path.as_ptr(), libc::RTLD_LAZY | libc::RTLD_LOCAL);
+		let handle = libc::dlopen(path.as_ptr(), flags);
 		if handle.is_null() {
 			return Err(dlerror());
 		}
@@ -20,7 +32,11 @@ impl Library {
 	}
 
 	/// Obtain a symbol from this library of the specified type.
-	pub(crate) unsafe fn symbol<'library, F>(&'library self, name: &std::ffi::CStr) -> Result<Symbol<'library, F>, String> {
+	///
+	/// # Safety
+	///
+	/// `F` must be the actual type of the symbol, usually an `unsafe extern "C" fn`.
+	pub unsafe fn symbol<'library, F>(&'library self, name: &std::ffi::CStr) -> Result<Symbol<'library, F>, String> {
 		let inner = libc::dlsym(self.handle, name.as_ptr());
 		if inner.is_null() {
 			return Err(dlerror());
@@ -43,7 +59,7 @@ impl Drop for Library {
 }
 
 /// A symbol obtained from a [`Library`].
-pub(crate) struct Symbol<'library, F> {
+pub struct Symbol<'library, F> {
 	inner: *mut std::ffi::c_void,
 	_library: std::marker::PhantomData<&'library Library>,
 	_type: std::marker::PhantomData<F>,
@@ -64,6 +80,11 @@ impl<F> std::ops::Deref for Symbol<'_, F> {
 
 unsafe fn dlerror() -> String {
 	let error = libc::dlerror();
+	if error.is_null() {
+		// dlerror only returns NULL if no error occurred since it was last called, such as if another thread already consumed it.
+		return "unknown error".to_owned();
+	}
+
 	let error = std::ffi::CStr::from_ptr(error);
 	let error = error.to_string_lossy();
 	error.into_owned()
@@ -0,0 +1,131 @@
+#![deny(rust_2018_idioms, warnings)]
+#![allow(
+)]
+
+//! Storage of the files that hold keys, certs and other state, shared by the services and their libraries.
+//!
+//! Files are never written in place. They are written to a temporary file in the same directory that only the owner can access,
+//! which is synced to disk and then renamed over the original. So after a crash or power loss, the file either has
+//! its old contents or its new contents, never a truncated mix of the two.
+
+/// Writes the given contents to the file at the given path, replacing the file if it already exists.
+///
+/// The file is created with mode 0600. If this function fails, the original file is unchanged.
+pub fn write_file(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+	let dir = parent(path)?;
+	let temp_path = temp_path(path)?;
+
+	let result = write_and_rename(&temp_path, path, contents);
+	if result.is_err() {
+		let _ = std::fs::remove_file(&temp_path);
+	}
+	result?;
+
+	// The rename is only durable once the directory entry has been synced too.
+	let dir = std::fs::File::open(dir)?;
+	dir.sync_all()?;
+
+	Ok(())
+}
+
+/// Checks that the given directory can be trusted to hold keys and certs.
+///
+/// The directory must exist, be owned by the user this process is running as, and not be writable by any other user.
+/// Otherwise another user could replace or remove the files in it.
+pub fn check_dir(path: &std::path::Path) -> std::io::Result<()> {
+	let metadata = std::fs::metadata(path)?;
+
+	if !metadata.is_dir() {
+		return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("{} is not a directory", path.display())));
+	}
+
+	let uid = std::os::unix::fs::MetadataExt::uid(&metadata);
+	let euid = unsafe { libc::geteuid() };
+	if uid != euid {
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::PermissionDenied,
+			format!("{} is owned by uid {} but this process is running as uid {}", path.display(), uid, euid),
+		));
+	}
+
+	let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions());
+	if mode & 0o022 != 0 {
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::PermissionDenied,
+			format!("{} has mode {:o} which allows other users to write to it", path.display(), mode & 0o7777),
+		));
+	}
+
+	Ok(())
+}
+
+fn write_and_rename(temp_path: &std::path::Path, path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+	let mut options = std::fs::OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+	let mut file = options.open(temp_path)?;
+	std::io::Write::write_all(&mut file, contents)?;
+	file.sync_all()?;
+	drop(file);
+
+	std::fs::rename(temp_path, path)?;
+
+	Ok(())
+}
+
+fn parent(path: &std::path::Path) -> std::io::Result<&std::path::Path> {
+	match path.parent() {
+		Some(dir) if dir.as_os_str().is_empty() => Ok(std::path::Path::new(".")),
+		Some(dir) => Ok(dir),
+		None => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} has no parent directory", path.display()))),
+	}
+}
+
+/// The temporary file that the file at the given path is written to before it is renamed.
+///
+/// The name includes the process ID and a counter, so that concurrent writes of the same file do not write to the same temporary file.
+/// The leading `.` keeps it out of the way of anything that lists the directory.
+fn temp_path(path: &std::path::Path) -> std::io::Result<std::path::PathBuf> {
+	static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+	let file_name = path.file_name().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} has no file name", path.display())))?;
+
+	let mut temp_file_name = std::ffi::OsString::from(".");
+	temp_file_name.push(file_name);
+	temp_file_name.push(format!(".{}.{}.tmp", std::process::id(), COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)));
+
+	Ok(path.with_file_name(temp_file_name))
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn write_file_replaces_contents_with_private_mode() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("file");
+
+		super::write_file(&path, b"old").unwrap();
+		super::write_file(&path, b"new").unwrap();
+
+		assert_eq!(std::fs::read(&path).unwrap(), b"new");
+
+		let mode = std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(&path).unwrap().permissions());
+		assert_eq!(mode & 0o777, 0o600);
+
+		// Only the file itself is left behind, not any temporary files.
+		assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn check_dir_rejects_writable_by_others() {
+		// tempfile creates the directory with mode 0700, owned by the current user.
+		let dir = tempfile::tempdir().unwrap();
+		super::check_dir(dir.path()).unwrap();
+
+		std::fs::set_permissions(dir.path(), std::os::unix::fs::PermissionsExt::from_mode(0o777)).unwrap();
+		assert_eq!(super::check_dir(dir.path()).unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+
+		assert!(super::check_dir(&dir.path().join("missing")).is_err());
+	}
+}
@@ -0,0 +1,395 @@
+/// A connector to an HTTP service, derived from the `http://` or `unix://` URI of the service.
+///
+/// - Clients use it to connect to the service, either synchronously via [`Connector::connect`]
+///   or asynchronously via its `hyper::service::Service<hyper::Uri>` impl.
+/// - Servers use it to listen for connections via [`Connector::incoming`].
+#[derive(Clone, Debug)]
+pub enum Connector {
+	Http {
+		host: std::sync::Arc<str>,
+		port: u16,
+	},
+
+	Unix {
+		socket_path: std::sync::Arc<std::path::Path>,
+	},
+}
+
+impl Connector {
+	pub fn new(uri: &url::Url) -> Result<Self, ConnectorError> {
+		match uri.scheme() {
+			"http" => {
+				let host = uri.host_str().ok_or_else(|| ConnectorError { uri: uri.clone(), inner: "http URI does not have a host".into() })?;
+				let port = uri.port().unwrap_or(80);
+				Ok(Connector::Http { host: host.into(), port })
+			},
+
+			"unix" => {
+				let socket_path =
+					uri.to_file_path()
+					.map_err(|()| ConnectorError { uri: uri.clone(), inner: "unix URI could not be converted to a file path".into() })?;
+				Ok(Connector::Unix { socket_path: socket_path.into() })
+			},
+
+			scheme => Err(ConnectorError { uri: uri.clone(), inner: format!("unrecognized scheme {:?}", scheme).into() }),
+		}
+	}
+
+	/// Connects to the service synchronously.
+	pub fn connect(&self) -> std::io::Result<Box<dyn Stream>> {
+		match self {
+			Connector::Http { host, port } => {
+				let stream = std::net::TcpStream::connect((&**host, *port))?;
+				Ok(Box::new(stream))
+			},
+
+			Connector::Unix { socket_path } => {
+				let stream = std::os::unix::net::UnixStream::connect(socket_path)?;
+				Ok(Box::new(stream))
+			},
+		}
+	}
+
+	/// Listens for connections to the service.
+	///
+	/// If the process was started via systemd socket activation and one of the sockets passed in by systemd
+	/// matches this connector, that socket is used. Otherwise a new socket is bound.
+	pub async fn incoming(self) -> std::io::Result<Incoming> {
+		match self {
+			Connector::Http { host, port } => {
+				let listener =
+					if let Some(listener) = systemd_listener(|fd| tcp_listener_from_fd(fd, port))? {
+						tokio::net::TcpListener::from_std(listener)?
+					}
+					else {
+						tokio::net::TcpListener::bind((&*host, port)).await?
+					};
+				Ok(Incoming::Tcp(listener))
+			},
+
+			Connector::Unix { socket_path } => {
+				let listener =
+					if let Some(listener) = systemd_listener(|fd| unix_listener_from_fd(fd, &socket_path))? {
+						tokio::net::UnixListener::from_std(listener)?
+					}
+					else {
+						match std::fs::remove_file(&socket_path) {
+							Ok(()) => (),
+							Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => (),
+							Err(err) => return Err(err),
+						}
+
+						tokio::net::UnixListener::bind(&socket_path)?
+					};
+				Ok(Incoming::Unix(listener))
+			},
+		}
+	}
+}
+
+impl hyper::service::Service<hyper::Uri> for Connector {
+	type Response = AsyncStream;
+	type Error = std::io::Error;
+	type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+	fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+		std::task::Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, _req: hyper::Uri) -> Self::Future {
+		match self {
+			Connector::Http { host, port } => {
+				let (host, port) = (host.clone(), *port);
+				let f = async move {
+					let stream = tokio::net::TcpStream::connect((&*host, port)).await?;
+					Ok(AsyncStream::Tcp(stream))
+				};
+				Box::pin(f)
+			},
+
+			Connector::Unix { socket_path } => {
+				let socket_path = socket_path.clone();
+				let f = async move {
+					let stream = tokio::net::UnixStream::connect(&*socket_path).await?;
+					Ok(AsyncStream::Unix(stream))
+				};
+				Box::pin(f)
+			},
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct ConnectorError {
+	uri: url::Url,
+	inner: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl std::fmt::Display for ConnectorError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "malformed connector URI {}", self.uri)
+	}
+}
+
+impl std::error::Error for ConnectorError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&*self.inner)
+	}
+}
+
+pub trait Stream: std::io::Read + std::io::Write {
+}
+
+impl<T> Stream for T where T: std::io::Read + std::io::Write {
+}
+
+/// A connection to or from an HTTP service.
+#[derive(Debug)]
+pub enum AsyncStream {
+	Tcp(tokio::net::TcpStream),
+	Unix(tokio::net::UnixStream),
+}
+
+impl tokio::io::AsyncRead for AsyncStream {
+	fn poll_read(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>, buf: &mut [u8]) -> std::task::Poll<std::io::Result<usize>> {
+		match &mut *self {
+			AsyncStream::Tcp(inner) => std::pin::Pin::new(inner).poll_read(cx, buf),
+			AsyncStream::Unix(inner) => std::pin::Pin::new(inner).poll_read(cx, buf),
+		}
+	}
+}
+
+impl tokio::io::AsyncWrite for AsyncStream {
+	fn poll_write(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>, buf: &[u8]) -> std::task::Poll<std::io::Result<usize>> {
+		match &mut *self {
+			AsyncStream::Tcp(inner) => std::pin::Pin::new(inner).poll_write(cx, buf),
+			AsyncStream::Unix(inner) => std::pin::Pin::new(inner).poll_write(cx, buf),
+		}
+	}
+
+	fn poll_flush(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<std::io::Result<()>> {
+		match &mut *self {
+			AsyncStream::Tcp(inner) => std::pin::Pin::new(inner).poll_flush(cx),
+			AsyncStream::Unix(inner) => std::pin::Pin::new(inner).poll_flush(cx),
+		}
+	}
+
+	fn poll_shutdown(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<std::io::Result<()>> {
+		match &mut *self {
+			AsyncStream::Tcp(inner) => std::pin::Pin::new(inner).poll_shutdown(cx),
+			AsyncStream::Unix(inner) => std::pin::Pin::new(inner).poll_shutdown(cx),
+		}
+	}
+}
+
+impl hyper::client::connect::Connection for AsyncStream {
+	fn connected(&self) -> hyper::client::connect::Connected {
+		match self {
+			AsyncStream::Tcp(inner) => hyper::client::connect::Connection::connected(inner),
+			AsyncStream::Unix(_) => hyper::client::connect::Connected::new(),
+		}
+	}
+}
+
+/// A stream of connections accepted by a listener, for use with `hyper::Server::builder`.
+#[derive(Debug)]
+pub enum Incoming {
+	Tcp(tokio::net::TcpListener),
+	Unix(tokio::net::UnixListener),
+}
+
+impl hyper::server::accept::Accept for Incoming {
+	type Conn = AsyncStream;
+	type Error = std::io::Error;
+
+	fn poll_accept(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Result<Self::Conn, Self::Error>>> {
+		match &mut *self {
+			Incoming::Tcp(listener) => match listener.poll_accept(cx) {
+				std::task::Poll::Ready(Ok((stream, _))) => std::task::Poll::Ready(Some(Ok(AsyncStream::Tcp(stream)))),
+				std::task::Poll::Ready(Err(err)) => std::task::Poll::Ready(Some(Err(err))),
+				std::task::Poll::Pending => std::task::Poll::Pending,
+			},
+
+			Incoming::Unix(listener) => match listener.poll_accept(cx) {
+				std::task::Poll::Ready(Ok((stream, _))) => std::task::Poll::Ready(Some(Ok(AsyncStream::Unix(stream)))),
+				std::task::Poll::Ready(Err(err)) => std::task::Poll::Ready(Some(Err(err))),
+				std::task::Poll::Pending => std::task::Poll::Pending,
+			},
+		}
+	}
+}
+
+/// The first file descriptor passed in by systemd socket activation.
+///
+/// Ref <https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html>
+const SD_LISTEN_FDS_START: std::os::unix::io::RawFd = 3;
+
+/// Returns the first socket passed in by systemd socket activation for which `f` returns `Some`.
+///
+/// Returns `None` if the process was not started via socket activation, or if none of the sockets matched.
+fn systemd_listener<F, T>(mut f: F) -> std::io::Result<Option<T>>
+where
+	F: FnMut(std::os::unix::io::RawFd) -> std::io::Result<Option<T>>,
+{
+	let listen_pid = match std::env::var("LISTEN_PID") {
+		Ok(listen_pid) => listen_pid,
+		Err(_) => return Ok(None),
+	};
+	let listen_pid: u32 = listen_pid.parse().map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("malformed LISTEN_PID: {}", err)))?;
+	if listen_pid != std::process::id() {
+		return Ok(None);
+	}
+
+	let listen_fds = match std::env::var("LISTEN_FDS") {
+		Ok(listen_fds) => listen_fds,
+		Err(_) => return Ok(None),
+	};
+	let listen_fds: std::os::unix::io::RawFd = listen_fds.parse().map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("malformed LISTEN_FDS: {}", err)))?;
+
+	for fd in SD_LISTEN_FDS_START..(SD_LISTEN_FDS_START + listen_fds) {
+		if let Some(listener) = f(fd)? {
+			return Ok(Some(listener));
+		}
+	}
+
+	Ok(None)
+}
+
+fn tcp_listener_from_fd(fd: std::os::unix::io::RawFd, port: u16) -> std::io::Result<Option<std::net::TcpListener>> {
+	let listener = unsafe { <std::net::TcpListener as std::os::unix::io::FromRawFd>::from_raw_fd(fd) };
+
+	match listener.local_addr() {
+		Ok(addr) if addr.port() == port => {
+			listener.set_nonblocking(true)?;
+			Ok(Some(listener))
+		},
+
+		// Not the socket we're looking for. Release the fd without closing it.
+		_ => {
+			let _ = std::os::unix::io::IntoRawFd::into_raw_fd(listener);
+			Ok(None)
+		},
+	}
+}
+
+fn unix_listener_from_fd(fd: std::os::unix::io::RawFd, socket_path: &std::path::Path) -> std::io::Result<Option<std::os::unix::net::UnixListener>> {
+	let listener = unsafe { <std::os::unix::net::UnixListener as std::os::unix::io::FromRawFd>::from_raw_fd(fd) };
+
+	match listener.local_addr() {
+		Ok(addr) if addr.as_pathname() == Some(socket_path) => {
+			listener.set_nonblocking(true)?;
+			Ok(Some(listener))
+		},
+
+		// Not the socket we're looking for. Release the fd without closing it.
+		_ => {
+			let _ = std::os::unix::io::IntoRawFd::into_raw_fd(listener);
+			Ok(None)
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn connector_from_uri() {
+		match super::Connector::new(&"http://localhost:8080".parse().unwrap()).unwrap() {
+			super::Connector::Http { host, port } => {
+				assert_eq!(&*host, "localhost");
+				assert_eq!(port, 8080);
+			},
+			connector => panic!("unexpected connector {:?}", connector),
+		}
+
+		match super::Connector::new(&"http://127.0.0.1".parse().unwrap()).unwrap() {
+			super::Connector::Http { host, port } => {
+				assert_eq!(&*host, "127.0.0.1");
+				assert_eq!(port, 80);
+			},
+			connector => panic!("unexpected connector {:?}", connector),
+		}
+
+		match super::Connector::new(&"unix:///run/aziot/keyd.sock".parse().unwrap()).unwrap() {
+			super::Connector::Unix { socket_path } => assert_eq!(&*socket_path, std::path::Path::new("/run/aziot/keyd.sock")),
+			connector => panic!("unexpected connector {:?}", connector),
+		}
+
+		let _ = super::Connector::new(&"unix://example.com/run/aziot/keyd.sock".parse().unwrap()).expect_err("expect unix URI with a host to fail");
+		let _ = super::Connector::new(&"https://localhost:8080".parse().unwrap()).expect_err("expect URI with unrecognized scheme to fail");
+	}
+
+	#[test]
+	fn listener_from_fd() {
+		let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let fd = std::os::unix::io::AsRawFd::as_raw_fd(&listener);
+
+		assert!(super::tcp_listener_from_fd(fd, port.wrapping_add(1)).unwrap().is_none());
+		assert!(super::unix_listener_from_fd(fd, std::path::Path::new("/nonexistent.sock")).unwrap().is_none());
+
+		// The fd must not have been closed by the mismatches.
+		assert_eq!(listener.local_addr().unwrap().port(), port);
+
+		let matched = super::tcp_listener_from_fd(fd, port).unwrap().expect("expect listener with the same port to match");
+		// The fd is still owned by `listener`.
+		let _ = std::os::unix::io::IntoRawFd::into_raw_fd(matched);
+	}
+
+	#[test]
+	fn systemd_listener() {
+		// All of LISTEN_PID and LISTEN_FDS are checked in this one test, since the environment is shared by all tests.
+
+		let dir = tempfile::tempdir().unwrap();
+		let socket_path = dir.path().join("listener.sock");
+		let listener = std::os::unix::net::UnixListener::bind(&socket_path).unwrap();
+		let fd = std::os::unix::io::AsRawFd::as_raw_fd(&listener);
+		assert!(fd >= super::SD_LISTEN_FDS_START);
+
+		std::env::remove_var("LISTEN_PID");
+		std::env::remove_var("LISTEN_FDS");
+		assert!(super::systemd_listener(|fd| super::unix_listener_from_fd(fd, &socket_path)).unwrap().is_none());
+
+		// LISTEN_FDS covers every fd from SD_LISTEN_FDS_START up to and including the listener's fd.
+		// The other fds are not this socket, so they are passed over without being closed.
+		std::env::set_var("LISTEN_FDS", (fd - super::SD_LISTEN_FDS_START + 1).to_string());
+
+		std::env::set_var("LISTEN_PID", (std::process::id() + 1).to_string());
+		assert!(super::systemd_listener(|fd| super::unix_listener_from_fd(fd, &socket_path)).unwrap().is_none());
+
+		std::env::set_var("LISTEN_PID", "foo");
+		let _ = super::systemd_listener(|fd| super::unix_listener_from_fd(fd, &socket_path)).expect_err("expect malformed LISTEN_PID to fail");
+
+		std::env::set_var("LISTEN_PID", std::process::id().to_string());
+
+		let other_socket_path = dir.path().join("other.sock");
+		assert!(super::systemd_listener(|fd| super::unix_listener_from_fd(fd, &other_socket_path)).unwrap().is_none());
+		assert_eq!(listener.local_addr().unwrap().as_pathname(), Some(&*socket_path));
+
+		let matched =
+			super::systemd_listener(|fd| super::unix_listener_from_fd(fd, &socket_path)).unwrap()
+			.expect("expect listener with the same path to match");
+		assert_eq!(std::os::unix::io::AsRawFd::as_raw_fd(&matched), fd);
+		// The fd is still owned by `listener`.
+		let _ = std::os::unix::io::IntoRawFd::into_raw_fd(matched);
+
+		std::env::set_var("LISTEN_FDS", "foo");
+		let _ = super::systemd_listener(|fd| super::unix_listener_from_fd(fd, &socket_path)).expect_err("expect malformed LISTEN_FDS to fail");
+
+		std::env::remove_var("LISTEN_PID");
+		std::env::remove_var("LISTEN_FDS");
+	}
+
+	#[tokio::test]
+	async fn incoming_removes_stale_socket() {
+		let dir = tempfile::tempdir().unwrap();
+		let socket_path = dir.path().join("stale.sock");
+
+		// Dropping the listener leaves the socket file behind, like a previous instance of the service that exited.
+		drop(std::os::unix::net::UnixListener::bind(&socket_path).unwrap());
+		assert!(socket_path.exists());
+
+		let connector = super::Connector::Unix { socket_path: socket_path.clone().into() };
+		let _incoming = connector.clone().incoming().await.unwrap();
+		let _ = connector.connect().unwrap();
+	}
+}
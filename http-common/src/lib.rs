@@ -2,6 +2,9 @@
 #![allow(
 )]
 
+mod connector;
+pub use connector::{AsyncStream, Connector, ConnectorError, Incoming, Stream};
+
 /// Ref <https://url.spec.whatwg.org/#path-percent-encode-set>
 pub const PATH_SEGMENT_ENCODE_SET: &percent_encoding::AsciiSet =
 	&percent_encoding::CONTROLS
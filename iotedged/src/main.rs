@@ -10,17 +10,18 @@ const IOTHUB_ENCODE_SET: &percent_encoding::AsciiSet =
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-	let mut key_engine = {
-		struct Connector;
+	let key_connector = {
+		let key_service_uri = get_uri_from_env("AZIOT_KEYD_URI", "unix:///var/run/aziot/keyd.sock")?;
+		http_common::Connector::new(&key_service_uri).map_err(Error::InvalidServiceUri)?
+	};
 
-		impl aziot_key_client::Connector for Connector {
-			fn connect(&self) -> std::io::Result<Box<dyn aziot_key_client::Stream>> {
-				let stream = std::net::TcpStream::connect(("localhost", 8888))?;
-				Ok(Box::new(stream))
-			}
-		}
+	let cert_connector = {
+		let cert_service_uri = get_uri_from_env("AZIOT_CERTD_URI", "unix:///var/run/aziot/certd.sock")?;
+		http_common::Connector::new(&cert_service_uri).map_err(Error::InvalidServiceUri)?
+	};
 
-		let key_client = aziot_key_client::Client::new(Box::new(Connector));
+	let mut key_engine = {
+		let key_client = aziot_key_client::Client::new(key_connector.clone());
 		let key_client = std::sync::Arc::new(key_client);
 
 		let key_engine = aziot_key_openssl_engine::load(key_client).map_err(Error::LoadKeyOpenslEngine)?;
@@ -28,55 +29,13 @@ async fn main() -> Result<(), Error> {
 	};
 
 	let key_client = {
-		#[derive(Clone, Copy)]
-		struct Connector;
-
-		impl hyper::service::Service<hyper::Uri> for Connector {
-			type Response = tokio::net::TcpStream;
-			type Error = std::io::Error;
-			type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
-
-			fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
-				std::task::Poll::Ready(Ok(()))
-			}
-
-			fn call(&mut self, _req: hyper::Uri) -> Self::Future {
-				let f = async {
-					let stream = tokio::net::TcpStream::connect(("localhost", 8888)).await?;
-					Ok(stream)
-				};
-				Box::pin(f)
-			}
-		}
-
-		let key_client = aziot_key_client_async::Client::new(Connector);
+		let key_client = aziot_key_client_async::Client::new(key_connector);
 		let key_client = std::sync::Arc::new(key_client);
 		key_client
 	};
 
 	let cert_client = {
-		#[derive(Clone, Copy)]
-		struct Connector;
-
-		impl hyper::service::Service<hyper::Uri> for Connector {
-			type Response = tokio::net::TcpStream;
-			type Error = std::io::Error;
-			type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
-
-			fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
-				std::task::Poll::Ready(Ok(()))
-			}
-
-			fn call(&mut self, _req: hyper::Uri) -> Self::Future {
-				let f = async {
-					let stream = tokio::net::TcpStream::connect(("localhost", 8889)).await?;
-					Ok(stream)
-				};
-				Box::pin(f)
-			}
-		}
-
-		let cert_client = aziot_cert_client_async::Client::new(Connector);
+		let cert_client = aziot_cert_client_async::Client::new(cert_connector);
 		let cert_client = std::sync::Arc::new(cert_client);
 		cert_client
 	};
@@ -97,7 +56,7 @@ async fn main() -> Result<(), Error> {
 	let device_ca_cert = {
 		let device_ca_cert = match cert_client.get_cert("device-ca").await {
 			Ok(device_ca_cert) => device_ca_cert,
-			Err(_) => {
+			Err(err) if err.code() == Some(aziot_cert_client_async::ErrorCode::CertNotFound) => {
 				let csr =
 					create_csr("device-ca", &device_ca_public_key, &device_ca_private_key)
 					.map_err(|err| Error::CreateOrLoadDeviceCaCert(Box::new(err)))?;
@@ -106,6 +65,8 @@ async fn main() -> Result<(), Error> {
 					.await.map_err(|err| Error::CreateOrLoadDeviceCaCert(Box::new(err)))?;
 				device_ca_cert
 			},
+
+			Err(err) => return Err(Error::CreateOrLoadDeviceCaCert(Box::new(err))),
 		};
 		let device_ca_cert = openssl::x509::X509::stack_from_pem(&device_ca_cert).map_err(|err| Error::CreateOrLoadDeviceCaCert(Box::new(err)))?;
 		device_ca_cert
@@ -160,7 +121,7 @@ async fn main() -> Result<(), Error> {
 	let workload_ca_cert = {
 		let workload_ca_cert = match cert_client.get_cert("workload-ca").await {
 			Ok(workload_ca_cert) => workload_ca_cert,
-			Err(_) => {
+			Err(err) if err.code() == Some(aziot_cert_client_async::ErrorCode::CertNotFound) => {
 				let csr =
 					create_csr("workload-ca", &workload_ca_public_key, &workload_ca_private_key)
 					.map_err(|err| Error::CreateOrLoadWorkloadCaCert(Box::new(err)))?;
@@ -169,6 +130,8 @@ async fn main() -> Result<(), Error> {
 					.await.map_err(|err| Error::CreateOrLoadWorkloadCaCert(Box::new(err)))?;
 				workload_ca_cert
 			},
+
+			Err(err) => return Err(Error::CreateOrLoadWorkloadCaCert(Box::new(err))),
 		};
 		let workload_ca_cert = openssl::x509::X509::stack_from_pem(&workload_ca_cert).map_err(|err| Error::CreateOrLoadWorkloadCaCert(Box::new(err)))?;
 		workload_ca_cert
@@ -328,6 +291,12 @@ async fn main() -> Result<(), Error> {
 	Ok(())
 }
 
+fn get_uri_from_env(name: &'static str, default: &str) -> Result<url::Url, Error> {
+	let value = std::env::var(name).unwrap_or_else(|_| default.to_owned());
+	let value = value.parse().map_err(|err| Error::InvalidServiceUriEnv(name, err))?;
+	Ok(value)
+}
+
 fn cert_public_key_matches_private_key(
 	cert: &openssl::x509::X509Ref,
 	private_key: &openssl::pkey::PKeyRef<openssl::pkey::Private>,
@@ -356,7 +325,7 @@ fn create_csr(
 
 	csr.set_pubkey(public_key)?;
 
-	csr.sign(private_key, openssl::hash::MessageDigest::sha256())?;
+	csr.sign(private_key, openssl2::signature_message_digest(private_key))?;
 
 	let csr = csr.build();
 	let csr = csr.to_pem()?;
@@ -414,9 +383,11 @@ enum VerifyWorkloadCaCertResult {
 
 enum Error {
 	CreateOrLoadDeviceCaCert(Box<dyn std::error::Error>),
-	CreateOrLoadDeviceCaKeyPair(std::io::Error),
+	CreateOrLoadDeviceCaKeyPair(aziot_key_client_async::Error),
 	CreateOrLoadWorkloadCaCert(Box<dyn std::error::Error>),
-	CreateOrLoadWorkloadCaKeyPair(std::io::Error),
+	CreateOrLoadWorkloadCaKeyPair(aziot_key_client_async::Error),
+	InvalidServiceUri(http_common::ConnectorError),
+	InvalidServiceUriEnv(&'static str, url::ParseError),
 	LoadKeyOpenslEngine(openssl2::Error),
 	VerifyWorkloadCaCert(openssl::error::ErrorStack),
 }
@@ -442,6 +413,8 @@ impl std::fmt::Display for Error {
 			Error::CreateOrLoadDeviceCaKeyPair(_) => f.write_str("could not create or load device CA key pair"),
 			Error::CreateOrLoadWorkloadCaCert(_) => f.write_str("could not create workload CA cert"),
 			Error::CreateOrLoadWorkloadCaKeyPair(_) => f.write_str("could not create or load workload CA key pair"),
+			Error::InvalidServiceUri(_) => f.write_str("invalid service URI"),
+			Error::InvalidServiceUriEnv(name, _) => write!(f, "env var {} does not contain a valid URI", name),
 			Error::LoadKeyOpenslEngine(_) => f.write_str("could not load aziot-key-openssl-engine"),
 			Error::VerifyWorkloadCaCert(_) => f.write_str("could not verify workload CA cert signature"),
 		}
@@ -455,6 +428,8 @@ impl std::error::Error for Error {
 			Error::CreateOrLoadDeviceCaKeyPair(err) => Some(err),
 			Error::CreateOrLoadWorkloadCaCert(err) => Some(&**err),
 			Error::CreateOrLoadWorkloadCaKeyPair(err) => Some(err),
+			Error::InvalidServiceUri(err) => Some(err),
+			Error::InvalidServiceUriEnv(_, err) => Some(err),
 			Error::LoadKeyOpenslEngine(err) => Some(err),
 			Error::VerifyWorkloadCaCert(err) => Some(err),
 		}
@@ -59,6 +59,8 @@ pub(crate) unsafe extern "C" fn set_parameter(
 				let value = value.to_str().map_err(|err| err_invalid_parameter("value", err))?;
 				let value: std::path::PathBuf = value.into();
 
+				fs_common::check_dir(&value).map_err(|err| err_invalid_parameter("value", err))?;
+
 				let mut guard = HOMEDIR_PATH.write().map_err(err_fatal)?;
 				*guard = Some(value);
 			},
@@ -200,7 +202,7 @@ pub(crate) unsafe extern "C" fn create_or_load_cert(
 										let cert = builder.build();
 
 										let cert_pem = cert.to_pem()?;
-										std::fs::write(&full_path, &cert_pem).map_err(err_external)?;
+										fs_common::write_file(&full_path, &cert_pem).map_err(err_external)?;
 
 										cert
 									},
@@ -279,7 +281,7 @@ pub unsafe extern "C" fn import_cert(
 		match uri {
 			Some(Uri::File(cert_path)) => {
 				let cert_pem = cert.to_pem()?;
-				std::fs::write(cert_path, &cert_pem).map_err(err_external)?;
+				fs_common::write_file(&cert_path, &cert_pem).map_err(err_external)?;
 
 				Ok(())
 			},
@@ -315,7 +317,7 @@ pub unsafe extern "C" fn import_cert(
 						full_path.push(&*filename);
 
 						let cert_pem = cert.to_pem()?;
-						std::fs::write(&full_path, &cert_pem).map_err(err_external)?;
+						fs_common::write_file(&full_path, &cert_pem).map_err(err_external)?;
 
 						Ok(())
 					},
@@ -4,31 +4,31 @@
 	clippy::unnested_or_patterns, // TODO: Remove when https://github.com/rust-lang/rust-clippy/issues/5704 is fixed
 )]
 
-pub struct Client<C> {
-	inner: hyper::Client<C, hyper::Body>,
+pub struct Client {
+	inner: hyper::Client<http_common::Connector, hyper::Body>,
 }
 
-impl<C> Client<C> where C: hyper::client::connect::Connect + Clone {
-	pub fn new(connect: C) -> Self {
-		let inner = hyper::Client::builder().build(connect);
+impl Client {
+	pub fn new(connector: http_common::Connector) -> Self {
+		let inner = hyper::Client::builder().build(connector);
 		Client {
 			inner,
 		}
 	}
 }
 
-impl<C> std::fmt::Debug for Client<C> {
+impl std::fmt::Debug for Client {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("Client").finish()
 	}
 }
 
-impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync + 'static {
+impl Client {
 	pub async fn create_key_pair_if_not_exists(
 		&self,
 		id: &str,
 		preferred_algorithms: Option<&str>,
-	) -> Result<aziot_key_common::KeyHandle, std::io::Error> {
+	) -> Result<aziot_key_common::KeyHandle, Error> {
 		let body = aziot_key_common_http::create_key_pair_if_not_exists::Request {
 			id: id.to_owned(),
 			preferred_algorithms: preferred_algorithms.map(ToOwned::to_owned),
@@ -46,7 +46,7 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 	pub async fn load_key_pair(
 		&self,
 		id: &str,
-	) -> Result<aziot_key_common::KeyHandle, std::io::Error> {
+	) -> Result<aziot_key_common::KeyHandle, Error> {
 		let uri = format!("/keypair/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
 
 		let res: aziot_key_common_http::load_key_pair::Response = request::<_, (), _>(
@@ -62,7 +62,7 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 		&self,
 		handle: &aziot_key_common::KeyHandle,
 		parameter_name: &str,
-	) -> Result<String, std::io::Error> {
+	) -> Result<String, Error> {
 		let uri = format!("/parameters/{}", percent_encoding::percent_encode(parameter_name.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
 
 		let body = aziot_key_common_http::get_key_pair_public_parameter::Request {
@@ -82,7 +82,7 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 		&self,
 		id: &str,
 		value: aziot_key_common::CreateKeyValue,
-	) -> Result<aziot_key_common::KeyHandle, std::io::Error> {
+	) -> Result<aziot_key_common::KeyHandle, Error> {
 		let body = match value {
 			aziot_key_common::CreateKeyValue::Generate { length } => aziot_key_common_http::create_key_if_not_exists::Request {
 				id: id.to_owned(),
@@ -110,32 +110,10 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::SignMechanism,
 		digest: &[u8],
-	) -> Result<Vec<u8>, std::io::Error> {
+	) -> Result<Vec<u8>, Error> {
 		let body = aziot_key_common_http::sign::Request {
 			key_handle: handle.clone(),
-			parameters: match mechanism {
-				aziot_key_common::SignMechanism::Ecdsa => aziot_key_common_http::sign::Parameters::Ecdsa {
-					digest: http_common::ByteString(digest.to_owned()),
-				},
-
-				aziot_key_common::SignMechanism::RsaPkcs1 { message_digest } => aziot_key_common_http::sign::Parameters::RsaPkcs1 {
-					message_digest_algorithm: match message_digest {
-						aziot_key_common::RsaPkcs1MessageDigest::Sha1 => "sha1".to_owned(),
-						aziot_key_common::RsaPkcs1MessageDigest::Sha224 => "sha224".to_owned(),
-						aziot_key_common::RsaPkcs1MessageDigest::Sha256 => "sha256".to_owned(),
-						aziot_key_common::RsaPkcs1MessageDigest::Sha384 => "sha384".to_owned(),
-						aziot_key_common::RsaPkcs1MessageDigest::Sha512 => "sha512".to_owned(),
-					},
-					message: http_common::ByteString(digest.to_owned()),
-				},
-
-				aziot_key_common::SignMechanism::RsaPss { mask_generation_function, salt_len } =>
-					unimplemented!("sign(RSA_PSS, {:?}, {})", mask_generation_function, salt_len),
-
-				aziot_key_common::SignMechanism::HmacSha256 => aziot_key_common_http::sign::Parameters::HmacSha256 {
-					message: http_common::ByteString(digest.to_owned()),
-				},
-			},
+			parameters: to_http_sign_parameters(mechanism, digest),
 		};
 
 		let res: aziot_key_common_http::sign::Response = request(
@@ -148,12 +126,34 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 		Ok(signature)
 	}
 
+	pub async fn verify(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+		mechanism: aziot_key_common::SignMechanism,
+		digest: &[u8],
+		signature: &[u8],
+	) -> Result<bool, Error> {
+		let body = aziot_key_common_http::verify::Request {
+			key_handle: handle.clone(),
+			parameters: to_http_sign_parameters(mechanism, digest),
+			signature: http_common::ByteString(signature.to_owned()),
+		};
+
+		let res: aziot_key_common_http::verify::Response = request(
+			&self.inner,
+			http::Method::POST,
+			"/verify",
+			Some(&body),
+		).await?;
+		Ok(res.ok)
+	}
+
 	pub async fn encrypt(
 		&self,
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::EncryptMechanism,
 		plaintext: &[u8],
-	) -> Result<Vec<u8>, std::io::Error> {
+	) -> Result<Vec<u8>, Error> {
 		let body = aziot_key_common_http::encrypt::Request {
 			key_handle: handle.clone(),
 			parameters: match mechanism {
@@ -161,6 +161,12 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 					iv: http_common::ByteString(iv),
 					aad: http_common::ByteString(aad),
 				},
+
+				aziot_key_common::EncryptMechanism::AeadGeneratedNonce { aad } => aziot_key_common_http::encrypt::Parameters::AeadGeneratedNonce {
+					aad: http_common::ByteString(aad),
+				},
+
+				aziot_key_common::EncryptMechanism::RsaOaep => aziot_key_common_http::encrypt::Parameters::RsaOaep,
 			},
 			plaintext: http_common::ByteString(plaintext.to_owned()),
 		};
@@ -180,7 +186,7 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::EncryptMechanism,
 		ciphertext: &[u8],
-	) -> Result<Vec<u8>, std::io::Error> {
+	) -> Result<Vec<u8>, Error> {
 		let body = aziot_key_common_http::decrypt::Request {
 			key_handle: handle.clone(),
 			parameters: match mechanism {
@@ -188,6 +194,12 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 					iv: http_common::ByteString(iv),
 					aad: http_common::ByteString(aad),
 				},
+
+				aziot_key_common::EncryptMechanism::AeadGeneratedNonce { aad } => aziot_key_common_http::decrypt::Parameters::AeadGeneratedNonce {
+					aad: http_common::ByteString(aad),
+				},
+
+				aziot_key_common::EncryptMechanism::RsaOaep => aziot_key_common_http::decrypt::Parameters::RsaOaep,
 			},
 			ciphertext: http_common::ByteString(ciphertext.to_owned()),
 		};
@@ -201,6 +213,281 @@ impl<C> Client<C> where C: hyper::client::connect::Connect + Clone + Send + Sync
 		let plaintext = res.plaintext.0;
 		Ok(plaintext)
 	}
+
+	pub async fn derive_shared_secret(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+		peer_public_key: &[u8],
+	) -> Result<Vec<u8>, Error> {
+		let body = aziot_key_common_http::derive_shared_secret::Request {
+			key_handle: handle.clone(),
+			peer_public_key: http_common::ByteString(peer_public_key.to_owned()),
+			derived_key_id: None,
+		};
+
+		let res: aziot_key_common_http::derive_shared_secret::Response = request(
+			&self.inner,
+			http::Method::POST,
+			"/derive",
+			Some(&body),
+		).await?;
+		match res {
+			aziot_key_common_http::derive_shared_secret::Response::SharedSecret { shared_secret } => Ok(shared_secret.0),
+			aziot_key_common_http::derive_shared_secret::Response::DerivedKey { .. } =>
+				Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"))),
+		}
+	}
+
+	pub async fn derive_key(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+		peer_public_key: &[u8],
+		derived_key_id: &str,
+	) -> Result<aziot_key_common::KeyHandle, Error> {
+		let body = aziot_key_common_http::derive_shared_secret::Request {
+			key_handle: handle.clone(),
+			peer_public_key: http_common::ByteString(peer_public_key.to_owned()),
+			derived_key_id: Some(derived_key_id.to_owned()),
+		};
+
+		let res: aziot_key_common_http::derive_shared_secret::Response = request(
+			&self.inner,
+			http::Method::POST,
+			"/derive",
+			Some(&body),
+		).await?;
+		match res {
+			aziot_key_common_http::derive_shared_secret::Response::DerivedKey { handle } => Ok(handle),
+			aziot_key_common_http::derive_shared_secret::Response::SharedSecret { .. } =>
+				Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"))),
+		}
+	}
+
+	pub async fn revoke_key_handles(
+		&self,
+		id: &str,
+		kind: aziot_key_common::KeyKind,
+	) -> Result<(), Error> {
+		let body = aziot_key_common_http::revoke_key_handles::Request {
+			id: id.to_owned(),
+			kind: match kind {
+				aziot_key_common::KeyKind::Key => aziot_key_common_http::list_keys::KeyKind::Key,
+				aziot_key_common::KeyKind::KeyPair => aziot_key_common_http::list_keys::KeyKind::KeyPair,
+			},
+		};
+
+		let () = request(
+			&self.inner,
+			http::Method::POST,
+			"/revoke",
+			Some(&body),
+		).await?;
+		Ok(())
+	}
+
+	pub async fn rotate_key(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+	) -> Result<(), Error> {
+		let body = aziot_key_common_http::rotate_key::Request {
+			key_handle: handle.clone(),
+		};
+
+		let () = request(
+			&self.inner,
+			http::Method::POST,
+			"/rotate",
+			Some(&body),
+		).await?;
+		Ok(())
+	}
+
+	pub async fn rewrap(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+		mechanism: aziot_key_common::EncryptMechanism,
+		ciphertext: &[u8],
+	) -> Result<Vec<u8>, Error> {
+		let body = aziot_key_common_http::rewrap::Request {
+			key_handle: handle.clone(),
+			parameters: match mechanism {
+				aziot_key_common::EncryptMechanism::Aead { iv, aad } => aziot_key_common_http::decrypt::Parameters::Aead {
+					iv: http_common::ByteString(iv),
+					aad: http_common::ByteString(aad),
+				},
+
+				aziot_key_common::EncryptMechanism::AeadGeneratedNonce { aad } => aziot_key_common_http::decrypt::Parameters::AeadGeneratedNonce {
+					aad: http_common::ByteString(aad),
+				},
+
+				aziot_key_common::EncryptMechanism::RsaOaep => aziot_key_common_http::decrypt::Parameters::RsaOaep,
+			},
+			ciphertext: http_common::ByteString(ciphertext.to_owned()),
+		};
+
+		let res: aziot_key_common_http::rewrap::Response = request(
+			&self.inner,
+			http::Method::POST,
+			"/rewrap",
+			Some(&body),
+		).await?;
+		let ciphertext = res.ciphertext.0;
+		Ok(ciphertext)
+	}
+
+	pub async fn list_key_pairs(
+		&self,
+	) -> Result<Vec<aziot_key_common::KeyInfo>, Error> {
+		let res: aziot_key_common_http::list_keys::Response = request::<_, (), _>(
+			&self.inner,
+			http::Method::GET,
+			"/keypairs",
+			None,
+		).await?;
+		Ok(res.keys.into_iter().map(from_http_key_info).collect())
+	}
+
+	pub async fn list_keys(
+		&self,
+	) -> Result<Vec<aziot_key_common::KeyInfo>, Error> {
+		let res: aziot_key_common_http::list_keys::Response = request::<_, (), _>(
+			&self.inner,
+			http::Method::GET,
+			"/keys",
+			None,
+		).await?;
+		Ok(res.keys.into_iter().map(from_http_key_info).collect())
+	}
+
+	pub async fn delete_key_pair(
+		&self,
+		id: &str,
+	) -> Result<(), Error> {
+		let uri = format!("/keypair/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
+
+		let () = request::<_, (), _>(
+			&self.inner,
+			http::Method::DELETE,
+			&uri,
+			None,
+		).await?;
+		Ok(())
+	}
+
+	pub async fn delete_key(
+		&self,
+		id: &str,
+	) -> Result<(), Error> {
+		let uri = format!("/key/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
+
+		let () = request::<_, (), _>(
+			&self.inner,
+			http::Method::DELETE,
+			&uri,
+			None,
+		).await?;
+		Ok(())
+	}
+}
+
+pub use aziot_key_common_http::ErrorCode;
+
+/// An error returned by the [`Client`].
+#[derive(Debug)]
+pub enum Error {
+	/// The request could not be sent to aziot-keyd, or its response could not be read.
+	Io(std::io::Error),
+
+	/// aziot-keyd failed the request.
+	Api {
+		code: ErrorCode,
+		message: String,
+	},
+}
+
+impl Error {
+	/// The code of the error returned by aziot-keyd, or `None` if the request did not get a response.
+	pub fn code(&self) -> Option<ErrorCode> {
+		match self {
+			Error::Io(_) => None,
+			Error::Api { code, .. } => Some(*code),
+		}
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Error::Io(_) => f.write_str("could not communicate with aziot-keyd"),
+			Error::Api { code, message } => write!(f, "aziot-keyd returned an error ({:?}): {}", code, message),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io(err) => Some(err),
+			Error::Api { .. } => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Self {
+		Error::Io(err)
+	}
+}
+
+fn to_http_sign_parameters(mechanism: aziot_key_common::SignMechanism, digest: &[u8]) -> aziot_key_common_http::sign::Parameters {
+	match mechanism {
+		aziot_key_common::SignMechanism::Ecdsa => aziot_key_common_http::sign::Parameters::Ecdsa {
+			digest: http_common::ByteString(digest.to_owned()),
+		},
+
+		aziot_key_common::SignMechanism::EdDsa => aziot_key_common_http::sign::Parameters::EdDsa {
+			message: http_common::ByteString(digest.to_owned()),
+		},
+
+		aziot_key_common::SignMechanism::RsaPkcs1 { message_digest } => aziot_key_common_http::sign::Parameters::RsaPkcs1 {
+			message_digest_algorithm: match message_digest {
+				aziot_key_common::RsaPkcs1MessageDigest::Sha1 => "sha1".to_owned(),
+				aziot_key_common::RsaPkcs1MessageDigest::Sha224 => "sha224".to_owned(),
+				aziot_key_common::RsaPkcs1MessageDigest::Sha256 => "sha256".to_owned(),
+				aziot_key_common::RsaPkcs1MessageDigest::Sha384 => "sha384".to_owned(),
+				aziot_key_common::RsaPkcs1MessageDigest::Sha512 => "sha512".to_owned(),
+			},
+			message: http_common::ByteString(digest.to_owned()),
+		},
+
+		aziot_key_common::SignMechanism::RsaPss { mask_generation_function, salt_len } => aziot_key_common_http::sign::Parameters::RsaPss {
+			mask_generation_function: match mask_generation_function {
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha1 => "sha1".to_owned(),
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha224 => "sha224".to_owned(),
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha256 => "sha256".to_owned(),
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha384 => "sha384".to_owned(),
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha512 => "sha512".to_owned(),
+			},
+			salt_len,
+			message: http_common::ByteString(digest.to_owned()),
+		},
+
+		aziot_key_common::SignMechanism::HmacSha256 => aziot_key_common_http::sign::Parameters::HmacSha256 {
+			message: http_common::ByteString(digest.to_owned()),
+		},
+	}
+}
+
+fn from_http_key_info(key_info: aziot_key_common_http::list_keys::KeyInfo) -> aziot_key_common::KeyInfo {
+	aziot_key_common::KeyInfo {
+		id: key_info.id,
+		kind: match key_info.kind {
+			aziot_key_common_http::list_keys::KeyKind::Key => aziot_key_common::KeyKind::Key,
+			aziot_key_common_http::list_keys::KeyKind::KeyPair => aziot_key_common::KeyKind::KeyPair,
+		},
+		algorithm: key_info.algorithm,
+		backend: key_info.backend,
+	}
 }
 
 async fn request<TConnect, TRequest, TResponse>(
@@ -208,7 +495,7 @@ async fn request<TConnect, TRequest, TResponse>(
 	method: http::Method,
 	uri: &str,
 	body: Option<&TRequest>,
-) -> std::io::Result<TResponse>
+) -> Result<TResponse, Error>
 where
 	TConnect: hyper::client::connect::Connect + Clone + Send + Sync + 'static,
 	TRequest: serde::Serialize,
@@ -246,8 +533,8 @@ where
 		}
 	}
 
-	if !is_json {
-		return Err(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"));
+	if res_status_code != hyper::StatusCode::NO_CONTENT && !is_json {
+		return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response")));
 	}
 
 	let body = hyper::body::to_bytes(body).await.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
@@ -258,12 +545,18 @@ where
 			res
 		},
 
+		hyper::StatusCode::NO_CONTENT => {
+			// No body. Callers that expect this status use `()` as the response type, which deserializes from JSON null.
+			let res = serde_json::from_slice(b"null").map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+			res
+		},
+
 		res_status_code if res_status_code.is_client_error() || res_status_code.is_server_error() => {
 			let res: aziot_key_common_http::Error = serde_json::from_slice(&body).map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
-			return Err(std::io::Error::new(std::io::ErrorKind::Other, res.message));
+			return Err(Error::Api { code: res.code, message: res.message.into_owned() });
 		},
 
-		_ => return Err(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response")),
+		_ => return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"))),
 	};
 	Ok(res)
 }
@@ -4,22 +4,12 @@
 	clippy::unnested_or_patterns, // TODO: Remove when https://github.com/rust-lang/rust-clippy/issues/5704 is fixed
 )]
 
-pub trait Connector {
-	fn connect(&self) -> std::io::Result<Box<dyn Stream>>;
-}
-
-pub trait Stream: std::io::Read + std::io::Write {
-}
-
-impl<T> Stream for T where T: std::io::Read + std::io::Write {
-}
-
 pub struct Client {
-	connector: Box<dyn Connector>,
+	connector: http_common::Connector,
 }
 
 impl Client {
-	pub fn new(connector: Box<dyn Connector>) -> Self {
+	pub fn new(connector: http_common::Connector) -> Self {
 		Client {
 			connector,
 		}
@@ -29,7 +19,7 @@ impl Client {
 		&self,
 		id: &str,
 		preferred_algorithms: Option<&str>,
-	) -> std::io::Result<aziot_key_common::KeyHandle> {
+	) -> Result<aziot_key_common::KeyHandle, Error> {
 		let mut stream = self.connector.connect()?;
 
 		let body = aziot_key_common_http::create_key_pair_if_not_exists::Request {
@@ -49,7 +39,7 @@ impl Client {
 	pub fn load_key_pair(
 		&self,
 		id: &str,
-	) -> std::io::Result<aziot_key_common::KeyHandle> {
+	) -> Result<aziot_key_common::KeyHandle, Error> {
 		let mut stream = self.connector.connect()?;
 
 		let uri = format!("/keypair/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
@@ -67,7 +57,7 @@ impl Client {
 		&self,
 		handle: &aziot_key_common::KeyHandle,
 		parameter_name: &str,
-	) -> std::io::Result<String> {
+	) -> Result<String, Error> {
 		let mut stream = self.connector.connect()?;
 
 		let uri = format!("/parameters/{}", percent_encoding::percent_encode(parameter_name.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
@@ -89,7 +79,7 @@ impl Client {
 		&self,
 		id: &str,
 		value: aziot_key_common::CreateKeyValue,
-	) -> std::io::Result<aziot_key_common::KeyHandle> {
+	) -> Result<aziot_key_common::KeyHandle, Error> {
 		let mut stream = self.connector.connect()?;
 
 		let body = match value {
@@ -119,34 +109,12 @@ impl Client {
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::SignMechanism,
 		digest: &[u8],
-	) -> std::io::Result<Vec<u8>> {
+	) -> Result<Vec<u8>, Error> {
 		let mut stream = self.connector.connect()?;
 
 		let body = aziot_key_common_http::sign::Request {
 			key_handle: handle.clone(),
-			parameters: match mechanism {
-				aziot_key_common::SignMechanism::Ecdsa => aziot_key_common_http::sign::Parameters::Ecdsa {
-					digest: http_common::ByteString(digest.to_owned()),
-				},
-
-				aziot_key_common::SignMechanism::RsaPkcs1 { message_digest } => aziot_key_common_http::sign::Parameters::RsaPkcs1 {
-					message_digest_algorithm: match message_digest {
-						aziot_key_common::RsaPkcs1MessageDigest::Sha1 => "sha1".to_owned(),
-						aziot_key_common::RsaPkcs1MessageDigest::Sha224 => "sha224".to_owned(),
-						aziot_key_common::RsaPkcs1MessageDigest::Sha256 => "sha256".to_owned(),
-						aziot_key_common::RsaPkcs1MessageDigest::Sha384 => "sha384".to_owned(),
-						aziot_key_common::RsaPkcs1MessageDigest::Sha512 => "sha512".to_owned(),
-					},
-					message: http_common::ByteString(digest.to_owned()),
-				},
-
-				aziot_key_common::SignMechanism::RsaPss { mask_generation_function, salt_len } =>
-					unimplemented!("sign(RSA_PSS, {:?}, {})", mask_generation_function, salt_len),
-
-				aziot_key_common::SignMechanism::HmacSha256 => aziot_key_common_http::sign::Parameters::HmacSha256 {
-					message: http_common::ByteString(digest.to_owned()),
-				},
-			},
+			parameters: to_http_sign_parameters(mechanism, digest),
 		};
 
 		let res: aziot_key_common_http::sign::Response = request(
@@ -159,12 +127,36 @@ impl Client {
 		Ok(signature)
 	}
 
+	pub fn verify(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+		mechanism: aziot_key_common::SignMechanism,
+		digest: &[u8],
+		signature: &[u8],
+	) -> Result<bool, Error> {
+		let mut stream = self.connector.connect()?;
+
+		let body = aziot_key_common_http::verify::Request {
+			key_handle: handle.clone(),
+			parameters: to_http_sign_parameters(mechanism, digest),
+			signature: http_common::ByteString(signature.to_owned()),
+		};
+
+		let res: aziot_key_common_http::verify::Response = request(
+			&mut stream,
+			http::Method::POST,
+			"/verify",
+			Some(&body),
+		)?;
+		Ok(res.ok)
+	}
+
 	pub fn encrypt(
 		&self,
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::EncryptMechanism,
 		plaintext: &[u8],
-	) -> std::io::Result<Vec<u8>> {
+	) -> Result<Vec<u8>, Error> {
 		let mut stream = self.connector.connect()?;
 
 		let body = aziot_key_common_http::encrypt::Request {
@@ -174,6 +166,12 @@ impl Client {
 					iv: http_common::ByteString(iv),
 					aad: http_common::ByteString(aad),
 				},
+
+				aziot_key_common::EncryptMechanism::AeadGeneratedNonce { aad } => aziot_key_common_http::encrypt::Parameters::AeadGeneratedNonce {
+					aad: http_common::ByteString(aad),
+				},
+
+				aziot_key_common::EncryptMechanism::RsaOaep => aziot_key_common_http::encrypt::Parameters::RsaOaep,
 			},
 			plaintext: http_common::ByteString(plaintext.to_owned()),
 		};
@@ -193,7 +191,7 @@ impl Client {
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::EncryptMechanism,
 		ciphertext: &[u8],
-	) -> std::io::Result<Vec<u8>> {
+	) -> Result<Vec<u8>, Error> {
 		let mut stream = self.connector.connect()?;
 
 		let body = aziot_key_common_http::decrypt::Request {
@@ -203,6 +201,12 @@ impl Client {
 					iv: http_common::ByteString(iv),
 					aad: http_common::ByteString(aad),
 				},
+
+				aziot_key_common::EncryptMechanism::AeadGeneratedNonce { aad } => aziot_key_common_http::decrypt::Parameters::AeadGeneratedNonce {
+					aad: http_common::ByteString(aad),
+				},
+
+				aziot_key_common::EncryptMechanism::RsaOaep => aziot_key_common_http::decrypt::Parameters::RsaOaep,
 			},
 			ciphertext: http_common::ByteString(ciphertext.to_owned()),
 		};
@@ -216,6 +220,199 @@ impl Client {
 		let plaintext = res.plaintext.0;
 		Ok(plaintext)
 	}
+
+	pub fn derive_shared_secret(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+		peer_public_key: &[u8],
+	) -> Result<Vec<u8>, Error> {
+		let mut stream = self.connector.connect()?;
+
+		let body = aziot_key_common_http::derive_shared_secret::Request {
+			key_handle: handle.clone(),
+			peer_public_key: http_common::ByteString(peer_public_key.to_owned()),
+			derived_key_id: None,
+		};
+
+		let res: aziot_key_common_http::derive_shared_secret::Response = request(
+			&mut stream,
+			http::Method::POST,
+			"/derive",
+			Some(&body),
+		)?;
+		match res {
+			aziot_key_common_http::derive_shared_secret::Response::SharedSecret { shared_secret } => Ok(shared_secret.0),
+			aziot_key_common_http::derive_shared_secret::Response::DerivedKey { .. } =>
+				Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"))),
+		}
+	}
+
+	pub fn derive_key(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+		peer_public_key: &[u8],
+		derived_key_id: &str,
+	) -> Result<aziot_key_common::KeyHandle, Error> {
+		let mut stream = self.connector.connect()?;
+
+		let body = aziot_key_common_http::derive_shared_secret::Request {
+			key_handle: handle.clone(),
+			peer_public_key: http_common::ByteString(peer_public_key.to_owned()),
+			derived_key_id: Some(derived_key_id.to_owned()),
+		};
+
+		let res: aziot_key_common_http::derive_shared_secret::Response = request(
+			&mut stream,
+			http::Method::POST,
+			"/derive",
+			Some(&body),
+		)?;
+		match res {
+			aziot_key_common_http::derive_shared_secret::Response::DerivedKey { handle } => Ok(handle),
+			aziot_key_common_http::derive_shared_secret::Response::SharedSecret { .. } =>
+				Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"))),
+		}
+	}
+
+	pub fn revoke_key_handles(
+		&self,
+		id: &str,
+		kind: aziot_key_common::KeyKind,
+	) -> Result<(), Error> {
+		let mut stream = self.connector.connect()?;
+
+		let body = aziot_key_common_http::revoke_key_handles::Request {
+			id: id.to_owned(),
+			kind: match kind {
+				aziot_key_common::KeyKind::Key => aziot_key_common_http::list_keys::KeyKind::Key,
+				aziot_key_common::KeyKind::KeyPair => aziot_key_common_http::list_keys::KeyKind::KeyPair,
+			},
+		};
+
+		let () = request(
+			&mut stream,
+			http::Method::POST,
+			"/revoke",
+			Some(&body),
+		)?;
+		Ok(())
+	}
+
+	pub fn rotate_key(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+	) -> Result<(), Error> {
+		let mut stream = self.connector.connect()?;
+
+		let body = aziot_key_common_http::rotate_key::Request {
+			key_handle: handle.clone(),
+		};
+
+		let () = request(
+			&mut stream,
+			http::Method::POST,
+			"/rotate",
+			Some(&body),
+		)?;
+		Ok(())
+	}
+
+	pub fn rewrap(
+		&self,
+		handle: &aziot_key_common::KeyHandle,
+		mechanism: aziot_key_common::EncryptMechanism,
+		ciphertext: &[u8],
+	) -> Result<Vec<u8>, Error> {
+		let mut stream = self.connector.connect()?;
+
+		let body = aziot_key_common_http::rewrap::Request {
+			key_handle: handle.clone(),
+			parameters: match mechanism {
+				aziot_key_common::EncryptMechanism::Aead { iv, aad } => aziot_key_common_http::decrypt::Parameters::Aead {
+					iv: http_common::ByteString(iv),
+					aad: http_common::ByteString(aad),
+				},
+
+				aziot_key_common::EncryptMechanism::AeadGeneratedNonce { aad } => aziot_key_common_http::decrypt::Parameters::AeadGeneratedNonce {
+					aad: http_common::ByteString(aad),
+				},
+
+				aziot_key_common::EncryptMechanism::RsaOaep => aziot_key_common_http::decrypt::Parameters::RsaOaep,
+			},
+			ciphertext: http_common::ByteString(ciphertext.to_owned()),
+		};
+
+		let res: aziot_key_common_http::rewrap::Response = request(
+			&mut stream,
+			http::Method::POST,
+			"/rewrap",
+			Some(&body),
+		)?;
+		let ciphertext = res.ciphertext.0;
+		Ok(ciphertext)
+	}
+
+	pub fn list_key_pairs(
+		&self,
+	) -> Result<Vec<aziot_key_common::KeyInfo>, Error> {
+		let mut stream = self.connector.connect()?;
+
+		let res: aziot_key_common_http::list_keys::Response = request::<_, (), _>(
+			&mut stream,
+			http::Method::GET,
+			"/keypairs",
+			None,
+		)?;
+		Ok(res.keys.into_iter().map(from_http_key_info).collect())
+	}
+
+	pub fn list_keys(
+		&self,
+	) -> Result<Vec<aziot_key_common::KeyInfo>, Error> {
+		let mut stream = self.connector.connect()?;
+
+		let res: aziot_key_common_http::list_keys::Response = request::<_, (), _>(
+			&mut stream,
+			http::Method::GET,
+			"/keys",
+			None,
+		)?;
+		Ok(res.keys.into_iter().map(from_http_key_info).collect())
+	}
+
+	pub fn delete_key_pair(
+		&self,
+		id: &str,
+	) -> Result<(), Error> {
+		let mut stream = self.connector.connect()?;
+
+		let uri = format!("/keypair/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
+
+		let () = request::<_, (), _>(
+			&mut stream,
+			http::Method::DELETE,
+			&uri,
+			None,
+		)?;
+		Ok(())
+	}
+
+	pub fn delete_key(
+		&self,
+		id: &str,
+	) -> Result<(), Error> {
+		let mut stream = self.connector.connect()?;
+
+		let uri = format!("/key/{}", percent_encoding::percent_encode(id.as_bytes(), http_common::PATH_SEGMENT_ENCODE_SET));
+
+		let () = request::<_, (), _>(
+			&mut stream,
+			http::Method::DELETE,
+			&uri,
+			None,
+		)?;
+		Ok(())
+	}
 }
 
 impl std::fmt::Debug for Client {
@@ -224,12 +421,112 @@ impl std::fmt::Debug for Client {
 	}
 }
 
+pub use aziot_key_common_http::ErrorCode;
+
+/// An error returned by the [`Client`].
+#[derive(Debug)]
+pub enum Error {
+	/// The request could not be sent to aziot-keyd, or its response could not be read.
+	Io(std::io::Error),
+
+	/// aziot-keyd failed the request.
+	Api {
+		code: ErrorCode,
+		message: String,
+	},
+}
+
+impl Error {
+	/// The code of the error returned by aziot-keyd, or `None` if the request did not get a response.
+	pub fn code(&self) -> Option<ErrorCode> {
+		match self {
+			Error::Io(_) => None,
+			Error::Api { code, .. } => Some(*code),
+		}
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Error::Io(_) => f.write_str("could not communicate with aziot-keyd"),
+			Error::Api { code, message } => write!(f, "aziot-keyd returned an error ({:?}): {}", code, message),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io(err) => Some(err),
+			Error::Api { .. } => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Self {
+		Error::Io(err)
+	}
+}
+
+fn to_http_sign_parameters(mechanism: aziot_key_common::SignMechanism, digest: &[u8]) -> aziot_key_common_http::sign::Parameters {
+	match mechanism {
+		aziot_key_common::SignMechanism::Ecdsa => aziot_key_common_http::sign::Parameters::Ecdsa {
+			digest: http_common::ByteString(digest.to_owned()),
+		},
+
+		aziot_key_common::SignMechanism::EdDsa => aziot_key_common_http::sign::Parameters::EdDsa {
+			message: http_common::ByteString(digest.to_owned()),
+		},
+
+		aziot_key_common::SignMechanism::RsaPkcs1 { message_digest } => aziot_key_common_http::sign::Parameters::RsaPkcs1 {
+			message_digest_algorithm: match message_digest {
+				aziot_key_common::RsaPkcs1MessageDigest::Sha1 => "sha1".to_owned(),
+				aziot_key_common::RsaPkcs1MessageDigest::Sha224 => "sha224".to_owned(),
+				aziot_key_common::RsaPkcs1MessageDigest::Sha256 => "sha256".to_owned(),
+				aziot_key_common::RsaPkcs1MessageDigest::Sha384 => "sha384".to_owned(),
+				aziot_key_common::RsaPkcs1MessageDigest::Sha512 => "sha512".to_owned(),
+			},
+			message: http_common::ByteString(digest.to_owned()),
+		},
+
+		aziot_key_common::SignMechanism::RsaPss { mask_generation_function, salt_len } => aziot_key_common_http::sign::Parameters::RsaPss {
+			mask_generation_function: match mask_generation_function {
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha1 => "sha1".to_owned(),
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha224 => "sha224".to_owned(),
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha256 => "sha256".to_owned(),
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha384 => "sha384".to_owned(),
+				aziot_key_common::RsaPssMaskGenerationFunction::Sha512 => "sha512".to_owned(),
+			},
+			salt_len,
+			message: http_common::ByteString(digest.to_owned()),
+		},
+
+		aziot_key_common::SignMechanism::HmacSha256 => aziot_key_common_http::sign::Parameters::HmacSha256 {
+			message: http_common::ByteString(digest.to_owned()),
+		},
+	}
+}
+
+fn from_http_key_info(key_info: aziot_key_common_http::list_keys::KeyInfo) -> aziot_key_common::KeyInfo {
+	aziot_key_common::KeyInfo {
+		id: key_info.id,
+		kind: match key_info.kind {
+			aziot_key_common_http::list_keys::KeyKind::Key => aziot_key_common::KeyKind::Key,
+			aziot_key_common_http::list_keys::KeyKind::KeyPair => aziot_key_common::KeyKind::KeyPair,
+		},
+		algorithm: key_info.algorithm,
+		backend: key_info.backend,
+	}
+}
+
 fn request<TStream, TRequest, TResponse>(
 	stream: &mut TStream,
 	method: http::Method,
 	uri: &str,
 	body: Option<&TRequest>,
-) -> std::io::Result<TResponse>
+) -> Result<TResponse, Error>
 where
 	TStream: std::io::Read + std::io::Write,
 	TRequest: serde::Serialize,
@@ -253,7 +550,7 @@ where
 		)?;
 	}
 	else {
-		stream.write_all(b"\r\n")?;
+		stream.write_all(b"connection: close\r\n\r\n")?;
 	}
 
 	let mut buf = vec![];
@@ -264,8 +561,8 @@ where
 
 	let body_start_pos = match res.parse(&buf) {
 		Ok(httparse::Status::Complete(body_start_pos)) => body_start_pos,
-		Ok(httparse::Status::Partial) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
-		Err(err) => return Err(std::io::Error::new(std::io::ErrorKind::Other, err)),
+		Ok(httparse::Status::Partial) => return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into())),
+		Err(err) => return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, err))),
 	};
 
 	let res_status_code = res.code;
@@ -286,15 +583,15 @@ where
 		}
 	}
 
-	if !is_json {
-		return Err(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"));
+	if res_status_code != Some(204) && !is_json {
+		return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response")));
 	}
 
 	let body = &buf[body_start_pos..];
 	let body =
 		if let Some(content_length) = content_length {
 			if body.len() < content_length {
-				return Err(std::io::ErrorKind::UnexpectedEof.into());
+				return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()));
 			}
 			else {
 				&body[..content_length]
@@ -310,12 +607,18 @@ where
 			res
 		},
 
+		Some(204) => {
+			// No body. Callers that expect this status use `()` as the response type, which deserializes from JSON null.
+			let res = serde_json::from_slice(b"null").map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+			res
+		},
+
 		Some(400..=499) | Some(500..=599) => {
 			let res: aziot_key_common_http::Error = serde_json::from_slice(body).map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
-			return Err(std::io::Error::new(std::io::ErrorKind::Other, res.message));
+			return Err(Error::Api { code: res.code, message: res.message.into_owned() });
 		},
 
-		Some(_) | None => return Err(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response")),
+		Some(_) | None => return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::Other, "malformed HTTP response"))),
 	};
 	Ok(res)
 }
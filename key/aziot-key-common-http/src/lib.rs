@@ -4,9 +4,51 @@
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct Error {
+	/// Servers that predate error codes do not send this field, in which case it deserializes as `ErrorCode::Unknown`.
+	#[serde(default)]
+	pub code: ErrorCode,
+
 	pub message: std::borrow::Cow<'static, str>,
 }
 
+/// A stable identifier of the kind of error in an [`Error`] response. Unlike the message, clients can match on it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum ErrorCode {
+	/// The request could not be parsed, or used the wrong path or method.
+	InvalidRequest,
+
+	/// A parameter of the request has an invalid value.
+	InvalidParameter,
+
+	/// The key handle is malformed, was not issued by this server, has expired or has been revoked.
+	InvalidHandle,
+
+	/// The key with the requested ID does not exist.
+	KeyNotFound,
+
+	/// The caller is not authorized to use the key.
+	Unauthorized,
+
+	/// The libaziot-keys in use does not support the operation.
+	UnsupportedOperation,
+
+	/// libaziot-keys could not use an external resource, such as an HSM. Retrying the request later might succeed.
+	BackendUnavailable,
+
+	/// The server encountered an internal error.
+	Internal,
+
+	/// An error code that this version of the crate does not recognize.
+	#[serde(other)]
+	Unknown,
+}
+
+impl Default for ErrorCode {
+	fn default() -> Self {
+		ErrorCode::Unknown
+	}
+}
+
 pub mod create_key_if_not_exists {
 	#[derive(Debug, serde::Deserialize, serde::Serialize)]
 	pub struct Request {
@@ -63,6 +105,14 @@ pub mod decrypt {
 			iv: http_common::ByteString,
 			aad: http_common::ByteString,
 		},
+
+		#[serde(rename = "AEAD_GENERATED_NONCE")]
+		AeadGeneratedNonce {
+			aad: http_common::ByteString,
+		},
+
+		#[serde(rename = "RSA_OAEP")]
+		RsaOaep,
 	}
 
 	#[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -71,6 +121,36 @@ pub mod decrypt {
 	}
 }
 
+pub mod derive_shared_secret {
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct Request {
+		#[serde(rename = "keyHandle")]
+		pub key_handle: aziot_key_common::KeyHandle,
+
+		/// The peer's public point in uncompressed form.
+		#[serde(rename = "peerPublicKey")]
+		pub peer_public_key: http_common::ByteString,
+
+		/// If set, the shared secret is stored as a new key with this ID instead of being returned.
+		#[serde(rename = "derivedKeyId")]
+		pub derived_key_id: Option<String>,
+	}
+
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	#[serde(untagged)]
+	pub enum Response {
+		SharedSecret {
+			#[serde(rename = "sharedSecret")]
+			shared_secret: http_common::ByteString,
+		},
+
+		DerivedKey {
+			#[serde(rename = "keyHandle")]
+			handle: aziot_key_common::KeyHandle,
+		},
+	}
+}
+
 pub mod encrypt {
 	#[derive(Debug, serde::Deserialize, serde::Serialize)]
 	pub struct Request {
@@ -91,6 +171,14 @@ pub mod encrypt {
 			iv: http_common::ByteString,
 			aad: http_common::ByteString,
 		},
+
+		#[serde(rename = "AEAD_GENERATED_NONCE")]
+		AeadGeneratedNonce {
+			aad: http_common::ByteString,
+		},
+
+		#[serde(rename = "RSA_OAEP")]
+		RsaOaep,
 	}
 
 	#[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -112,6 +200,34 @@ pub mod get_key_pair_public_parameter {
 	}
 }
 
+pub mod list_keys {
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct Response {
+		pub keys: Vec<KeyInfo>,
+	}
+
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct KeyInfo {
+		#[serde(rename = "keyId")]
+		pub id: String,
+
+		pub kind: KeyKind,
+
+		pub algorithm: String,
+
+		pub backend: String,
+	}
+
+	#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+	pub enum KeyKind {
+		#[serde(rename = "key")]
+		Key,
+
+		#[serde(rename = "keyPair")]
+		KeyPair,
+	}
+}
+
 pub mod load_key_pair {
 	#[derive(Debug, serde::Deserialize, serde::Serialize)]
 	pub struct Response {
@@ -120,6 +236,43 @@ pub mod load_key_pair {
 	}
 }
 
+pub mod revoke_key_handles {
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct Request {
+		#[serde(rename = "keyId")]
+		pub id: String,
+
+		/// Keys and key pairs have separate IDs, so a key and a key pair with the same ID have separate handles.
+		pub kind: crate::list_keys::KeyKind,
+	}
+}
+
+pub mod rewrap {
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct Request {
+		#[serde(rename = "keyHandle")]
+		pub key_handle: aziot_key_common::KeyHandle,
+
+		#[serde(flatten)]
+		pub parameters: crate::decrypt::Parameters,
+
+		pub ciphertext: http_common::ByteString,
+	}
+
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct Response {
+		pub ciphertext: http_common::ByteString,
+	}
+}
+
+pub mod rotate_key {
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct Request {
+		#[serde(rename = "keyHandle")]
+		pub key_handle: aziot_key_common::KeyHandle,
+	}
+}
+
 pub mod sign {
 	#[derive(Debug, serde::Deserialize, serde::Serialize)]
 	pub struct Request {
@@ -138,6 +291,11 @@ pub mod sign {
 			digest: http_common::ByteString,
 		},
 
+		#[serde(rename = "EDDSA")]
+		EdDsa {
+			message: http_common::ByteString,
+		},
+
 		#[serde(rename = "RSA_PKCS1")]
 		RsaPkcs1 {
 			#[serde(rename = "messageDigestAlgorithm")]
@@ -147,6 +305,18 @@ pub mod sign {
 			message: http_common::ByteString,
 		},
 
+		#[serde(rename = "RSA_PSS")]
+		RsaPss {
+			#[serde(rename = "maskGenerationFunction")]
+			mask_generation_function: String,
+
+			#[serde(rename = "saltLength")]
+			salt_len: usize,
+
+			#[serde(rename = "message")]
+			message: http_common::ByteString,
+		},
+
 		#[serde(rename = "HMAC-SHA256")]
 		HmacSha256 {
 			message: http_common::ByteString,
@@ -158,3 +328,43 @@ pub mod sign {
 		pub signature: http_common::ByteString,
 	}
 }
+
+pub mod verify {
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct Request {
+		#[serde(rename = "keyHandle")]
+		pub key_handle: aziot_key_common::KeyHandle,
+
+		#[serde(flatten)]
+		pub parameters: super::sign::Parameters,
+
+		pub signature: http_common::ByteString,
+	}
+
+	#[derive(Debug, serde::Deserialize, serde::Serialize)]
+	pub struct Response {
+		pub ok: bool,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Error, ErrorCode};
+
+	#[test]
+	fn error_code() {
+		let err: Error = serde_json::from_str(r#"{"code":"KeyNotFound","message":"key not found"}"#).unwrap();
+		assert_eq!(err.code, ErrorCode::KeyNotFound);
+
+		// Response from a server that predates error codes
+		let err: Error = serde_json::from_str(r#"{"message":"key not found"}"#).unwrap();
+		assert_eq!(err.code, ErrorCode::Unknown);
+
+		// Response from a server that has error codes this crate does not know about
+		let err: Error = serde_json::from_str(r#"{"code":"SomeNewCode","message":"something"}"#).unwrap();
+		assert_eq!(err.code, ErrorCode::Unknown);
+
+		let err = Error { code: ErrorCode::InvalidHandle, message: "key handle is invalid: expired".into() };
+		assert_eq!(serde_json::to_string(&err).unwrap(), r#"{"code":"InvalidHandle","message":"key handle is invalid: expired"}"#);
+	}
+}
@@ -5,6 +5,26 @@
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct KeyHandle(pub String);
 
+/// A key or key pair that is known to the keys service.
+#[derive(Clone, Debug)]
+pub struct KeyInfo {
+	pub id: String,
+	pub kind: KeyKind,
+
+	/// The algorithm of the key, as reported by libaziot-keys. For example `"ec-p256"` or `"rsa-2048"` for key pairs,
+	/// and `"symmetric-256"` for keys.
+	pub algorithm: String,
+
+	/// Where the key is stored, as reported by libaziot-keys. For example `"filesystem"`, `"pkcs11"` or `"preloaded"`.
+	pub backend: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyKind {
+	Key,
+	KeyPair,
+}
+
 #[derive(Clone, Debug)]
 pub enum CreateKeyValue {
 	Generate { length: usize },
@@ -18,12 +38,19 @@ pub enum SignMechanism {
 	Ecdsa,
 
 
+	// Ed25519 keys
+
+	/// EdDSA signs the whole message instead of a digest.
+	EdDsa,
+
+
 	// RSA keys
 
 	RsaPkcs1 {
 		message_digest: RsaPkcs1MessageDigest,
 	},
 
+	/// The digest must have been computed with the same hash algorithm as the one used by the mask generation function.
 	RsaPss {
 		mask_generation_function: RsaPssMaskGenerationFunction,
 		salt_len: usize,
@@ -58,5 +85,13 @@ pub enum EncryptMechanism {
 	Aead {
 		iv: Vec<u8>,
 		aad: Vec<u8>,
-	}
+	},
+
+	/// AEAD where the Keys Service generates a random nonce for every encryption and stores it in the ciphertext.
+	AeadGeneratedNonce {
+		aad: Vec<u8>,
+	},
+
+	/// RSA-OAEP with SHA-256. Only valid for RSA key pairs.
+	RsaOaep,
 }
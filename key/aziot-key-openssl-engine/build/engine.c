@@ -8,6 +8,8 @@
 
 #include <openssl/engine.h>
 
+#include <openssl/evp.h>
+
 #include <openssl/rsa.h>
 
 /**
@@ -51,3 +53,12 @@ void aziot_key_freef_rsa_ex_data(void* parent, void* ptr, CRYPTO_EX_DATA* ad, in
 int aziot_key_get_rsa_ex_index() {
 	return RSA_get_ex_new_index(0, NULL, NULL, aziot_key_dupf_rsa_ex_data, aziot_key_freef_rsa_ex_data);
 }
+
+#if OPENSSL_VERSION_NUMBER >= 0x30000000L
+int aziot_key_dupf_evp_pkey_ex_data(CRYPTO_EX_DATA *to, const CRYPTO_EX_DATA *from, void **from_d, int idx, long argl, void *argp);
+void aziot_key_freef_evp_pkey_ex_data(void* parent, void* ptr, CRYPTO_EX_DATA* ad, int idx, long argl, void* argp);
+
+int aziot_key_get_evp_pkey_ex_index() {
+	return EVP_PKEY_get_ex_new_index(0, NULL, NULL, aziot_key_dupf_evp_pkey_ex_data, aziot_key_freef_evp_pkey_ex_data);
+}
+#endif
@@ -0,0 +1,280 @@
+// Unlike EC_KEY and RSA, an Ed25519 EVP_PKEY has no inner key object that can hold ex data.
+//
+// With openssl 3.0, the key's client and handle are held as ex data of the EVP_PKEY itself, and are freed along with it.
+//
+// Older versions of openssl do not support ex data on EVP_PKEY, and have no callback for when the key is freed.
+// So the client and handle are looked up by the raw public key instead. Loading the same key again replaces its entry,
+// so the map holds at most one entry per key pair in the Keys Service.
+
+#[cfg(ossl300)]
+impl crate::ex_data::HasExData<crate::ex_data::KeyExData> for openssl_sys::EVP_PKEY {
+	unsafe fn index() -> openssl::ex_data::Index<Self, crate::ex_data::KeyExData> {
+		crate::ex_data::ex_indices().evp_pkey
+	}
+}
+
+#[cfg(ossl300)]
+#[no_mangle]
+#[allow(clippy::similar_names)]
+unsafe extern "C" fn aziot_key_dupf_evp_pkey_ex_data(
+	_to: *mut openssl_sys::CRYPTO_EX_DATA,
+	_from: *const openssl_sys::CRYPTO_EX_DATA,
+	from_d: *mut std::ffi::c_void,
+	idx: std::os::raw::c_int,
+	_argl: std::os::raw::c_long,
+	_argp: *mut std::ffi::c_void,
+) -> std::os::raw::c_int {
+	crate::ex_data::dup::<openssl_sys::EVP_PKEY, crate::ex_data::KeyExData>(from_d, idx);
+	1
+}
+
+#[cfg(ossl300)]
+#[no_mangle]
+#[allow(clippy::similar_names)]
+unsafe extern "C" fn aziot_key_freef_evp_pkey_ex_data(
+	_parent: *mut std::ffi::c_void,
+	ptr: *mut std::ffi::c_void,
+	_ad: *mut openssl_sys::CRYPTO_EX_DATA,
+	idx: std::os::raw::c_int,
+	_argl: std::os::raw::c_long,
+	_argp: *mut std::ffi::c_void,
+) {
+	crate::ex_data::free::<openssl_sys::EVP_PKEY, crate::ex_data::KeyExData>(ptr, idx);
+}
+
+#[cfg(not(ossl300))]
+lazy_static::lazy_static! {
+	static ref KEYS: std::sync::Mutex<std::collections::BTreeMap<Vec<u8>, crate::ex_data::KeyExData>> = std::sync::Mutex::default();
+}
+
+/// Associates the key's client and handle with the key.
+#[cfg(ossl300)]
+unsafe fn set_key_ex_data(
+	openssl_key_raw: *mut openssl_sys::EVP_PKEY,
+	_public_key: &[u8],
+	key_ex_data: crate::ex_data::KeyExData,
+) -> Result<(), openssl2::Error> {
+	crate::ex_data::set(openssl_key_raw, key_ex_data)
+}
+
+/// Associates the key's client and handle with the key.
+#[cfg(not(ossl300))]
+unsafe fn set_key_ex_data(
+	_openssl_key_raw: *mut openssl_sys::EVP_PKEY,
+	public_key: &[u8],
+	key_ex_data: crate::ex_data::KeyExData,
+) -> Result<(), openssl2::Error> {
+	let mut keys = KEYS.lock().expect("ed25519 keys mutex poisoned");
+	keys.insert(public_key.to_owned(), key_ex_data);
+	Ok(())
+}
+
+/// Gets the client and handle of the key, or `None` if the key was not loaded from the Keys Service.
+#[cfg(ossl300)]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn get_key_ex_data(
+	key: &openssl::pkey::PKeyRef<openssl::pkey::Private>,
+) -> Result<Option<crate::ex_data::KeyExData>, Box<dyn std::error::Error>> {
+	let key = foreign_types_shared::ForeignTypeRef::as_ptr(key);
+	let ex_index = crate::ex_data::ex_indices().evp_pkey.as_raw();
+
+	let key_ex_data: *const crate::ex_data::KeyExData = openssl_sys2::EVP_PKEY_get_ex_data(key, ex_index) as _;
+	Ok(key_ex_data.as_ref().map(|crate::ex_data::KeyExData { client, handle }| crate::ex_data::KeyExData {
+		client: client.clone(),
+		handle: handle.clone(),
+	}))
+}
+
+/// Gets the client and handle of the key, or `None` if the key was not loaded from the Keys Service.
+#[cfg(not(ossl300))]
+unsafe fn get_key_ex_data(
+	key: &openssl::pkey::PKeyRef<openssl::pkey::Private>,
+) -> Result<Option<crate::ex_data::KeyExData>, Box<dyn std::error::Error>> {
+	let public_key = key.raw_public_key()?;
+
+	let keys = KEYS.lock().expect("ed25519 keys mutex poisoned");
+	Ok(keys.get(&public_key).map(|crate::ex_data::KeyExData { client, handle }| crate::ex_data::KeyExData {
+		client: client.clone(),
+		handle: handle.clone(),
+	}))
+}
+
+pub(super) unsafe fn new_public_key(
+	e: *mut openssl_sys::ENGINE,
+	public_key: &[u8],
+	key_ex_data: Option<crate::ex_data::KeyExData>,
+) -> Result<*mut openssl_sys::EVP_PKEY, openssl2::Error> {
+	// Passing in the engine makes openssl use the engine's pkey method for this key, rather than its built-in implementation.
+	let openssl_key_raw =
+		openssl2::openssl_returns_nonnull(openssl_sys::EVP_PKEY_new_raw_public_key(
+			openssl_sys::EVP_PKEY_ED25519,
+			e,
+			public_key.as_ptr(),
+			public_key.len(),
+		))?;
+
+	if let Some(key_ex_data) = key_ex_data {
+		if let Err(err) = set_key_ex_data(openssl_key_raw, public_key, key_ex_data) {
+			openssl_sys::EVP_PKEY_free(openssl_key_raw);
+			return Err(err);
+		}
+	}
+
+	Ok(openssl_key_raw)
+}
+
+static mut OPENSSL_ED25519_DIGESTSIGN: Option<unsafe extern "C" fn(
+	ctx: *mut openssl_sys::EVP_MD_CTX,
+	sig: *mut std::os::raw::c_uchar,
+	siglen: *mut usize,
+	tbs: *const std::os::raw::c_uchar,
+	tbslen: usize,
+) -> std::os::raw::c_int> = None;
+
+pub(super) unsafe fn get_evp_ed25519_method() -> Result<*const openssl_sys2::EVP_PKEY_METHOD, openssl2::Error> {
+	let openssl_method = openssl2::openssl_returns_nonnull_const(openssl_sys2::EVP_PKEY_meth_find(openssl_sys::EVP_PKEY_ED25519))?;
+	let result =
+		openssl2::openssl_returns_nonnull(
+			openssl_sys2::EVP_PKEY_meth_new(openssl_sys::EVP_PKEY_ED25519, openssl_sys2::EVP_PKEY_FLAG_SIGCTX_CUSTOM))?;
+	openssl_sys2::EVP_PKEY_meth_copy(result, openssl_method);
+
+	openssl_sys2::EVP_PKEY_meth_get_digestsign(openssl_method, &mut OPENSSL_ED25519_DIGESTSIGN);
+	openssl_sys2::EVP_PKEY_meth_set_digestsign(result, Some(evp_ed25519_digestsign));
+
+	Ok(result)
+}
+
+unsafe extern "C" fn evp_ed25519_digestsign(
+	ctx: *mut openssl_sys::EVP_MD_CTX,
+	sig: *mut std::os::raw::c_uchar,
+	siglen: *mut usize,
+	tbs: *const std::os::raw::c_uchar,
+	tbslen: usize,
+) -> std::os::raw::c_int {
+	const SIGNATURE_LEN: usize = 64;
+
+	let result = super::r#catch(Some(|| super::Error::ED25519_SIGN), || {
+		let pkey_ctx = openssl2::openssl_returns_nonnull(openssl_sys2::EVP_MD_CTX_pkey_ctx(ctx))?;
+		let private_key = openssl2::openssl_returns_nonnull(openssl_sys2::EVP_PKEY_CTX_get0_pkey(pkey_ctx))?;
+		let private_key: &openssl::pkey::PKeyRef<openssl::pkey::Private> = foreign_types_shared::ForeignTypeRef::from_ptr(private_key);
+
+		let key_ex_data = get_key_ex_data(private_key)?;
+		let crate::ex_data::KeyExData { client, handle } =
+			if let Some(key_ex_data) = key_ex_data {
+				key_ex_data
+			}
+			else {
+				let openssl_ed25519_digestsign =
+					OPENSSL_ED25519_DIGESTSIGN.expect("OPENSSL_ED25519_DIGESTSIGN must have been set by get_evp_ed25519_method earlier");
+				match openssl_ed25519_digestsign(ctx, sig, siglen, tbs, tbslen) {
+					result if result <= 0 => return Err(format!("OPENSSL_ED25519_DIGESTSIGN returned {}", result).into()),
+					_ => return Ok(()),
+				}
+			};
+
+		// openssl calls this once with a null signature buffer to query the length of the signature.
+		if sig.is_null() {
+			*siglen = SIGNATURE_LEN;
+			return Ok(());
+		}
+
+		let mechanism = aziot_key_common::SignMechanism::EdDsa;
+
+		let message = std::slice::from_raw_parts(tbs, tbslen);
+
+		let signature = client.sign(&handle, mechanism, message)?;
+		let signature_len = signature.len();
+
+		if *siglen < signature_len {
+			return Err(format!("openssl expected signature of length <= {} but ks returned a signature of length {}", *siglen, signature_len).into());
+		}
+
+		let signature_out = std::slice::from_raw_parts_mut(sig, *siglen);
+		signature_out[..signature_len].copy_from_slice(&signature);
+		*siglen = signature_len;
+
+		Ok(())
+	});
+	match result {
+		Ok(()) => 1,
+		Err(()) => 0,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	/// Signs `message` with `key` by calling `evp_ed25519_digestsign` the same way openssl does.
+	unsafe fn digestsign(key: &openssl::pkey::PKeyRef<openssl::pkey::Private>, message: &[u8]) -> Vec<u8> {
+		let _ = super::get_evp_ed25519_method().unwrap();
+
+		let ctx = openssl2::openssl_returns_nonnull(openssl_sys::EVP_MD_CTX_new()).unwrap();
+		openssl2::openssl_returns_1(openssl_sys::EVP_DigestSignInit(
+			ctx,
+			std::ptr::null_mut(),
+			std::ptr::null(),
+			std::ptr::null_mut(),
+			foreign_types_shared::ForeignTypeRef::as_ptr(key),
+		)).unwrap();
+
+		let mut signature_len = 0;
+		assert_eq!(super::evp_ed25519_digestsign(ctx, std::ptr::null_mut(), &mut signature_len, message.as_ptr(), message.len()), 1);
+
+		let mut signature = vec![0_u8; signature_len];
+		assert_eq!(super::evp_ed25519_digestsign(ctx, signature.as_mut_ptr(), &mut signature_len, message.as_ptr(), message.len()), 1);
+		signature.truncate(signature_len);
+
+		openssl_sys::EVP_MD_CTX_free(ctx);
+
+		signature
+	}
+
+	#[test]
+	fn sign_with_openssl_key() {
+		let key = openssl::pkey::PKey::generate_ed25519().unwrap();
+		let message = b"hello, world";
+
+		let signature = unsafe { digestsign(&key, message) };
+		assert_eq!(signature.len(), 64);
+
+		let mut verifier = openssl::sign::Verifier::new_without_digest(&key).unwrap();
+		assert!(verifier.verify_oneshot(&signature, message).unwrap());
+
+		let mut verifier = openssl::sign::Verifier::new_without_digest(&key).unwrap();
+		assert!(!verifier.verify_oneshot(&signature, b"goodbye, world").unwrap());
+	}
+
+	#[cfg(ossl300)]
+	#[test]
+	fn key_ex_data_is_freed_with_key() {
+		let private_key = openssl::pkey::PKey::generate_ed25519().unwrap();
+		let public_key = private_key.raw_public_key().unwrap();
+
+		let client = std::sync::Arc::new(aziot_key_client::Client::new(http_common::Connector::Unix {
+			socket_path: std::path::Path::new("/nonexistent.sock").into(),
+		}));
+		let handle = aziot_key_common::KeyHandle("key-handle".to_owned());
+
+		unsafe {
+			let key = super::new_public_key(
+				std::ptr::null_mut(),
+				&public_key,
+				Some(crate::ex_data::KeyExData { client: client.clone(), handle }),
+			).unwrap();
+			assert_eq!(std::sync::Arc::strong_count(&client), 2);
+
+			{
+				let key: &openssl::pkey::PKeyRef<openssl::pkey::Private> = foreign_types_shared::ForeignTypeRef::from_ptr(key);
+				let key_ex_data = super::get_key_ex_data(key).unwrap().unwrap();
+				assert!(std::sync::Arc::ptr_eq(&key_ex_data.client, &client));
+				assert_eq!(key_ex_data.handle.0, "key-handle");
+			}
+
+			openssl_sys::EVP_PKEY_free(key);
+		}
+
+		assert_eq!(std::sync::Arc::strong_count(&client), 1);
+
+		let key: &openssl::pkey::PKeyRef<openssl::pkey::Private> = &private_key;
+		assert!(unsafe { super::get_key_ex_data(key) }.unwrap().is_none());
+	}
+}
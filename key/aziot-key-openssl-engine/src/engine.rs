@@ -171,6 +171,14 @@ unsafe extern "C" fn engine_load_privkey(
 				openssl_key_raw
 			},
 
+			#[cfg(ossl111)]
+			"ED25519" => {
+				let public_key = client.get_key_pair_public_parameter(&key_handle, "ec-point")?;
+				let public_key = base64::decode(&public_key)?;
+
+				super::ed25519::new_public_key(e, &public_key, Some(key_ex_data))?
+			},
+
 			key_algorithm => return Err(format!("unrecognized key algorithm {}", key_algorithm).into()),
 		};
 
@@ -251,6 +259,14 @@ unsafe extern "C" fn engine_load_pubkey(
 				openssl_key_raw
 			},
 
+			#[cfg(ossl111)]
+			"ED25519" => {
+				let public_key = client.get_key_pair_public_parameter(&key_handle, "ec-point")?;
+				let public_key = base64::decode(&public_key)?;
+
+				super::ed25519::new_public_key(std::ptr::null_mut(), &public_key, None)?
+			},
+
 			key_algorithm => return Err(format!("unrecognized key algorithm {}", key_algorithm).into()),
 		};
 
@@ -281,6 +297,8 @@ unsafe extern "C" fn engine_pkey_meths(
 	let result = super::r#catch(Some(|| super::Error::ENGINE_PKEY_METHS), || {
 		const SUPPORTED_NIDS: &[std::os::raw::c_int] = &[
 			openssl_sys::EVP_PKEY_EC,
+			#[cfg(ossl111)]
+			openssl_sys::EVP_PKEY_ED25519,
 			openssl_sys::EVP_PKEY_RSA,
 		];
 
@@ -302,6 +320,12 @@ unsafe extern "C" fn engine_pkey_meths(
 					Ok(1)
 				},
 
+				#[cfg(ossl111)]
+				openssl_sys::EVP_PKEY_ED25519 => {
+					*pmeth = super::ed25519::get_evp_ed25519_method()?;
+					Ok(1)
+				},
+
 				openssl_sys::EVP_PKEY_RSA => {
 					*pmeth = super::rsa::get_evp_rsa_method()?;
 					Ok(1)
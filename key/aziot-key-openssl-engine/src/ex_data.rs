@@ -3,6 +3,8 @@ pub(crate) struct ExIndices {
 	pub(crate) engine: openssl::ex_data::Index<openssl_sys::ENGINE, crate::engine::Engine>,
 	pub(crate) ec_key: openssl::ex_data::Index<openssl_sys::EC_KEY, KeyExData>,
 	pub(crate) rsa: openssl::ex_data::Index<openssl_sys::RSA, KeyExData>,
+	#[cfg(ossl300)]
+	pub(crate) evp_pkey: openssl::ex_data::Index<openssl_sys::EVP_PKEY, KeyExData>,
 }
 
 pub(crate) unsafe fn ex_indices() -> ExIndices {
@@ -17,6 +19,8 @@ pub(crate) unsafe fn ex_indices() -> ExIndices {
 				fn aziot_key_get_engine_ex_index() -> std::os::raw::c_int;
 				fn aziot_key_get_ec_key_ex_index() -> std::os::raw::c_int;
 				fn aziot_key_get_rsa_ex_index() -> std::os::raw::c_int;
+				#[cfg(ossl300)]
+				fn aziot_key_get_evp_pkey_ex_index() -> std::os::raw::c_int;
 			}
 
 			let engine_ex_index = aziot_key_get_engine_ex_index();
@@ -34,10 +38,21 @@ pub(crate) unsafe fn ex_indices() -> ExIndices {
 				return Err(format!("could not register RSA ex index: {}", openssl::error::ErrorStack::get()).into());
 			}
 
+			#[cfg(ossl300)]
+			let evp_pkey_ex_index = {
+				let evp_pkey_ex_index = aziot_key_get_evp_pkey_ex_index();
+				if evp_pkey_ex_index == -1 {
+					return Err(format!("could not register EVP_PKEY ex index: {}", openssl::error::ErrorStack::get()).into());
+				}
+				evp_pkey_ex_index
+			};
+
 			let ex_indices = ExIndices {
 				engine: openssl::ex_data::Index::from_raw(engine_ex_index),
 				ec_key: openssl::ex_data::Index::from_raw(ec_key_ex_index),
 				rsa: openssl::ex_data::Index::from_raw(rsa_ex_index),
+				#[cfg(ossl300)]
+				evp_pkey: openssl::ex_data::Index::from_raw(evp_pkey_ex_index),
 			};
 			RESULT = Box::into_raw(Box::new(ex_indices));
 
@@ -15,6 +15,9 @@
 
 mod ec_key;
 
+#[cfg(ossl111)]
+mod ed25519;
+
 mod engine;
 
 pub(crate) mod ex_data;
@@ -39,6 +42,8 @@ openssl_errors::openssl_errors! {
 
 			EC_SIGN("aziot_key_ec_sign");
 
+			ED25519_SIGN("aziot_key_ed25519_sign");
+
 			RSA_SIGN("aziot_key_rsa_sign");
 		}
 
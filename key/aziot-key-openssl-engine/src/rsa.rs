@@ -102,41 +102,40 @@ unsafe extern "C" fn evp_rsa_sign(
 				aziot_key_common::SignMechanism::RsaPkcs1 { message_digest }
 			},
 
-			// openssl_sys::RSA_PKCS1_PSS_PADDING => {
-			// 	let message_digest = match openssl_sys::EVP_MD_type(signature_md) {
-			// 		openssl_sys::NID_sha1 => aziot_key_common::SignMessageDigest::Sha1,
-			// 		openssl_sys::NID_sha224 => aziot_key_common::SignMessageDigest::Sha224,
-			// 		openssl_sys::NID_sha256 => aziot_key_common::SignMessageDigest::Sha256,
-			// 		openssl_sys::NID_sha384 => aziot_key_common::SignMessageDigest::Sha384,
-			// 		openssl_sys::NID_sha512 => aziot_key_common::SignMessageDigest::Sha512,
-			// 		nid => return Err(format!("unrecognized signature_md nid 0x{:08x}", nid).into()),
-			// 	};
-
-			// 	let mut rsa_mgf1_md = std::ptr::null();
-			// 	openssl2::openssl_returns_positive(openssl_sys2::EVP_PKEY_CTX_get_rsa_mgf1_md_f(ctx, &mut rsa_mgf1_md))?;
-
-			// 	let mut rsa_pss_salt_len = 0;
-			// 	openssl2::openssl_returns_positive(openssl_sys2::EVP_PKEY_CTX_get_rsa_pss_saltlen_f(ctx, &mut rsa_pss_salt_len))?;
-
-			// 	let rsa_pss_salt_len = match rsa_pss_salt_len {
-			// 		rsa_pss_salt_len if rsa_pss_salt_len > 0 => rsa_pss_salt_len,
-			// 		-1 => openssl_sys::EVP_MD_size(signature_md),
-			// 		rsa_pss_salt_len => return Err(format!("invalid rsa_pss_salt_len 0x{:08x}", rsa_pss_salt_len).into()),
-			// 	};
-
-			// 	(aziot_key_common::SignMechanism::RsaPss {
-			// 		mask_generation_function: match openssl_sys::EVP_MD_type(rsa_mgf1_md) {
-			// 			openssl_sys::NID_sha1 => aziot_key_common::RsaPssMaskGenerationFunction::Sha1,
-			// 			openssl_sys::NID_sha224 => aziot_key_common::RsaPssMaskGenerationFunction::Sha224,
-			// 			openssl_sys::NID_sha256 => aziot_key_common::RsaPssMaskGenerationFunction::Sha256,
-			// 			openssl_sys::NID_sha384 => aziot_key_common::RsaPssMaskGenerationFunction::Sha384,
-			// 			openssl_sys::NID_sha512 => aziot_key_common::RsaPssMaskGenerationFunction::Sha512,
-			// 			nid => return Err(format!("unrecognized rsa_mgf1_md nid 0x{:08x}", nid).into()),
-			// 		},
-
-			// 		salt_len: std::convert::TryInto::try_into(rsa_pss_salt_len).expect("c_int -> CK_ULONG"),
-			// 	}, message_digest)
-			// },
+			openssl_sys::RSA_PKCS1_PSS_PADDING => {
+				let mut signature_md = std::ptr::null();
+				openssl2::openssl_returns_positive(openssl_sys2::EVP_PKEY_CTX_get_signature_md_f(ctx, &mut signature_md))?;
+
+				let mut rsa_mgf1_md = std::ptr::null();
+				openssl2::openssl_returns_positive(openssl_sys2::EVP_PKEY_CTX_get_rsa_mgf1_md_f(ctx, &mut rsa_mgf1_md))?;
+
+				// The Key Service uses the same hash algorithm for the digest and the mask generation function.
+				if openssl_sys::EVP_MD_type(signature_md) != openssl_sys::EVP_MD_type(rsa_mgf1_md) {
+					return Err("RSA-PSS signature_md and rsa_mgf1_md must be the same".into());
+				}
+
+				let mut rsa_pss_salt_len = 0;
+				openssl2::openssl_returns_positive(openssl_sys2::EVP_PKEY_CTX_get_rsa_pss_saltlen_f(ctx, &mut rsa_pss_salt_len))?;
+
+				let rsa_pss_salt_len = match rsa_pss_salt_len {
+					rsa_pss_salt_len if rsa_pss_salt_len >= 0 => rsa_pss_salt_len,
+					-1 => openssl_sys::EVP_MD_size(signature_md),
+					rsa_pss_salt_len => return Err(format!("invalid rsa_pss_salt_len 0x{:08x}", rsa_pss_salt_len).into()),
+				};
+
+				aziot_key_common::SignMechanism::RsaPss {
+					mask_generation_function: match openssl_sys::EVP_MD_type(rsa_mgf1_md) {
+						openssl_sys::NID_sha1 => aziot_key_common::RsaPssMaskGenerationFunction::Sha1,
+						openssl_sys::NID_sha224 => aziot_key_common::RsaPssMaskGenerationFunction::Sha224,
+						openssl_sys::NID_sha256 => aziot_key_common::RsaPssMaskGenerationFunction::Sha256,
+						openssl_sys::NID_sha384 => aziot_key_common::RsaPssMaskGenerationFunction::Sha384,
+						openssl_sys::NID_sha512 => aziot_key_common::RsaPssMaskGenerationFunction::Sha512,
+						nid => return Err(format!("unrecognized rsa_mgf1_md nid 0x{:08x}", nid).into()),
+					},
+
+					salt_len: std::convert::TryInto::try_into(rsa_pss_salt_len).expect("c_int -> usize"),
+				}
+			},
 
 			padding => return Err(format!("unexpected padding {}", padding).into()),
 		};
@@ -0,0 +1,218 @@
+/// The identity of the process that sent a request to the server.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Caller {
+	/// The caller connected over a Unix socket and its uid was read from the socket's peer credentials.
+	Uid(u32),
+
+	/// The caller's identity could not be determined, such as when it connected over TCP.
+	Unknown,
+}
+
+impl std::fmt::Display for Caller {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Caller::Uid(uid) => write!(f, "caller with uid {}", uid),
+			Caller::Unknown => f.write_str("unknown caller"),
+		}
+	}
+}
+
+/// An operation that the ACL can grant on a key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+	/// Create or import a key or key pair.
+	Create,
+
+	/// Sign with a key or key pair.
+	Sign,
+
+	/// Verify a signature with a key or key pair.
+	Verify,
+
+	/// Encrypt with a key or key pair.
+	Encrypt,
+
+	/// Decrypt with a key or key pair.
+	Decrypt,
+
+	/// Derive a shared secret or a new key from a key pair.
+	Derive,
+
+	/// Rotate a key to a new version.
+	Rotate,
+
+	/// Revoke the outstanding handles of a key or key pair.
+	Revoke,
+
+	/// Delete a key or key pair.
+	Delete,
+}
+
+impl std::fmt::Display for Operation {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Operation::Create => f.write_str("create"),
+			Operation::Sign => f.write_str("sign with"),
+			Operation::Verify => f.write_str("verify with"),
+			Operation::Encrypt => f.write_str("encrypt with"),
+			Operation::Decrypt => f.write_str("decrypt with"),
+			Operation::Derive => f.write_str("derive from"),
+			Operation::Rotate => f.write_str("rotate"),
+			Operation::Revoke => f.write_str("revoke handles of"),
+			Operation::Delete => f.write_str("delete"),
+		}
+	}
+}
+
+/// The ACL that decides which callers may use which keys.
+///
+/// Any operation that is not explicitly allowed by one of the principals is denied.
+#[derive(Debug, Default)]
+pub(crate) struct Acl {
+	principals: Vec<(u32, crate::settings::Principal)>,
+}
+
+impl Acl {
+	/// Creates an ACL from the given principals.
+	///
+	/// Principals that are identified by a user name are resolved to that user's uid.
+	/// A principal whose user does not exist is skipped, so that the default principals of services that are not installed
+	/// do not prevent aziot-keyd from starting.
+	pub(crate) fn new(principals: Vec<crate::settings::Principal>) -> Self {
+		let principals =
+			principals.into_iter()
+			.filter_map(|principal| {
+				let uid = match (principal.uid, &principal.name) {
+					(Some(uid), _) => uid,
+
+					(None, Some(name)) => match uid_of_user(name) {
+						Ok(Some(uid)) => uid,
+
+						Ok(None) => {
+							eprintln!("user {:?} of principal does not exist, so the principal will not match any caller", name);
+							return None;
+						},
+
+						Err(err) => {
+							eprintln!("could not look up uid of user {:?} of principal, so the principal will not match any caller: {}", name, err);
+							return None;
+						},
+					},
+
+					(None, None) => return None,
+				};
+
+				Some((uid, principal))
+			})
+			.collect();
+
+		Acl {
+			principals,
+		}
+	}
+
+	/// Checks that `caller` may use the key with the given ID.
+	///
+	/// If `operation` is `None`, the caller only needs to be allowed some operation on the key.
+	/// This is used for operations that only identify a key, like loading a key pair or reading its public parameters.
+	pub(crate) fn authorize(&self, caller: Caller, key_id: &str, operation: Option<Operation>) -> Result<(), crate::Error> {
+		let uid = match caller {
+			Caller::Uid(uid) => uid,
+			Caller::Unknown => return Err(crate::Error::Unauthorized(caller, key_id.to_owned(), operation)),
+		};
+
+		let allowed = self.principals.iter().any(|(principal_uid, principal)| {
+			*principal_uid == uid &&
+				principal.keys.iter().any(|pattern| key_id_matches(pattern, key_id)) &&
+				operation.map_or(!principal.operations.is_empty(), |operation| principal.operations.contains(&operation))
+		});
+		if allowed {
+			Ok(())
+		}
+		else {
+			Err(crate::Error::Unauthorized(caller, key_id.to_owned(), operation))
+		}
+	}
+}
+
+/// A pattern that ends with `*` matches any key ID that starts with the rest of the pattern.
+/// Any other pattern matches only the key ID that is equal to it.
+fn key_id_matches(pattern: &str, key_id: &str) -> bool {
+	if let Some(prefix) = pattern.strip_suffix('*') {
+		key_id.starts_with(prefix)
+	}
+	else {
+		key_id == pattern
+	}
+}
+
+/// Looks up the uid of the user with the given name. Returns `Ok(None)` if there is no such user.
+fn uid_of_user(name: &str) -> std::io::Result<Option<u32>> {
+	let name = std::ffi::CString::new(name).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+
+	let mut buf = vec![0_u8; 1024];
+
+	loop {
+		let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+		let mut result = std::ptr::null_mut();
+		let err = unsafe { libc::getpwnam_r(name.as_ptr(), &mut passwd, buf.as_mut_ptr() as _, buf.len(), &mut result) };
+		match err {
+			0 if result.is_null() => return Ok(None),
+			0 => return Ok(Some(passwd.pw_uid)),
+			libc::ERANGE => buf.resize(buf.len() * 2, 0),
+			err => return Err(std::io::Error::from_raw_os_error(err)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Acl, Caller, Operation};
+
+	#[test]
+	fn authorize() {
+		let acl = Acl::new(vec![
+			crate::settings::Principal {
+				uid: Some(1001),
+				name: None,
+				keys: vec!["device-id".to_owned(), "module-*".to_owned()],
+				operations: vec![Operation::Create, Operation::Sign],
+			},
+			crate::settings::Principal {
+				uid: Some(1002),
+				name: None,
+				keys: vec!["*".to_owned()],
+				operations: vec![Operation::Decrypt],
+			},
+			crate::settings::Principal {
+				uid: None,
+				name: Some("root".to_owned()),
+				keys: vec!["root-key".to_owned()],
+				operations: vec![Operation::Sign],
+			},
+			crate::settings::Principal {
+				uid: None,
+				name: Some("aziot-keyd-no-such-user".to_owned()),
+				keys: vec!["*".to_owned()],
+				operations: vec![Operation::Sign],
+			},
+		]);
+
+		assert!(acl.authorize(Caller::Uid(1001), "device-id", Some(Operation::Create)).is_ok());
+		assert!(acl.authorize(Caller::Uid(1001), "module-foo", Some(Operation::Sign)).is_ok());
+		assert!(acl.authorize(Caller::Uid(1001), "module-foo", None).is_ok());
+		assert!(acl.authorize(Caller::Uid(1001), "device-id", Some(Operation::Encrypt)).is_err());
+		assert!(acl.authorize(Caller::Uid(1001), "device-id-2", Some(Operation::Sign)).is_err());
+		assert!(acl.authorize(Caller::Uid(1001), "device-ca", None).is_err());
+
+		assert!(acl.authorize(Caller::Uid(1002), "anything", Some(Operation::Decrypt)).is_ok());
+		assert!(acl.authorize(Caller::Uid(1002), "anything", Some(Operation::Create)).is_err());
+
+		assert!(acl.authorize(Caller::Uid(0), "root-key", Some(Operation::Sign)).is_ok());
+		assert!(acl.authorize(Caller::Uid(0), "device-id", Some(Operation::Sign)).is_err());
+
+		assert!(acl.authorize(Caller::Uid(1003), "device-id", None).is_err());
+		assert!(acl.authorize(Caller::Unknown, "device-id", None).is_err());
+	}
+}
@@ -1,7 +1,12 @@
 #[derive(Debug)]
 pub enum Error {
+	BackendUnavailable(InternalError),
 	Internal(InternalError),
+	InvalidHandle(&'static str),
 	InvalidParameter(Option<(&'static str, Box<dyn std::error::Error + Send + Sync>)>),
+	KeyNotFound,
+	Unauthorized(crate::Caller, String, Option<crate::Operation>),
+	UnsupportedOperation(&'static str),
 }
 
 impl Error {
@@ -13,9 +18,15 @@ impl Error {
 impl std::fmt::Display for Error {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
+			Error::BackendUnavailable(_) => f.write_str("key backend is unavailable"),
 			Error::Internal(_) => f.write_str("internal error"),
+			Error::InvalidHandle(reason) => write!(f, "key handle is invalid: {}", reason),
 			Error::InvalidParameter(Some((name, _))) => write!(f, "parameter {:?} has an invalid value", name),
 			Error::InvalidParameter(None) => f.write_str("a parameter has an invalid value"),
+			Error::KeyNotFound => f.write_str("key not found"),
+			Error::Unauthorized(caller, key_id, Some(operation)) => write!(f, "{} is not authorized to {} key {:?}", caller, operation, key_id),
+			Error::Unauthorized(caller, key_id, None) => write!(f, "{} is not authorized to use key {:?}", caller, key_id),
+			Error::UnsupportedOperation(operation) => write!(f, "libaziot-keys does not support the {} operation", operation),
 		}
 	}
 }
@@ -23,9 +34,14 @@ impl std::fmt::Display for Error {
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
+			Error::BackendUnavailable(err) => Some(err),
 			Error::Internal(err) => Some(err),
+			Error::InvalidHandle(_) => None,
 			Error::InvalidParameter(Some((_, err))) => Some(&**err),
 			Error::InvalidParameter(None) => None,
+			Error::KeyNotFound => None,
+			Error::Unauthorized(_, _, _) => None,
+			Error::UnsupportedOperation(_) => None,
 		}
 	}
 }
@@ -37,10 +53,19 @@ pub enum InternalError {
 	CreateKeyPairIfNotExists(crate::keys::CreateKeyPairIfNotExistsError),
 	GetKeyPairPublicParameter(crate::keys::GetKeyPairPublicParameterError),
 	Decrypt(crate::keys::DecryptError),
+	DeleteKey(crate::keys::DeleteKeyError),
+	DeleteKeyPair(crate::keys::DeleteKeyPairError),
+	DeriveSharedSecret(crate::keys::DeriveSharedSecretError),
 	Encrypt(crate::keys::EncryptError),
 	GenerateNonce(openssl::error::ErrorStack),
+	ListKeys(crate::keys::ListKeysError),
+	LoadKey(crate::keys::LoadKeyError),
 	LoadKeyPair(crate::keys::LoadKeyPairError),
 	LoadLibrary(crate::keys::LoadLibraryError),
+	LoadRevocations(std::io::Error),
+	Rewrap(crate::keys::RewrapError),
+	RotateKey(crate::keys::RotateKeyError),
+	SaveRevocations(std::io::Error),
 	SetLibraryParameter(crate::keys::SetLibraryParameterError),
 	Sign(crate::keys::SignError),
 	Verify(crate::keys::VerifyError),
@@ -53,11 +78,20 @@ impl std::fmt::Display for InternalError {
 			InternalError::CreateKeyIfNotExistsImport(_) => f.write_str("could not import key"),
 			InternalError::CreateKeyPairIfNotExists(_) => f.write_str("could not create key pair"),
 			InternalError::Decrypt(_) => f.write_str("could not decrypt"),
+			InternalError::DeleteKey(_) => f.write_str("could not delete key"),
+			InternalError::DeleteKeyPair(_) => f.write_str("could not delete key pair"),
+			InternalError::DeriveSharedSecret(_) => f.write_str("could not derive shared secret"),
 			InternalError::Encrypt(_) => f.write_str("could not encrypt"),
 			InternalError::GetKeyPairPublicParameter(_) => f.write_str("could not get key pair parameter"),
 			InternalError::GenerateNonce(_) => f.write_str("could not generate nonce"),
+			InternalError::ListKeys(_) => f.write_str("could not list keys"),
+			InternalError::LoadKey(_) => f.write_str("could not load key"),
 			InternalError::LoadKeyPair(_) => f.write_str("could not load key pair"),
 			InternalError::LoadLibrary(_) => f.write_str("could not load libaziot-keys"),
+			InternalError::LoadRevocations(_) => f.write_str("could not load key handle revocations"),
+			InternalError::Rewrap(_) => f.write_str("could not rewrap ciphertext"),
+			InternalError::RotateKey(_) => f.write_str("could not rotate key"),
+			InternalError::SaveRevocations(_) => f.write_str("could not save key handle revocations"),
 			InternalError::SetLibraryParameter(_) => f.write_str("could not set parameter on libaziot-keys"),
 			InternalError::Sign(_) => f.write_str("could not sign"),
 			InternalError::Verify(_) => f.write_str("could not verify"),
@@ -72,11 +106,20 @@ impl std::error::Error for InternalError {
 			InternalError::CreateKeyIfNotExistsImport(err) => Some(err),
 			InternalError::CreateKeyPairIfNotExists(err) => Some(err),
 			InternalError::Decrypt(err) => Some(err),
+			InternalError::DeleteKey(err) => Some(err),
+			InternalError::DeleteKeyPair(err) => Some(err),
+			InternalError::DeriveSharedSecret(err) => Some(err),
 			InternalError::Encrypt(err) => Some(err),
 			InternalError::GetKeyPairPublicParameter(err) => Some(err),
 			InternalError::GenerateNonce(err) => Some(err),
+			InternalError::ListKeys(err) => Some(err),
+			InternalError::LoadKey(err) => Some(err),
 			InternalError::LoadKeyPair(err) => Some(err),
 			InternalError::LoadLibrary(err) => Some(err),
+			InternalError::LoadRevocations(err) => Some(err),
+			InternalError::Rewrap(err) => Some(err),
+			InternalError::RotateKey(err) => Some(err),
+			InternalError::SaveRevocations(err) => Some(err),
 			InternalError::SetLibraryParameter(err) => Some(err),
 			InternalError::Sign(err) => Some(err),
 			InternalError::Verify(err) => Some(err),
@@ -100,15 +143,27 @@ impl From<crate::keys::CreateKeyPairIfNotExistsError> for Error {
 	fn from(err: crate::keys::CreateKeyPairIfNotExistsError) -> Self {
 		match err.err.0 {
 			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::InvalidParameter(None),
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::CreateKeyPairIfNotExists(err)),
 			_ => Error::Internal(InternalError::CreateKeyPairIfNotExists(err)),
 		}
 	}
 }
 
+impl From<crate::keys::LoadKeyError> for Error {
+	fn from(err: crate::keys::LoadKeyError) -> Self {
+		match err.err.0 {
+			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::KeyNotFound,
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::LoadKey(err)),
+			_ => Error::Internal(InternalError::LoadKey(err)),
+		}
+	}
+}
+
 impl From<crate::keys::LoadKeyPairError> for Error {
 	fn from(err: crate::keys::LoadKeyPairError) -> Self {
 		match err.err.0 {
-			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::InvalidParameter(None),
+			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::KeyNotFound,
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::LoadKeyPair(err)),
 			_ => Error::Internal(InternalError::LoadKeyPair(err)),
 		}
 	}
@@ -122,6 +177,11 @@ impl From<crate::keys::GetKeyPairPublicParameterError> for Error {
 			} =>
 				Error::InvalidParameter(None),
 
+			crate::keys::GetKeyPairPublicParameterError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE),
+			} =>
+				Error::BackendUnavailable(InternalError::GetKeyPairPublicParameter(err)),
+
 			_ => Error::Internal(InternalError::GetKeyPairPublicParameter(err)),
 		}
 	}
@@ -131,6 +191,7 @@ impl From<crate::keys::CreateKeyIfNotExistsError> for Error {
 	fn from(err: crate::keys::CreateKeyIfNotExistsError) -> Self {
 		match err.err.0 {
 			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::InvalidParameter(None),
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::CreateKeyIfNotExistsGenerate(err)),
 			_ => Error::Internal(InternalError::CreateKeyIfNotExistsGenerate(err)),
 		}
 	}
@@ -140,6 +201,7 @@ impl From<crate::keys::ImportKeyError> for Error {
 	fn from(err: crate::keys::ImportKeyError) -> Self {
 		match err.err.0 {
 			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::InvalidParameter(None),
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::CreateKeyIfNotExistsImport(err)),
 			_ => Error::Internal(InternalError::CreateKeyIfNotExistsImport(err)),
 		}
 	}
@@ -149,6 +211,7 @@ impl From<crate::keys::SignError> for Error {
 	fn from(err: crate::keys::SignError) -> Self {
 		match err.err.0 {
 			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::InvalidParameter(None),
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::Sign(err)),
 			_ => Error::Internal(InternalError::Sign(err)),
 		}
 	}
@@ -158,6 +221,7 @@ impl From<crate::keys::VerifyError> for Error {
 	fn from(err: crate::keys::VerifyError) -> Self {
 		match err.err.0 {
 			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::InvalidParameter(None),
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::Verify(err)),
 			_ => Error::Internal(InternalError::Verify(err)),
 		}
 	}
@@ -167,6 +231,7 @@ impl From<crate::keys::EncryptError> for Error {
 	fn from(err: crate::keys::EncryptError) -> Self {
 		match err.err.0 {
 			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::InvalidParameter(None),
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::Encrypt(err)),
 			_ => Error::Internal(InternalError::Encrypt(err)),
 		}
 	}
@@ -176,7 +241,139 @@ impl From<crate::keys::DecryptError> for Error {
 	fn from(err: crate::keys::DecryptError) -> Self {
 		match err.err.0 {
 			crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER => Error::InvalidParameter(None),
+			crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => Error::BackendUnavailable(InternalError::Decrypt(err)),
 			_ => Error::Internal(InternalError::Decrypt(err)),
 		}
 	}
 }
+
+impl From<crate::keys::DeleteKeyPairError> for Error {
+	fn from(err: crate::keys::DeleteKeyPairError) -> Self {
+		match err {
+			crate::keys::DeleteKeyPairError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER),
+			} =>
+				Error::InvalidParameter(None),
+
+			crate::keys::DeleteKeyPairError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE),
+			} =>
+				Error::BackendUnavailable(InternalError::DeleteKeyPair(err)),
+
+			crate::keys::DeleteKeyPairError::UnsupportedOperation => Error::UnsupportedOperation("delete key pair"),
+
+			_ => Error::Internal(InternalError::DeleteKeyPair(err)),
+		}
+	}
+}
+
+impl From<crate::keys::DeleteKeyError> for Error {
+	fn from(err: crate::keys::DeleteKeyError) -> Self {
+		match err {
+			crate::keys::DeleteKeyError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER),
+			} =>
+				Error::InvalidParameter(None),
+
+			crate::keys::DeleteKeyError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE),
+			} =>
+				Error::BackendUnavailable(InternalError::DeleteKey(err)),
+
+			crate::keys::DeleteKeyError::UnsupportedOperation => Error::UnsupportedOperation("delete key"),
+
+			_ => Error::Internal(InternalError::DeleteKey(err)),
+		}
+	}
+}
+
+impl From<crate::keys::DeriveSharedSecretError> for Error {
+	fn from(err: crate::keys::DeriveSharedSecretError) -> Self {
+		match err {
+			crate::keys::DeriveSharedSecretError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER),
+			} =>
+				Error::InvalidParameter(None),
+
+			crate::keys::DeriveSharedSecretError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE),
+			} =>
+				Error::BackendUnavailable(InternalError::DeriveSharedSecret(err)),
+
+			crate::keys::DeriveSharedSecretError::UnsupportedOperation => Error::UnsupportedOperation("derive shared secret"),
+
+			_ => Error::Internal(InternalError::DeriveSharedSecret(err)),
+		}
+	}
+}
+
+impl From<crate::keys::RewrapError> for Error {
+	fn from(err: crate::keys::RewrapError) -> Self {
+		match err {
+			crate::keys::RewrapError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER),
+			} =>
+				Error::InvalidParameter(None),
+
+			crate::keys::RewrapError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE),
+			} =>
+				Error::BackendUnavailable(InternalError::Rewrap(err)),
+
+			crate::keys::RewrapError::UnsupportedOperation => Error::UnsupportedOperation("rewrap"),
+
+			_ => Error::Internal(InternalError::Rewrap(err)),
+		}
+	}
+}
+
+impl From<crate::keys::RotateKeyError> for Error {
+	fn from(err: crate::keys::RotateKeyError) -> Self {
+		match err {
+			crate::keys::RotateKeyError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER),
+			} =>
+				Error::InvalidParameter(None),
+
+			crate::keys::RotateKeyError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE),
+			} =>
+				Error::BackendUnavailable(InternalError::RotateKey(err)),
+
+			crate::keys::RotateKeyError::UnsupportedOperation => Error::UnsupportedOperation("rotate key"),
+
+			_ => Error::Internal(InternalError::RotateKey(err)),
+		}
+	}
+}
+
+impl From<crate::keys::ListKeysError> for Error {
+	fn from(err: crate::keys::ListKeysError) -> Self {
+		match err {
+			crate::keys::ListKeysError::Api {
+				err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE),
+			} =>
+				Error::BackendUnavailable(InternalError::ListKeys(err)),
+
+			crate::keys::ListKeysError::UnsupportedOperation => Error::UnsupportedOperation("list keys"),
+
+			_ => Error::Internal(InternalError::ListKeys(err)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn decrypt_error() {
+		// A tag mismatch is reported by libaziot-keys as an invalid parameter, not as a backend failure.
+		let err: super::Error = crate::keys::DecryptError { err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_INVALID_PARAMETER) }.into();
+		assert!(matches!(err, super::Error::InvalidParameter(None)), "{:?}", err);
+
+		let err: super::Error = crate::keys::DecryptError { err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_EXTERNAL) }.into();
+		assert!(matches!(err, super::Error::Internal(super::InternalError::Decrypt(_))), "{:?}", err);
+
+		let err: super::Error = crate::keys::DecryptError { err: crate::keys::KeysRawError(crate::keys::sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE) }.into();
+		assert!(matches!(err, super::Error::BackendUnavailable(super::InternalError::Decrypt(_))), "{:?}", err);
+	}
+}
@@ -1,6 +1,7 @@
 pub(super) fn handle(
 	req: hyper::Request<hyper::Body>,
 	inner: std::sync::Arc<aziot_keyd::Server>,
+	caller: aziot_keyd::Caller,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<hyper::Response<hyper::Body>, hyper::Request<hyper::Body>>> + Send>> {
 	Box::pin(async move {
 		if req.uri().path() != "/keypair" {
@@ -14,6 +15,7 @@ pub(super) fn handle(
 			return Ok(super::err_response(
 				hyper::StatusCode::METHOD_NOT_ALLOWED,
 				Some((hyper::header::ALLOW, "POST")),
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				"method not allowed".into(),
 			));
 		}
@@ -22,6 +24,7 @@ pub(super) fn handle(
 			return Ok(super::err_response(
 				hyper::StatusCode::UNSUPPORTED_MEDIA_TYPE,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				"request body must be application/json".into(),
 			));
 		}
@@ -31,6 +34,7 @@ pub(super) fn handle(
 			Err(err) => return Ok(super::err_response(
 				hyper::StatusCode::BAD_REQUEST,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				super::error_to_message(&err).into(),
 			)),
 		};
@@ -39,11 +43,12 @@ pub(super) fn handle(
 			Err(err) => return Ok(super::err_response(
 				hyper::StatusCode::UNPROCESSABLE_ENTITY,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				super::error_to_message(&err).into(),
 			)),
 		};
 
-		let handle = match inner.create_key_pair_if_not_exists(&body.id, body.preferred_algorithms.as_deref()) {
+		let handle = match inner.create_key_pair_if_not_exists(caller, &body.id, body.preferred_algorithms.as_deref()) {
 			Ok(handle) => handle,
 			Err(err) => return Ok(super::ToHttpResponse::to_http_response(&err)),
 		};
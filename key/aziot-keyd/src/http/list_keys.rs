@@ -0,0 +1,33 @@
+pub(super) fn handle(
+	req: hyper::Request<hyper::Body>,
+	inner: std::sync::Arc<aziot_keyd::Server>,
+	caller: aziot_keyd::Caller,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<hyper::Response<hyper::Body>, hyper::Request<hyper::Body>>> + Send>> {
+	Box::pin(async move {
+		if req.uri().path() != "/keys" {
+			return Err(req);
+		}
+
+		let (http::request::Parts { method, .. }, _) = req.into_parts();
+
+		if method != hyper::Method::GET {
+			return Ok(super::err_response(
+				hyper::StatusCode::METHOD_NOT_ALLOWED,
+				Some((hyper::header::ALLOW, "GET")),
+				aziot_key_common_http::ErrorCode::InvalidRequest,
+				"method not allowed".into(),
+			));
+		}
+
+		let keys = match inner.list_keys(caller) {
+			Ok(keys) => keys,
+			Err(err) => return Ok(super::ToHttpResponse::to_http_response(&err)),
+		};
+
+		let res = aziot_key_common_http::list_keys::Response {
+			keys: keys.into_iter().map(super::to_http_key_info).collect(),
+		};
+		let res = super::json_response(hyper::StatusCode::OK, &res);
+		Ok(res)
+	})
+}
@@ -7,6 +7,7 @@ lazy_static::lazy_static! {
 pub(super) fn handle(
 	req: hyper::Request<hyper::Body>,
 	inner: std::sync::Arc<aziot_keyd::Server>,
+	caller: aziot_keyd::Caller,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<hyper::Response<hyper::Body>, hyper::Request<hyper::Body>>> + Send>> {
 	Box::pin(async move {
 		let captures = match URI_REGEX.captures(req.uri().path()) {
@@ -21,6 +22,7 @@ pub(super) fn handle(
 			Err(err) => return Ok(super::err_response(
 				hyper::StatusCode::BAD_REQUEST,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				super::error_to_message(&err).into(),
 			)),
 		};
@@ -30,12 +32,13 @@ pub(super) fn handle(
 		if method != hyper::Method::GET {
 			return Ok(super::err_response(
 				hyper::StatusCode::METHOD_NOT_ALLOWED,
-				Some((hyper::header::ALLOW, "GET")),
+				Some((hyper::header::ALLOW, "GET, DELETE")),
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				"method not allowed".into(),
 			));
 		}
 
-		let handle = match inner.load_key_pair(&key_id) {
+		let handle = match inner.load_key_pair(caller, &key_id) {
 			Ok(handle) => handle,
 			Err(err) => return Ok(super::ToHttpResponse::to_http_response(&err)),
 		};
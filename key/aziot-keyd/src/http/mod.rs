@@ -1,22 +1,34 @@
 mod create_key_if_not_exists;
 mod create_key_pair_if_not_exists;
 mod decrypt;
+mod delete_key;
+mod delete_key_pair;
+mod derive_shared_secret;
 mod encrypt;
 mod get_key_pair_public_parameter;
+mod list_key_pairs;
+mod list_keys;
 mod load_key_pair;
+mod revoke_key_handles;
+mod rewrap;
+mod rotate_key;
 mod sign;
+mod verify;
 
 pub(crate) struct Server {
 	pub(crate) inner: std::sync::Arc<aziot_keyd::Server>,
+	pub(crate) caller: aziot_keyd::Caller,
 }
 
-/// A route is an async function that receives the hyper request and the `aziot_keyd::Server` value.
+/// A route is an async function that receives the hyper request, the `aziot_keyd::Server` value,
+/// and the identity of the caller that sent the request.
 ///
 /// It returns `Ok(res)` if it successfully matched the incoming request, and `Err(req)` if it didn't.
 type Route =
 	fn(
 		hyper::Request<hyper::Body>,
 		std::sync::Arc<aziot_keyd::Server>,
+		aziot_keyd::Caller,
 	) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<hyper::Response<hyper::Body>, hyper::Request<hyper::Body>>> + Send>>;
 
 impl hyper::service::Service<hyper::Request<hyper::Body>> for Server {
@@ -30,23 +42,33 @@ impl hyper::service::Service<hyper::Request<hyper::Body>> for Server {
 
 	fn call(&mut self, mut req: hyper::Request<hyper::Body>) -> Self::Future {
 		let inner = self.inner.clone();
+		let caller = self.caller;
 
 		Box::pin(async move {
 			const ROUTES: &[Route] = &[
 				create_key_if_not_exists::handle,
 				create_key_pair_if_not_exists::handle,
 				decrypt::handle,
+				delete_key::handle,
+				delete_key_pair::handle,
+				derive_shared_secret::handle,
 				encrypt::handle,
 				get_key_pair_public_parameter::handle,
+				list_key_pairs::handle,
+				list_keys::handle,
 				load_key_pair::handle,
+				revoke_key_handles::handle,
+				rewrap::handle,
+				rotate_key::handle,
 				sign::handle,
+				verify::handle,
 			];
 
 			eprintln!("Received request {:?}", req);
 
 			let mut res = None;
 			for route in ROUTES {
-				req = match route(req, inner.clone()).await {
+				req = match route(req, inner.clone(), caller).await {
 					Ok(res_) => { res = Some(res_); break; },
 					Err(req) => req,
 				};
@@ -54,6 +76,7 @@ impl hyper::service::Service<hyper::Request<hyper::Body>> for Server {
 			let res = res.unwrap_or_else(|| err_response(
 				hyper::StatusCode::NOT_FOUND,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				"not found".into(),
 			));
 
@@ -92,12 +115,26 @@ fn json_response(status_code: hyper::StatusCode, body: &impl serde::Serialize) -
 	res
 }
 
+fn to_http_key_info(key_info: aziot_key_common::KeyInfo) -> aziot_key_common_http::list_keys::KeyInfo {
+	aziot_key_common_http::list_keys::KeyInfo {
+		id: key_info.id,
+		kind: match key_info.kind {
+			aziot_key_common::KeyKind::Key => aziot_key_common_http::list_keys::KeyKind::Key,
+			aziot_key_common::KeyKind::KeyPair => aziot_key_common_http::list_keys::KeyKind::KeyPair,
+		},
+		algorithm: key_info.algorithm,
+		backend: key_info.backend,
+	}
+}
+
 fn err_response(
 	status_code: hyper::StatusCode,
 	extra_header: Option<(hyper::header::HeaderName, &'static str)>,
+	code: aziot_key_common_http::ErrorCode,
 	message: std::borrow::Cow<'static, str>,
 ) -> hyper::Response<hyper::Body> {
 	let body = aziot_key_common_http::Error {
+		code,
 		message,
 	};
 
@@ -117,15 +154,52 @@ trait ToHttpResponse {
 impl ToHttpResponse for aziot_keyd::Error {
 	fn to_http_response(&self) -> hyper::Response<hyper::Body> {
 		match self {
+			aziot_keyd::Error::BackendUnavailable(_) => err_response(
+				hyper::StatusCode::SERVICE_UNAVAILABLE,
+				None,
+				aziot_key_common_http::ErrorCode::BackendUnavailable,
+				self.to_string().into(), // Same as Error::Internal
+			),
+
 			aziot_keyd::Error::Internal(_) => err_response(
 				hyper::StatusCode::INTERNAL_SERVER_ERROR,
 				None,
+				aziot_key_common_http::ErrorCode::Internal,
 				self.to_string().into(), // Do not use error_to_message for Error::Internal because we don't want to leak internal errors
 			),
 
+			err @ aziot_keyd::Error::InvalidHandle(_) => err_response(
+				hyper::StatusCode::BAD_REQUEST,
+				None,
+				aziot_key_common_http::ErrorCode::InvalidHandle,
+				error_to_message(err).into(),
+			),
+
 			err @ aziot_keyd::Error::InvalidParameter(_) => err_response(
 				hyper::StatusCode::BAD_REQUEST,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidParameter,
+				error_to_message(err).into(),
+			),
+
+			err @ aziot_keyd::Error::KeyNotFound => err_response(
+				hyper::StatusCode::NOT_FOUND,
+				None,
+				aziot_key_common_http::ErrorCode::KeyNotFound,
+				error_to_message(err).into(),
+			),
+
+			err @ aziot_keyd::Error::Unauthorized(_, _, _) => err_response(
+				hyper::StatusCode::FORBIDDEN,
+				None,
+				aziot_key_common_http::ErrorCode::Unauthorized,
+				error_to_message(err).into(),
+			),
+
+			err @ aziot_keyd::Error::UnsupportedOperation(_) => err_response(
+				hyper::StatusCode::NOT_IMPLEMENTED,
+				None,
+				aziot_key_common_http::ErrorCode::UnsupportedOperation,
 				error_to_message(err).into(),
 			),
 		}
@@ -0,0 +1,68 @@
+pub(super) fn handle(
+	req: hyper::Request<hyper::Body>,
+	inner: std::sync::Arc<aziot_keyd::Server>,
+	caller: aziot_keyd::Caller,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<hyper::Response<hyper::Body>, hyper::Request<hyper::Body>>> + Send>> {
+	Box::pin(async move {
+		if req.uri().path() != "/revoke" {
+			return Err(req);
+		}
+
+		let (http::request::Parts { method, headers, .. }, body) = req.into_parts();
+		let content_type = headers.get(hyper::header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+
+		if method != hyper::Method::POST {
+			return Ok(super::err_response(
+				hyper::StatusCode::METHOD_NOT_ALLOWED,
+				Some((hyper::header::ALLOW, "POST")),
+				aziot_key_common_http::ErrorCode::InvalidRequest,
+				"method not allowed".into(),
+			));
+		}
+
+		if content_type.as_deref() != Some("application/json") {
+			return Ok(super::err_response(
+				hyper::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
+				"request body must be application/json".into(),
+			));
+		}
+
+		let body = match hyper::body::to_bytes(body).await {
+			Ok(body) => body,
+			Err(err) => return Ok(super::err_response(
+				hyper::StatusCode::BAD_REQUEST,
+				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
+				super::error_to_message(&err).into(),
+			)),
+		};
+		let body: aziot_key_common_http::revoke_key_handles::Request = match serde_json::from_slice(&body) {
+			Ok(body) => body,
+			Err(err) => return Ok(super::err_response(
+				hyper::StatusCode::UNPROCESSABLE_ENTITY,
+				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
+				super::error_to_message(&err).into(),
+			)),
+		};
+
+		let kind = match body.kind {
+			aziot_key_common_http::list_keys::KeyKind::Key => aziot_key_common::KeyKind::Key,
+			aziot_key_common_http::list_keys::KeyKind::KeyPair => aziot_key_common::KeyKind::KeyPair,
+		};
+
+		match inner.revoke_key_handles(caller, &body.id, kind) {
+			Ok(()) => (),
+			Err(err) => return Ok(super::ToHttpResponse::to_http_response(&err)),
+		};
+
+		let res =
+			hyper::Response::builder()
+			.status(hyper::StatusCode::NO_CONTENT)
+			.body(Default::default())
+			.expect("cannot fail to serialize hyper response");
+		Ok(res)
+	})
+}
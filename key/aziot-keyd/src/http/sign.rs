@@ -1,6 +1,7 @@
 pub(super) fn handle(
 	req: hyper::Request<hyper::Body>,
 	inner: std::sync::Arc<aziot_keyd::Server>,
+	caller: aziot_keyd::Caller,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<hyper::Response<hyper::Body>, hyper::Request<hyper::Body>>> + Send>> {
 	Box::pin(async move {
 		if req.uri().path() != "/sign" {
@@ -14,6 +15,7 @@ pub(super) fn handle(
 			return Ok(super::err_response(
 				hyper::StatusCode::METHOD_NOT_ALLOWED,
 				Some((hyper::header::ALLOW, "POST")),
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				"method not allowed".into(),
 			));
 		}
@@ -22,6 +24,7 @@ pub(super) fn handle(
 			return Ok(super::err_response(
 				hyper::StatusCode::UNSUPPORTED_MEDIA_TYPE,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				"request body must be application/json".into(),
 			));
 		}
@@ -31,6 +34,7 @@ pub(super) fn handle(
 			Err(err) => return Ok(super::err_response(
 				hyper::StatusCode::BAD_REQUEST,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				super::error_to_message(&err).into(),
 			)),
 		};
@@ -39,33 +43,21 @@ pub(super) fn handle(
 			Err(err) => return Ok(super::err_response(
 				hyper::StatusCode::UNPROCESSABLE_ENTITY,
 				None,
+				aziot_key_common_http::ErrorCode::InvalidRequest,
 				super::error_to_message(&err).into(),
 			)),
 		};
-		let (mechanism, digest) = match body.parameters {
-			aziot_key_common_http::sign::Parameters::Ecdsa { digest } => (aziot_key_common::SignMechanism::Ecdsa, digest),
-
-			aziot_key_common_http::sign::Parameters::RsaPkcs1 { message_digest_algorithm, message } => {
-				let message_digest = match &*message_digest_algorithm {
-					"sha1" => aziot_key_common::RsaPkcs1MessageDigest::Sha1,
-					"sha224" => aziot_key_common::RsaPkcs1MessageDigest::Sha224,
-					"sha256" => aziot_key_common::RsaPkcs1MessageDigest::Sha256,
-					"sha384" => aziot_key_common::RsaPkcs1MessageDigest::Sha384,
-					"sha512" => aziot_key_common::RsaPkcs1MessageDigest::Sha512,
-					message_digest_algorithm => return Ok(super::err_response(
-						hyper::StatusCode::UNPROCESSABLE_ENTITY,
-						None,
-						format!("invalid value of parameters.messageDigestAlgorithm {:?}", message_digest_algorithm).into(),
-					)),
-				};
-
-				(aziot_key_common::SignMechanism::RsaPkcs1 { message_digest }, message)
-			},
-
-			aziot_key_common_http::sign::Parameters::HmacSha256 { message } => (aziot_key_common::SignMechanism::HmacSha256, message),
+		let (mechanism, digest) = match to_sign_mechanism(body.parameters) {
+			Ok(mechanism) => mechanism,
+			Err(message) => return Ok(super::err_response(
+				hyper::StatusCode::UNPROCESSABLE_ENTITY,
+				None,
+				aziot_key_common_http::ErrorCode::InvalidParameter,
+				message.into(),
+			)),
 		};
 
-		let signature = match inner.sign(&body.key_handle, mechanism, &digest.0) {
+		let signature = match inner.sign(caller, &body.key_handle, mechanism, &digest.0) {
 			Ok(signature) => signature,
 			Err(err) => return Ok(super::ToHttpResponse::to_http_response(&err)),
 		};
@@ -77,3 +69,46 @@ pub(super) fn handle(
 		Ok(res)
 	})
 }
+
+/// Converts the mechanism parameters of a `/sign` or `/verify` request into the mechanism and the digest to sign or verify.
+///
+/// Returns the error message of an invalid parameter as the `Err` value.
+pub(super) fn to_sign_mechanism(
+	parameters: aziot_key_common_http::sign::Parameters,
+) -> Result<(aziot_key_common::SignMechanism, http_common::ByteString), String> {
+	let mechanism = match parameters {
+		aziot_key_common_http::sign::Parameters::Ecdsa { digest } => (aziot_key_common::SignMechanism::Ecdsa, digest),
+
+		aziot_key_common_http::sign::Parameters::EdDsa { message } => (aziot_key_common::SignMechanism::EdDsa, message),
+
+		aziot_key_common_http::sign::Parameters::RsaPkcs1 { message_digest_algorithm, message } => {
+			let message_digest = match &*message_digest_algorithm {
+				"sha1" => aziot_key_common::RsaPkcs1MessageDigest::Sha1,
+				"sha224" => aziot_key_common::RsaPkcs1MessageDigest::Sha224,
+				"sha256" => aziot_key_common::RsaPkcs1MessageDigest::Sha256,
+				"sha384" => aziot_key_common::RsaPkcs1MessageDigest::Sha384,
+				"sha512" => aziot_key_common::RsaPkcs1MessageDigest::Sha512,
+				message_digest_algorithm => return Err(format!("invalid value of parameters.messageDigestAlgorithm {:?}", message_digest_algorithm)),
+			};
+
+			(aziot_key_common::SignMechanism::RsaPkcs1 { message_digest }, message)
+		},
+
+		aziot_key_common_http::sign::Parameters::RsaPss { mask_generation_function, salt_len, message } => {
+			let mask_generation_function = match &*mask_generation_function {
+				"sha1" => aziot_key_common::RsaPssMaskGenerationFunction::Sha1,
+				"sha224" => aziot_key_common::RsaPssMaskGenerationFunction::Sha224,
+				"sha256" => aziot_key_common::RsaPssMaskGenerationFunction::Sha256,
+				"sha384" => aziot_key_common::RsaPssMaskGenerationFunction::Sha384,
+				"sha512" => aziot_key_common::RsaPssMaskGenerationFunction::Sha512,
+				mask_generation_function => return Err(format!("invalid value of parameters.maskGenerationFunction {:?}", mask_generation_function)),
+			};
+
+			(aziot_key_common::SignMechanism::RsaPss { mask_generation_function, salt_len }, message)
+		},
+
+		aziot_key_common_http::sign::Parameters::HmacSha256 { message } => (aziot_key_common::SignMechanism::HmacSha256, message),
+	};
+
+	Ok(mechanism)
+}
@@ -7,6 +7,7 @@ impl std::fmt::Display for KeysRawError {
 			sys::KEYGEN_ERROR_FATAL => f.write_str("KEYGEN_ERROR_FATAL"),
 			sys::KEYGEN_ERROR_INVALID_PARAMETER => f.write_str("KEYGEN_ERROR_INVALID_PARAMETER"),
 			sys::KEYGEN_ERROR_EXTERNAL => f.write_str("KEYGEN_ERROR_EXTERNAL"),
+			sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => f.write_str("KEYGEN_ERROR_BACKEND_UNAVAILABLE"),
 			err => write!(f, "0x{:08x}", err),
 		}
 	}
@@ -88,66 +89,292 @@ pub(crate) enum Keys {
 			plaintext_len: *mut usize,
 		) -> sys::KEYGEN_ERROR,
 	},
+
+	V2_1_0_0 {
+		set_parameter: unsafe extern "C" fn(
+			name: *const std::os::raw::c_char,
+			value: *const std::os::raw::c_char,
+		) -> sys::KEYGEN_ERROR,
+
+		create_key_pair_if_not_exists: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			preferred_algorithms: *const std::os::raw::c_char,
+		) -> sys::KEYGEN_ERROR,
+
+		load_key_pair: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+		) -> sys::KEYGEN_ERROR,
+
+		get_key_pair_parameter: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			r#type: sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE,
+			value: *mut std::os::raw::c_uchar,
+			value_len: *mut usize,
+		) -> sys::KEYGEN_ERROR,
+
+		create_key_if_not_exists: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			length: usize,
+		) -> sys::KEYGEN_ERROR,
+
+		import_key: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			bytes: *const u8,
+			bytes_length: usize,
+		) -> sys::KEYGEN_ERROR,
+
+		sign: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			mechanism: sys::KEYGEN_SIGN_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			digest: *const std::os::raw::c_uchar,
+			digest_len: usize,
+			signature: *mut std::os::raw::c_uchar,
+			signature_len: *mut usize,
+		) -> sys::KEYGEN_ERROR,
+
+		verify: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			mechanism: sys::KEYGEN_SIGN_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			digest: *const std::os::raw::c_uchar,
+			digest_len: usize,
+			signature: *const std::os::raw::c_uchar,
+			signature_len: usize,
+			ok: *mut std::os::raw::c_int,
+		) -> sys::KEYGEN_ERROR,
+
+		encrypt: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			mechanism: sys::KEYGEN_SIGN_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			plaintext: *const std::os::raw::c_uchar,
+			plaintext_len: usize,
+			ciphertext: *mut std::os::raw::c_uchar,
+			ciphertext_len: *mut usize,
+		) -> sys::KEYGEN_ERROR,
+
+		decrypt: unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			mechanism: sys::KEYGEN_SIGN_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			ciphertext: *const std::os::raw::c_uchar,
+			ciphertext_len: usize,
+			plaintext: *mut std::os::raw::c_uchar,
+			plaintext_len: *mut usize,
+		) -> sys::KEYGEN_ERROR,
+
+		delete_key_pair: Option<unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+		) -> sys::KEYGEN_ERROR>,
+
+		delete_key: Option<unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+		) -> sys::KEYGEN_ERROR>,
+
+		list_key_pairs: Option<unsafe extern "C" fn(
+			list: *mut std::os::raw::c_char,
+			list_len: *mut usize,
+		) -> sys::KEYGEN_ERROR>,
+
+		list_keys: Option<unsafe extern "C" fn(
+			list: *mut std::os::raw::c_char,
+			list_len: *mut usize,
+		) -> sys::KEYGEN_ERROR>,
+
+		derive_shared_secret: Option<unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			peer_public_key: *const std::os::raw::c_uchar,
+			peer_public_key_len: usize,
+			shared_secret: *mut std::os::raw::c_uchar,
+			shared_secret_len: *mut usize,
+		) -> sys::KEYGEN_ERROR>,
+
+		rotate_key: Option<unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+		) -> sys::KEYGEN_ERROR>,
+
+		rewrap: Option<unsafe extern "C" fn(
+			id: *const std::os::raw::c_char,
+			mechanism: sys::KEYGEN_ENCRYPT_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			ciphertext: *const std::os::raw::c_uchar,
+			ciphertext_len: usize,
+			new_ciphertext: *mut std::os::raw::c_uchar,
+			new_ciphertext_len: *mut usize,
+		) -> sys::KEYGEN_ERROR>,
+
+		get_capabilities: Option<unsafe extern "C" fn(
+			capabilities: *mut sys::KEYGEN_CAPABILITIES,
+		) -> sys::KEYGEN_ERROR>,
+	},
 }
 
 impl Keys {
-	pub(crate) fn new() -> Result<Self, LoadLibraryError> {
+	/// Loads the libaziot-keys at the given path, or the built-in one that aziot-keyd is linked against if no path is given.
+	pub(crate) fn new(lib_path: Option<&std::path::Path>) -> Result<Self, LoadLibraryError> {
 		unsafe {
+			let get_function_list: GetFunctionList = match lib_path {
+				Some(lib_path) => {
+					let library = dl_common::Library::load(lib_path, libc::RTLD_NOW | libc::RTLD_LOCAL).map_err(LoadLibraryError::LoadLibrary)?;
+					let get_function_list =
+						*library.symbol(std::ffi::CStr::from_bytes_with_nul(b"KEYGEN_get_function_list\0").unwrap())
+						.map_err(LoadLibraryError::LoadLibrary)?;
+
+					// The function list points into the library, and the returned Keys is used for the lifetime of the process.
+					// So the library is never unloaded.
+					std::mem::forget(library);
+
+					get_function_list
+				},
+
+				None => sys::KEYGEN_get_function_list,
+			};
+
+			// Request the newest version this crate knows about. A library that only implements an older version
+			// rejects it with KEYGEN_ERROR_INVALID_PARAMETER, in which case fall back to 2.0.0.0.
 			let mut function_list: *const sys::KEYGEN_FUNCTION_LIST = std::ptr::null_mut();
-			keys_fn(|| sys::KEYGEN_get_function_list(sys::KEYGEN_VERSION_2_0_0_0, &mut function_list)).map_err(LoadLibraryError::GetFunctionList)?;
+			match keys_fn(|| get_function_list(sys::KEYGEN_VERSION_2_1_0_0, &mut function_list)) {
+				Err(KeysRawError(sys::KEYGEN_ERROR_INVALID_PARAMETER)) =>
+					keys_fn(|| get_function_list(sys::KEYGEN_VERSION_2_0_0_0, &mut function_list)).map_err(LoadLibraryError::GetFunctionList)?,
+				result => result.map_err(LoadLibraryError::GetFunctionList)?,
+			}
 
 			let api_version = (*function_list).version;
-			if api_version != sys::KEYGEN_VERSION_2_0_0_0 {
-				return Err(LoadLibraryError::UnsupportedApiVersion(api_version));
-			}
 
-			// KEYGEN_FUNCTION_LIST has looser alignment than KEYGEN_FUNCTION_LIST_2_0_0_0, but the pointer comes from the library itself,
-			// so it will be correctly aligned already.
-			#[allow(clippy::cast_ptr_alignment)]
-			let function_list: *const sys::KEYGEN_FUNCTION_LIST_2_0_0_0 = function_list as _;
+			let result = match api_version {
+				sys::KEYGEN_VERSION_2_0_0_0 => {
+					// KEYGEN_FUNCTION_LIST has looser alignment than KEYGEN_FUNCTION_LIST_2_0_0_0, but the pointer comes from the library itself,
+					// so it will be correctly aligned already.
+					#[allow(clippy::cast_ptr_alignment)]
+					let function_list: *const sys::KEYGEN_FUNCTION_LIST_2_0_0_0 = function_list as _;
+
+					Keys::V2_0_0_0 {
+						set_parameter:
+							(*function_list).set_parameter.ok_or(LoadLibraryError::MissingFunction("set_parameter"))?,
+
+						create_key_pair_if_not_exists:
+							(*function_list).create_key_pair_if_not_exists.ok_or(LoadLibraryError::MissingFunction("create_key_pair_if_not_exists"))?,
+
+						load_key_pair:
+							(*function_list).load_key_pair.ok_or(LoadLibraryError::MissingFunction("load_key_pair"))?,
+
+						get_key_pair_parameter:
+							(*function_list).get_key_pair_parameter.ok_or(LoadLibraryError::MissingFunction("get_key_pair_parameter"))?,
+
+						create_key_if_not_exists:
+							(*function_list).create_key_if_not_exists.ok_or(LoadLibraryError::MissingFunction("create_key_if_not_exists"))?,
+
+						import_key:
+							(*function_list).import_key.ok_or(LoadLibraryError::MissingFunction("import_key"))?,
+
+						sign:
+							(*function_list).sign.ok_or(LoadLibraryError::MissingFunction("sign"))?,
+
+						verify:
+							(*function_list).verify.ok_or(LoadLibraryError::MissingFunction("verify"))?,
+
+						encrypt:
+							(*function_list).encrypt.ok_or(LoadLibraryError::MissingFunction("encrypt"))?,
+
+						decrypt:
+							(*function_list).decrypt.ok_or(LoadLibraryError::MissingFunction("decrypt"))?,
+					}
+				},
+
+				sys::KEYGEN_VERSION_2_1_0_0 => {
+					// KEYGEN_FUNCTION_LIST has looser alignment than KEYGEN_FUNCTION_LIST_2_1_0_0, but the pointer comes from the library itself,
+					// so it will be correctly aligned already.
+					#[allow(clippy::cast_ptr_alignment)]
+					let function_list: *const sys::KEYGEN_FUNCTION_LIST_2_1_0_0 = function_list as _;
+
+					// The functions added in 2.1.0.0 are optional, so a missing one only fails the operations that need it.
+					Keys::V2_1_0_0 {
+						set_parameter:
+							(*function_list).set_parameter.ok_or(LoadLibraryError::MissingFunction("set_parameter"))?,
+
+						create_key_pair_if_not_exists:
+							(*function_list).create_key_pair_if_not_exists.ok_or(LoadLibraryError::MissingFunction("create_key_pair_if_not_exists"))?,
+
+						load_key_pair:
+							(*function_list).load_key_pair.ok_or(LoadLibraryError::MissingFunction("load_key_pair"))?,
 
-			let result = Keys::V2_0_0_0 {
-				set_parameter:
-					(*function_list).set_parameter.ok_or(LoadLibraryError::MissingFunction("set_parameter"))?,
+						get_key_pair_parameter:
+							(*function_list).get_key_pair_parameter.ok_or(LoadLibraryError::MissingFunction("get_key_pair_parameter"))?,
 
-				create_key_pair_if_not_exists:
-					(*function_list).create_key_pair_if_not_exists.ok_or(LoadLibraryError::MissingFunction("create_key_pair_if_not_exists"))?,
+						create_key_if_not_exists:
+							(*function_list).create_key_if_not_exists.ok_or(LoadLibraryError::MissingFunction("create_key_if_not_exists"))?,
 
-				load_key_pair:
-					(*function_list).load_key_pair.ok_or(LoadLibraryError::MissingFunction("load_key_pair"))?,
+						import_key:
+							(*function_list).import_key.ok_or(LoadLibraryError::MissingFunction("import_key"))?,
 
-				get_key_pair_parameter:
-					(*function_list).get_key_pair_parameter.ok_or(LoadLibraryError::MissingFunction("get_key_pair_parameter"))?,
+						sign:
+							(*function_list).sign.ok_or(LoadLibraryError::MissingFunction("sign"))?,
 
-				create_key_if_not_exists:
-					(*function_list).create_key_if_not_exists.ok_or(LoadLibraryError::MissingFunction("create_key_if_not_exists"))?,
+						verify:
+							(*function_list).verify.ok_or(LoadLibraryError::MissingFunction("verify"))?,
 
-				import_key:
-					(*function_list).import_key.ok_or(LoadLibraryError::MissingFunction("import_key"))?,
+						encrypt:
+							(*function_list).encrypt.ok_or(LoadLibraryError::MissingFunction("encrypt"))?,
 
-				sign:
-					(*function_list).sign.ok_or(LoadLibraryError::MissingFunction("sign"))?,
+						decrypt:
+							(*function_list).decrypt.ok_or(LoadLibraryError::MissingFunction("decrypt"))?,
 
-				verify:
-					(*function_list).verify.ok_or(LoadLibraryError::MissingFunction("verify"))?,
+						delete_key_pair: (*function_list).delete_key_pair,
 
-				encrypt:
-					(*function_list).encrypt.ok_or(LoadLibraryError::MissingFunction("encrypt"))?,
+						delete_key: (*function_list).delete_key,
 
-				decrypt:
-					(*function_list).decrypt.ok_or(LoadLibraryError::MissingFunction("decrypt"))?,
+						list_key_pairs: (*function_list).list_key_pairs,
+
+						list_keys: (*function_list).list_keys,
+
+						derive_shared_secret: (*function_list).derive_shared_secret,
+
+						rotate_key: (*function_list).rotate_key,
+
+						rewrap: (*function_list).rewrap,
+
+						get_capabilities: (*function_list).get_capabilities,
+					}
+				},
+
+				api_version => return Err(LoadLibraryError::UnsupportedApiVersion(api_version)),
 			};
 
-			println!("Loaded libaziot-keys with version 0x{:08x}, {:?}", api_version, result);
+			// Calls into the library are always serialized by the Server's mutex, which is valid whether or not the library is thread-safe.
+			// The capabilities are only logged for diagnostics.
+			let capabilities = result.capabilities().map_err(LoadLibraryError::GetCapabilities)?;
+
+			println!("Loaded libaziot-keys with version 0x{:08x} and capabilities 0x{:08x}, {:?}", api_version, capabilities, result);
 
 			Ok(result)
 		}
 	}
+
+	unsafe fn capabilities(&self) -> Result<sys::KEYGEN_CAPABILITIES, KeysRawError> {
+		match self {
+			Keys::V2_0_0_0 { .. } |
+			Keys::V2_1_0_0 { get_capabilities: None, .. } => Ok(0),
+
+			Keys::V2_1_0_0 { get_capabilities: Some(get_capabilities), .. } => {
+				let mut capabilities = 0;
+				keys_fn(|| get_capabilities(&mut capabilities))?;
+				Ok(capabilities)
+			},
+		}
+	}
 }
 
+type GetFunctionList = unsafe extern "C" fn(
+	version: sys::KEYGEN_VERSION,
+	pfunction_list: *mut *const sys::KEYGEN_FUNCTION_LIST,
+) -> sys::KEYGEN_ERROR;
+
 #[derive(Debug)]
 pub enum LoadLibraryError {
+	GetCapabilities(KeysRawError),
 	GetFunctionList(KeysRawError),
+	LoadLibrary(String),
 	MissingFunction(&'static str),
 	UnsupportedApiVersion(sys::KEYGEN_VERSION),
 }
@@ -155,7 +382,9 @@ pub enum LoadLibraryError {
 impl std::fmt::Display for LoadLibraryError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
+			LoadLibraryError::GetCapabilities(inner) => write!(f, "could not get library capabilities: {}", inner),
 			LoadLibraryError::GetFunctionList(inner) => write!(f, "could not get function list: {}", inner),
+			LoadLibraryError::LoadLibrary(inner) => write!(f, "could not load library: {}", inner),
 			LoadLibraryError::MissingFunction(name) => write!(f, "library does not define {}", name),
 			LoadLibraryError::UnsupportedApiVersion(api_version) => write!(f, "library exports API version 0x{:08x} which is not supported", api_version),
 		}
@@ -169,7 +398,8 @@ impl Keys {
 	pub(crate) fn set_parameter(&mut self, name: &std::ffi::CStr, value: &std::ffi::CStr) -> Result<(), SetLibraryParameterError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { set_parameter, .. } => {
+				Keys::V2_0_0_0 { set_parameter, .. } |
+				Keys::V2_1_0_0 { set_parameter, .. } => {
 					keys_fn(|| set_parameter(
 						name.as_ptr(),
 						value.as_ptr(),
@@ -205,7 +435,8 @@ impl Keys {
 	) -> Result<(), CreateKeyPairIfNotExistsError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { create_key_pair_if_not_exists, .. } => {
+				Keys::V2_0_0_0 { create_key_pair_if_not_exists, .. } |
+				Keys::V2_1_0_0 { create_key_pair_if_not_exists, .. } => {
 					keys_fn(|| create_key_pair_if_not_exists(
 						id.as_ptr(),
 						preferred_algorithms.map_or(std::ptr::null(), |preferred_algorithms| preferred_algorithms.as_ptr()),
@@ -239,7 +470,8 @@ impl Keys {
 	) -> Result<(), LoadKeyPairError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { load_key_pair, .. } => {
+				Keys::V2_0_0_0 { load_key_pair, .. } |
+				Keys::V2_1_0_0 { load_key_pair, .. } => {
 					keys_fn(|| load_key_pair(
 						id.as_ptr(),
 					)).map_err(|err| LoadKeyPairError { err })?;
@@ -265,6 +497,54 @@ impl std::fmt::Display for LoadKeyPairError {
 impl std::error::Error for LoadKeyPairError {
 }
 
+impl Keys {
+	/// Checks that the key with the given ID exists.
+	///
+	/// API version 2.0.0.0 has no function that only loads a key. But every key can be used for HMAC-SHA256 signatures,
+	/// so this asks for the length of the signature of an empty digest instead. This fails with `KEYGEN_ERROR_INVALID_PARAMETER`
+	/// if the key does not exist.
+	pub(crate) fn load_key(
+		&mut self,
+		id: &std::ffi::CStr,
+	) -> Result<(), LoadKeyError> {
+		unsafe {
+			match self {
+				Keys::V2_0_0_0 { sign, .. } |
+				Keys::V2_1_0_0 { sign, .. } => {
+					let digest = [];
+					let mut signature_len = 0;
+
+					keys_fn(|| sign(
+						id.as_ptr(),
+						sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256,
+						std::ptr::null(),
+						digest.as_ptr(),
+						0,
+						std::ptr::null_mut(),
+						&mut signature_len,
+					)).map_err(|err| LoadKeyError { err })?;
+
+					Ok(())
+				},
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct LoadKeyError {
+	pub err: KeysRawError,
+}
+
+impl std::fmt::Display for LoadKeyError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "could not load key: {}", self.err)
+	}
+}
+
+impl std::error::Error for LoadKeyError {
+}
+
 impl Keys {
 	pub(crate) fn get_key_pair_public_parameter(
 		&mut self,
@@ -273,7 +553,8 @@ impl Keys {
 	) -> Result<String, GetKeyPairPublicParameterError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { get_key_pair_parameter, .. } => {
+				Keys::V2_0_0_0 { get_key_pair_parameter, .. } |
+				Keys::V2_1_0_0 { get_key_pair_parameter, .. } => {
 					match parameter_name {
 						"algorithm" => {
 							let mut algorithm: sys::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM = 0;
@@ -293,6 +574,7 @@ impl Keys {
 							let algorithm = match algorithm {
 								sys::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_EC => "ECDSA".to_owned(),
 								sys::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_RSA => "RSA".to_owned(),
+								sys::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_ED25519 => "ED25519".to_owned(),
 								algorithm => return Err(GetKeyPairPublicParameterError::UnrecognizedKeyAlgorithm { algorithm }),
 							};
 							Ok(algorithm)
@@ -357,7 +639,8 @@ impl Keys {
 	) -> Result<(), CreateKeyIfNotExistsError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { create_key_if_not_exists, .. } => {
+				Keys::V2_0_0_0 { create_key_if_not_exists, .. } |
+				Keys::V2_1_0_0 { create_key_if_not_exists, .. } => {
 					keys_fn(|| create_key_if_not_exists(
 						id.as_ptr(),
 						length,
@@ -392,7 +675,8 @@ impl Keys {
 	) -> Result<(), ImportKeyError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { import_key, .. } => {
+				Keys::V2_0_0_0 { import_key, .. } |
+				Keys::V2_1_0_0 { import_key, .. } => {
 					keys_fn(|| import_key(
 						id.as_ptr(),
 						bytes.as_ptr(),
@@ -430,7 +714,8 @@ impl Keys {
 	) -> Result<Vec<u8>, SignError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { sign, .. } => {
+				Keys::V2_0_0_0 { sign, .. } |
+				Keys::V2_1_0_0 { sign, .. } => {
 					let digest_len = std::convert::TryInto::try_into(digest.len()).expect("usize -> c_ulong");
 
 					let mut signature_len = 0;
@@ -501,7 +786,8 @@ impl Keys {
 	) -> Result<bool, VerifyError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { verify, .. } => {
+				Keys::V2_0_0_0 { verify, .. } |
+				Keys::V2_1_0_0 { verify, .. } => {
 					let digest_len = std::convert::TryInto::try_into(digest.len()).expect("usize -> c_ulong");
 					let signature_len = std::convert::TryInto::try_into(signature.len()).expect("usize -> c_ulong");
 
@@ -550,7 +836,8 @@ impl Keys {
 	) -> Result<Vec<u8>, EncryptError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { encrypt, .. } => {
+				Keys::V2_0_0_0 { encrypt, .. } |
+				Keys::V2_1_0_0 { encrypt, .. } => {
 					let plaintext_len = std::convert::TryInto::try_into(plaintext.len()).expect("usize -> c_ulong");
 
 					let mut ciphertext_len = 0;
@@ -620,7 +907,8 @@ impl Keys {
 	) -> Result<Vec<u8>, DecryptError> {
 		unsafe {
 			match self {
-				Keys::V2_0_0_0 { decrypt, .. } => {
+				Keys::V2_0_0_0 { decrypt, .. } |
+				Keys::V2_1_0_0 { decrypt, .. } => {
 					let ciphertext_len = std::convert::TryInto::try_into(ciphertext.len()).expect("usize -> c_ulong");
 
 					let mut plaintext_len = 0;
@@ -680,6 +968,310 @@ impl std::fmt::Display for DecryptError {
 impl std::error::Error for DecryptError {
 }
 
+impl Keys {
+	pub(crate) fn delete_key_pair(
+		&mut self,
+		id: &std::ffi::CStr,
+	) -> Result<(), DeleteKeyPairError> {
+		unsafe {
+			match self {
+				Keys::V2_0_0_0 { .. } |
+				Keys::V2_1_0_0 { delete_key_pair: None, .. } => Err(DeleteKeyPairError::UnsupportedOperation),
+
+				Keys::V2_1_0_0 { delete_key_pair: Some(delete_key_pair), .. } => {
+					keys_fn(|| delete_key_pair(
+						id.as_ptr(),
+					)).map_err(|err| DeleteKeyPairError::Api { err })?;
+
+					Ok(())
+				},
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum DeleteKeyPairError {
+	Api { err: KeysRawError },
+	UnsupportedOperation,
+}
+
+impl std::fmt::Display for DeleteKeyPairError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DeleteKeyPairError::Api { err } => write!(f, "could not delete key pair: {}", err),
+			DeleteKeyPairError::UnsupportedOperation => f.write_str("could not delete key pair: library does not implement delete_key_pair"),
+		}
+	}
+}
+
+impl std::error::Error for DeleteKeyPairError {
+}
+
+impl Keys {
+	pub(crate) fn delete_key(
+		&mut self,
+		id: &std::ffi::CStr,
+	) -> Result<(), DeleteKeyError> {
+		unsafe {
+			match self {
+				Keys::V2_0_0_0 { .. } |
+				Keys::V2_1_0_0 { delete_key: None, .. } => Err(DeleteKeyError::UnsupportedOperation),
+
+				Keys::V2_1_0_0 { delete_key: Some(delete_key), .. } => {
+					keys_fn(|| delete_key(
+						id.as_ptr(),
+					)).map_err(|err| DeleteKeyError::Api { err })?;
+
+					Ok(())
+				},
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum DeleteKeyError {
+	Api { err: KeysRawError },
+	UnsupportedOperation,
+}
+
+impl std::fmt::Display for DeleteKeyError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DeleteKeyError::Api { err } => write!(f, "could not delete key: {}", err),
+			DeleteKeyError::UnsupportedOperation => f.write_str("could not delete key: library does not implement delete_key"),
+		}
+	}
+}
+
+impl std::error::Error for DeleteKeyError {
+}
+
+impl Keys {
+	pub(crate) fn list_key_pairs(&mut self) -> Result<Vec<aziot_key_common::KeyInfo>, ListKeysError> {
+		unsafe {
+			match self {
+				Keys::V2_0_0_0 { .. } |
+				Keys::V2_1_0_0 { list_key_pairs: None, .. } => Err(ListKeysError::UnsupportedOperation),
+
+				Keys::V2_1_0_0 { list_key_pairs: Some(list_key_pairs), .. } => {
+					let list = list_byte_buf(*list_key_pairs).map_err(|err| ListKeysError::Api { err })?;
+					parse_list(&list, aziot_key_common::KeyKind::KeyPair)
+				},
+			}
+		}
+	}
+
+	pub(crate) fn list_keys(&mut self) -> Result<Vec<aziot_key_common::KeyInfo>, ListKeysError> {
+		unsafe {
+			match self {
+				Keys::V2_0_0_0 { .. } |
+				Keys::V2_1_0_0 { list_keys: None, .. } => Err(ListKeysError::UnsupportedOperation),
+
+				Keys::V2_1_0_0 { list_keys: Some(list_keys), .. } => {
+					let list = list_byte_buf(*list_keys).map_err(|err| ListKeysError::Api { err })?;
+					parse_list(&list, aziot_key_common::KeyKind::Key)
+				},
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum ListKeysError {
+	Api { err: KeysRawError },
+	MalformedList,
+	UnsupportedOperation,
+}
+
+impl std::fmt::Display for ListKeysError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ListKeysError::Api { err } => write!(f, "could not list keys: {}", err),
+			ListKeysError::MalformedList => f.write_str("could not list keys: library returned a malformed list"),
+			ListKeysError::UnsupportedOperation => f.write_str("could not list keys: library does not implement listing"),
+		}
+	}
+}
+
+impl std::error::Error for ListKeysError {
+}
+
+impl Keys {
+	pub(crate) fn derive_shared_secret(
+		&mut self,
+		id: &std::ffi::CStr,
+		peer_public_key: &[u8],
+	) -> Result<Vec<u8>, DeriveSharedSecretError> {
+		unsafe {
+			match self {
+				Keys::V2_0_0_0 { .. } |
+				Keys::V2_1_0_0 { derive_shared_secret: None, .. } => Err(DeriveSharedSecretError::UnsupportedOperation),
+
+				Keys::V2_1_0_0 { derive_shared_secret: Some(derive_shared_secret), .. } => {
+					let mut shared_secret_len = 0;
+
+					keys_fn(|| derive_shared_secret(
+						id.as_ptr(),
+						peer_public_key.as_ptr(),
+						peer_public_key.len(),
+						std::ptr::null_mut(),
+						&mut shared_secret_len,
+					)).map_err(|err| DeriveSharedSecretError::Api { err })?;
+
+					let mut shared_secret = vec![0_u8; shared_secret_len];
+
+					keys_fn(|| derive_shared_secret(
+						id.as_ptr(),
+						peer_public_key.as_ptr(),
+						peer_public_key.len(),
+						shared_secret.as_mut_ptr(),
+						&mut shared_secret_len,
+					)).map_err(|err| DeriveSharedSecretError::Api { err })?;
+
+					if shared_secret_len > shared_secret.len() {
+						// libaziot-keys scribbled past the end of the buffer. Crash as soon as possible.
+						std::process::abort();
+					}
+
+					shared_secret.truncate(shared_secret_len);
+
+					Ok(shared_secret)
+				},
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum DeriveSharedSecretError {
+	Api { err: KeysRawError },
+	UnsupportedOperation,
+}
+
+impl std::fmt::Display for DeriveSharedSecretError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DeriveSharedSecretError::Api { err } => write!(f, "could not derive shared secret: {}", err),
+			DeriveSharedSecretError::UnsupportedOperation => f.write_str("could not derive shared secret: library does not implement derive_shared_secret"),
+		}
+	}
+}
+
+impl std::error::Error for DeriveSharedSecretError {
+}
+
+impl Keys {
+	pub(crate) fn rotate_key(
+		&mut self,
+		id: &std::ffi::CStr,
+	) -> Result<(), RotateKeyError> {
+		unsafe {
+			match self {
+				Keys::V2_0_0_0 { .. } |
+				Keys::V2_1_0_0 { rotate_key: None, .. } => Err(RotateKeyError::UnsupportedOperation),
+
+				Keys::V2_1_0_0 { rotate_key: Some(rotate_key), .. } => {
+					keys_fn(|| rotate_key(
+						id.as_ptr(),
+					)).map_err(|err| RotateKeyError::Api { err })?;
+
+					Ok(())
+				},
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum RotateKeyError {
+	Api { err: KeysRawError },
+	UnsupportedOperation,
+}
+
+impl std::fmt::Display for RotateKeyError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			RotateKeyError::Api { err } => write!(f, "could not rotate key: {}", err),
+			RotateKeyError::UnsupportedOperation => f.write_str("could not rotate key: library does not implement rotate_key"),
+		}
+	}
+}
+
+impl std::error::Error for RotateKeyError {
+}
+
+impl Keys {
+	pub(crate) fn rewrap(
+		&mut self,
+		id: &std::ffi::CStr,
+		mechanism: sys::KEYGEN_ENCRYPT_MECHANISM,
+		parameters: *const std::ffi::c_void,
+		ciphertext: &[u8],
+	) -> Result<Vec<u8>, RewrapError> {
+		unsafe {
+			match self {
+				Keys::V2_0_0_0 { .. } |
+				Keys::V2_1_0_0 { rewrap: None, .. } => Err(RewrapError::UnsupportedOperation),
+
+				Keys::V2_1_0_0 { rewrap: Some(rewrap), .. } => {
+					let mut new_ciphertext_len = 0;
+
+					keys_fn(|| rewrap(
+						id.as_ptr(),
+						mechanism,
+						parameters,
+						ciphertext.as_ptr(),
+						ciphertext.len(),
+						std::ptr::null_mut(),
+						&mut new_ciphertext_len,
+					)).map_err(|err| RewrapError::Api { err })?;
+
+					let mut new_ciphertext = vec![0_u8; new_ciphertext_len];
+
+					keys_fn(|| rewrap(
+						id.as_ptr(),
+						mechanism,
+						parameters,
+						ciphertext.as_ptr(),
+						ciphertext.len(),
+						new_ciphertext.as_mut_ptr(),
+						&mut new_ciphertext_len,
+					)).map_err(|err| RewrapError::Api { err })?;
+
+					if new_ciphertext_len > new_ciphertext.len() {
+						// libaziot-keys scribbled past the end of the buffer. Crash as soon as possible.
+						std::process::abort();
+					}
+
+					new_ciphertext.truncate(new_ciphertext_len);
+
+					Ok(new_ciphertext)
+				},
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum RewrapError {
+	Api { err: KeysRawError },
+	UnsupportedOperation,
+}
+
+impl std::fmt::Display for RewrapError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			RewrapError::Api { err } => write!(f, "could not rewrap ciphertext: {}", err),
+			RewrapError::UnsupportedOperation => f.write_str("could not rewrap ciphertext: library does not implement rewrap"),
+		}
+	}
+}
+
+impl std::error::Error for RewrapError {
+}
+
 fn keys_fn(f: impl FnOnce() -> sys::KEYGEN_ERROR) -> Result<(), KeysRawError> {
 	match f() {
 		sys::KEYGEN_SUCCESS => Ok(()),
@@ -723,6 +1315,62 @@ unsafe fn get_key_pair_parameter_byte_buf(
 	Ok(value)
 }
 
+unsafe fn list_byte_buf(
+	list_fn: unsafe extern "C" fn(
+		list: *mut std::os::raw::c_char,
+		list_len: *mut usize,
+	) -> sys::KEYGEN_ERROR,
+) -> Result<Vec<u8>, KeysRawError> {
+	let mut list_len: usize = 0;
+
+	keys_fn(|| list_fn(
+		std::ptr::null_mut(),
+		&mut list_len,
+	))?;
+
+	let mut list = vec![0_u8; list_len];
+
+	keys_fn(|| list_fn(
+		list.as_mut_ptr() as _,
+		&mut list_len,
+	))?;
+
+	if list_len > list.len() {
+		// libaziot-keys scribbled past the end of the buffer. Crash as soon as possible.
+		std::process::abort();
+	}
+
+	list.truncate(list_len);
+
+	Ok(list)
+}
+
+/// Parses a list in the format written by `list_key_pairs` and `list_keys`, ie a sequence of entries of three NUL-terminated strings each.
+fn parse_list(list: &[u8], kind: aziot_key_common::KeyKind) -> Result<Vec<aziot_key_common::KeyInfo>, ListKeysError> {
+	let list = list.strip_suffix(b"\0").unwrap_or(list);
+	if list.is_empty() {
+		return Ok(vec![]);
+	}
+
+	let mut components = list.split(|&b| b == b'\0').map(|component| std::str::from_utf8(component).map_err(|_| ListKeysError::MalformedList));
+
+	let mut result = vec![];
+
+	while let Some(id) = components.next() {
+		let algorithm = components.next().ok_or(ListKeysError::MalformedList)?;
+		let backend = components.next().ok_or(ListKeysError::MalformedList)?;
+
+		result.push(aziot_key_common::KeyInfo {
+			id: id?.to_owned(),
+			kind,
+			algorithm: algorithm?.to_owned(),
+			backend: backend?.to_owned(),
+		});
+	}
+
+	Ok(result)
+}
+
 pub(crate) mod sys {
 	#![allow(
 		non_camel_case_types,
@@ -735,3 +1383,24 @@ pub(crate) mod sys {
 
 	include!("keys.generated.rs");
 }
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn parse_list() {
+		let list = b"device-id\0ec-p256\0filesystem\0device-ca\0rsa-2048\0pkcs11\0";
+		let entries = super::parse_list(list, aziot_key_common::KeyKind::KeyPair).unwrap();
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].id, "device-id");
+		assert_eq!(entries[0].kind, aziot_key_common::KeyKind::KeyPair);
+		assert_eq!(entries[0].algorithm, "ec-p256");
+		assert_eq!(entries[0].backend, "filesystem");
+		assert_eq!(entries[1].id, "device-ca");
+		assert_eq!(entries[1].algorithm, "rsa-2048");
+		assert_eq!(entries[1].backend, "pkcs11");
+
+		assert!(super::parse_list(b"", aziot_key_common::KeyKind::Key).unwrap().is_empty());
+
+		assert!(super::parse_list(b"device-id\0ec-p256\0", aziot_key_common::KeyKind::KeyPair).is_err());
+	}
+}
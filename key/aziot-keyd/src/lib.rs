@@ -3,22 +3,50 @@
 	clippy::let_and_return,
 )]
 
+mod acl;
+pub use acl::{Caller, Operation};
+
+
 mod error;
 pub use error::{Error, InternalError};
 
 pub mod keys;
 
+mod revocations;
+
+pub mod settings;
+
 pub struct Server {
 	keys: std::sync::Mutex<keys::Keys>,
+	acl: acl::Acl,
+	key_handle_validity: Option<std::time::Duration>,
+	revocations: std::sync::Mutex<revocations::Revocations>,
 }
 
 impl Server {
-	pub fn new() -> Result<Self, Error> {
-		let keys = keys::Keys::new()?;
+	pub fn new(
+		homedir: &std::path::Path,
+		aziot_keys_lib_path: Option<&std::path::Path>,
+		key_handles: settings::KeyHandles,
+		principals: Vec<settings::Principal>,
+	) -> Result<Self, Error> {
+		let keys = keys::Keys::new(aziot_keys_lib_path)?;
 		let keys = std::sync::Mutex::new(keys);
 
+		let acl = acl::Acl::new(principals);
+
+		let key_handle_validity = key_handles.validity.map(std::time::Duration::from_secs);
+
+		let revocations =
+			revocations::Revocations::load(homedir.join("revoked-key-handles.json"))
+			.map_err(|err| Error::Internal(InternalError::LoadRevocations(err)))?;
+		let revocations = std::sync::Mutex::new(revocations);
+
 		Ok(Server {
 			keys,
+			acl,
+			key_handle_validity,
+			revocations,
 		})
 	}
 
@@ -32,12 +60,19 @@ impl Server {
 
 	pub fn create_key_pair_if_not_exists(
 		&self,
+		caller: Caller,
 		id: &str,
 		preferred_algorithms: Option<&str>,
 	) -> Result<aziot_key_common::KeyHandle, Error> {
+		self.acl.authorize(caller, id, Some(Operation::Create))?;
+
 		let mut keys = self.keys.lock().expect("keys mutex poisoned");
 		let keys = &mut *keys;
 
+		if let Some(preferred_algorithms) = preferred_algorithms {
+			validate_preferred_algorithms(preferred_algorithms)?;
+		}
+
 		let id_cstr = std::ffi::CString::new(id.to_owned()).map_err(|err| Error::invalid_parameter("id", err))?;
 		let preferred_algorithms =
 			preferred_algorithms
@@ -46,33 +81,38 @@ impl Server {
 			.map_err(|err| Error::invalid_parameter("preferred_algorithms", err))?;
 		keys.create_key_pair_if_not_exists(&id_cstr, preferred_algorithms.as_ref().map(AsRef::as_ref))?;
 
-		let handle = key_id_to_handle(&KeyId::KeyPair(id.into()), keys)?;
+		let handle = self.key_id_to_handle(&KeyId::KeyPair(id.into()), keys)?;
 		Ok(handle)
 	}
 
 	pub fn load_key_pair(
 		&self,
+		caller: Caller,
 		id: &str,
 	) -> Result<aziot_key_common::KeyHandle, Error> {
+		self.acl.authorize(caller, id, None)?;
+
 		let mut keys = self.keys.lock().expect("keys mutex poisoned");
 		let keys = &mut *keys;
 
 		let id_cstr = std::ffi::CString::new(id.to_owned()).map_err(|err| Error::invalid_parameter("id", err))?;
 		keys.load_key_pair(&id_cstr)?;
 
-		let handle = key_id_to_handle(&KeyId::KeyPair(id.into()), keys)?;
+		let handle = self.key_id_to_handle(&KeyId::KeyPair(id.into()), keys)?;
 		Ok(handle)
 	}
 
 	pub fn get_key_pair_public_parameter(
 		&self,
+		caller: Caller,
 		handle: &aziot_key_common::KeyHandle,
 		parameter_name: &str,
 	) -> Result<String, Error> {
 		let mut keys = self.keys.lock().expect("keys mutex poisoned");
 		let keys = &mut *keys;
 
-		let (_, id_cstr) = key_handle_to_id(handle, keys)?;
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), None)?;
 
 		let parameter_value = keys.get_key_pair_public_parameter(&id_cstr, parameter_name)?;
 		Ok(parameter_value)
@@ -80,13 +120,19 @@ impl Server {
 
 	pub fn create_key_if_not_exists(
 		&self,
+		caller: Caller,
 		id: &str,
 		value: aziot_key_common::CreateKeyValue,
 	) -> Result<aziot_key_common::KeyHandle, Error> {
+		self.acl.authorize(caller, id, Some(Operation::Create))?;
+
 		let mut keys = self.keys.lock().expect("keys mutex poisoned");
 		let keys = &mut *keys;
 
 		let id_cstr = std::ffi::CString::new(id.to_owned()).map_err(|err| Error::invalid_parameter("id", err))?;
+		if id_cstr.as_c_str() == master_encryption_key_id() {
+			return Err(Error::invalid_parameter("id", "cannot create or import the master encryption key"));
+		}
 
 		match value {
 			aziot_key_common::CreateKeyValue::Generate { length } =>
@@ -96,12 +142,13 @@ impl Server {
 				keys.import_key(&id_cstr, &bytes)?,
 		}
 
-		let handle = key_id_to_handle(&KeyId::Key(id.into()), keys)?;
+		let handle = self.key_id_to_handle(&KeyId::Key(id.into()), keys)?;
 		Ok(handle)
 	}
 
 	pub fn sign(
 		&self,
+		caller: Caller,
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::SignMechanism,
 		digest: &[u8],
@@ -109,67 +156,36 @@ impl Server {
 		let mut keys = self.keys.lock().expect("keys mutex poisoned");
 		let keys = &mut *keys;
 
-		let (id, id_cstr) = key_handle_to_id(handle, keys)?;
-
-		let signature = match (id, mechanism) {
-			(KeyId::KeyPair(_), aziot_key_common::SignMechanism::Ecdsa) =>
-				keys.sign(&id_cstr, keys::sys::KEYGEN_SIGN_MECHANISM_ECDSA, std::ptr::null(), digest)?,
-
-			(KeyId::KeyPair(_), aziot_key_common::SignMechanism::RsaPkcs1 { message_digest }) => {
-				let message_digest = match message_digest {
-					aziot_key_common::RsaPkcs1MessageDigest::Sha1 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA1,
-					aziot_key_common::RsaPkcs1MessageDigest::Sha224 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA224,
-					aziot_key_common::RsaPkcs1MessageDigest::Sha256 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA256,
-					aziot_key_common::RsaPkcs1MessageDigest::Sha384 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA384,
-					aziot_key_common::RsaPkcs1MessageDigest::Sha512 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA512,
-				};
-
-				keys.sign(
-					&id_cstr,
-					keys::sys::KEYGEN_SIGN_MECHANISM_RSA_PKCS1,
-					&message_digest as *const _ as *const std::ffi::c_void,
-					digest,
-				)?
-			},
-
-			(KeyId::KeyPair(_), aziot_key_common::SignMechanism::RsaPss { mask_generation_function, salt_len }) => {
-				/*
-				let salt_len = std::convert::TryInto::try_into(salt_len).map_err(|err| Error::invalid_parameter("mechanism.salt_len", err))?;
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Sign))?;
 
-				let parameters = keys::sys::KEYGEN_SIGN_RSA_PSS_PARAMETERS {
-					mask_generation_function: match mask_generation_function {
-						aziot_key_common::RsaPssMaskGenerationFunction::Sha1 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA1,
-						aziot_key_common::RsaPssMaskGenerationFunction::Sha224 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA224,
-						aziot_key_common::RsaPssMaskGenerationFunction::Sha256 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA256,
-						aziot_key_common::RsaPssMaskGenerationFunction::Sha384 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA384,
-						aziot_key_common::RsaPssMaskGenerationFunction::Sha512 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA512,
-					},
+		let signature = with_sign_mechanism(&id, mechanism, |mechanism, parameters| Ok(keys.sign(&id_cstr, mechanism, parameters, digest)?))?;
 
-					salt_len,
-				};
-
-				keys.sign(&id_cstr, keys::sys::KEYGEN_SIGN_MECHANISM_RSA_PSS, message_digest, &parameters as *const _ as _, digest).map_err(Error::Sign)?
-				*/
+		Ok(signature)
+	}
 
-				unimplemented!("sign(RSA_PSS, {:?}, {})", mask_generation_function, salt_len);
-			},
+	pub fn verify(
+		&self,
+		caller: Caller,
+		handle: &aziot_key_common::KeyHandle,
+		mechanism: aziot_key_common::SignMechanism,
+		digest: &[u8],
+		signature: &[u8],
+	) -> Result<bool, Error> {
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+		let keys = &mut *keys;
 
-			(KeyId::Key(_), aziot_key_common::SignMechanism::HmacSha256) =>
-				keys.sign(
-					&id_cstr,
-					keys::sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256,
-					std::ptr::null(),
-					digest,
-				)?,
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Verify))?;
 
-			_ => return Err(Error::invalid_parameter("mechanism", "mechanism cannot be used with this key type")),
-		};
+		let ok = with_sign_mechanism(&id, mechanism, |mechanism, parameters| Ok(keys.verify(&id_cstr, mechanism, parameters, digest, signature)?))?;
 
-		Ok(signature)
+		Ok(ok)
 	}
 
 	pub fn encrypt(
 		&self,
+		caller: Caller,
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::EncryptMechanism,
 		plaintext: &[u8],
@@ -177,33 +193,64 @@ impl Server {
 		let mut keys = self.keys.lock().expect("keys mutex poisoned");
 		let keys = &mut *keys;
 
-		let (id, id_cstr) = key_handle_to_id(handle, keys)?;
-
-		let ciphertext = match (id, mechanism) {
-			(KeyId::Key(_), aziot_key_common::EncryptMechanism::Aead { iv, aad }) => {
-				let parameters = keys::sys::KEYGEN_ENCRYPT_AEAD_PARAMETERS {
-					iv: iv.as_ptr(),
-					iv_len: iv.len(),
-					aad: aad.as_ptr(),
-					aad_len: aad.len(),
-				};
-
-				keys.encrypt(
-					&id_cstr,
-					keys::sys::KEYGEN_ENCRYPT_MECHANISM_AEAD,
-					&parameters as *const _ as *const std::ffi::c_void,
-					plaintext,
-				)?
-			},
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Encrypt))?;
 
-			_ => return Err(Error::invalid_parameter("mechanism", "mechanism cannot be used with this key type")),
-		};
+		let ciphertext = with_encrypt_mechanism(&id, mechanism, |mechanism, parameters| Ok(keys.encrypt(&id_cstr, mechanism, parameters, plaintext)?))?;
 
 		Ok(ciphertext)
 	}
 
 	pub fn decrypt(
 		&self,
+		caller: Caller,
+		handle: &aziot_key_common::KeyHandle,
+		mechanism: aziot_key_common::EncryptMechanism,
+		ciphertext: &[u8],
+	) -> Result<Vec<u8>, Error> {
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+		let keys = &mut *keys;
+
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Decrypt))?;
+
+		let plaintext = with_encrypt_mechanism(&id, mechanism, |mechanism, parameters| Ok(keys.decrypt(&id_cstr, mechanism, parameters, ciphertext)?))?;
+
+		Ok(plaintext)
+	}
+
+	/// Rotates the key to a new random version of the same length.
+	///
+	/// Ciphertexts created by previous versions of the key can still be decrypted, and can be moved to the new version with [`Server::rewrap`].
+	pub fn rotate_key(
+		&self,
+		caller: Caller,
+		handle: &aziot_key_common::KeyHandle,
+	) -> Result<(), Error> {
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+		let keys = &mut *keys;
+
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Rotate))?;
+
+		if id_cstr.as_c_str() == master_encryption_key_id() {
+			return Err(Error::invalid_parameter("handle", "cannot rotate the master encryption key"));
+		}
+
+		match id {
+			KeyId::Key(_) => keys.rotate_key(&id_cstr)?,
+			KeyId::KeyPair(_) => return Err(Error::invalid_parameter("handle", "only keys can be rotated")),
+		}
+
+		Ok(())
+	}
+
+	/// Decrypts the ciphertext with whichever version of the key created it, and encrypts the plaintext again with the key's current version.
+	///
+	/// The plaintext is not returned to the caller, so this requires the caller to be allowed to both encrypt and decrypt with the key.
+	pub fn rewrap(
+		&self,
+		caller: Caller,
 		handle: &aziot_key_common::KeyHandle,
 		mechanism: aziot_key_common::EncryptMechanism,
 		ciphertext: &[u8],
@@ -211,29 +258,304 @@ impl Server {
 		let mut keys = self.keys.lock().expect("keys mutex poisoned");
 		let keys = &mut *keys;
 
-		let (id, id_cstr) = key_handle_to_id(handle, keys)?;
-
-		let plaintext = match (id, mechanism) {
-			(KeyId::Key(_), aziot_key_common::EncryptMechanism::Aead { iv, aad }) => {
-				let parameters = keys::sys::KEYGEN_ENCRYPT_AEAD_PARAMETERS {
-					iv: iv.as_ptr(),
-					iv_len: iv.len(),
-					aad: aad.as_ptr(),
-					aad_len: aad.len(),
-				};
-
-				keys.decrypt(
-					&id_cstr,
-					keys::sys::KEYGEN_ENCRYPT_MECHANISM_AEAD,
-					&parameters as *const _ as *const std::ffi::c_void,
-					ciphertext,
-				)?
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Decrypt))?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Encrypt))?;
+
+		if let KeyId::KeyPair(_) = id {
+			return Err(Error::invalid_parameter("handle", "only ciphertexts created by keys can be rewrapped"));
+		}
+
+		let new_ciphertext = with_encrypt_mechanism(&id, mechanism, |mechanism, parameters| Ok(keys.rewrap(&id_cstr, mechanism, parameters, ciphertext)?))?;
+
+		Ok(new_ciphertext)
+	}
+
+	/// Derives a shared secret from the EC key pair and the peer's public key using ECDH.
+	///
+	/// `peer_public_key` is the peer's public point in uncompressed form. The returned secret is the raw X coordinate of the shared point.
+	pub fn derive_shared_secret(
+		&self,
+		caller: Caller,
+		handle: &aziot_key_common::KeyHandle,
+		peer_public_key: &[u8],
+	) -> Result<Vec<u8>, Error> {
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+		let keys = &mut *keys;
+
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Derive))?;
+
+		let shared_secret = match id {
+			KeyId::KeyPair(_) => keys.derive_shared_secret(&id_cstr, peer_public_key)?,
+			KeyId::Key(_) => return Err(Error::invalid_parameter("handle", "shared secrets can only be derived from key pairs")),
+		};
+
+		Ok(shared_secret)
+	}
+
+	/// Like [`Server::derive_shared_secret`], but instead of returning the shared secret, stores it as a new key with the ID `derived_key_id`.
+	///
+	/// The new key can be used like any other key, such as for HMAC-SHA256 signatures and AEAD encryption.
+	pub fn derive_key(
+		&self,
+		caller: Caller,
+		handle: &aziot_key_common::KeyHandle,
+		peer_public_key: &[u8],
+		derived_key_id: &str,
+	) -> Result<aziot_key_common::KeyHandle, Error> {
+		self.acl.authorize(caller, derived_key_id, Some(Operation::Create))?;
+
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+		let keys = &mut *keys;
+
+		let (id, id_cstr) = self.key_handle_to_id(handle, keys)?;
+		self.acl.authorize(caller, id.as_str(), Some(Operation::Derive))?;
+
+		let derived_key_id_cstr =
+			std::ffi::CString::new(derived_key_id.to_owned())
+			.map_err(|err| Error::invalid_parameter("derived_key_id", err))?;
+		if derived_key_id_cstr.as_c_str() == master_encryption_key_id() {
+			return Err(Error::invalid_parameter("derived_key_id", "cannot overwrite the master encryption key"));
+		}
+		// Importing over an existing key would rotate it, so the key must not exist yet.
+		match keys.load_key(&derived_key_id_cstr) {
+			Ok(()) => return Err(Error::invalid_parameter("derived_key_id", "a key with this ID already exists")),
+			Err(keys::LoadKeyError { err: keys::KeysRawError(keys::sys::KEYGEN_ERROR_INVALID_PARAMETER) }) => (),
+			Err(err) => return Err(err.into()),
+		}
+
+		let shared_secret = match id {
+			KeyId::KeyPair(_) => keys.derive_shared_secret(&id_cstr, peer_public_key)?,
+			KeyId::Key(_) => return Err(Error::invalid_parameter("handle", "shared secrets can only be derived from key pairs")),
+		};
+
+		keys.import_key(&derived_key_id_cstr, &shared_secret)?;
+
+		let handle = self.key_id_to_handle(&KeyId::Key(derived_key_id.into()), keys)?;
+		Ok(handle)
+	}
+
+	/// Lists the key pairs that the caller may use.
+	pub fn list_key_pairs(
+		&self,
+		caller: Caller,
+	) -> Result<Vec<aziot_key_common::KeyInfo>, Error> {
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+
+		let mut key_pairs = keys.list_key_pairs()?;
+		key_pairs.retain(|key_pair| self.acl.authorize(caller, &key_pair.id, None).is_ok());
+
+		Ok(key_pairs)
+	}
+
+	/// Lists the keys that the caller may use.
+	pub fn list_keys(
+		&self,
+		caller: Caller,
+	) -> Result<Vec<aziot_key_common::KeyInfo>, Error> {
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+
+		let mut key_infos = keys.list_keys()?;
+		key_infos.retain(|key| self.acl.authorize(caller, &key.id, None).is_ok());
+
+		Ok(key_infos)
+	}
+
+	/// Deletes the key pair with the given ID and revokes all its outstanding handles.
+	///
+	/// It is not an error if the key pair does not exist.
+	pub fn delete_key_pair(
+		&self,
+		caller: Caller,
+		id: &str,
+	) -> Result<(), Error> {
+		self.acl.authorize(caller, id, Some(Operation::Delete))?;
+
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+		let keys = &mut *keys;
+
+		let id_cstr = std::ffi::CString::new(id.to_owned()).map_err(|err| Error::invalid_parameter("id", err))?;
+		if id_cstr.as_c_str() == master_encryption_key_id() {
+			return Err(Error::invalid_parameter("id", "cannot delete the master encryption key"));
+		}
+		keys.delete_key_pair(&id_cstr)?;
+
+		let mut revocations = self.revocations.lock().expect("revocations mutex poisoned");
+		revocations.revoke(aziot_key_common::KeyKind::KeyPair, id).map_err(|err| Error::Internal(InternalError::SaveRevocations(err)))?;
+
+		Ok(())
+	}
+
+	/// Deletes the key with the given ID and revokes all its outstanding handles.
+	///
+	/// It is not an error if the key does not exist.
+	pub fn delete_key(
+		&self,
+		caller: Caller,
+		id: &str,
+	) -> Result<(), Error> {
+		self.acl.authorize(caller, id, Some(Operation::Delete))?;
+
+		let mut keys = self.keys.lock().expect("keys mutex poisoned");
+		let keys = &mut *keys;
+
+		let id_cstr = std::ffi::CString::new(id.to_owned()).map_err(|err| Error::invalid_parameter("id", err))?;
+		if id_cstr.as_c_str() == master_encryption_key_id() {
+			return Err(Error::invalid_parameter("id", "cannot delete the master encryption key"));
+		}
+		keys.delete_key(&id_cstr)?;
+
+		let mut revocations = self.revocations.lock().expect("revocations mutex poisoned");
+		revocations.revoke(aziot_key_common::KeyKind::Key, id).map_err(|err| Error::Internal(InternalError::SaveRevocations(err)))?;
+
+		Ok(())
+	}
+
+	/// Revokes all outstanding handles of the key or key pair with the given ID.
+	///
+	/// Handles issued after this call are not affected, nor are the handles of a key pair with the same ID as a revoked key or vice versa.
+	pub fn revoke_key_handles(
+		&self,
+		caller: Caller,
+		id: &str,
+		kind: aziot_key_common::KeyKind,
+	) -> Result<(), Error> {
+		self.acl.authorize(caller, id, Some(Operation::Revoke))?;
+
+		let mut revocations = self.revocations.lock().expect("revocations mutex poisoned");
+		revocations.revoke(kind, id).map_err(|err| Error::Internal(InternalError::SaveRevocations(err)))?;
+
+		Ok(())
+	}
+
+	fn key_handle_to_id(&self, handle: &aziot_key_common::KeyHandle, keys: &mut keys::Keys) -> Result<(KeyId<'static>, std::ffi::CString), Error> {
+		// DEVNOTE:
+		//
+		// Map errors from using the master encryption key to Error::Internal instead of relying on `?`,
+		// because all errors from using the master encryption key are internal errors.
+
+		let params = handle.0.split('&');
+
+		let mut sr = None;
+		let mut se = None;
+		let mut sig = None;
+
+		for param in params {
+			if param.starts_with("sr=") {
+				let value = &param["sr=".len()..];
+				let value = base64::decode(value.as_bytes()).map_err(|_| Error::InvalidHandle("malformed"))?;
+				let value = String::from_utf8(value).map_err(|_| Error::InvalidHandle("malformed"))?;
+				sr = Some(value);
+			}
+			else if param.starts_with("se=") {
+				let value = &param["se=".len()..];
+				let value: u64 = value.parse().map_err(|_| Error::InvalidHandle("malformed"))?;
+				se = Some(value);
+			}
+			else if param.starts_with("sig=") {
+				let value = &param["sig=".len()..];
+				let value = base64::decode(value.as_bytes()).map_err(|_| Error::InvalidHandle("malformed"))?;
+				sig = Some(value);
+			}
+		}
+
+		let sr = sr.ok_or(Error::InvalidHandle("malformed"))?;
+		let sig = sig.ok_or(Error::InvalidHandle("malformed"))?;
+
+		let master_encryption_key_id = master_encryption_key_id();
+		keys.create_key_if_not_exists(master_encryption_key_id, 32).map_err(|err| Error::Internal(InternalError::CreateKeyIfNotExistsGenerate(err)))?;
+		let ok =
+			keys.verify(
+				master_encryption_key_id,
+				keys::sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256,
+				std::ptr::null(),
+				signed_payload(&sr, se).as_bytes(),
+				&sig,
+			).map_err(|err| Error::Internal(InternalError::Verify(err)))?;
+		if !ok {
+			return Err(Error::InvalidHandle("signature does not match"));
+		}
+
+		if let Some(se) = se {
+			if unix_time_now() > se {
+				return Err(Error::InvalidHandle("expired"));
+			}
+		}
+		else if self.key_handle_validity.is_some() {
+			// Handles issued before a validity was configured never expire, so they must not be accepted once it is.
+			return Err(Error::InvalidHandle("does not expire"));
+		}
+
+		let sr: Sr<'static> = serde_json::from_str(&sr).map_err(|_| Error::InvalidHandle("malformed"))?;
+
+		let revocations = self.revocations.lock().expect("revocations mutex poisoned");
+		if sr.generation != revocations.generation(sr.key_id.kind(), sr.key_id.as_str()) {
+			return Err(Error::InvalidHandle("revoked"));
+		}
+
+		let id = sr.key_id;
+
+		let id_cstr = match &id {
+			KeyId::KeyPair(id) => {
+				let id_cstr = std::ffi::CString::new(id.clone().into_owned()).map_err(|_| Error::InvalidHandle("malformed"))?;
+				id_cstr
 			},
 
-			_ => return Err(Error::invalid_parameter("mechanism", "mechanism cannot be used with this key type")),
+			KeyId::Key(id) => {
+				let id_cstr = std::ffi::CString::new(id.clone().into_owned()).map_err(|_| Error::InvalidHandle("malformed"))?;
+				id_cstr
+			},
 		};
 
-		Ok(plaintext)
+		Ok((id, id_cstr))
+	}
+
+	fn key_id_to_handle(&self, id: &KeyId<'_>, keys: &mut keys::Keys) -> Result<aziot_key_common::KeyHandle, Error> {
+		let se = self.key_handle_validity.map(|validity| unix_time_now().saturating_add(validity.as_secs()));
+		self.issue_handle(id, se, keys)
+	}
+
+	/// Creates a handle for the given key ID that expires at `se`, or never expires if `se` is `None`.
+	fn issue_handle(&self, id: &KeyId<'_>, se: Option<u64>, keys: &mut keys::Keys) -> Result<aziot_key_common::KeyHandle, Error> {
+		let sr = {
+			let mut nonce = [0_u8; 64];
+			openssl::rand::rand_bytes(&mut nonce).map_err(|err| Error::Internal(InternalError::GenerateNonce(err)))?;
+			let nonce = base64::encode(&nonce[..]);
+
+			let revocations = self.revocations.lock().expect("revocations mutex poisoned");
+
+			let sr = Sr {
+				key_id: id.borrow(),
+				nonce,
+				generation: revocations.generation(id.kind(), id.as_str()),
+			};
+			let sr = serde_json::to_string(&sr).expect("cannot fail to convert Sr to JSON");
+			sr
+		};
+
+		let master_encryption_key_id = master_encryption_key_id();
+		keys.create_key_if_not_exists(master_encryption_key_id, 32).map_err(|err| Error::Internal(InternalError::CreateKeyIfNotExistsGenerate(err)))?;
+		let sig =
+			keys.sign(
+				master_encryption_key_id,
+				keys::sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256,
+				std::ptr::null(),
+				signed_payload(&sr, se).as_bytes(),
+			).map_err(|err| Error::Internal(InternalError::Sign(err)))?;
+
+		// This *could* use percent-encoding instead of string concat. However, the only potential problem with base64-encoded values can arise from a trailing =,
+		// since = is also used between a key and its value. But that usage of = is not ambiguous, so it isn't a problem.
+		let token =
+			if let Some(se) = se {
+				format!("sr={}&se={}&sig={}", base64::encode(sr.as_bytes()), se, base64::encode(&sig))
+			}
+			else {
+				format!("sr={}&sig={}", base64::encode(sr.as_bytes()), base64::encode(&sig))
+			};
+
+		let handle = aziot_key_common::KeyHandle(token);
+		Ok(handle)
 	}
 }
 
@@ -251,6 +573,20 @@ impl KeyId<'_> {
 			KeyId::Key(id) => KeyId::Key(std::borrow::Cow::Borrowed(&*id)),
 		}
 	}
+
+	fn as_str(&self) -> &str {
+		match self {
+			KeyId::KeyPair(id) => id,
+			KeyId::Key(id) => id,
+		}
+	}
+
+	fn kind(&self) -> aziot_key_common::KeyKind {
+		match self {
+			KeyId::KeyPair(_) => aziot_key_common::KeyKind::KeyPair,
+			KeyId::Key(_) => aziot_key_common::KeyKind::Key,
+		}
+	}
 }
 
 fn master_encryption_key_id() -> &'static std::ffi::CStr {
@@ -262,99 +598,364 @@ fn master_encryption_key_id() -> &'static std::ffi::CStr {
 struct Sr<'a> {
 	key_id: KeyId<'a>,
 	nonce: String,
+
+	/// The revocation generation of the key ID when the handle was issued. See `revocations::Revocations`.
+	#[serde(default)]
+	generation: u64,
+}
+
+/// Converts `mechanism` into the libaziot-keys mechanism and parameters that `f` should pass to `Keys::sign` or `Keys::verify`.
+fn with_sign_mechanism<T>(
+	id: &KeyId<'_>,
+	mechanism: aziot_key_common::SignMechanism,
+	f: impl FnOnce(keys::sys::KEYGEN_SIGN_MECHANISM, *const std::ffi::c_void) -> Result<T, Error>,
+) -> Result<T, Error> {
+	match (id, mechanism) {
+		(KeyId::KeyPair(_), aziot_key_common::SignMechanism::Ecdsa) =>
+			f(keys::sys::KEYGEN_SIGN_MECHANISM_ECDSA, std::ptr::null()),
+
+		(KeyId::KeyPair(_), aziot_key_common::SignMechanism::EdDsa) =>
+			f(keys::sys::KEYGEN_SIGN_MECHANISM_EDDSA, std::ptr::null()),
+
+		(KeyId::KeyPair(_), aziot_key_common::SignMechanism::RsaPkcs1 { message_digest }) => {
+			let message_digest = match message_digest {
+				aziot_key_common::RsaPkcs1MessageDigest::Sha1 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA1,
+				aziot_key_common::RsaPkcs1MessageDigest::Sha224 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA224,
+				aziot_key_common::RsaPkcs1MessageDigest::Sha256 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA256,
+				aziot_key_common::RsaPkcs1MessageDigest::Sha384 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA384,
+				aziot_key_common::RsaPkcs1MessageDigest::Sha512 => keys::sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA512,
+			};
+
+			f(
+				keys::sys::KEYGEN_SIGN_MECHANISM_RSA_PKCS1,
+				&message_digest as *const _ as *const std::ffi::c_void,
+			)
+		},
+
+		(KeyId::KeyPair(_), aziot_key_common::SignMechanism::RsaPss { mask_generation_function, salt_len }) => {
+			let parameters = keys::sys::KEYGEN_SIGN_RSA_PSS_PARAMETERS {
+				mask_generation_function: match mask_generation_function {
+					aziot_key_common::RsaPssMaskGenerationFunction::Sha1 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA1,
+					aziot_key_common::RsaPssMaskGenerationFunction::Sha224 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA224,
+					aziot_key_common::RsaPssMaskGenerationFunction::Sha256 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA256,
+					aziot_key_common::RsaPssMaskGenerationFunction::Sha384 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA384,
+					aziot_key_common::RsaPssMaskGenerationFunction::Sha512 => keys::sys::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA512,
+				},
+
+				salt_len,
+			};
+
+			f(
+				keys::sys::KEYGEN_SIGN_MECHANISM_RSA_PSS,
+				&parameters as *const _ as *const std::ffi::c_void,
+			)
+		},
+
+		(KeyId::Key(_), aziot_key_common::SignMechanism::HmacSha256) =>
+			f(keys::sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256, std::ptr::null()),
+
+		_ => Err(Error::invalid_parameter("mechanism", "mechanism cannot be used with this key type")),
+	}
 }
 
-fn key_handle_to_id(handle: &aziot_key_common::KeyHandle, keys: &mut keys::Keys) -> Result<(KeyId<'static>, std::ffi::CString), Error> {
-	// DEVNOTE:
-	//
-	// Map errors from using the master encryption key to Error::Internal instead of relying on `?`,
-	// because all errors from using the master encryption key are internal errors.
+/// Converts `mechanism` into the libaziot-keys mechanism and parameters that `f` should pass to `Keys::encrypt`, `Keys::decrypt` or `Keys::rewrap`.
+fn with_encrypt_mechanism<T>(
+	id: &KeyId<'_>,
+	mechanism: aziot_key_common::EncryptMechanism,
+	f: impl FnOnce(keys::sys::KEYGEN_ENCRYPT_MECHANISM, *const std::ffi::c_void) -> Result<T, Error>,
+) -> Result<T, Error> {
+	match (id, mechanism) {
+		(KeyId::Key(_), aziot_key_common::EncryptMechanism::Aead { iv, aad }) => {
+			let parameters = keys::sys::KEYGEN_ENCRYPT_AEAD_PARAMETERS {
+				iv: iv.as_ptr(),
+				iv_len: iv.len(),
+				aad: aad.as_ptr(),
+				aad_len: aad.len(),
+			};
+
+			f(
+				keys::sys::KEYGEN_ENCRYPT_MECHANISM_AEAD,
+				&parameters as *const _ as *const std::ffi::c_void,
+			)
+		},
 
-	let params = handle.0.split('&');
+		(KeyId::Key(_), aziot_key_common::EncryptMechanism::AeadGeneratedNonce { aad }) => {
+			let parameters = keys::sys::KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS {
+				aad: aad.as_ptr(),
+				aad_len: aad.len(),
+			};
 
-	let mut sr = None;
-	let mut sig = None;
+			f(
+				keys::sys::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE,
+				&parameters as *const _ as *const std::ffi::c_void,
+			)
+		},
+
+		(KeyId::KeyPair(_), aziot_key_common::EncryptMechanism::RsaOaep) =>
+			f(keys::sys::KEYGEN_ENCRYPT_MECHANISM_RSA_OAEP, std::ptr::null()),
 
-	for param in params {
-		if param.starts_with("sr=") {
-			let value = &param["sr=".len()..];
-			let value = base64::decode(value.as_bytes()).map_err(|_| Error::invalid_parameter("handle", "invalid handle"))?;
-			let value = String::from_utf8(value).map_err(|_| Error::invalid_parameter("handle", "invalid handle"))?;
-			sr = Some(value);
+		_ => Err(Error::invalid_parameter("mechanism", "mechanism cannot be used with this key type")),
+	}
+}
+
+/// Checks that every component of `preferred_algorithms` is one of the algorithm names of libaziot-keys 2.1.
+///
+/// libaziot-keys 2.0 implementations ignore components they do not recognize, so this is checked here
+/// to reject typos the same way regardless of the version of the library.
+fn validate_preferred_algorithms(preferred_algorithms: &str) -> Result<(), Error> {
+	for component in preferred_algorithms.split(':') {
+		match component {
+			"ec-p256" | "ec-p384" | "ec-p521" | "ed25519" | "rsa-2048" | "rsa-4096" | "*" => (),
+			component => return Err(Error::invalid_parameter("preferred_algorithms", format!("unrecognized algorithm {:?}", component))),
 		}
-		else if param.starts_with("sig=") {
-			let value = &param["sig=".len()..];
-			let value = base64::decode(value.as_bytes()).map_err(|_| Error::invalid_parameter("handle", "invalid handle"))?;
-			sig = Some(value);
+	}
+
+	Ok(())
+}
+
+/// The payload that is signed with the master encryption key to create the `sig` of a key handle.
+///
+/// The expiry is part of the payload so that it cannot be removed or extended without invalidating the signature.
+fn signed_payload(sr: &str, se: Option<u64>) -> std::borrow::Cow<'_, str> {
+	match se {
+		Some(se) => format!("{}\n{}", sr, se).into(),
+		None => sr.into(),
+	}
+}
+
+fn unix_time_now() -> u64 {
+	std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.expect("system time is before the Unix epoch")
+		.as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+	const UID: u32 = 1000;
+
+	lazy_static::lazy_static! {
+		static ref LIBAZIOT_KEYS_LOCK: std::sync::Mutex<()> = Default::default();
+	}
+
+	/// A homedir for the built-in libaziot-keys.
+	///
+	/// libaziot-keys is configured per process, so a test holds the lock for as long as it uses the homedir.
+	struct Homedir {
+		dir: tempfile::TempDir,
+		_lock: std::sync::MutexGuard<'static, ()>,
+	}
+
+	fn homedir() -> Homedir {
+		let lock = LIBAZIOT_KEYS_LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		let dir = tempfile::tempdir().unwrap();
+		Homedir { dir, _lock: lock }
+	}
+
+	/// Creates a server backed by the built-in libaziot-keys that allows `UID` to perform every operation on every key.
+	fn server(homedir: &Homedir, validity: Option<u64>) -> super::Server {
+		let homedir = homedir.dir.path();
+
+		let principals = vec![crate::settings::Principal {
+			uid: Some(UID),
+			name: None,
+			keys: vec!["*".to_owned()],
+			operations: vec![
+				super::Operation::Create,
+				super::Operation::Sign,
+				super::Operation::Verify,
+				super::Operation::Encrypt,
+				super::Operation::Decrypt,
+				super::Operation::Derive,
+				super::Operation::Rotate,
+				super::Operation::Revoke,
+				super::Operation::Delete,
+			],
+		}];
+
+		let mut server = super::Server::new(homedir, None, crate::settings::KeyHandles { validity }, principals).unwrap();
+
+		let value = std::ffi::CString::new(std::os::unix::ffi::OsStrExt::as_bytes(homedir.as_os_str())).unwrap();
+		server.set_parameter(std::ffi::CStr::from_bytes_with_nul(b"HOMEDIR_PATH\0").unwrap(), &value).unwrap();
+
+		server
+	}
+
+	#[test]
+	fn create_key_pair_rejects_unrecognized_algorithms() {
+		let homedir = homedir();
+		let server = server(&homedir, None);
+		let caller = super::Caller::Uid(UID);
+
+		for &preferred_algorithms in &["dsa", "ec-p256:ed448", "ec-p256:", "EC-P256"] {
+			let err = server.create_key_pair_if_not_exists(caller, "unrecognized-algorithm-key-pair", Some(preferred_algorithms)).unwrap_err();
+			assert!(matches!(err, super::Error::InvalidParameter(Some(("preferred_algorithms", _)))), "{:?}: {:?}", preferred_algorithms, err);
 		}
 	}
 
-	let sr = sr.ok_or_else(|| Error::invalid_parameter("handle", "invalid handle"))?;
-	let sig = sig.ok_or_else(|| Error::invalid_parameter("handle", "invalid handle"))?;
+	#[test]
+	fn derive_key_does_not_overwrite() {
+		let homedir = homedir();
+		let server = server(&homedir, None);
+		let caller = super::Caller::Uid(UID);
+
+		let handle = server.create_key_pair_if_not_exists(caller, "derive-key-pair", Some("ec-p256")).unwrap();
+
+		let group = openssl::ec::EcGroup::from_curve_name(openssl::nid::Nid::X9_62_PRIME256V1).unwrap();
+		let peer_key = openssl::ec::EcKey::generate(&group).unwrap();
+		let mut ctx = openssl::bn::BigNumContext::new().unwrap();
+		let peer_public_key =
+			peer_key.public_key().to_bytes(&group, openssl::ec::PointConversionForm::UNCOMPRESSED, &mut ctx).unwrap();
 
-	let master_encryption_key_id = master_encryption_key_id();
-	keys.create_key_if_not_exists(master_encryption_key_id, 32).map_err(|err| Error::Internal(InternalError::CreateKeyIfNotExistsGenerate(err)))?;
-	let ok =
-		keys.verify(
-			master_encryption_key_id,
-			keys::sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256,
-			std::ptr::null(),
-			sr.as_bytes(),
-			&sig,
-		).map_err(|err| Error::Internal(InternalError::Verify(err)))?;
-	if !ok {
-		return Err(Error::invalid_parameter("handle", "invalid handle"));
+		server.derive_key(caller, &handle, &peer_public_key, "derive-key-derived").unwrap();
+
+		let err = server.derive_key(caller, &handle, &peer_public_key, "derive-key-derived").unwrap_err();
+		assert!(matches!(err, super::Error::InvalidParameter(Some(("derived_key_id", _)))), "{:?}", err);
 	}
 
-	let sr: Sr<'static> = serde_json::from_str(&sr).map_err(|_| Error::invalid_parameter("handle", "invalid handle"))?;
+	#[test]
+	fn derive_key_does_not_overwrite_unindexed_key() {
+		let homedir = homedir();
+		let server = server(&homedir, None);
+		let caller = super::Caller::Uid(UID);
+
+		let handle = server.create_key_pair_if_not_exists(caller, "derive-key-pair-unindexed", Some("ec-p256")).unwrap();
+
+		let group = openssl::ec::EcGroup::from_curve_name(openssl::nid::Nid::X9_62_PRIME256V1).unwrap();
+		let peer_key = openssl::ec::EcKey::generate(&group).unwrap();
+		let mut ctx = openssl::bn::BigNumContext::new().unwrap();
+		let peer_public_key =
+			peer_key.public_key().to_bytes(&group, openssl::ec::PointConversionForm::UNCOMPRESSED, &mut ctx).unwrap();
+
+		// A key file written directly to the homedir, like one created before libaziot-keys kept an index of its keys.
+		let key_path = {
+			let filename = openssl::hash::hash(openssl::hash::MessageDigest::sha256(), b"derive-key-unindexed").unwrap();
+			let filename: String = filename.iter().map(|b| format!("{:02x}", b)).collect();
+			homedir.dir.path().join(format!("{}.key", filename))
+		};
+		std::fs::write(&key_path, [0x01_u8; 32]).unwrap();
 
-	let id = sr.key_id;
+		let err = server.derive_key(caller, &handle, &peer_public_key, "derive-key-unindexed").unwrap_err();
+		assert!(matches!(err, super::Error::InvalidParameter(Some(("derived_key_id", _)))), "{:?}", err);
 
-	let id_cstr = match &id {
-		KeyId::KeyPair(id) => {
-			let id_cstr = std::ffi::CString::new(id.clone().into_owned()).map_err(|err| Error::invalid_parameter("handle", err))?;
-			id_cstr
-		},
+		assert_eq!(std::fs::read(&key_path).unwrap(), [0x01_u8; 32]);
+	}
 
-		KeyId::Key(id) => {
-			let id_cstr = std::ffi::CString::new(id.clone().into_owned()).map_err(|err| Error::invalid_parameter("handle", err))?;
-			id_cstr
-		},
-	};
+	#[test]
+	fn rotate_key() {
+		let homedir = homedir();
+		let server = server(&homedir, None);
+		let caller = super::Caller::Uid(UID);
+		let mechanism = aziot_key_common::EncryptMechanism::AeadGeneratedNonce { aad: b"aad".to_vec() };
 
-	Ok((id, id_cstr))
-}
+		let handle = server.create_key_if_not_exists(caller, "rotate-key", aziot_key_common::CreateKeyValue::Generate { length: 32 }).unwrap();
+		let old_ciphertext = server.encrypt(caller, &handle, mechanism.clone(), b"plaintext").unwrap();
+
+		server.rotate_key(caller, &handle).unwrap();
+
+		let new_ciphertext = server.rewrap(caller, &handle, mechanism.clone(), &old_ciphertext).unwrap();
+		assert_ne!(new_ciphertext, old_ciphertext);
+
+		for ciphertext in &[old_ciphertext, new_ciphertext] {
+			assert_eq!(server.decrypt(caller, &handle, mechanism.clone(), ciphertext).unwrap(), b"plaintext");
+		}
+	}
 
-fn key_id_to_handle(id: &KeyId<'_>, keys: &mut keys::Keys) -> Result<aziot_key_common::KeyHandle, Error> {
-	let sr = {
-		let mut nonce = [0_u8; 64];
-		openssl::rand::rand_bytes(&mut nonce).map_err(|err| Error::Internal(InternalError::GenerateNonce(err)))?;
-		let nonce = base64::encode(&nonce[..]);
+	#[test]
+	fn rotate_key_rejects_master_encryption_key() {
+		let homedir = homedir();
+		let server = server(&homedir, None);
+		let caller = super::Caller::Uid(UID);
 
-		let sr = Sr {
-			key_id: id.borrow(),
-			nonce,
+		let key_handle = server.create_key_if_not_exists(caller, "rotate-master-encryption-key", aziot_key_common::CreateKeyValue::Generate { length: 32 }).unwrap();
+
+		let master_encryption_key_handle = {
+			let mut keys = server.keys.lock().unwrap();
+			let id = super::master_encryption_key_id().to_str().unwrap();
+			server.issue_handle(&super::KeyId::Key(id.into()), None, &mut keys).unwrap()
 		};
-		let sr = serde_json::to_string(&sr).expect("cannot fail to convert Sr to JSON");
-		sr
-	};
-
-	let master_encryption_key_id = master_encryption_key_id();
-	keys.create_key_if_not_exists(master_encryption_key_id, 32).map_err(|err| Error::Internal(InternalError::CreateKeyIfNotExistsGenerate(err)))?;
-	let sig =
-		keys.sign(
-			master_encryption_key_id,
-			keys::sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256,
-			std::ptr::null(),
-			sr.as_bytes(),
-		).map_err(|err| Error::Internal(InternalError::Sign(err)))?;
-
-	// TODO: se for expiry
-
-	// This *could* use percent-encoding instead of string concat. However, the only potential problem with base64-encoded values can arise from a trailing =,
-	// since = is also used between a key and its value. But that usage of = is not ambiguous, so it isn't a problem.
-	let token = format!("sr={}&sig={}", base64::encode(sr.as_bytes()), base64::encode(&sig));
-
-	let handle = aziot_key_common::KeyHandle(token);
-	Ok(handle)
+
+		let err = server.rotate_key(caller, &master_encryption_key_handle).unwrap_err();
+		assert!(matches!(err, super::Error::InvalidParameter(Some(("handle", _)))), "{:?}", err);
+
+		// Handles signed by the master encryption key are still valid.
+		server.sign(caller, &key_handle, aziot_key_common::SignMechanism::HmacSha256, b"message").unwrap();
+	}
+
+	#[test]
+	fn create_key_rejects_master_encryption_key() {
+		let homedir = homedir();
+		let server = server(&homedir, None);
+		let caller = super::Caller::Uid(UID);
+
+		let id = super::master_encryption_key_id().to_str().unwrap();
+
+		let err = server.create_key_if_not_exists(caller, id, aziot_key_common::CreateKeyValue::Generate { length: 32 }).unwrap_err();
+		assert!(matches!(err, super::Error::InvalidParameter(Some(("id", _)))), "{:?}", err);
+
+		let err = server.create_key_if_not_exists(caller, id, aziot_key_common::CreateKeyValue::Import { bytes: vec![0x01; 32] }).unwrap_err();
+		assert!(matches!(err, super::Error::InvalidParameter(Some(("id", _)))), "{:?}", err);
+	}
+
+	fn assert_invalid_handle(result: Result<Vec<u8>, super::Error>, expected_reason: &str) {
+		match result {
+			Err(super::Error::InvalidHandle(reason)) => assert_eq!(reason, expected_reason),
+			result => panic!("expected InvalidHandle({:?}) but got {:?}", expected_reason, result),
+		}
+	}
+
+	#[test]
+	fn expired_handle() {
+		let homedir = homedir();
+		let server = server(&homedir, Some(60));
+		let caller = super::Caller::Uid(UID);
+
+		let handle = server.create_key_if_not_exists(caller, "handle-expired", aziot_key_common::CreateKeyValue::Generate { length: 32 }).unwrap();
+		server.sign(caller, &handle, aziot_key_common::SignMechanism::HmacSha256, b"message").unwrap();
+
+		let expired_handle = {
+			let mut keys = server.keys.lock().unwrap();
+			server.issue_handle(&super::KeyId::Key("handle-expired".into()), Some(1), &mut keys).unwrap()
+		};
+		assert_invalid_handle(server.sign(caller, &expired_handle, aziot_key_common::SignMechanism::HmacSha256, b"message"), "expired");
+	}
+
+	#[test]
+	fn handle_without_expiry() {
+		let homedir = homedir();
+		let caller = super::Caller::Uid(UID);
+
+		let handle = server(&homedir, None).create_key_if_not_exists(caller, "handle-without-expiry", aziot_key_common::CreateKeyValue::Generate { length: 32 }).unwrap();
+
+		// Once a validity is configured, handles that were issued without an expiry are rejected.
+		let server = server(&homedir, Some(60));
+		assert_invalid_handle(server.sign(caller, &handle, aziot_key_common::SignMechanism::HmacSha256, b"message"), "does not expire");
+	}
+
+	#[test]
+	fn revoked_handle() {
+		let homedir = homedir();
+		let server = server(&homedir, None);
+		let caller = super::Caller::Uid(UID);
+
+		let key_handle = server.create_key_if_not_exists(caller, "handle-revoked", aziot_key_common::CreateKeyValue::Generate { length: 32 }).unwrap();
+		let key_pair_handle = {
+			let mut keys = server.keys.lock().unwrap();
+			server.issue_handle(&super::KeyId::KeyPair("handle-revoked".into()), None, &mut keys).unwrap()
+		};
+
+		server.revoke_key_handles(caller, "handle-revoked", aziot_key_common::KeyKind::Key).unwrap();
+		assert_invalid_handle(server.sign(caller, &key_handle, aziot_key_common::SignMechanism::HmacSha256, b"message"), "revoked");
+
+		// Handles of a key pair with the same ID are not affected.
+		{
+			let mut keys = server.keys.lock().unwrap();
+			let (id, _) = server.key_handle_to_id(&key_pair_handle, &mut keys).unwrap();
+			assert!(matches!(id, super::KeyId::KeyPair(id) if id == "handle-revoked"));
+		}
+
+		// Handles issued after the revocation are valid.
+		let key_handle = server.create_key_if_not_exists(caller, "handle-revoked", aziot_key_common::CreateKeyValue::Generate { length: 32 }).unwrap();
+		server.sign(caller, &key_handle, aziot_key_common::SignMechanism::HmacSha256, b"message").unwrap();
+	}
 }
@@ -9,53 +9,86 @@ mod http;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-	let mut server = aziot_keyd::Server::new()?;
-
-	// TODO: Read these from config
-	if let Some(value) = std::env::var_os("HOMEDIR_PATH") {
-		let value = std::os::unix::ffi::OsStringExt::into_vec(value);
-		let value = std::ffi::CString::new(value).unwrap();
-
+	let config_path: std::path::PathBuf =
+		std::env::var_os("AZIOT_KEYD_CONFIG")
+		.unwrap_or_else(|| "/etc/aziot/keyd/config.toml".into())
+		.into();
+
+	let aziot_keyd::settings::Settings {
+		homedir,
+		aziot_keys_lib_path,
+		key_encryption_key,
+		pkcs11,
+		preloaded_keys,
+		listen,
+		key_handles,
+		principals,
+	} = aziot_keyd::settings::Settings::new(&config_path)?;
+
+	let mut server = aziot_keyd::Server::new(&homedir, aziot_keys_lib_path.as_deref(), key_handles, principals)?;
+
+	{
+		let value = std::os::unix::ffi::OsStrExt::as_bytes(homedir.as_os_str());
+		let value = std::ffi::CString::new(value)?;
 		server.set_parameter(
 			std::ffi::CStr::from_bytes_with_nul(b"HOMEDIR_PATH\0").unwrap(),
 			&value,
 		)?;
 	}
 
-	if let Ok(value) = std::env::var("PKCS11_LIB_PATH") {
-		let value = std::ffi::CString::new(value).unwrap();
+	if let Some(key_encryption_key) = key_encryption_key {
+		let value = std::ffi::CString::new(key_encryption_key)?;
 		server.set_parameter(
-			std::ffi::CStr::from_bytes_with_nul(b"PKCS11_LIB_PATH\0").unwrap(),
+			std::ffi::CStr::from_bytes_with_nul(b"KEY_ENCRYPTION_KEY\0").unwrap(),
 			&value,
 		)?;
 	}
 
-	if let Ok(value) = std::env::var("PKCS11_BASE_SLOT") {
-		let value = std::ffi::CString::new(value).unwrap();
+	if let Some(aziot_keyd::settings::Pkcs11 { lib_path, base_slot }) = pkcs11 {
+		let value = std::os::unix::ffi::OsStrExt::as_bytes(lib_path.as_os_str());
+		let value = std::ffi::CString::new(value)?;
 		server.set_parameter(
-			std::ffi::CStr::from_bytes_with_nul(b"PKCS11_BASE_SLOT\0").unwrap(),
+			std::ffi::CStr::from_bytes_with_nul(b"PKCS11_LIB_PATH\0").unwrap(),
 			&value,
 		)?;
-	}
 
-	for (key, value) in std::env::vars() {
-		if key.starts_with("PRELOADED_KEY:") {
-			let key = std::ffi::CString::new(key).unwrap();
-			let value = std::ffi::CString::new(value).unwrap();
-			server.set_parameter(&key, &value)?;
+		if let Some(base_slot) = base_slot {
+			let value = std::ffi::CString::new(base_slot)?;
+			server.set_parameter(
+				std::ffi::CStr::from_bytes_with_nul(b"PKCS11_BASE_SLOT\0").unwrap(),
+				&value,
+			)?;
 		}
 	}
 
+	for (key_id, location) in preloaded_keys {
+		let name = std::ffi::CString::new(format!("PRELOADED_KEY:{}", key_id))?;
+		let value = std::ffi::CString::new(location.as_str())?;
+		server.set_parameter(&name, &value)?;
+	}
+
 	let server = std::sync::Arc::new(server);
 
 	eprintln!("Starting server...");
 
-	let incoming = hyper::server::conn::AddrIncoming::bind(&"0.0.0.0:8888".parse()?)?;
+	let incoming = http_common::Connector::new(&listen.api_uri)?.incoming().await?;
 
 	let server =
 		hyper::Server::builder(incoming)
-		.serve(hyper::service::make_service_fn(|_| {
-			let server = http::Server { inner: server.clone() };
+		.serve(hyper::service::make_service_fn(|stream: &http_common::AsyncStream| {
+			let caller = match stream {
+				http_common::AsyncStream::Unix(stream) => match stream.peer_cred() {
+					Ok(ucred) => aziot_keyd::Caller::Uid(ucred.uid),
+					Err(err) => {
+						eprintln!("could not get peer credentials of connection: {}", err);
+						aziot_keyd::Caller::Unknown
+					},
+				},
+
+				http_common::AsyncStream::Tcp(_) => aziot_keyd::Caller::Unknown,
+			};
+
+			let server = http::Server { inner: server.clone(), caller };
 			futures_util::future::ok::<_, std::convert::Infallible>(server)
 		}));
 	let () = server.await?;
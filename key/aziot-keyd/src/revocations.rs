@@ -0,0 +1,98 @@
+/// Tracks the generation of the handles of each key and key pair.
+///
+/// Every handle records the generation of its key or key pair at the time it was issued. Revoking the handles of a key or key pair
+/// increments its generation, which invalidates all handles issued before the revocation.
+///
+/// Keys and key pairs have separate IDs, so their generations are tracked separately.
+///
+/// The generations are persisted to a file so that revoked handles stay revoked across restarts.
+#[derive(Debug)]
+pub(crate) struct Revocations {
+	path: std::path::PathBuf,
+	generations: Generations,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+struct Generations {
+	#[serde(default)]
+	keys: std::collections::BTreeMap<String, u64>,
+
+	#[serde(default, rename = "keyPairs")]
+	key_pairs: std::collections::BTreeMap<String, u64>,
+}
+
+impl Generations {
+	fn get(&self, kind: aziot_key_common::KeyKind, key_id: &str) -> u64 {
+		let generations = match kind {
+			aziot_key_common::KeyKind::Key => &self.keys,
+			aziot_key_common::KeyKind::KeyPair => &self.key_pairs,
+		};
+		generations.get(key_id).copied().unwrap_or_default()
+	}
+
+	fn increment(&mut self, kind: aziot_key_common::KeyKind, key_id: &str) {
+		let generations = match kind {
+			aziot_key_common::KeyKind::Key => &mut self.keys,
+			aziot_key_common::KeyKind::KeyPair => &mut self.key_pairs,
+		};
+		*generations.entry(key_id.to_owned()).or_default() += 1;
+	}
+}
+
+impl Revocations {
+	pub(crate) fn load(path: std::path::PathBuf) -> Result<Self, std::io::Error> {
+		let generations = match std::fs::read(&path) {
+			Ok(generations) =>
+				serde_json::from_slice(&generations).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?,
+			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => Default::default(),
+			Err(err) => return Err(err),
+		};
+
+		Ok(Revocations {
+			path,
+			generations,
+		})
+	}
+
+	pub(crate) fn generation(&self, kind: aziot_key_common::KeyKind, key_id: &str) -> u64 {
+		self.generations.get(kind, key_id)
+	}
+
+	pub(crate) fn revoke(&mut self, kind: aziot_key_common::KeyKind, key_id: &str) -> Result<(), std::io::Error> {
+		let mut generations = self.generations.clone();
+		generations.increment(kind, key_id);
+
+		let contents = serde_json::to_vec(&generations).expect("cannot fail to serialize generations");
+		fs_common::write_file(&self.path, &contents)?;
+
+		self.generations = generations;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Revocations;
+	use aziot_key_common::KeyKind;
+
+	#[test]
+	fn revoke_persists_generations() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("revoked-key-handles.json");
+
+		let mut revocations = Revocations::load(path.clone()).unwrap();
+		assert_eq!(revocations.generation(KeyKind::KeyPair, "device-id"), 0);
+
+		revocations.revoke(KeyKind::KeyPair, "device-id").unwrap();
+		revocations.revoke(KeyKind::KeyPair, "device-id").unwrap();
+		assert_eq!(revocations.generation(KeyKind::KeyPair, "device-id"), 2);
+		assert_eq!(revocations.generation(KeyKind::Key, "device-id"), 0);
+		assert_eq!(revocations.generation(KeyKind::KeyPair, "device-ca"), 0);
+
+		let revocations = Revocations::load(path).unwrap();
+		assert_eq!(revocations.generation(KeyKind::KeyPair, "device-id"), 2);
+		assert_eq!(revocations.generation(KeyKind::Key, "device-id"), 0);
+		assert_eq!(revocations.generation(KeyKind::KeyPair, "device-ca"), 0);
+	}
+}
@@ -0,0 +1,361 @@
+#[derive(Debug, serde::Deserialize)]
+pub struct Settings {
+	/// Directory where libaziot-keys stores the keys that it creates.
+	pub homedir: std::path::PathBuf,
+
+	/// Path of the libaziot-keys implementation to load, if not the one that aziot-keyd is built with.
+	pub aziot_keys_lib_path: Option<std::path::PathBuf>,
+
+	/// Where the key encryption key comes from, if the files of the keys and key pairs in the homedir should be encrypted.
+	///
+	/// This is a `file://` URI to a file containing the key, `credential:<name>` for a systemd credential containing the key,
+	/// or `passphrase:<passphrase>` for a passphrase that the key is derived from.
+	pub key_encryption_key: Option<String>,
+
+	/// Configuration of the PKCS#11 library used to store keys, if any.
+	pub pkcs11: Option<Pkcs11>,
+
+	/// Map of key ID to the location of a key that was provisioned outside aziot-keyd.
+	///
+	/// The location is either a `file://` URI or a `pkcs11:` URI.
+	#[serde(default)]
+	pub preloaded_keys: std::collections::BTreeMap<String, url::Url>,
+
+	pub listen: Listen,
+
+	#[serde(default)]
+	pub key_handles: KeyHandles,
+
+	/// The ACL of callers that may use keys.
+	#[serde(default, rename = "principal")]
+	pub principals: Vec<Principal>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Pkcs11 {
+	/// Path of the PKCS#11 library.
+	pub lib_path: std::path::PathBuf,
+
+	/// PKCS#11 URI of the slot where new keys will be created.
+	pub base_slot: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Listen {
+	pub api_uri: url::Url,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct KeyHandles {
+	/// Number of seconds for which a key handle is valid after it is issued.
+	///
+	/// If not set, key handles do not expire.
+	pub validity: Option<u64>,
+}
+
+/// An entry in the ACL that allows the caller with the given uid to perform some operations on some keys.
+///
+/// The caller is identified by exactly one of `uid` and `name`.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Principal {
+	pub uid: Option<u32>,
+
+	/// The name of the user whose uid identifies the caller. It is looked up when aziot-keyd starts.
+	pub name: Option<String>,
+
+	/// The IDs of the keys and key pairs that this principal may use.
+	///
+	/// An ID that ends with `*` is a prefix that matches all IDs that start with it.
+	pub keys: Vec<String>,
+
+	/// The operations this principal may perform on its keys.
+	pub operations: Vec<crate::Operation>,
+}
+
+impl Settings {
+	pub fn new(filename: &std::path::Path) -> Result<Self, LoadSettingsError> {
+		let settings = std::fs::read_to_string(filename).map_err(LoadSettingsError::Read)?;
+		let settings = settings.parse()?;
+		Ok(settings)
+	}
+
+	fn validate(&self) -> Result<(), LoadSettingsError> {
+		if !self.homedir.is_absolute() {
+			return Err(LoadSettingsError::InvalidValue { name: "homedir".into(), err: "path must be absolute".into() });
+		}
+
+		if let Some(aziot_keys_lib_path) = &self.aziot_keys_lib_path {
+			if !aziot_keys_lib_path.is_absolute() {
+				return Err(LoadSettingsError::InvalidValue { name: "aziot_keys_lib_path".into(), err: "path must be absolute".into() });
+			}
+		}
+
+		if let Some(key_encryption_key) = &self.key_encryption_key {
+			let name = || "key_encryption_key".to_owned();
+
+			let scheme_end_index = key_encryption_key.find(':').ok_or_else(|| LoadSettingsError::InvalidValue { name: name(), err: "missing scheme".into() })?;
+			let (scheme, rest) = (&key_encryption_key[..scheme_end_index], &key_encryption_key[(scheme_end_index + 1)..]);
+
+			match scheme {
+				"file" => {
+					let uri: url::Url = key_encryption_key.parse().map_err(|err| LoadSettingsError::InvalidValue { name: name(), err: Box::new(err) })?;
+					let _ = uri.to_file_path().map_err(|()| LoadSettingsError::InvalidValue { name: name(), err: "file URI must contain an absolute path".into() })?;
+				},
+
+				"credential" | "passphrase" => if rest.is_empty() {
+					return Err(LoadSettingsError::InvalidValue { name: name(), err: format!("{}: value must not be empty", scheme).into() });
+				},
+
+				scheme => return Err(LoadSettingsError::InvalidValue { name: name(), err: format!("unsupported scheme {:?}", scheme).into() }),
+			}
+		}
+
+		if let Some(pkcs11) = &self.pkcs11 {
+			if !pkcs11.lib_path.is_absolute() {
+				return Err(LoadSettingsError::InvalidValue { name: "pkcs11.lib_path".into(), err: "path must be absolute".into() });
+			}
+
+			if let Some(base_slot) = &pkcs11.base_slot {
+				if !base_slot.starts_with("pkcs11:") {
+					return Err(LoadSettingsError::InvalidValue { name: "pkcs11.base_slot".into(), err: "must be a pkcs11: URI".into() });
+				}
+			}
+		}
+
+		for (key_id, location) in &self.preloaded_keys {
+			let name = || format!("preloaded_keys.{:?}", key_id);
+
+			if key_id.is_empty() {
+				return Err(LoadSettingsError::InvalidValue { name: name(), err: "key ID must not be empty".into() });
+			}
+
+			match location.scheme() {
+				"file" => {
+					let _ = location.to_file_path().map_err(|()| LoadSettingsError::InvalidValue { name: name(), err: "file URI must contain an absolute path".into() })?;
+				},
+
+				"pkcs11" => if self.pkcs11.is_none() {
+					return Err(LoadSettingsError::InvalidValue { name: name(), err: "pkcs11: URI requires the [pkcs11] section to be set".into() });
+				},
+
+				scheme => return Err(LoadSettingsError::InvalidValue { name: name(), err: format!("unsupported URI scheme {:?}", scheme).into() }),
+			}
+		}
+
+		for (i, principal) in self.principals.iter().enumerate() {
+			match (principal.uid, &principal.name) {
+				(Some(_), None) => (),
+				(None, Some(name)) => if name.is_empty() {
+					return Err(LoadSettingsError::InvalidValue { name: format!("principal[{}].name", i), err: "user name must not be empty".into() });
+				},
+				_ => return Err(LoadSettingsError::InvalidValue { name: format!("principal[{}]", i), err: "exactly one of uid and name must be set".into() }),
+			}
+
+			if principal.keys.iter().any(String::is_empty) {
+				return Err(LoadSettingsError::InvalidValue { name: format!("principal[{}].keys", i), err: "key ID must not be empty".into() });
+			}
+		}
+
+		if self.key_handles.validity == Some(0) {
+			return Err(LoadSettingsError::InvalidValue { name: "key_handles.validity".into(), err: "validity must be greater than 0".into() });
+		}
+
+		let _ = http_common::Connector::new(&self.listen.api_uri).map_err(|err| LoadSettingsError::InvalidValue { name: "listen.api_uri".into(), err: err.into() })?;
+		if self.listen.api_uri.scheme() != "unix" {
+			// The ACL identifies callers by the peer credentials of the Unix socket, so callers over any other transport would always be rejected.
+			return Err(LoadSettingsError::InvalidValue { name: "listen.api_uri".into(), err: "URI must use the unix scheme".into() });
+		}
+
+		Ok(())
+	}
+}
+
+impl std::str::FromStr for Settings {
+	type Err = LoadSettingsError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let settings: Settings = toml::from_str(s).map_err(LoadSettingsError::Parse)?;
+		settings.validate()?;
+		Ok(settings)
+	}
+}
+
+#[derive(Debug)]
+pub enum LoadSettingsError {
+	InvalidValue { name: String, err: Box<dyn std::error::Error + Send + Sync> },
+	Parse(toml::de::Error),
+	Read(std::io::Error),
+}
+
+impl std::fmt::Display for LoadSettingsError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			LoadSettingsError::InvalidValue { name, .. } => write!(f, "setting {} has an invalid value", name),
+			LoadSettingsError::Parse(_) => f.write_str("could not parse settings"),
+			LoadSettingsError::Read(_) => f.write_str("could not read settings file"),
+		}
+	}
+}
+
+impl std::error::Error for LoadSettingsError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			LoadSettingsError::InvalidValue { err, .. } => Some(&**err),
+			LoadSettingsError::Parse(err) => Some(err),
+			LoadSettingsError::Read(err) => Some(err),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn parse_default_settings() {
+		let settings: super::Settings = include_str!("../config/unix/default.toml").parse().unwrap();
+		assert_eq!(settings.homedir, std::path::Path::new("/var/lib/aziot/keyd"));
+		assert!(settings.aziot_keys_lib_path.is_none());
+		assert!(settings.key_encryption_key.is_none());
+		assert!(settings.pkcs11.is_none());
+		assert!(settings.preloaded_keys.is_empty());
+		assert_eq!(settings.listen.api_uri.as_str(), "unix:///var/run/aziot/keyd.sock");
+		assert_eq!(settings.key_handles.validity, None);
+		assert_eq!(settings.principals.len(), 2);
+		assert_eq!(settings.principals[0].name.as_deref(), Some("aziotcs"));
+		assert_eq!(settings.principals[1].name.as_deref(), Some("iotedge"));
+		assert!(settings.principals.iter().all(|principal| principal.uid.is_none()));
+	}
+
+	#[test]
+	fn parse_full_settings() {
+		let settings: super::Settings = r#"
+			homedir = "/var/lib/aziot/keyd"
+			aziot_keys_lib_path = "/usr/lib/libvendor-aziot-keys.so"
+			key_encryption_key = "credential:aziot-keyd-kek"
+
+			[pkcs11]
+			lib_path = "/usr/lib64/pkcs11/libsofthsm2.so"
+			base_slot = "pkcs11:token=Key pairs?pin-value=1234"
+
+			[preloaded_keys]
+			device-id = "file:///var/secrets/device-id.key"
+			device-ca = "pkcs11:token=Key%20pairs;object=device-ca"
+
+			[listen]
+			api_uri = "unix:///var/run/aziot/keyd.sock"
+
+			[key_handles]
+			validity = 3600
+
+			[[principal]]
+			uid = 1001
+			keys = ["device-id", "module-*"]
+			operations = ["create", "sign"]
+
+			[[principal]]
+			name = "aziotcs"
+			keys = ["*"]
+			operations = ["sign"]
+		"#.parse().unwrap();
+
+		assert_eq!(settings.aziot_keys_lib_path.as_deref(), Some(std::path::Path::new("/usr/lib/libvendor-aziot-keys.so")));
+		assert_eq!(settings.key_encryption_key.as_deref(), Some("credential:aziot-keyd-kek"));
+		let pkcs11 = settings.pkcs11.unwrap();
+		assert_eq!(pkcs11.lib_path, std::path::Path::new("/usr/lib64/pkcs11/libsofthsm2.so"));
+		assert_eq!(pkcs11.base_slot.as_deref(), Some("pkcs11:token=Key pairs?pin-value=1234"));
+		assert_eq!(settings.preloaded_keys.len(), 2);
+		assert_eq!(settings.preloaded_keys["device-id"].scheme(), "file");
+		assert_eq!(settings.preloaded_keys["device-ca"].scheme(), "pkcs11");
+		assert_eq!(settings.key_handles.validity, Some(3600));
+		assert_eq!(settings.principals.len(), 2);
+		assert_eq!(settings.principals[0].uid, Some(1001));
+		assert_eq!(settings.principals[0].name, None);
+		assert_eq!(settings.principals[0].keys, ["device-id", "module-*"]);
+		assert_eq!(settings.principals[0].operations, [crate::Operation::Create, crate::Operation::Sign]);
+		assert_eq!(settings.principals[1].uid, None);
+		assert_eq!(settings.principals[1].name.as_deref(), Some("aziotcs"));
+	}
+
+	#[test]
+	fn reject_invalid_settings() {
+		for (input, expected_name) in &[
+			(r#"
+				homedir = "relative/path"
+				[listen]
+				api_uri = "unix:///var/run/aziot/keyd.sock"
+			"#, "homedir"),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				aziot_keys_lib_path = "libvendor-aziot-keys.so"
+				[listen]
+				api_uri = "unix:///var/run/aziot/keyd.sock"
+			"#, "aziot_keys_lib_path"),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				key_encryption_key = "https://example.org/kek"
+				[listen]
+				api_uri = "unix:///var/run/aziot/keyd.sock"
+			"#, "key_encryption_key"),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				key_encryption_key = "passphrase:"
+				[listen]
+				api_uri = "unix:///var/run/aziot/keyd.sock"
+			"#, "key_encryption_key"),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				[preloaded_keys]
+				device-id = "pkcs11:object=device-id"
+				[listen]
+				api_uri = "unix:///var/run/aziot/keyd.sock"
+			"#, "preloaded_keys.\"device-id\""),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				[preloaded_keys]
+				device-id = "https://example.org/device-id.key"
+				[listen]
+				api_uri = "unix:///var/run/aziot/keyd.sock"
+			"#, "preloaded_keys.\"device-id\""),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				[listen]
+				api_uri = "ftp://127.0.0.1:8888"
+			"#, "listen.api_uri"),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				[listen]
+				api_uri = "http://127.0.0.1:8888"
+			"#, "listen.api_uri"),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				[listen]
+				api_uri = "unix:///var/run/aziot/keyd.sock"
+				[[principal]]
+				uid = 1001
+				name = "aziotcs"
+				keys = ["*"]
+				operations = ["sign"]
+			"#, "principal[0]"),
+
+			(r#"
+				homedir = "/var/lib/aziot/keyd"
+				[listen]
+				api_uri = "unix:///var/run/aziot/keyd.sock"
+				[key_handles]
+				validity = 0
+			"#, "key_handles.validity"),
+		] {
+			match input.parse::<super::Settings>() {
+				Err(super::LoadSettingsError::InvalidValue { name, .. }) => assert_eq!(&name, expected_name),
+				result => panic!("{:?} did not fail with an invalid value for {}: {:?}", input, expected_name, result),
+			}
+		}
+	}
+}
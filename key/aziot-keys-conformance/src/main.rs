@@ -0,0 +1,117 @@
+#![deny(rust_2018_idioms, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+#![allow(
+	clippy::default_trait_access,
+	clippy::too_many_lines,
+	clippy::use_self,
+)]
+
+//! Checks that a libaziot-keys implementation follows the contract documented for `KEYGEN_FUNCTION_LIST_2_0_0_0`.
+//!
+//! The library is loaded from the given path, so this can be run against any implementation, not just the one in this repository.
+//! Every documented requirement is reported as passed, failed or skipped. The process exits with a non-zero code if any requirement failed.
+
+mod requirements;
+
+fn main() -> Result<(), Error> {
+	openssl::init();
+
+	let Options {
+		lib_path,
+		homedir_path,
+		parameters,
+	} = structopt::StructOpt::from_args();
+
+	let get_function_list = unsafe { load_get_function_list(&lib_path)? };
+
+	// Unless the caller specified a homedir, use a new one so that the keys created by the suite do not collide with existing ones.
+	// The new homedir is removed when `temp_homedir` is dropped.
+	let (homedir_path, temp_homedir) =
+		if let Some(homedir_path) = homedir_path {
+			(homedir_path, None)
+		}
+		else {
+			let temp_homedir = tempfile::Builder::new().prefix("aziot-keys-conformance-").tempdir()?;
+			(temp_homedir.path().to_owned(), Some(temp_homedir))
+		};
+
+	let mut suite = requirements::Suite::new(get_function_list);
+	let result = suite.run(&homedir_path, &parameters);
+
+	drop(temp_homedir);
+
+	let () = result?;
+
+	println!();
+	println!("{} passed, {} failed, {} skipped", suite.passed, suite.failed, suite.skipped);
+
+	if suite.failed > 0 {
+		std::process::exit(1);
+	}
+
+	Ok(())
+}
+
+/// Loads the library at the given path and returns its `KEYGEN_get_function_list`.
+///
+/// The library is never unloaded, since the function list returned by `KEYGEN_get_function_list` points into it.
+unsafe fn load_get_function_list(lib_path: &std::path::Path) -> Result<requirements::GetFunctionList, Error> {
+	let library = dl_common::Library::load(lib_path, libc::RTLD_NOW | libc::RTLD_LOCAL)?;
+
+	let get_function_list: requirements::GetFunctionList =
+		*library.symbol(std::ffi::CStr::from_bytes_with_nul(b"KEYGEN_get_function_list\0").expect("hard-coded C string is NUL-terminated"))?;
+
+	std::mem::forget(library);
+
+	Ok(get_function_list)
+}
+
+struct Error(Box<dyn std::error::Error>, backtrace::Backtrace);
+
+impl std::fmt::Debug for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "{}", self.0)?;
+
+		let mut source = self.0.source();
+		while let Some(err) = source {
+			writeln!(f, "caused by: {}", err)?;
+			source = err.source();
+		}
+
+		writeln!(f)?;
+
+		writeln!(f, "{:?}", self.1)?;
+
+		Ok(())
+	}
+}
+
+impl<E> From<E> for Error where E: Into<Box<dyn std::error::Error>> {
+	fn from(err: E) -> Self {
+		Error(err.into(), Default::default())
+	}
+}
+
+#[derive(structopt::StructOpt)]
+struct Options {
+	/// Path of the libaziot-keys library to test.
+	#[structopt(long)]
+	lib_path: std::path::PathBuf,
+
+	/// The directory to set as the library's `HOMEDIR_PATH` parameter.
+	///
+	/// Defaults to a new temporary directory that is removed when the suite completes.
+	#[structopt(long)]
+	homedir_path: Option<std::path::PathBuf>,
+
+	/// Additional parameters to set on the library before running the suite, in NAME=VALUE format. Can be specified multiple times.
+	#[structopt(long = "parameter", parse(try_from_str = parse_parameter))]
+	parameters: Vec<(std::ffi::CString, std::ffi::CString)>,
+}
+
+fn parse_parameter(s: &str) -> Result<(std::ffi::CString, std::ffi::CString), Box<dyn std::error::Error>> {
+	let separator_index = s.find('=').ok_or("expected NAME=VALUE")?;
+	let name = std::ffi::CString::new(&s[..separator_index])?;
+	let value = std::ffi::CString::new(&s[(separator_index + 1)..])?;
+	Ok((name, value))
+}
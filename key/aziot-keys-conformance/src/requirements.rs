@@ -0,0 +1,1111 @@
+// Each check corresponds to one requirement in the docs of `KEYGEN_get_function_list`, `KEYGEN_FUNCTION_LIST_2_0_0_0`
+// and the constants they refer to, and is named after it. Key rotation and the rejection of unrecognized algorithms
+// are only in `KEYGEN_FUNCTION_LIST_2_1_0_0`, so their checks are skipped for implementations that do not support that version,
+// and the rotation checks are also skipped for implementations that do not implement rotation.
+//
+// Checks that need a key to have been created by an earlier check are skipped if that check did not pass,
+// so that one broken function does not show up as a failure of every other function too.
+
+pub(crate) type GetFunctionList = unsafe extern "C" fn(
+	version: sys::KEYGEN_VERSION,
+	pfunction_list: *mut *const sys::KEYGEN_FUNCTION_LIST,
+) -> sys::KEYGEN_ERROR;
+
+type Functions = sys::KEYGEN_FUNCTION_LIST_2_0_0_0;
+type Functions2_1 = sys::KEYGEN_FUNCTION_LIST_2_1_0_0;
+
+/// Calls the function with the given name from the function list.
+///
+/// The suite checks that none of the functions are NULL before it calls any of them.
+macro_rules! call {
+	($functions:expr, $name:ident($($arg:expr),* $(,)?)) => {
+		($functions.$name.expect(concat!(stringify!($name), " is NULL")))($($arg),*)
+	};
+}
+
+const EC_KEY_PAIR_ID: &[u8] = b"conformance-ec-key-pair\0";
+const ANY_KEY_PAIR_ID: &[u8] = b"conformance-any-key-pair\0";
+const RSA_KEY_PAIR_ID: &[u8] = b"conformance-rsa-key-pair\0";
+const UNRECOGNIZED_ALGORITHM_KEY_PAIR_ID: &[u8] = b"conformance-unrecognized-algorithm-key-pair\0";
+const IMPORTED_KEY_ID: &[u8] = b"conformance-imported-key\0";
+const AEAD_KEY_ID: &[u8] = b"conformance-aead-key\0";
+const ROTATED_KEY_ID: &[u8] = b"conformance-rotated-key\0";
+const NONEXISTENT_ID: &[u8] = b"conformance-nonexistent\0";
+
+/// The DER encoding of the `DigestInfo` of a SHA-256 digest without the digest itself, as used by RSA PKCS1 signatures. See RFC 8017 section 9.2
+const SHA256_DIGEST_INFO_PREFIX: &[u8] = &[
+	0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+];
+
+/// An AEAD ciphertext of `b"aziot-keys conformance message"` created with the imported key, the IV `[0x04; 12]` and the AAD `b"aziot-keys conformance aad"`.
+///
+/// It is in the format that the library has always used for keys that were never rotated: 0x01, then the 16-byte tag, then the actual ciphertext.
+const KNOWN_ANSWER_CIPHERTEXT: &[u8] = &[
+	0x01,
+	0x82, 0xfb, 0xb0, 0xb2, 0x12, 0x9d, 0xbb, 0x47, 0x5d, 0xf8, 0x1e, 0xf4, 0x3d, 0x4e, 0xa5, 0x95,
+	0xa3, 0x50, 0xea, 0xa0, 0xe8, 0x43, 0x4a, 0x59, 0xb0, 0x1e, 0xe6, 0x48, 0x6c, 0x90, 0x27, 0x56, 0x5f, 0x07, 0x0b, 0xc5, 0x15, 0x61, 0x71, 0xdb, 0xaa, 0xc9, 0xb8, 0x94, 0x57, 0xc8,
+];
+
+pub(crate) struct Suite {
+	get_function_list: GetFunctionList,
+	pub(crate) passed: usize,
+	pub(crate) failed: usize,
+	pub(crate) skipped: usize,
+}
+
+impl Suite {
+	pub(crate) fn new(get_function_list: GetFunctionList) -> Self {
+		Suite {
+			get_function_list,
+			passed: 0,
+			failed: 0,
+			skipped: 0,
+		}
+	}
+
+	/// Runs all the checks against the library.
+	///
+	/// Returns an error if the suite could not be run to completion, ie the library did not return a usable function list
+	/// or did not accept the parameters needed to set it up.
+	pub(crate) fn run(
+		&mut self,
+		homedir_path: &std::path::Path,
+		parameters: &[(std::ffi::CString, std::ffi::CString)],
+	) -> Result<(), String> {
+		let functions = self.get_function_list().ok_or("cannot run the remaining checks without a function list")?;
+
+		self.set_parameter(&functions);
+
+		unsafe {
+			let homedir_path = std::os::unix::ffi::OsStrExt::as_bytes(homedir_path.as_os_str()).to_owned();
+			let homedir_path = std::ffi::CString::new(homedir_path).map_err(|err| err.to_string())?;
+			keys_fn(|| call!(functions, set_parameter(cstr(b"HOMEDIR_PATH\0").as_ptr(), homedir_path.as_ptr())))
+				.map_err(|err| format!("could not set HOMEDIR_PATH parameter: {}", err))?;
+
+			for (name, value) in parameters {
+				keys_fn(|| call!(functions, set_parameter(name.as_ptr(), value.as_ptr())))
+					.map_err(|err| format!("could not set {} parameter: {}", name.to_string_lossy(), err))?;
+			}
+		}
+
+		self.key_pairs(&functions);
+		self.keys(&functions);
+
+		let functions_2_1 = self.get_function_list_2_1();
+		self.key_pairs_2_1(&functions_2_1);
+		self.keys_2_1(&functions_2_1);
+		self.rotation(&functions_2_1);
+
+		Ok(())
+	}
+
+	fn check(&mut self, requirement: &str, f: impl FnOnce() -> Result<(), Failure>) -> bool {
+		match f() {
+			Ok(()) => {
+				println!("PASS {}", requirement);
+				self.passed += 1;
+				true
+			},
+
+			Err(Failure::Failed(reason)) => {
+				println!("FAIL {}: {}", requirement, reason);
+				self.failed += 1;
+				false
+			},
+
+			Err(Failure::Skipped(reason)) => {
+				println!("SKIP {}: {}", requirement, reason);
+				self.skipped += 1;
+				false
+			},
+		}
+	}
+
+	fn get_function_list(&mut self) -> Option<Functions> {
+		let get_function_list = self.get_function_list;
+
+		self.check("KEYGEN_get_function_list fails with KEYGEN_ERROR_INVALID_PARAMETER if version is not recognized", || unsafe {
+			let mut function_list = std::ptr::null();
+			expect_error(keys_fn(|| get_function_list(0x01_00_00_00, &mut function_list)), sys::KEYGEN_ERROR_INVALID_PARAMETER)
+		});
+
+		self.check("KEYGEN_get_function_list fails with KEYGEN_ERROR_INVALID_PARAMETER if pfunction_list is NULL", || unsafe {
+			expect_error(keys_fn(|| get_function_list(sys::KEYGEN_VERSION_2_0_0_0, std::ptr::null_mut())), sys::KEYGEN_ERROR_INVALID_PARAMETER)
+		});
+
+		let mut functions = None;
+
+		let ok = self.check("KEYGEN_get_function_list returns a function list with base.version set to KEYGEN_VERSION_2_0_0_0", || unsafe {
+			let mut function_list = std::ptr::null();
+			keys_fn(|| get_function_list(sys::KEYGEN_VERSION_2_0_0_0, &mut function_list))?;
+
+			if function_list.is_null() {
+				return Err(Failure::Failed("function list is NULL".to_owned()));
+			}
+
+			let version = (*function_list).version;
+			if version != sys::KEYGEN_VERSION_2_0_0_0 {
+				return Err(Failure::Failed(format!("base.version is 0x{:08x}", version)));
+			}
+
+			// KEYGEN_FUNCTION_LIST has looser alignment than KEYGEN_FUNCTION_LIST_2_0_0_0, but the pointer comes from the library itself,
+			// so it will be correctly aligned already.
+			#[allow(clippy::cast_ptr_alignment)]
+			let function_list = function_list as *const Functions;
+			functions = Some(*function_list);
+
+			Ok(())
+		});
+		if !ok {
+			return None;
+		}
+
+		let functions = functions?;
+
+		let ok = self.check("KEYGEN_FUNCTION_LIST_2_0_0_0 has no NULL functions", || {
+			let missing: Vec<_> =
+				[
+					("set_parameter", functions.set_parameter.is_none()),
+					("create_key_pair_if_not_exists", functions.create_key_pair_if_not_exists.is_none()),
+					("load_key_pair", functions.load_key_pair.is_none()),
+					("get_key_pair_parameter", functions.get_key_pair_parameter.is_none()),
+					("create_key_if_not_exists", functions.create_key_if_not_exists.is_none()),
+					("import_key", functions.import_key.is_none()),
+					("sign", functions.sign.is_none()),
+					("verify", functions.verify.is_none()),
+					("encrypt", functions.encrypt.is_none()),
+					("decrypt", functions.decrypt.is_none()),
+				].iter()
+				.filter_map(|&(name, is_none)| if is_none { Some(name) } else { None })
+				.collect();
+			if missing.is_empty() {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed(format!("{} are NULL", missing.join(", "))))
+			}
+		});
+		if !ok {
+			return None;
+		}
+
+		self.check("KEYGEN_get_function_list can be called more than once", || unsafe {
+			let mut function_list = std::ptr::null();
+			keys_fn(|| get_function_list(sys::KEYGEN_VERSION_2_0_0_0, &mut function_list))?;
+			Ok(())
+		});
+
+		Some(functions)
+	}
+
+	fn set_parameter(&mut self, functions: &Functions) {
+		self.check("set_parameter fails with KEYGEN_ERROR_INVALID_PARAMETER if name is NULL", || unsafe {
+			expect_error(
+				keys_fn(|| call!(functions, set_parameter(std::ptr::null(), cstr(b"value\0").as_ptr()))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("set_parameter fails with KEYGEN_ERROR_INVALID_PARAMETER if name is not recognized", || unsafe {
+			expect_error(
+				keys_fn(|| call!(functions, set_parameter(cstr(b"CONFORMANCE_UNRECOGNIZED\0").as_ptr(), cstr(b"value\0").as_ptr()))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+	}
+
+	fn key_pairs(&mut self, functions: &Functions) {
+		self.check("create_key_pair_if_not_exists fails with KEYGEN_ERROR_INVALID_PARAMETER if id is NULL", || unsafe {
+			expect_error(
+				keys_fn(|| call!(functions, create_key_pair_if_not_exists(std::ptr::null(), cstr(b"ec-p256\0").as_ptr()))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		// Implementations are allowed to not support an algorithm, but every consumer of libaziot-keys creates ec-p256 key pairs by default,
+		// so not supporting it is treated as a failure rather than skipping the checks that need it.
+		let ec_key_pair = self.check("create_key_pair_if_not_exists creates a key pair for preferred_algorithms \"ec-p256\"", || unsafe {
+			keys_fn(|| call!(functions, create_key_pair_if_not_exists(cstr(EC_KEY_PAIR_ID).as_ptr(), cstr(b"ec-p256\0").as_ptr())))?;
+			Ok(())
+		});
+		let ec_key_pair = if ec_key_pair { Ok(()) } else { Err(Failure::Skipped("ec-p256 key pair could not be created".to_owned())) };
+
+		self.check("create_key_pair_if_not_exists loads the existing key pair if one with the same ID exists", || unsafe {
+			ec_key_pair.clone()?;
+
+			let point = key_pair_parameter(functions, cstr(EC_KEY_PAIR_ID), sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT)?;
+			keys_fn(|| call!(functions, create_key_pair_if_not_exists(cstr(EC_KEY_PAIR_ID).as_ptr(), cstr(b"ec-p256\0").as_ptr())))?;
+			let new_point = key_pair_parameter(functions, cstr(EC_KEY_PAIR_ID), sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT)?;
+
+			if point == new_point {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed("key pair was replaced with a new one".to_owned()))
+			}
+		});
+
+		self.check("create_key_pair_if_not_exists interprets NULL preferred_algorithms as \"*\"", || unsafe {
+			keys_fn(|| call!(functions, create_key_pair_if_not_exists(cstr(ANY_KEY_PAIR_ID).as_ptr(), std::ptr::null())))?;
+			Ok(())
+		});
+
+		self.check("load_key_pair loads a key pair created by create_key_pair_if_not_exists", || unsafe {
+			ec_key_pair.clone()?;
+
+			keys_fn(|| call!(functions, load_key_pair(cstr(EC_KEY_PAIR_ID).as_ptr())))?;
+			Ok(())
+		});
+
+		self.check("get_key_pair_parameter fails with KEYGEN_ERROR_INVALID_PARAMETER if id is NULL", || unsafe {
+			let mut value_len = 0;
+			expect_error(
+				keys_fn(|| call!(functions, get_key_pair_parameter(
+					std::ptr::null(),
+					sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT,
+					std::ptr::null_mut(),
+					&mut value_len,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("get_key_pair_parameter fails with KEYGEN_ERROR_INVALID_PARAMETER if the key does not exist", || unsafe {
+			let mut value_len = 0;
+			expect_error(
+				keys_fn(|| call!(functions, get_key_pair_parameter(
+					cstr(NONEXISTENT_ID).as_ptr(),
+					sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT,
+					std::ptr::null_mut(),
+					&mut value_len,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("get_key_pair_parameter fails with KEYGEN_ERROR_INVALID_PARAMETER if type_ is not valid for the key", || unsafe {
+			ec_key_pair.clone()?;
+
+			let mut value_len = 0;
+			expect_error(
+				keys_fn(|| call!(functions, get_key_pair_parameter(
+					cstr(EC_KEY_PAIR_ID).as_ptr(),
+					sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_RSA_MODULUS,
+					std::ptr::null_mut(),
+					&mut value_len,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("get_key_pair_parameter sets value_len to the required length if value is NULL", || unsafe {
+			ec_key_pair.clone()?;
+
+			let mut value_len = 0;
+			keys_fn(|| call!(functions, get_key_pair_parameter(
+				cstr(EC_KEY_PAIR_ID).as_ptr(),
+				sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT,
+				std::ptr::null_mut(),
+				&mut value_len,
+			)))?;
+			if value_len == 0 {
+				return Err(Failure::Failed("value_len was set to 0".to_owned()));
+			}
+
+			let mut value = vec![0_u8; value_len];
+			let mut actual_value_len = value_len;
+			keys_fn(|| call!(functions, get_key_pair_parameter(
+				cstr(EC_KEY_PAIR_ID).as_ptr(),
+				sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT,
+				value.as_mut_ptr(),
+				&mut actual_value_len,
+			)))?;
+			if actual_value_len != value_len {
+				return Err(Failure::Failed(format!("value_len was {} when value was NULL but {} when it was not", value_len, actual_value_len)));
+			}
+
+			Ok(())
+		});
+
+		self.check("get_key_pair_parameter fails with KEYGEN_ERROR_INVALID_PARAMETER if value_len is too small", || unsafe {
+			ec_key_pair.clone()?;
+
+			let mut value_len = 0;
+			keys_fn(|| call!(functions, get_key_pair_parameter(
+				cstr(EC_KEY_PAIR_ID).as_ptr(),
+				sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT,
+				std::ptr::null_mut(),
+				&mut value_len,
+			)))?;
+
+			let mut value = vec![0_u8; value_len];
+			let mut value_len = value_len - 1;
+			expect_error(
+				keys_fn(|| call!(functions, get_key_pair_parameter(
+					cstr(EC_KEY_PAIR_ID).as_ptr(),
+					sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT,
+					value.as_mut_ptr(),
+					&mut value_len,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("get_key_pair_parameter returns KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_EC as the algorithm of an ec-p256 key pair", || unsafe {
+			ec_key_pair.clone()?;
+
+			let algorithm = key_pair_algorithm(functions, cstr(EC_KEY_PAIR_ID))?;
+			if algorithm == sys::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_EC {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed(format!("algorithm is {}", algorithm)))
+			}
+		});
+
+		self.check("get_key_pair_parameter returns the DER-encoded OID of secp256r1 as the curve OID of an ec-p256 key pair", || unsafe {
+			ec_key_pair.clone()?;
+
+			let curve_oid = key_pair_parameter(functions, cstr(EC_KEY_PAIR_ID), sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_CURVE_OID)?;
+			if openssl2::EcCurve::from_oid_der(&curve_oid) == Some(openssl2::EcCurve::NistP256) {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed(format!("curve OID is {:02x?}", curve_oid)))
+			}
+		});
+
+		let digest = openssl::sha::sha256(b"aziot-keys conformance");
+
+		self.check("sign fails with KEYGEN_ERROR_INVALID_PARAMETER if id is NULL", || unsafe {
+			let mut signature_len = 0;
+			expect_error(
+				keys_fn(|| call!(functions, sign(
+					std::ptr::null(),
+					sys::KEYGEN_SIGN_MECHANISM_ECDSA,
+					std::ptr::null(),
+					digest.as_ptr(),
+					digest.len(),
+					std::ptr::null_mut(),
+					&mut signature_len,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("sign fails with KEYGEN_ERROR_INVALID_PARAMETER if the key does not exist", || unsafe {
+			let mut signature_len = 0;
+			expect_error(
+				keys_fn(|| call!(functions, sign(
+					cstr(NONEXISTENT_ID).as_ptr(),
+					sys::KEYGEN_SIGN_MECHANISM_ECDSA,
+					std::ptr::null(),
+					digest.as_ptr(),
+					digest.len(),
+					std::ptr::null_mut(),
+					&mut signature_len,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("sign sets signature_len to the required length if signature is NULL", || unsafe {
+			ec_key_pair.clone()?;
+
+			let mut signature_len = 0;
+			keys_fn(|| call!(functions, sign(
+				cstr(EC_KEY_PAIR_ID).as_ptr(),
+				sys::KEYGEN_SIGN_MECHANISM_ECDSA,
+				std::ptr::null(),
+				digest.as_ptr(),
+				digest.len(),
+				std::ptr::null_mut(),
+				&mut signature_len,
+			)))?;
+			if signature_len == 0 {
+				return Err(Failure::Failed("signature_len was set to 0".to_owned()));
+			}
+
+			Ok(())
+		});
+
+		self.check("verify fails with KEYGEN_ERROR_INVALID_PARAMETER if id is NULL", || unsafe {
+			let signature = [0_u8; 64];
+			let mut ok = 0;
+			expect_error(
+				keys_fn(|| call!(functions, verify(
+					std::ptr::null(),
+					sys::KEYGEN_SIGN_MECHANISM_ECDSA,
+					std::ptr::null(),
+					digest.as_ptr(),
+					digest.len(),
+					signature.as_ptr(),
+					signature.len(),
+					&mut ok,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("verify accepts an ECDSA signature created by sign, and rejects it for a different digest", || unsafe {
+			ec_key_pair.clone()?;
+
+			let signature = sign(functions, cstr(EC_KEY_PAIR_ID), sys::KEYGEN_SIGN_MECHANISM_ECDSA, std::ptr::null(), &digest)?;
+			expect_verify(functions, cstr(EC_KEY_PAIR_ID), sys::KEYGEN_SIGN_MECHANISM_ECDSA, std::ptr::null(), &digest, &signature)
+		});
+
+		self.check("ECDSA signatures are DER-encoded and can be verified with the public parameters of the key pair", || unsafe {
+			ec_key_pair.clone()?;
+
+			let signature = sign(functions, cstr(EC_KEY_PAIR_ID), sys::KEYGEN_SIGN_MECHANISM_ECDSA, std::ptr::null(), &digest)?;
+
+			let curve_oid = key_pair_parameter(functions, cstr(EC_KEY_PAIR_ID), sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_CURVE_OID)?;
+			let curve = openssl2::EcCurve::from_oid_der(&curve_oid).ok_or("curve OID is not recognized")?;
+			let group = openssl::ec::EcGroup::from_curve_name(curve.as_nid())?;
+
+			let point = key_pair_parameter(functions, cstr(EC_KEY_PAIR_ID), sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT)?;
+			let mut big_num_context = openssl::bn::BigNumContext::new()?;
+			let point = openssl::ec::EcPoint::from_bytes(&group, &point, &mut big_num_context)?;
+
+			let public_key = openssl::ec::EcKey::from_public_key(&group, &point)?;
+
+			let signature = openssl::ecdsa::EcdsaSig::from_der(&signature)?;
+			if signature.verify(&digest, &public_key)? {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed("signature does not verify".to_owned()))
+			}
+		});
+
+		// RSA support is optional, so if the implementation cannot create an RSA key pair the checks that need one are skipped.
+		let rsa_key_pair = unsafe {
+			keys_fn(|| call!(functions, create_key_pair_if_not_exists(cstr(RSA_KEY_PAIR_ID).as_ptr(), cstr(b"rsa-2048\0").as_ptr())))
+			.map_err(|err| Failure::Skipped(format!("rsa-2048 key pair could not be created: {}", err)))
+		};
+
+		let rsa_pkcs1_parameters = sys::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA256;
+		let rsa_pkcs1_parameters = &rsa_pkcs1_parameters as *const _ as *const std::ffi::c_void;
+
+		self.check("get_key_pair_parameter returns KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_RSA as the algorithm of an rsa-2048 key pair", || unsafe {
+			rsa_key_pair.clone()?;
+
+			let algorithm = key_pair_algorithm(functions, cstr(RSA_KEY_PAIR_ID))?;
+			if algorithm == sys::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_RSA {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed(format!("algorithm is {}", algorithm)))
+			}
+		});
+
+		self.check("verify accepts an RSA PKCS1 signature created by sign, and rejects it for a different digest", || unsafe {
+			rsa_key_pair.clone()?;
+
+			let signature = sign(functions, cstr(RSA_KEY_PAIR_ID), sys::KEYGEN_SIGN_MECHANISM_RSA_PKCS1, rsa_pkcs1_parameters, &digest)?;
+			expect_verify(functions, cstr(RSA_KEY_PAIR_ID), sys::KEYGEN_SIGN_MECHANISM_RSA_PKCS1, rsa_pkcs1_parameters, &digest, &signature)
+		});
+
+		self.check("RSA PKCS1 signatures can be verified with the public parameters of the key pair", || unsafe {
+			rsa_key_pair.clone()?;
+
+			let signature = sign(functions, cstr(RSA_KEY_PAIR_ID), sys::KEYGEN_SIGN_MECHANISM_RSA_PKCS1, rsa_pkcs1_parameters, &digest)?;
+
+			let modulus = key_pair_parameter(functions, cstr(RSA_KEY_PAIR_ID), sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_RSA_MODULUS)?;
+			let modulus = openssl::bn::BigNum::from_slice(&modulus)?;
+			let exponent = key_pair_parameter(functions, cstr(RSA_KEY_PAIR_ID), sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_RSA_EXPONENT)?;
+			let exponent = openssl::bn::BigNum::from_slice(&exponent)?;
+			let public_key = openssl::rsa::Rsa::from_public_components(modulus, exponent)?;
+
+			let mut digest_info = vec![0_u8; std::convert::TryInto::try_into(public_key.size()).expect("u32 -> usize")];
+			let digest_info_len = public_key.public_decrypt(&signature, &mut digest_info, openssl::rsa::Padding::PKCS1)?;
+			let digest_info = &digest_info[..digest_info_len];
+
+			if digest_info.starts_with(SHA256_DIGEST_INFO_PREFIX) && digest_info[SHA256_DIGEST_INFO_PREFIX.len()..] == digest[..] {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed("signature does not contain the DigestInfo of the digest".to_owned()))
+			}
+		});
+	}
+
+	fn keys(&mut self, functions: &Functions) {
+		self.check("create_key_if_not_exists fails with KEYGEN_ERROR_INVALID_PARAMETER if id is NULL", || unsafe {
+			expect_error(
+				keys_fn(|| call!(functions, create_key_if_not_exists(std::ptr::null(), 32))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("import_key fails with KEYGEN_ERROR_INVALID_PARAMETER if id is NULL", || unsafe {
+			let bytes = [0_u8; 32];
+			expect_error(
+				keys_fn(|| call!(functions, import_key(std::ptr::null(), bytes.as_ptr(), bytes.len()))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		let imported_key_bytes = openssl::sha::sha256(b"aziot-keys conformance imported key");
+
+		let imported_key = self.check("import_key imports a key from its bytes", || unsafe {
+			keys_fn(|| call!(functions, import_key(cstr(IMPORTED_KEY_ID).as_ptr(), imported_key_bytes.as_ptr(), imported_key_bytes.len())))?;
+			Ok(())
+		});
+		let imported_key = if imported_key { Ok(()) } else { Err(Failure::Skipped("key could not be imported".to_owned())) };
+
+		let message = b"aziot-keys conformance message";
+
+		self.check("HMAC-SHA256 signatures of an imported key are the HMAC-SHA256 of the message with the imported bytes", || unsafe {
+			imported_key.clone()?;
+
+			let signature = sign(functions, cstr(IMPORTED_KEY_ID), sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256, std::ptr::null(), message)?;
+
+			let key = openssl::pkey::PKey::hmac(&imported_key_bytes)?;
+			let mut signer = openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &key)?;
+			signer.update(message)?;
+			let expected_signature = signer.sign_to_vec()?;
+
+			if signature == expected_signature {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed("signature does not match".to_owned()))
+			}
+		});
+
+		self.check("verify accepts an HMAC-SHA256 signature created by sign, and rejects it for a different message", || unsafe {
+			imported_key.clone()?;
+
+			let signature = sign(functions, cstr(IMPORTED_KEY_ID), sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256, std::ptr::null(), message)?;
+			expect_verify(functions, cstr(IMPORTED_KEY_ID), sys::KEYGEN_SIGN_MECHANISM_HMAC_SHA256, std::ptr::null(), message, &signature)
+		});
+
+		let aead_key = self.check("create_key_if_not_exists creates a 32-byte key", || unsafe {
+			keys_fn(|| call!(functions, create_key_if_not_exists(cstr(AEAD_KEY_ID).as_ptr(), 32)))?;
+			Ok(())
+		});
+		let aead_key = if aead_key { Ok(()) } else { Err(Failure::Skipped("key could not be created".to_owned())) };
+
+		let iv = [0x01_u8; 12];
+		let aad = b"aziot-keys conformance aad";
+		let aead_parameters = new_aead_parameters(&iv, aad);
+		let aead_parameters = &aead_parameters as *const _ as *const std::ffi::c_void;
+
+		self.check("encrypt fails with KEYGEN_ERROR_INVALID_PARAMETER if the key does not exist", || unsafe {
+			let mut ciphertext_len = 0;
+			expect_error(
+				keys_fn(|| call!(functions, encrypt(
+					cstr(NONEXISTENT_ID).as_ptr(),
+					sys::KEYGEN_ENCRYPT_MECHANISM_AEAD,
+					aead_parameters,
+					message.as_ptr(),
+					message.len(),
+					std::ptr::null_mut(),
+					&mut ciphertext_len,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("encrypt fails with KEYGEN_ERROR_INVALID_PARAMETER if the parameters of KEYGEN_ENCRYPT_MECHANISM_AEAD are NULL", || unsafe {
+			aead_key.clone()?;
+
+			let mut ciphertext_len = 0;
+			expect_error(
+				keys_fn(|| call!(functions, encrypt(
+					cstr(AEAD_KEY_ID).as_ptr(),
+					sys::KEYGEN_ENCRYPT_MECHANISM_AEAD,
+					std::ptr::null(),
+					message.as_ptr(),
+					message.len(),
+					std::ptr::null_mut(),
+					&mut ciphertext_len,
+				))),
+				sys::KEYGEN_ERROR_INVALID_PARAMETER,
+			)
+		});
+
+		self.check("encrypt sets ciphertext_len to the required length if ciphertext is NULL", || unsafe {
+			aead_key.clone()?;
+
+			let mut ciphertext_len = 0;
+			keys_fn(|| call!(functions, encrypt(
+				cstr(AEAD_KEY_ID).as_ptr(),
+				sys::KEYGEN_ENCRYPT_MECHANISM_AEAD,
+				aead_parameters,
+				message.as_ptr(),
+				message.len(),
+				std::ptr::null_mut(),
+				&mut ciphertext_len,
+			)))?;
+			if ciphertext_len < message.len() {
+				return Err(Failure::Failed(format!("ciphertext_len was set to {} for a plaintext of length {}", ciphertext_len, message.len())));
+			}
+
+			Ok(())
+		});
+
+		self.check("decrypt returns the plaintext of an AEAD ciphertext created by encrypt with the same IV and AAD", || unsafe {
+			aead_key.clone()?;
+
+			for plaintext in &[&b""[..], &b"a"[..], &[0x5a_u8; 1024][..]] {
+				let ciphertext = encrypt(functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, plaintext)?;
+				let decrypted = decrypt(functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, &ciphertext)?;
+				if decrypted != *plaintext {
+					return Err(Failure::Failed(format!("plaintext of length {} was decrypted incorrectly", plaintext.len())));
+				}
+			}
+
+			Ok(())
+		});
+
+		self.check("decrypt fails for an AEAD ciphertext if the AAD, the IV or the ciphertext itself is different", || unsafe {
+			aead_key.clone()?;
+
+			let ciphertext = encrypt(functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, message)?;
+
+			let other_aad_parameters = new_aead_parameters(&iv, b"other aad");
+			let other_aad_parameters = &other_aad_parameters as *const _ as *const std::ffi::c_void;
+			if decrypt(functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, other_aad_parameters, &ciphertext).is_ok() {
+				return Err(Failure::Failed("ciphertext was decrypted with a different AAD".to_owned()));
+			}
+
+			let other_iv = [0x02_u8; 12];
+			let other_iv_parameters = new_aead_parameters(&other_iv, aad);
+			let other_iv_parameters = &other_iv_parameters as *const _ as *const std::ffi::c_void;
+			if decrypt(functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, other_iv_parameters, &ciphertext).is_ok() {
+				return Err(Failure::Failed("ciphertext was decrypted with a different IV".to_owned()));
+			}
+
+			let mut modified_ciphertext = ciphertext;
+			*modified_ciphertext.last_mut().expect("ciphertext of non-empty plaintext is non-empty") ^= 0x01;
+			if decrypt(functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, &modified_ciphertext).is_ok() {
+				return Err(Failure::Failed("modified ciphertext was decrypted".to_owned()));
+			}
+
+			Ok(())
+		});
+
+		self.check("AEAD ciphertexts can still be decrypted after create_key_if_not_exists loads the existing key again", || unsafe {
+			aead_key.clone()?;
+
+			let ciphertext = encrypt(functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, message)?;
+
+			keys_fn(|| call!(functions, create_key_if_not_exists(cstr(AEAD_KEY_ID).as_ptr(), 32)))?;
+
+			let decrypted = decrypt(functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, &ciphertext)?;
+			if decrypted == message {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed("ciphertext was decrypted incorrectly".to_owned()))
+			}
+		});
+
+		self.check("decrypt returns the plaintext of an AEAD ciphertext of an imported key that was created by earlier versions of the library", || unsafe {
+			imported_key.clone()?;
+
+			let iv = [0x04_u8; 12];
+			let parameters = new_aead_parameters(&iv, aad);
+			let parameters = &parameters as *const _ as *const std::ffi::c_void;
+
+			let decrypted = decrypt(functions, cstr(IMPORTED_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, parameters, KNOWN_ANSWER_CIPHERTEXT)?;
+			if decrypted == message {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed("ciphertext was decrypted incorrectly".to_owned()))
+			}
+		});
+	}
+
+	/// Gets the `KEYGEN_FUNCTION_LIST_2_1_0_0` of the library, or the reason the checks that need it are skipped.
+	fn get_function_list_2_1(&self) -> Result<Functions2_1, Failure> {
+		let get_function_list = self.get_function_list;
+
+		unsafe {
+			let mut function_list = std::ptr::null();
+			match keys_fn(|| get_function_list(sys::KEYGEN_VERSION_2_1_0_0, &mut function_list)) {
+				Ok(()) if !function_list.is_null() && (*function_list).version == sys::KEYGEN_VERSION_2_1_0_0 => {
+					// KEYGEN_FUNCTION_LIST has looser alignment than KEYGEN_FUNCTION_LIST_2_1_0_0, but the pointer comes from the library itself,
+					// so it will be correctly aligned already.
+					#[allow(clippy::cast_ptr_alignment)]
+					let function_list = function_list as *const Functions2_1;
+					Ok(*function_list)
+				},
+				_ => Err(Failure::Skipped("implementation does not support KEYGEN_VERSION_2_1_0_0".to_owned())),
+			}
+		}
+	}
+
+	fn key_pairs_2_1(&mut self, functions: &Result<Functions2_1, Failure>) {
+		self.check(
+			"create_key_pair_if_not_exists of KEYGEN_FUNCTION_LIST_2_1_0_0 fails with KEYGEN_ERROR_INVALID_PARAMETER if preferred_algorithms contains an unrecognized component",
+			|| unsafe {
+				let functions = functions.clone()?;
+				expect_error(
+					keys_fn(|| call!(functions, create_key_pair_if_not_exists(
+						cstr(UNRECOGNIZED_ALGORITHM_KEY_PAIR_ID).as_ptr(),
+						cstr(b"conformance-unrecognized\0").as_ptr(),
+					))),
+					sys::KEYGEN_ERROR_INVALID_PARAMETER,
+				)
+			},
+		);
+	}
+
+	fn keys_2_1(&mut self, functions: &Result<Functions2_1, Failure>) {
+		let message = b"aziot-keys conformance message";
+		let aad = b"aziot-keys conformance aad";
+
+		self.check("KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE creates a different ciphertext every time, each of which decrypts with the same AAD", || unsafe {
+			let functions = functions.clone()?;
+
+			keys_fn(|| call!(functions, create_key_if_not_exists(cstr(AEAD_KEY_ID).as_ptr(), 32)))?;
+
+			let parameters = sys::KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS {
+				aad: aad.as_ptr(),
+				aad_len: aad.len(),
+			};
+			let parameters = &parameters as *const _ as *const std::ffi::c_void;
+
+			let ciphertext1 = encrypt_2_1(&functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, parameters, message)?;
+			let ciphertext2 = encrypt_2_1(&functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, parameters, message)?;
+			if ciphertext1 == ciphertext2 {
+				return Err(Failure::Failed("two ciphertexts of the same plaintext are identical".to_owned()));
+			}
+
+			for ciphertext in &[ciphertext1, ciphertext2] {
+				let decrypted = decrypt_2_1(&functions, cstr(AEAD_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, parameters, ciphertext)?;
+				if decrypted != message {
+					return Err(Failure::Failed("ciphertext was decrypted incorrectly".to_owned()));
+				}
+			}
+
+			Ok(())
+		});
+	}
+
+	fn rotation(&mut self, functions: &Result<Functions2_1, Failure>) {
+		let functions = functions.clone().and_then(|functions|
+			if functions.rotate_key.is_some() && functions.rewrap.is_some() {
+				Ok(functions)
+			}
+			else {
+				Err(Failure::Skipped("implementation does not support key rotation".to_owned()))
+			});
+
+		let message = b"aziot-keys conformance message";
+		let iv = [0x03_u8; 12];
+		let aad = b"aziot-keys conformance aad";
+		let aead_parameters = new_aead_parameters(&iv, aad);
+		let aead_parameters = &aead_parameters as *const _ as *const std::ffi::c_void;
+
+		self.check("rotate_key fails with KEYGEN_ERROR_INVALID_PARAMETER if the key does not exist", || unsafe {
+			let functions = functions.clone()?;
+			expect_error(keys_fn(|| call!(functions, rotate_key(cstr(NONEXISTENT_ID).as_ptr()))), sys::KEYGEN_ERROR_INVALID_PARAMETER)
+		});
+
+		let mut old_ciphertext = Err(Failure::Skipped("key could not be rotated".to_owned()));
+
+		self.check("decrypt returns the plaintext of a ciphertext created by encrypt before the key was rotated", || unsafe {
+			let functions = functions.clone()?;
+
+			keys_fn(|| call!(functions, create_key_if_not_exists(cstr(ROTATED_KEY_ID).as_ptr(), 32)))?;
+			let ciphertext = encrypt_2_1(&functions, cstr(ROTATED_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, message)?;
+
+			keys_fn(|| call!(functions, rotate_key(cstr(ROTATED_KEY_ID).as_ptr())))?;
+
+			let decrypted = decrypt_2_1(&functions, cstr(ROTATED_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, &ciphertext)?;
+			if decrypted != message {
+				return Err(Failure::Failed("ciphertext was decrypted incorrectly".to_owned()));
+			}
+
+			// The same IV and AAD with a different key gives a different ciphertext.
+			let new_ciphertext = encrypt_2_1(&functions, cstr(ROTATED_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, message)?;
+			if new_ciphertext == ciphertext {
+				return Err(Failure::Failed("encrypt did not use the new version of the key".to_owned()));
+			}
+
+			old_ciphertext = Ok(ciphertext);
+			Ok(())
+		});
+
+		self.check("rewrap returns a ciphertext of the same plaintext created by the active version of the key", || unsafe {
+			let functions = functions.clone()?;
+			let old_ciphertext = old_ciphertext?;
+
+			let new_ciphertext = byte_buf(|new_ciphertext, new_ciphertext_len| call!(functions, rewrap(
+				cstr(ROTATED_KEY_ID).as_ptr(),
+				sys::KEYGEN_ENCRYPT_MECHANISM_AEAD,
+				aead_parameters,
+				old_ciphertext.as_ptr(),
+				old_ciphertext.len(),
+				new_ciphertext,
+				new_ciphertext_len,
+			)))?;
+			if new_ciphertext == old_ciphertext {
+				return Err(Failure::Failed("rewrap returned the same ciphertext".to_owned()));
+			}
+
+			let new_ciphertext_from_encrypt = encrypt_2_1(&functions, cstr(ROTATED_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, message)?;
+			if new_ciphertext != new_ciphertext_from_encrypt {
+				return Err(Failure::Failed("rewrapped ciphertext was not created by the active version of the key".to_owned()));
+			}
+
+			let decrypted = decrypt_2_1(&functions, cstr(ROTATED_KEY_ID), sys::KEYGEN_ENCRYPT_MECHANISM_AEAD, aead_parameters, &new_ciphertext)?;
+			if decrypted == message {
+				Ok(())
+			}
+			else {
+				Err(Failure::Failed("rewrapped ciphertext was decrypted incorrectly".to_owned()))
+			}
+		});
+	}
+}
+
+/// Why a requirement did not pass.
+#[derive(Clone)]
+enum Failure {
+	/// The implementation does not meet the requirement.
+	Failed(String),
+
+	/// The requirement could not be checked.
+	Skipped(String),
+}
+
+impl<E> From<E> for Failure where E: std::fmt::Display {
+	fn from(err: E) -> Self {
+		Failure::Failed(err.to_string())
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+struct KeysRawError(sys::KEYGEN_ERROR);
+
+impl std::fmt::Display for KeysRawError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.0 {
+			sys::KEYGEN_ERROR_FATAL => f.write_str("KEYGEN_ERROR_FATAL"),
+			sys::KEYGEN_ERROR_INVALID_PARAMETER => f.write_str("KEYGEN_ERROR_INVALID_PARAMETER"),
+			sys::KEYGEN_ERROR_EXTERNAL => f.write_str("KEYGEN_ERROR_EXTERNAL"),
+			sys::KEYGEN_ERROR_BACKEND_UNAVAILABLE => f.write_str("KEYGEN_ERROR_BACKEND_UNAVAILABLE"),
+			err => write!(f, "0x{:08x}", err),
+		}
+	}
+}
+
+fn keys_fn(f: impl FnOnce() -> sys::KEYGEN_ERROR) -> Result<(), KeysRawError> {
+	match f() {
+		sys::KEYGEN_SUCCESS => Ok(()),
+		err => Err(KeysRawError(err)),
+	}
+}
+
+fn expect_error(result: Result<(), KeysRawError>, expected: sys::KEYGEN_ERROR) -> Result<(), Failure> {
+	match result {
+		Ok(()) => Err(Failure::Failed(format!("succeeded instead of failing with {}", KeysRawError(expected)))),
+		Err(KeysRawError(err)) if err == expected => Ok(()),
+		Err(err) => Err(Failure::Failed(format!("failed with {} instead of {}", err, KeysRawError(expected)))),
+	}
+}
+
+fn cstr(s: &'static [u8]) -> &'static std::ffi::CStr {
+	std::ffi::CStr::from_bytes_with_nul(s).expect("hard-coded C string is NUL-terminated")
+}
+
+fn new_aead_parameters(iv: &[u8], aad: &[u8]) -> sys::KEYGEN_ENCRYPT_AEAD_PARAMETERS {
+	sys::KEYGEN_ENCRYPT_AEAD_PARAMETERS {
+		iv: iv.as_ptr(),
+		iv_len: iv.len(),
+		aad: aad.as_ptr(),
+		aad_len: aad.len(),
+	}
+}
+
+/// Calls a function that writes a byte buffer, first with a NULL buffer to get the required length and then with a buffer of that length.
+unsafe fn byte_buf(
+	mut f: impl FnMut(*mut std::os::raw::c_uchar, *mut usize) -> sys::KEYGEN_ERROR,
+) -> Result<Vec<u8>, KeysRawError> {
+	let mut len = 0;
+	keys_fn(|| f(std::ptr::null_mut(), &mut len))?;
+
+	let mut buf = vec![0_u8; len];
+	keys_fn(|| f(buf.as_mut_ptr(), &mut len))?;
+
+	if len > buf.len() {
+		// The library scribbled past the end of the buffer. Crash as soon as possible.
+		eprintln!("library wrote {} bytes to a buffer of {} bytes", len, buf.len());
+		std::process::abort();
+	}
+
+	buf.truncate(len);
+
+	Ok(buf)
+}
+
+unsafe fn key_pair_parameter(
+	functions: &Functions,
+	id: &std::ffi::CStr,
+	r#type: sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE,
+) -> Result<Vec<u8>, KeysRawError> {
+	byte_buf(|value, value_len| call!(functions, get_key_pair_parameter(id.as_ptr(), r#type, value, value_len)))
+}
+
+unsafe fn key_pair_algorithm(functions: &Functions, id: &std::ffi::CStr) -> Result<sys::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM, Failure> {
+	let mut algorithm: sys::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM = 0;
+	let mut algorithm_len = std::mem::size_of_val(&algorithm);
+
+	keys_fn(|| call!(functions, get_key_pair_parameter(
+		id.as_ptr(),
+		sys::KEYGEN_KEY_PAIR_PARAMETER_TYPE_ALGORITHM,
+		&mut algorithm as *mut _ as _,
+		&mut algorithm_len,
+	)))?;
+
+	if algorithm_len != std::mem::size_of_val(&algorithm) {
+		return Err(Failure::Failed(format!("algorithm has length {}", algorithm_len)));
+	}
+
+	Ok(algorithm)
+}
+
+unsafe fn sign(
+	functions: &Functions,
+	id: &std::ffi::CStr,
+	mechanism: sys::KEYGEN_SIGN_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	digest: &[u8],
+) -> Result<Vec<u8>, KeysRawError> {
+	byte_buf(|signature, signature_len| call!(functions, sign(
+		id.as_ptr(),
+		mechanism,
+		parameters,
+		digest.as_ptr(),
+		digest.len(),
+		signature,
+		signature_len,
+	)))
+}
+
+/// Checks that `verify` accepts the given signature of the given digest, and rejects it for a different digest.
+unsafe fn expect_verify(
+	functions: &Functions,
+	id: &std::ffi::CStr,
+	mechanism: sys::KEYGEN_SIGN_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	digest: &[u8],
+	signature: &[u8],
+) -> Result<(), Failure> {
+	let verify = |digest: &[u8]| -> Result<bool, KeysRawError> {
+		let mut ok = 0;
+		keys_fn(|| call!(functions, verify(
+			id.as_ptr(),
+			mechanism,
+			parameters,
+			digest.as_ptr(),
+			digest.len(),
+			signature.as_ptr(),
+			signature.len(),
+			&mut ok,
+		)))?;
+		Ok(ok != 0)
+	};
+
+	if !verify(digest)? {
+		return Err(Failure::Failed("signature was rejected".to_owned()));
+	}
+
+	let mut other_digest = digest.to_owned();
+	other_digest[0] ^= 0x01;
+	if verify(&other_digest)? {
+		return Err(Failure::Failed("signature was accepted for a different digest".to_owned()));
+	}
+
+	Ok(())
+}
+
+unsafe fn encrypt(
+	functions: &Functions,
+	id: &std::ffi::CStr,
+	mechanism: sys::KEYGEN_ENCRYPT_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	plaintext: &[u8],
+) -> Result<Vec<u8>, KeysRawError> {
+	byte_buf(|ciphertext, ciphertext_len| call!(functions, encrypt(
+		id.as_ptr(),
+		mechanism,
+		parameters,
+		plaintext.as_ptr(),
+		plaintext.len(),
+		ciphertext,
+		ciphertext_len,
+	)))
+}
+
+unsafe fn decrypt(
+	functions: &Functions,
+	id: &std::ffi::CStr,
+	mechanism: sys::KEYGEN_ENCRYPT_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	ciphertext: &[u8],
+) -> Result<Vec<u8>, KeysRawError> {
+	byte_buf(|plaintext, plaintext_len| call!(functions, decrypt(
+		id.as_ptr(),
+		mechanism,
+		parameters,
+		ciphertext.as_ptr(),
+		ciphertext.len(),
+		plaintext,
+		plaintext_len,
+	)))
+}
+
+unsafe fn encrypt_2_1(
+	functions: &Functions2_1,
+	id: &std::ffi::CStr,
+	mechanism: sys::KEYGEN_ENCRYPT_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	plaintext: &[u8],
+) -> Result<Vec<u8>, KeysRawError> {
+	byte_buf(|ciphertext, ciphertext_len| call!(functions, encrypt(
+		id.as_ptr(),
+		mechanism,
+		parameters,
+		plaintext.as_ptr(),
+		plaintext.len(),
+		ciphertext,
+		ciphertext_len,
+	)))
+}
+
+unsafe fn decrypt_2_1(
+	functions: &Functions2_1,
+	id: &std::ffi::CStr,
+	mechanism: sys::KEYGEN_ENCRYPT_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	ciphertext: &[u8],
+) -> Result<Vec<u8>, KeysRawError> {
+	byte_buf(|plaintext, plaintext_len| call!(functions, decrypt(
+		id.as_ptr(),
+		mechanism,
+		parameters,
+		ciphertext.as_ptr(),
+		ciphertext.len(),
+		plaintext,
+		plaintext_len,
+	)))
+}
+
+mod sys {
+	#![allow(
+		non_camel_case_types,
+		non_snake_case,
+		unused,
+		clippy::unreadable_literal,
+	)]
+
+	// The same bindings that aziot-keyd uses, generated from aziot-keys.h
+	include!("../../aziot-keyd/src/keys.generated.rs");
+}
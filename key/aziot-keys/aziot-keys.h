@@ -12,6 +12,7 @@
  * All calls to [`KEYGEN_get_function_list`] or any function in [`KEYGEN_FUNCTION_LIST`] are serialized, ie a function will not be called
  * while another function is running. However, it is not guaranteed that all function calls will be made from the same operating system thread.
  * Thus, implementations do not need to worry about locking to prevent concurrent access, but should also not store data in thread-local storage.
+ * Implementations that do support concurrent calls can declare so with [`KEYGEN_CAPABILITY_THREAD_SAFE`], in which case callers may stop serializing them.
  */
 
 #include <stdint.h>
@@ -136,7 +137,8 @@ typedef struct {
     /**
      * Verifies the signature of the given digest using the key identified by the specified `id`.
      *
-     * `mechanism` must be set to one of the `KEYGEN_SIGN_MECHANISM_*` constants.
+     * `mechanism` must be set to one of the `KEYGEN_SIGN_MECHANISM_*` constants, and `parameters` must be set the same way as for `sign`.
+     * The signature of a key pair is verified with its public key.
      *
      * If the function returns `KEYGEN_SUCCESS`, then `ok` is set to 0 if the signature is invalid and non-zero if the signature is valid.
      *
@@ -154,6 +156,228 @@ typedef struct {
     KEYGEN_ERROR (*decrypt)(const char *id, KEYGEN_ENCRYPT_MECHANISM mechanism, const void *parameters, const unsigned char *ciphertext, uintptr_t ciphertext_len, unsigned char *plaintext, uintptr_t *plaintext_len);
 } KEYGEN_FUNCTION_LIST_2_0_0_0;
 
+/**
+ * The capabilities of an implementation, returned by [`KEYGEN_FUNCTION_LIST_2_1_0_0::get_capabilities`].
+ *
+ * This is a combination of the `KEYGEN_CAPABILITY_*` flags.
+ */
+typedef unsigned int KEYGEN_CAPABILITIES;
+
+/**
+ * The specific implementation of [`KEYGEN_FUNCTION_LIST`] for API version 2.1.0.0
+ *
+ * This version contains all the functions of [`KEYGEN_FUNCTION_LIST_2_0_0_0`] in the same order and with the same semantics,
+ * followed by the functions that were added in this version.
+ *
+ * The functions that were added in this version are optional. An implementation that does not support one of them sets it to `NULL`,
+ * so callers must check for `NULL` before calling them.
+ */
+typedef struct {
+    /**
+     * The value of `base.version` must be [`KEYGEN_VERSION_2_1_0_0`].
+     */
+    KEYGEN_FUNCTION_LIST base;
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::set_parameter`]
+     */
+    KEYGEN_ERROR (*set_parameter)(const char *name, const char *value);
+    /**
+     * Create or load a key pair identified by the specified `id`.
+     *
+     * This is the same as [`KEYGEN_FUNCTION_LIST_2_0_0_0::create_key_pair_if_not_exists`], except that the valid components of `preferred_algorithms`
+     * are `"ec-p256"` for secp256r1, `"ec-p384"` for secp384r1, `"ec-p521"` for secp521r1, `"ed25519"` for Ed25519,
+     * `"rsa-2048"` for 2048-bit RSA, `"rsa-4096"` for 4096-bit RSA, and `"*"`, and that a component that is not one of these
+     * must be rejected instead of ignored.
+     *
+     * If the implementation is unable to use a valid component's algorithm to generate a key pair, it should ignore that component and try the next one.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `id` is NULL.
+     *   - `preferred_algorithms` contains a component that is not one of the valid components.
+     *
+     * - `KEYGEN_ERROR_EXTERNAL`
+     */
+    KEYGEN_ERROR (*create_key_pair_if_not_exists)(const char *id, const char *preferred_algorithms);
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::load_key_pair`]
+     */
+    KEYGEN_ERROR (*load_key_pair)(const char *id);
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::get_key_pair_parameter`]
+     */
+    KEYGEN_ERROR (*get_key_pair_parameter)(const char *id, KEYGEN_KEY_PAIR_PARAMETER_TYPE type_, unsigned char *value, uintptr_t *value_len);
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::create_key_if_not_exists`]
+     */
+    KEYGEN_ERROR (*create_key_if_not_exists)(const char *id, uintptr_t length);
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::import_key`]
+     */
+    KEYGEN_ERROR (*import_key)(const char *id, const uint8_t *bytes, uintptr_t bytes_len);
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::sign`]
+     */
+    KEYGEN_ERROR (*sign)(const char *id, KEYGEN_SIGN_MECHANISM mechanism, const void *parameters, const unsigned char *digest, uintptr_t digest_len, unsigned char *signature, uintptr_t *signature_len);
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::verify`]
+     */
+    KEYGEN_ERROR (*verify)(const char *id, KEYGEN_SIGN_MECHANISM mechanism, const void *parameters, const unsigned char *digest, uintptr_t digest_len, const unsigned char *signature, uintptr_t signature_len, int *ok);
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::encrypt`]
+     */
+    KEYGEN_ERROR (*encrypt)(const char *id, KEYGEN_ENCRYPT_MECHANISM mechanism, const void *parameters, const unsigned char *plaintext, uintptr_t plaintext_len, unsigned char *ciphertext, uintptr_t *ciphertext_len);
+    /**
+     * See [`KEYGEN_FUNCTION_LIST_2_0_0_0::decrypt`]
+     */
+    KEYGEN_ERROR (*decrypt)(const char *id, KEYGEN_ENCRYPT_MECHANISM mechanism, const void *parameters, const unsigned char *ciphertext, uintptr_t ciphertext_len, unsigned char *plaintext, uintptr_t *plaintext_len);
+    /**
+     * Delete the key pair identified by the specified `id`.
+     *
+     * Both the public and private components of the key pair are deleted.
+     * It is not an error if the key pair does not exist.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `id` is NULL.
+     *   - `id` identifies a key pair that cannot be deleted, such as one that was preloaded into the implementation.
+     *
+     * - `KEYGEN_ERROR_EXTERNAL`
+     */
+    KEYGEN_ERROR (*delete_key_pair)(const char *id);
+    /**
+     * Delete the key identified by the specified `id`.
+     *
+     * It is not an error if the key does not exist.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `id` is NULL.
+     *   - `id` identifies a key that cannot be deleted, such as one that was preloaded into the implementation.
+     *
+     * - `KEYGEN_ERROR_EXTERNAL`
+     */
+    KEYGEN_ERROR (*delete_key)(const char *id);
+    /**
+     * Lists the key pairs known to this implementation.
+     *
+     * The list is written to `list` as a sequence of entries, one per key pair. Each entry is three NUL-terminated strings:
+     *
+     * - The ID of the key pair.
+     * - The algorithm of the key pair, such as `"ec-p256"` or `"rsa-2048"`.
+     * - The backend that stores the key pair. The values are defined by the implementation. This implementation uses
+     *   `"filesystem"`, `"pkcs11"` and `"preloaded"`.
+     *
+     * `list` may be `NULL`, in which case only `list_len` is set to the length required to hold the list. Thus callers can call this function
+     * twice, first with `list` set to `NULL` to learn the required length, and then with `list` set to a buffer of that length.
+     *
+     * Implementations may not be able to list key pairs that were created before they started keeping track of them, until those key pairs are loaded again with `load_key_pair` or `create_key_pair_if_not_exists`.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `list` is not `NULL` and `list_len` is too small to hold the list.
+     *   - `list_len` is `NULL`.
+     *
+     * - `KEYGEN_ERROR_EXTERNAL`
+     */
+    KEYGEN_ERROR (*list_key_pairs)(char *list, uintptr_t *list_len);
+    /**
+     * Lists the keys known to this implementation.
+     *
+     * The list is written in the same format as for [`KEYGEN_FUNCTION_LIST_2_1_0_0::list_key_pairs`]. The algorithm of a key
+     * is its length in bits, such as `"symmetric-256"`.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `list` is not `NULL` and `list_len` is too small to hold the list.
+     *   - `list_len` is `NULL`.
+     *
+     * - `KEYGEN_ERROR_EXTERNAL`
+     */
+    KEYGEN_ERROR (*list_keys)(char *list, uintptr_t *list_len);
+    /**
+     * Derives a shared secret via ECDH from the EC key pair identified by the specified `id` and the given peer public key.
+     *
+     * `peer_public_key` must be a point on the same curve as the key pair, encoded in uncompressed form (`0x04 || X || Y`).
+     * The shared secret is the X coordinate of the derived point. No KDF is applied to it.
+     *
+     * `shared_secret` may be `NULL`, in which case only `shared_secret_len` is set to the length of the shared secret. Thus callers can call this function
+     * twice, first with `shared_secret` set to `NULL` to learn the required length, and then with `shared_secret` set to a buffer of that length.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `id` is NULL.
+     *   - The key pair specified by `id` does not exist, or is not an EC key pair.
+     *   - `peer_public_key` is NULL, or is not a point on the curve of the key pair.
+     *   - `shared_secret` is not `NULL` and `shared_secret_len` is too small to hold the shared secret.
+     *   - `shared_secret_len` is `NULL`.
+     *
+     * - `KEYGEN_ERROR_EXTERNAL`
+     */
+    KEYGEN_ERROR (*derive_shared_secret)(const char *id, const unsigned char *peer_public_key, uintptr_t peer_public_key_len, unsigned char *shared_secret, uintptr_t *shared_secret_len);
+    /**
+     * Rotates the symmetric key identified by the specified `id`.
+     *
+     * A new random key of the same length becomes the active version of the key, and the previous versions are retained.
+     * Subsequent encrypt operations use the active version. Ciphertexts record the version that created them,
+     * so decrypt operations continue to work with ciphertexts created by previous versions.
+     *
+     * Importing different bytes over an existing key with `import_key` also rotates it, with the imported bytes as the new active version.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `id` is NULL.
+     *   - The key specified by `id` does not exist.
+     *   - `id` identifies a key that cannot be rotated, such as one that was preloaded into the implementation.
+     *
+     * - `KEYGEN_ERROR_EXTERNAL`
+     */
+    KEYGEN_ERROR (*rotate_key)(const char *id);
+    /**
+     * Decrypts the given ciphertext with the symmetric key identified by the specified `id`, and encrypts the result with the active version of the key.
+     *
+     * This is used to migrate ciphertexts created by previous versions of the key to its active version after it has been rotated.
+     * `mechanism` and `parameters` are used for both the decrypt and the encrypt operation, and must be set the same way as for `encrypt`.
+     *
+     * `new_ciphertext` may be `NULL`, in which case only `new_ciphertext_len` is set to the length of the new ciphertext. Thus callers can call this function
+     * twice, first with `new_ciphertext` set to `NULL` to learn the required length, and then with `new_ciphertext` set to a buffer of that length.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `id` is NULL.
+     *   - The key specified by `id` does not exist.
+     *   - `mechanism` is not a valid encrypt mechanism for the key specified by `id`.
+     *   - `ciphertext` is NULL, or was not created by any version of the key.
+     *   - `new_ciphertext` is not `NULL` and `new_ciphertext_len` is too small to hold the new ciphertext.
+     *   - `new_ciphertext_len` is `NULL`.
+     *
+     * - `KEYGEN_ERROR_EXTERNAL`
+     */
+    KEYGEN_ERROR (*rewrap)(const char *id, KEYGEN_ENCRYPT_MECHANISM mechanism, const void *parameters, const unsigned char *ciphertext, uintptr_t ciphertext_len, unsigned char *new_ciphertext, uintptr_t *new_ciphertext_len);
+    /**
+     * Gets the capabilities of this implementation.
+     *
+     * `capabilities` is set to a combination of the `KEYGEN_CAPABILITY_*` flags. Unrecognized flags must be ignored by the caller,
+     * so that future versions of this API can add new capabilities.
+     *
+     * If this function is `NULL`, the implementation has none of the capabilities.
+     *
+     * # Errors
+     *
+     * - `KEYGEN_ERROR_INVALID_PARAMETER`:
+     *   - `capabilities` is `NULL`.
+     */
+    KEYGEN_ERROR (*get_capabilities)(KEYGEN_CAPABILITIES *capabilities);
+} KEYGEN_FUNCTION_LIST_2_1_0_0;
+
 /**
  * Represents the mask generation function used for a sign operation with the [`KEYGEN_SIGN_MECHANISM_RSA_PSS`] mechanism.
  */
@@ -161,6 +385,8 @@ typedef unsigned int KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION;
 
 /**
  * Holds parameters for a sign operation with the [`KEYGEN_SIGN_MECHANISM_RSA_PSS`] mechanism.
+ *
+ * The digest given to the sign operation must have been computed with the same hash algorithm as the one used by the mask generation function.
  */
 typedef struct {
     KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION mask_generation_function;
@@ -177,6 +403,14 @@ typedef struct {
     uintptr_t aad_len;
 } KEYGEN_ENCRYPT_AEAD_PARAMETERS;
 
+/**
+ * Holds parameters for an encrypt operation with the [`KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE`] mechanism.
+ */
+typedef struct {
+    const unsigned char *aad;
+    uintptr_t aad_len;
+} KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS;
+
 typedef unsigned int KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM;
 
 /**
@@ -186,11 +420,42 @@ typedef unsigned int KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM;
  */
 typedef unsigned int KEYGEN_RSA_PKCS1_MESSAGE_DIGEST;
 
+/**
+ * The functions of the implementation may be called concurrently from multiple threads.
+ *
+ * Callers are not required to make use of this, so implementations that declare it must still work if their calls are serialized.
+ */
+#define KEYGEN_CAPABILITY_THREAD_SAFE 1
+
 /**
  * AEAD (eg AES-256-GCM)
  */
 #define KEYGEN_ENCRYPT_MECHANISM_AEAD 1
 
+/**
+ * AEAD (eg AES-256-GCM) with a random nonce generated by the library for every encrypt operation.
+ *
+ * The nonce is stored in the ciphertext, so decrypt only needs the same AAD that was used to encrypt.
+ */
+#define KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE 3
+
+/**
+ * RSA-OAEP with SHA-256 as the hash function of both the label and MGF1, and an empty label.
+ *
+ * Only valid for RSA key pairs. Encrypts with the public key and decrypts with the private key. The parameters must be NULL.
+ */
+#define KEYGEN_ENCRYPT_MECHANISM_RSA_OAEP 2
+
+/**
+ * The library could not reach the backend that holds the key, such as a PKCS#11 token that could not be opened or logged in to.
+ *
+ * Unlike `KEYGEN_ERROR_EXTERNAL`, the operation may succeed if retried later.
+ *
+ * This is only returned if API version 2.1.0.0 was requested from `KEYGEN_get_function_list`. Any function that can return `KEYGEN_ERROR_EXTERNAL`
+ * can then also return this. Under API version 2.0.0.0, these errors are returned as `KEYGEN_ERROR_EXTERNAL` instead.
+ */
+#define KEYGEN_ERROR_BACKEND_UNAVAILABLE 4
+
 /**
  * The library encountered an error with an external resource, such as an I/O error or RPC error.
  */
@@ -210,6 +475,11 @@ typedef unsigned int KEYGEN_RSA_PKCS1_MESSAGE_DIGEST;
 
 #define KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_RSA 2
 
+/**
+ * Ed25519. The curve OID and point can be read with the `KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_*` parameter types.
+ */
+#define KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_ED25519 3
+
 /**
  * Used as the parameter type with `get_key_pair_parameter` to get the key algorithm.
  *
@@ -218,7 +488,7 @@ typedef unsigned int KEYGEN_RSA_PKCS1_MESSAGE_DIGEST;
 #define KEYGEN_KEY_PAIR_PARAMETER_TYPE_ALGORITHM 1
 
 /**
- * Used as the parameter type with `get_key_pair_parameter` to get the curve OID of an EC key.
+ * Used as the parameter type with `get_key_pair_parameter` to get the curve OID of an EC or Ed25519 key.
  *
  * The value returned by `get_key_pair_parameter` will be a byte buffer containing a DER-encoded OID.
  */
@@ -228,6 +498,8 @@ typedef unsigned int KEYGEN_RSA_PKCS1_MESSAGE_DIGEST;
  * Used as the parameter type with `get_key_pair_parameter` to get the point of an EC key.
  *
  * The value returned by `get_key_pair_parameter` will be a byte buffer containing a DER-encoded octet string in RFC 5490 format.
+ *
+ * For an Ed25519 key, the value is the 32-byte public key as defined in RFC 8032.
  */
 #define KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT 3
 
@@ -275,6 +547,14 @@ typedef unsigned int KEYGEN_RSA_PKCS1_MESSAGE_DIGEST;
  */
 #define KEYGEN_SIGN_MECHANISM_ECDSA 1
 
+/**
+ * EdDSA with Ed25519 (pure Ed25519 as defined in RFC 8032)
+ *
+ * Only valid for Ed25519 key pairs. EdDSA cannot sign pre-hashed digests, so the `digest` given to `sign` and `verify` must be the whole message.
+ * The parameters must be NULL.
+ */
+#define KEYGEN_SIGN_MECHANISM_EDDSA 5
+
 /**
  * HMAC-SHA256
  */
@@ -325,6 +605,11 @@ typedef unsigned int KEYGEN_RSA_PKCS1_MESSAGE_DIGEST;
  */
 #define KEYGEN_VERSION_2_0_0_0 33554432
 
+/**
+ * Version 2.1.0.0
+ */
+#define KEYGEN_VERSION_2_1_0_0 33619968
+
 
 /**
  * Get the list of functions for operations corresponding to the specified version.
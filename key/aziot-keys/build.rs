@@ -0,0 +1,5 @@
+#![deny(rust_2018_idioms, warnings)]
+
+fn main() {
+	openssl_build::define_version_number_cfg();
+}
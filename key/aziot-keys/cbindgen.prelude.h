@@ -12,5 +12,6 @@
  * All calls to [`KEYGEN_get_function_list`] or any function in [`KEYGEN_FUNCTION_LIST`] are serialized, ie a function will not be called
  * while another function is running. However, it is not guaranteed that all function calls will be made from the same operating system thread.
  * Thus, implementations do not need to worry about locking to prevent concurrent access, but should also not store data in thread-local storage.
+ * Implementations that do support concurrent calls can declare so with [`KEYGEN_CAPABILITY_THREAD_SAFE`], in which case callers may stop serializing them.
  */
 
@@ -47,6 +47,23 @@ pub(crate) unsafe fn get_function_list(
 				version: crate::KEYGEN_VERSION_2_0_0_0,
 			},
 
+			set_parameter: v2_0_0_0::set_parameter,
+			create_key_pair_if_not_exists: v2_0_0_0::create_key_pair_if_not_exists,
+			load_key_pair: v2_0_0_0::load_key_pair,
+			get_key_pair_parameter: v2_0_0_0::get_key_pair_parameter,
+			create_key_if_not_exists: v2_0_0_0::create_key_if_not_exists,
+			import_key: v2_0_0_0::import_key,
+			sign: v2_0_0_0::sign,
+			verify: v2_0_0_0::verify,
+			encrypt: v2_0_0_0::encrypt,
+			decrypt: v2_0_0_0::decrypt,
+		};
+
+		static KEYGEN_FUNCTION_LIST_2_1_0_0: crate::KEYGEN_FUNCTION_LIST_2_1_0_0 = crate::KEYGEN_FUNCTION_LIST_2_1_0_0 {
+			base: crate::KEYGEN_FUNCTION_LIST {
+				version: crate::KEYGEN_VERSION_2_1_0_0,
+			},
+
 			set_parameter,
 			create_key_pair_if_not_exists: crate::key_pair::create_key_pair_if_not_exists,
 			load_key_pair: crate::key_pair::load_key_pair,
@@ -57,20 +74,134 @@ pub(crate) unsafe fn get_function_list(
 			verify,
 			encrypt,
 			decrypt,
+			delete_key_pair: Some(crate::key_pair::delete_key_pair),
+			delete_key: Some(crate::key::delete_key),
+			list_key_pairs: Some(crate::key_pair::list_key_pairs),
+			list_keys: Some(crate::key::list_keys),
+			derive_shared_secret: Some(crate::key_pair::derive_shared_secret),
+			rotate_key: Some(crate::key::rotate_key),
+			rewrap: Some(crate::key::rewrap),
+			get_capabilities: Some(get_capabilities),
 		};
 
 		match version {
 			crate::KEYGEN_VERSION_2_0_0_0 => {
 				let mut function_list_out = std::ptr::NonNull::new(pfunction_list).ok_or_else(|| err_invalid_parameter("pfunction_list", "expected non-NULL"))?;
 				*function_list_out.as_mut() = &KEYGEN_FUNCTION_LIST_2_0_0_0 as *const _ as *const _;
-				Ok(())
 			},
 
-			_ => Err(err_invalid_parameter("version", "unsupported version")),
+			crate::KEYGEN_VERSION_2_1_0_0 => {
+				let mut function_list_out = std::ptr::NonNull::new(pfunction_list).ok_or_else(|| err_invalid_parameter("pfunction_list", "expected non-NULL"))?;
+				*function_list_out.as_mut() = &KEYGEN_FUNCTION_LIST_2_1_0_0 as *const _ as *const _;
+			},
+
+			_ => return Err(err_invalid_parameter("version", "unsupported version")),
 		}
+
+		Ok(())
 	})
 }
 
+/// Entry points of the 2.0.0.0 function list.
+///
+/// `KEYGEN_ERROR_BACKEND_UNAVAILABLE` was added in API version 2.1.0.0, so callers of 2.0.0.0 only know the errors that it defined.
+/// These wrap the regular implementations and report that error as `KEYGEN_ERROR_EXTERNAL` instead.
+mod v2_0_0_0 {
+	macro_rules! wrap {
+		($(fn $name:ident($($arg:ident : $ty:ty),* $(,)?) => $f:path;)*) => {
+			$(
+				pub(super) unsafe extern "C" fn $name($($arg: $ty),*) -> crate::KEYGEN_ERROR {
+					to_2_0_0_0_error($f($($arg),*))
+				}
+			)*
+		};
+	}
+
+	wrap! {
+		fn set_parameter(
+			name: *const std::os::raw::c_char,
+			value: *const std::os::raw::c_char,
+		) => super::set_parameter;
+
+		fn create_key_pair_if_not_exists(
+			id: *const std::os::raw::c_char,
+			preferred_algorithms: *const std::os::raw::c_char,
+		) => crate::key_pair::create_key_pair_if_not_exists_2_0_0_0;
+
+		fn load_key_pair(
+			id: *const std::os::raw::c_char,
+		) => crate::key_pair::load_key_pair;
+
+		fn get_key_pair_parameter(
+			id: *const std::os::raw::c_char,
+			type_: crate::KEYGEN_KEY_PAIR_PARAMETER_TYPE,
+			value: *mut std::os::raw::c_uchar,
+			value_len: *mut usize,
+		) => crate::key_pair::get_key_pair_parameter;
+
+		fn create_key_if_not_exists(
+			id: *const std::os::raw::c_char,
+			length: usize,
+		) => crate::key::create_key_if_not_exists;
+
+		fn import_key(
+			id: *const std::os::raw::c_char,
+			bytes: *const u8,
+			bytes_len: usize,
+		) => crate::key::import_key;
+
+		fn sign(
+			id: *const std::os::raw::c_char,
+			mechanism: crate::KEYGEN_SIGN_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			digest: *const std::os::raw::c_uchar,
+			digest_len: usize,
+			signature: *mut std::os::raw::c_uchar,
+			signature_len: *mut usize,
+		) => super::sign;
+
+		fn verify(
+			id: *const std::os::raw::c_char,
+			mechanism: crate::KEYGEN_SIGN_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			digest: *const std::os::raw::c_uchar,
+			digest_len: usize,
+			signature: *const std::os::raw::c_uchar,
+			signature_len: usize,
+			ok: *mut std::os::raw::c_int,
+		) => super::verify;
+
+		fn encrypt(
+			id: *const std::os::raw::c_char,
+			mechanism: crate::KEYGEN_ENCRYPT_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			plaintext: *const std::os::raw::c_uchar,
+			plaintext_len: usize,
+			ciphertext: *mut std::os::raw::c_uchar,
+			ciphertext_len: *mut usize,
+		) => super::encrypt;
+
+		fn decrypt(
+			id: *const std::os::raw::c_char,
+			mechanism: crate::KEYGEN_ENCRYPT_MECHANISM,
+			parameters: *const std::ffi::c_void,
+			ciphertext: *const std::os::raw::c_uchar,
+			ciphertext_len: usize,
+			plaintext: *mut std::os::raw::c_uchar,
+			plaintext_len: *mut usize,
+		) => super::decrypt;
+	}
+
+	pub(super) fn to_2_0_0_0_error(err: crate::KEYGEN_ERROR) -> crate::KEYGEN_ERROR {
+		if err == crate::KEYGEN_ERROR_BACKEND_UNAVAILABLE {
+			crate::KEYGEN_ERROR_EXTERNAL
+		}
+		else {
+			err
+		}
+	}
+}
+
 pub(crate) unsafe extern "C" fn set_parameter(
 	name: *const std::os::raw::c_char,
 	value: *const std::os::raw::c_char,
@@ -87,6 +218,8 @@ pub(crate) unsafe extern "C" fn set_parameter(
 				let value = value.to_str().map_err(|err| err_invalid_parameter("value", err))?;
 				let value: std::path::PathBuf = value.into();
 
+				fs_common::check_dir(&value).map_err(|err| err_invalid_parameter("value", err))?;
+
 				let mut guard = HOMEDIR_PATH.write().map_err(err_fatal)?;
 				*guard = Some(value);
 			},
@@ -105,12 +238,26 @@ pub(crate) unsafe extern "C" fn set_parameter(
 				let value = value.as_ref().ok_or_else(|| err_invalid_parameter("value", "expected non-NULL"))?;
 				let value = std::ffi::CStr::from_ptr(value);
 				let value = value.to_str().map_err(|err| err_invalid_parameter("value", err))?;
-				let value = value.parse().map_err(|err| err_invalid_parameter("value", err))?;
+				let mut value: pkcs11::Uri = value.parse().map_err(|err| err_invalid_parameter("value", err))?;
+
+				// Key pairs in the base slot are identified by their labels alone, so drop any object attributes the URI might have.
+				value.object_label = None;
+				value.object_id = None;
+				value.object_type = None;
 
 				let mut guard = PKCS11_BASE_SLOT.write().map_err(err_fatal)?;
 				*guard = Some(value);
 			},
 
+			"KEY_ENCRYPTION_KEY" => {
+				let value = value.as_ref().ok_or_else(|| err_invalid_parameter("value", "expected non-NULL"))?;
+				let value = std::ffi::CStr::from_ptr(value);
+				let value = value.to_str().map_err(|err| err_invalid_parameter("value", err))?;
+				let value: crate::kek::Source = value.parse().map_err(|err| err_invalid_parameter("value", err))?;
+
+				crate::kek::set_source(value)?;
+			},
+
 			name if name.starts_with("PRELOADED_KEY:") => {
 				let key_id = &name["PRELOADED_KEY:".len()..];
 				if key_id.is_empty() {
@@ -133,6 +280,20 @@ pub(crate) unsafe extern "C" fn set_parameter(
 	})
 }
 
+pub(crate) unsafe extern "C" fn get_capabilities(
+	capabilities: *mut crate::KEYGEN_CAPABILITIES,
+) -> crate::KEYGEN_ERROR {
+	crate::r#catch(|| {
+		let mut capabilities = std::ptr::NonNull::new(capabilities).ok_or_else(|| err_invalid_parameter("capabilities", "expected non-NULL"))?;
+
+		// Not thread-safe, since two concurrent calls to `create_key_if_not_exists` or `rotate_key` for the same key could race
+		// to write the key file and the index.
+		*capabilities.as_mut() = crate::KEYGEN_CAPABILITIES { inner: 0 };
+
+		Ok(())
+	})
+}
+
 pub(crate) unsafe extern "C" fn sign(
 	id: *const std::os::raw::c_char,
 	mechanism: crate::KEYGEN_SIGN_MECHANISM,
@@ -167,7 +328,8 @@ pub(crate) unsafe extern "C" fn sign(
 		let (expected_signature_len, expected_signature) = match mechanism {
 			crate::KEYGEN_SIGN_MECHANISM_ECDSA |
 			crate::KEYGEN_SIGN_MECHANISM_RSA_PKCS1 |
-			crate::KEYGEN_SIGN_MECHANISM_RSA_PSS =>
+			crate::KEYGEN_SIGN_MECHANISM_RSA_PSS |
+			crate::KEYGEN_SIGN_MECHANISM_EDDSA =>
 				crate::key_pair::sign(&location, mechanism, parameters, digest)?,
 
 			crate::KEYGEN_SIGN_MECHANISM_HMAC_SHA256 =>
@@ -200,7 +362,7 @@ pub(crate) unsafe extern "C" fn sign(
 pub(crate) unsafe extern "C" fn verify(
 	id: *const std::os::raw::c_char,
 	mechanism: crate::KEYGEN_SIGN_MECHANISM,
-	_parameters: *const std::ffi::c_void, // Currently unused, but may be used in the future
+	parameters: *const std::ffi::c_void,
 	digest: *const std::os::raw::c_uchar,
 	digest_len: usize,
 	signature: *const std::os::raw::c_uchar,
@@ -238,12 +400,11 @@ pub(crate) unsafe extern "C" fn verify(
 		let location = Location::of(id)?;
 
 		let ok = match mechanism {
-			// Verify is not supported for asymmetric keys.
-			// Clients can verify signatures themselves from the public parameters of the key pair.
 			crate::KEYGEN_SIGN_MECHANISM_ECDSA |
 			crate::KEYGEN_SIGN_MECHANISM_RSA_PKCS1 |
-			crate::KEYGEN_SIGN_MECHANISM_RSA_PSS =>
-				return Err(err_invalid_parameter("mechanism", "unrecognized value")),
+			crate::KEYGEN_SIGN_MECHANISM_RSA_PSS |
+			crate::KEYGEN_SIGN_MECHANISM_EDDSA =>
+				crate::key_pair::verify(&location, mechanism, parameters, digest, signature)?,
 
 			crate::KEYGEN_SIGN_MECHANISM_HMAC_SHA256 =>
 				crate::key::verify(&location, digest, signature)?,
@@ -289,9 +450,13 @@ pub(crate) unsafe extern "C" fn encrypt(
 		let location = Location::of(id)?;
 
 		let (expected_ciphertext_len, expected_ciphertext) = match mechanism {
-			crate::KEYGEN_ENCRYPT_MECHANISM_AEAD =>
+			crate::KEYGEN_ENCRYPT_MECHANISM_AEAD |
+			crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE =>
 				crate::key::encrypt(&location, mechanism, parameters, plaintext)?,
 
+			crate::KEYGEN_ENCRYPT_MECHANISM_RSA_OAEP =>
+				crate::key_pair::encrypt(&location, mechanism, parameters, plaintext)?,
+
 			_ => return Err(err_invalid_parameter("mechanism", "unrecognized value")),
 		};
 
@@ -348,9 +513,13 @@ pub(crate) unsafe extern "C" fn decrypt(
 		let location = Location::of(id)?;
 
 		let (expected_plaintext_len, expected_plaintext) = match mechanism {
-			crate::KEYGEN_ENCRYPT_MECHANISM_AEAD =>
+			crate::KEYGEN_ENCRYPT_MECHANISM_AEAD |
+			crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE =>
 				crate::key::decrypt(&location, mechanism, parameters, ciphertext)?,
 
+			crate::KEYGEN_ENCRYPT_MECHANISM_RSA_OAEP =>
+				crate::key_pair::decrypt(&location, mechanism, parameters, ciphertext)?,
+
 			_ => return Err(err_invalid_parameter("mechanism", "unrecognized value")),
 		};
 
@@ -428,6 +597,97 @@ impl Location {
 				Err(err_invalid_parameter("id", "no way to create keys")),
 		}
 	}
+
+	/// Like [`Location::of`], but fails if the key is a preloaded key.
+	///
+	/// Preloaded keys are owned by whoever provisioned them, so they must not be modified or deleted by this library.
+	pub(crate) fn of_deletable(id: &str) -> Result<Self, crate::KEYGEN_ERROR> {
+		if is_preloaded(id)? {
+			return Err(err_invalid_parameter("id", "cannot delete preloaded key"));
+		}
+
+		Location::of(id)
+	}
+}
+
+pub(crate) fn homedir_path() -> Result<Option<std::path::PathBuf>, crate::KEYGEN_ERROR> {
+	let homedir_path_guard = HOMEDIR_PATH.read().map_err(err_fatal)?;
+	Ok(homedir_path_guard.clone())
+}
+
+pub(crate) fn is_preloaded(id: &str) -> Result<bool, crate::KEYGEN_ERROR> {
+	let preloaded_keys_guard = PRELOADED_KEYS.read().map_err(err_fatal)?;
+	Ok(preloaded_keys_guard.contains_key(id))
+}
+
+/// The files of the preloaded keys that are in the filesystem.
+pub(crate) fn preloaded_paths() -> Result<Vec<std::path::PathBuf>, crate::KEYGEN_ERROR> {
+	let preloaded_keys_guard = PRELOADED_KEYS.read().map_err(err_fatal)?;
+	Ok(preloaded_keys_guard.values().filter_map(|location| match location {
+		PreloadedKeyLocation::Filesystem { path } => Some(path.clone()),
+		PreloadedKeyLocation::Pkcs11 { .. } => None,
+	}).collect())
+}
+
+pub(crate) fn preloaded_key_ids() -> Result<Vec<String>, crate::KEYGEN_ERROR> {
+	let preloaded_keys_guard = PRELOADED_KEYS.read().map_err(err_fatal)?;
+	Ok(preloaded_keys_guard.keys().cloned().collect())
+}
+
+/// The PKCS#11 library and the slot where new key pairs are created, if PKCS#11 is configured.
+pub(crate) fn pkcs11_base_slot() -> Result<Option<(std::path::PathBuf, pkcs11::Uri)>, crate::KEYGEN_ERROR> {
+	let pkcs11_lib_path_guard = PKCS11_LIB_PATH.read().map_err(err_fatal)?;
+	let pkcs11_base_slot_guard = PKCS11_BASE_SLOT.read().map_err(err_fatal)?;
+
+	match (&*pkcs11_lib_path_guard, &*pkcs11_base_slot_guard) {
+		(Some(pkcs11_lib_path), Some(pkcs11_base_slot)) => Ok(Some((pkcs11_lib_path.clone(), pkcs11_base_slot.clone()))),
+		_ => Ok(None),
+	}
+}
+
+/// An entry in the list written by `list_key_pairs` and `list_keys`.
+pub(crate) struct ListEntry {
+	pub(crate) id: String,
+	pub(crate) algorithm: String,
+	pub(crate) backend: &'static str,
+}
+
+/// Writes the given entries to the caller's buffer in the format documented for `list_key_pairs` and `list_keys`.
+pub(crate) unsafe fn write_list(
+	entries: &[ListEntry],
+	list: *mut std::os::raw::c_char,
+	list_len: *mut usize,
+) -> Result<(), crate::KEYGEN_ERROR> {
+	let mut list_len_out = std::ptr::NonNull::new(list_len).ok_or_else(|| err_invalid_parameter("list_len", "expected non-NULL"))?;
+
+	let mut expected_list = vec![];
+	for ListEntry { id, algorithm, backend } in entries {
+		for s in &[&**id, &**algorithm, *backend] {
+			if s.contains('\0') {
+				return Err(err_external(format!("list entry {:?} contains a NUL character", s)));
+			}
+
+			expected_list.extend_from_slice(s.as_bytes());
+			expected_list.push(b'\0');
+		}
+	}
+
+	let expected_list_len = expected_list.len();
+	let actual_list_len = *list_len_out.as_ref();
+
+	*list_len_out.as_mut() = expected_list_len;
+
+	if !list.is_null() {
+		if actual_list_len < expected_list_len {
+			return Err(err_invalid_parameter("list", "insufficient size"));
+		}
+
+		let list_out = std::slice::from_raw_parts_mut(list as *mut u8, actual_list_len);
+
+		list_out[..expected_list_len].copy_from_slice(&expected_list);
+	}
+
+	Ok(())
 }
 
 impl From<openssl::error::Error> for crate::KEYGEN_ERROR {
@@ -453,6 +713,11 @@ pub(crate) fn err_external<E>(err: E) -> crate::KEYGEN_ERROR where E: std::fmt::
 	crate::KEYGEN_ERROR_EXTERNAL
 }
 
+pub(crate) fn err_backend_unavailable<E>(err: E) -> crate::KEYGEN_ERROR where E: std::fmt::Display {
+	eprintln!("{}", err);
+	crate::KEYGEN_ERROR_BACKEND_UNAVAILABLE
+}
+
 pub(crate) fn err_fatal<E>(err: E) -> crate::KEYGEN_ERROR where E: std::fmt::Display {
 	eprintln!("{}", err);
 	crate::KEYGEN_ERROR_EXTERNAL
@@ -462,3 +727,15 @@ pub(crate) fn err_invalid_parameter<E>(name: &str, err: E) -> crate::KEYGEN_ERRO
 	eprintln!("invalid parameter {:?}: {}", name, err);
 	crate::KEYGEN_ERROR_INVALID_PARAMETER
 }
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn backend_unavailable_is_external_for_2_0_0_0() {
+		assert_eq!(super::v2_0_0_0::to_2_0_0_0_error(crate::KEYGEN_ERROR_BACKEND_UNAVAILABLE), crate::KEYGEN_ERROR_EXTERNAL);
+
+		for &err in &[crate::KEYGEN_SUCCESS, crate::KEYGEN_ERROR_FATAL, crate::KEYGEN_ERROR_INVALID_PARAMETER, crate::KEYGEN_ERROR_EXTERNAL] {
+			assert_eq!(super::v2_0_0_0::to_2_0_0_0_error(err), err);
+		}
+	}
+}
@@ -0,0 +1,83 @@
+// Keys in the homedir are stored in files named after the SHA-256 of their ID, so the IDs cannot be recovered from the filenames.
+// This index records the ID and kind of every key and key pair that this library creates in the homedir, so that they can be listed.
+//
+// Keys that were created before the index existed are not in it. They are added to it the next time they are created, imported or loaded
+// by ID, which clients do to get a handle to a key before they can use it. So they are listed once a client has used them again.
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Kind {
+	Key,
+	KeyPair,
+}
+
+pub(crate) fn load() -> Result<std::collections::BTreeMap<String, Kind>, crate::KEYGEN_ERROR> {
+	let path = match path()? {
+		Some(path) => path,
+		None => return Ok(Default::default()),
+	};
+
+	match std::fs::read(&path) {
+		Ok(index) => {
+			let index = serde_json::from_slice(&index).map_err(crate::implementation::err_external)?;
+			Ok(index)
+		},
+		Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Default::default()),
+		Err(err) => Err(crate::implementation::err_external(err)),
+	}
+}
+
+/// Records that the key or key pair with the given ID exists at the given location.
+///
+/// Only keys that this library creates in the homedir are recorded. Keys in other locations can be enumerated without the index.
+pub(crate) fn add(location: &crate::implementation::Location, id: &str, kind: Kind) -> Result<(), crate::KEYGEN_ERROR> {
+	if !is_indexed(location, id)? {
+		return Ok(());
+	}
+
+	let mut index = load()?;
+	if index.get(id) == Some(&kind) {
+		return Ok(());
+	}
+
+	index.insert(id.to_owned(), kind);
+	save(&index)
+}
+
+/// Records that the key or key pair with the given ID no longer exists at the given location.
+pub(crate) fn remove(location: &crate::implementation::Location, id: &str) -> Result<(), crate::KEYGEN_ERROR> {
+	if !is_indexed(location, id)? {
+		return Ok(());
+	}
+
+	let mut index = load()?;
+	if index.remove(id).is_none() {
+		return Ok(());
+	}
+
+	save(&index)
+}
+
+fn is_indexed(location: &crate::implementation::Location, id: &str) -> Result<bool, crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(_) => Ok(!crate::implementation::is_preloaded(id)?),
+		crate::implementation::Location::Pkcs11 { .. } => Ok(false),
+	}
+}
+
+fn save(index: &std::collections::BTreeMap<String, Kind>) -> Result<(), crate::KEYGEN_ERROR> {
+	let path = match path()? {
+		Some(path) => path,
+		None => return Ok(()),
+	};
+
+	let contents = serde_json::to_vec(index).map_err(crate::implementation::err_external)?;
+	fs_common::write_file(&path, &contents).map_err(crate::implementation::err_external)?;
+
+	Ok(())
+}
+
+fn path() -> Result<Option<std::path::PathBuf>, crate::KEYGEN_ERROR> {
+	let homedir_path = crate::implementation::homedir_path()?;
+	Ok(homedir_path.map(|homedir_path| homedir_path.join("key-ids.json")))
+}
@@ -0,0 +1,357 @@
+// If a key encryption key (KEK) is configured, the files of the keys and key pairs that this library creates in the homedir
+// are encrypted with it using AES-256-GCM, so that reading the disk is not enough to recover them.
+//
+// An encrypted file is `MAGIC | nonce | tag | ciphertext`. The AAD is `MAGIC | file name`, so an encrypted file cannot be copied over
+// the file of another key without failing to decrypt.
+//
+// Files that do not start with MAGIC are plaintext, either because they were written before the KEK was configured or because they are preloaded keys.
+// Plaintext files in the homedir are encrypted the first time they are loaded. Preloaded keys are owned by whoever provisioned them,
+// so they are never rewritten, even if they happen to be in the homedir.
+
+lazy_static::lazy_static! {
+	static ref KEK: std::sync::Mutex<Option<Kek>> = Default::default();
+}
+
+const MAGIC: &[u8] = b"AZIOTKEK";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+const PASSPHRASE_SALT_FILE_NAME: &str = "kek-salt";
+const PASSPHRASE_SALT_LEN: usize = 16;
+const PASSPHRASE_ITERATIONS: usize = 600_000;
+
+struct Kek {
+	source: Source,
+
+	/// The KEK itself. It is only resolved from the source when it is first needed, since that might require the homedir to have been set.
+	key: Option<Vec<u8>>,
+}
+
+/// Where the KEK comes from.
+#[derive(Debug)]
+pub(crate) enum Source {
+	/// A file that contains the 32-byte KEK.
+	File(std::path::PathBuf),
+
+	/// A systemd credential that contains the 32-byte KEK. It is read from the directory in the `CREDENTIALS_DIRECTORY` env var.
+	Credential(String),
+
+	/// A passphrase that the KEK is derived from with PBKDF2. The salt is generated the first time and stored in the homedir.
+	Passphrase(String),
+}
+
+impl std::str::FromStr for Source {
+	type Err = Box<dyn std::error::Error>;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let scheme_end_index = s.find(':').ok_or("missing scheme")?;
+		let scheme = &s[..scheme_end_index];
+		let rest = &s[(scheme_end_index + 1)..];
+
+		match scheme {
+			"file" => {
+				let uri: url::Url = s.parse()?;
+				let path = uri.to_file_path().map_err(|()| "cannot convert to file path")?;
+				Ok(Source::File(path))
+			},
+
+			"credential" => {
+				if rest.is_empty() || rest.contains('/') {
+					return Err("invalid credential name".into());
+				}
+
+				Ok(Source::Credential(rest.to_owned()))
+			},
+
+			"passphrase" => {
+				if rest.is_empty() {
+					return Err("passphrase is empty".into());
+				}
+
+				Ok(Source::Passphrase(rest.to_owned()))
+			},
+
+			_ => Err("unrecognized scheme".into())
+		}
+	}
+}
+
+pub(crate) fn set_source(source: Source) -> Result<(), crate::KEYGEN_ERROR> {
+	let mut guard = KEK.lock().map_err(crate::implementation::err_fatal)?;
+	*guard = Some(Kek { source, key: None });
+	Ok(())
+}
+
+/// Reads the key file at the given path, decrypting it if it is encrypted.
+///
+/// If the file is plaintext but would have been encrypted had it been written now, it is encrypted in place before returning.
+pub(crate) fn read(path: &std::path::Path) -> Result<Option<Vec<u8>>, crate::KEYGEN_ERROR> {
+	with_context(|context| context.read(path))
+}
+
+/// Writes the key file at the given path, encrypting it if a KEK is configured and the file is in the homedir.
+pub(crate) fn write(path: &std::path::Path, bytes: &[u8]) -> Result<(), crate::KEYGEN_ERROR> {
+	with_context(|context| context.write(path, bytes))
+}
+
+/// Calls `f` with the context made of the library's parameters.
+fn with_context<T>(f: impl FnOnce(&Context<'_>) -> Result<T, crate::KEYGEN_ERROR>) -> Result<T, crate::KEYGEN_ERROR> {
+	let homedir_path = crate::implementation::homedir_path()?;
+	let preloaded_paths = crate::implementation::preloaded_paths()?;
+
+	f(&Context {
+		homedir_path: homedir_path.as_deref(),
+		preloaded_paths: &preloaded_paths,
+		kek: &*KEK,
+	})
+}
+
+/// The parameters that decide whether and how key files are encrypted.
+struct Context<'a> {
+	/// The homedir. Only the files directly in it are encrypted.
+	homedir_path: Option<&'a std::path::Path>,
+
+	/// The files of preloaded keys, which are never encrypted.
+	preloaded_paths: &'a [std::path::PathBuf],
+
+	/// The KEK, if one is configured.
+	kek: &'a std::sync::Mutex<Option<Kek>>,
+}
+
+impl Context<'_> {
+	fn read(&self, path: &std::path::Path) -> Result<Option<Vec<u8>>, crate::KEYGEN_ERROR> {
+		let contents = match std::fs::read(path) {
+			Ok(contents) => contents,
+			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+			Err(err) => return Err(crate::implementation::err_external(err)),
+		};
+
+		if contents.starts_with(MAGIC) {
+			let kek = self.key()?.ok_or_else(|| crate::implementation::err_external("key file is encrypted but no key encryption key is configured"))?;
+			let bytes = unwrap(&kek, &aad(path), &contents[MAGIC.len()..])?;
+			Ok(Some(bytes))
+		}
+		else {
+			if self.is_managed(path) {
+				if let Some(kek) = self.key()? {
+					let wrapped = wrap(&kek, &aad(path), &contents)?;
+					fs_common::write_file(path, &wrapped).map_err(crate::implementation::err_external)?;
+				}
+			}
+
+			Ok(Some(contents))
+		}
+	}
+
+	fn write(&self, path: &std::path::Path, bytes: &[u8]) -> Result<(), crate::KEYGEN_ERROR> {
+		let kek = if self.is_managed(path) { self.key()? } else { None };
+
+		match kek {
+			Some(kek) => {
+				let wrapped = wrap(&kek, &aad(path), bytes)?;
+				fs_common::write_file(path, &wrapped).map_err(crate::implementation::err_external)
+			},
+
+			None => fs_common::write_file(path, bytes).map_err(crate::implementation::err_external),
+		}
+	}
+
+	/// Whether the file at the given path is one that this library created in the homedir, as opposed to a preloaded key.
+	fn is_managed(&self, path: &std::path::Path) -> bool {
+		self.homedir_path.is_some() &&
+			path.parent() == self.homedir_path &&
+			!self.preloaded_paths.iter().any(|preloaded_path| preloaded_path == path)
+	}
+
+	fn key(&self) -> Result<Option<Vec<u8>>, crate::KEYGEN_ERROR> {
+		let mut guard = self.kek.lock().map_err(crate::implementation::err_fatal)?;
+		let kek = match &mut *guard {
+			Some(kek) => kek,
+			None => return Ok(None),
+		};
+
+		if let Some(key) = &kek.key {
+			return Ok(Some(key.clone()));
+		}
+
+		let key = match &kek.source {
+			Source::File(path) => std::fs::read(path).map_err(crate::implementation::err_external)?,
+
+			Source::Credential(name) => {
+				let credentials_directory =
+					std::env::var_os("CREDENTIALS_DIRECTORY")
+					.ok_or_else(|| crate::implementation::err_external("key encryption key is a credential but CREDENTIALS_DIRECTORY is not set"))?;
+				let path = std::path::Path::new(&credentials_directory).join(name);
+				std::fs::read(path).map_err(crate::implementation::err_external)?
+			},
+
+			Source::Passphrase(passphrase) => {
+				let salt = self.passphrase_salt()?;
+
+				let mut key = vec![0_u8; KEY_LEN];
+				openssl::pkcs5::pbkdf2_hmac(
+					passphrase.as_bytes(),
+					&salt,
+					PASSPHRASE_ITERATIONS,
+					openssl::hash::MessageDigest::sha256(),
+					&mut key,
+				)?;
+				key
+			},
+		};
+
+		if key.len() != KEY_LEN {
+			return Err(crate::implementation::err_external(format!("key encryption key must be {} bytes but is {} bytes", KEY_LEN, key.len())));
+		}
+
+		kek.key = Some(key.clone());
+
+		Ok(Some(key))
+	}
+
+	fn passphrase_salt(&self) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+		let homedir_path =
+			self.homedir_path
+			.ok_or_else(|| crate::implementation::err_external("key encryption key is a passphrase but the homedir is not set"))?;
+		let path = homedir_path.join(PASSPHRASE_SALT_FILE_NAME);
+
+		match std::fs::read(&path) {
+			Ok(salt) => Ok(salt),
+
+			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => {
+				let mut salt = vec![0_u8; PASSPHRASE_SALT_LEN];
+				openssl::rand::rand_bytes(&mut salt)?;
+				fs_common::write_file(&path, &salt).map_err(crate::implementation::err_external)?;
+				Ok(salt)
+			},
+
+			Err(err) => Err(crate::implementation::err_external(err)),
+		}
+	}
+}
+
+/// The AAD of the encrypted file at the given path.
+fn aad(path: &std::path::Path) -> Vec<u8> {
+	let mut aad = MAGIC.to_owned();
+	if let Some(file_name) = path.file_name() {
+		aad.extend_from_slice(std::os::unix::ffi::OsStrExt::as_bytes(file_name));
+	}
+	aad
+}
+
+fn wrap(kek: &[u8], aad: &[u8], bytes: &[u8]) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+	let cipher = openssl::symm::Cipher::aes_256_gcm();
+
+	let mut nonce = [0_u8; NONCE_LEN];
+	openssl::rand::rand_bytes(&mut nonce)?;
+
+	let mut tag = [0_u8; TAG_LEN];
+
+	let ciphertext = openssl::symm::encrypt_aead(cipher, kek, Some(&nonce), aad, bytes, &mut tag)?;
+
+	let mut result = MAGIC.to_owned();
+	result.extend_from_slice(&nonce);
+	result.extend_from_slice(&tag);
+	result.extend_from_slice(&ciphertext);
+	Ok(result)
+}
+
+fn unwrap(kek: &[u8], aad: &[u8], wrapped: &[u8]) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+	if wrapped.len() < NONCE_LEN + TAG_LEN {
+		return Err(crate::implementation::err_external("encrypted key file is truncated"));
+	}
+
+	let (nonce, rest) = wrapped.split_at(NONCE_LEN);
+	let (tag, ciphertext) = rest.split_at(TAG_LEN);
+
+	let cipher = openssl::symm::Cipher::aes_256_gcm();
+
+	let bytes =
+		openssl::symm::decrypt_aead(cipher, kek, Some(nonce), aad, ciphertext, tag)
+		.map_err(|_| crate::implementation::err_external("could not decrypt key file with the key encryption key"))?;
+	Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn wrap_unwrap() {
+		let kek = [0x01_u8; super::KEY_LEN];
+
+		let wrapped = super::wrap(&kek, b"aad", b"key").unwrap();
+		assert!(wrapped.starts_with(super::MAGIC));
+		assert_eq!(wrapped.len(), super::MAGIC.len() + super::NONCE_LEN + super::TAG_LEN + b"key".len());
+
+		let wrapped = &wrapped[super::MAGIC.len()..];
+		assert_eq!(super::unwrap(&kek, b"aad", wrapped).unwrap(), b"key");
+
+		assert!(super::unwrap(&kek, b"other aad", wrapped).is_err());
+		assert!(super::unwrap(&[0x02_u8; super::KEY_LEN], b"aad", wrapped).is_err());
+		assert!(super::unwrap(&kek, b"aad", &wrapped[..(super::NONCE_LEN + super::TAG_LEN - 1)]).is_err());
+	}
+
+	#[test]
+	fn source_from_str() {
+		match "file:///var/secrets/kek".parse().unwrap() {
+			super::Source::File(path) => assert_eq!(path, std::path::Path::new("/var/secrets/kek")),
+			source => panic!("unexpected source {:?}", source),
+		}
+
+		match "credential:aziot-keyd-kek".parse().unwrap() {
+			super::Source::Credential(name) => assert_eq!(name, "aziot-keyd-kek"),
+			source => panic!("unexpected source {:?}", source),
+		}
+
+		match "passphrase:hunter2".parse().unwrap() {
+			super::Source::Passphrase(passphrase) => assert_eq!(passphrase, "hunter2"),
+			source => panic!("unexpected source {:?}", source),
+		}
+
+		for s in &["", "kek", "credential:", "credential:../kek", "passphrase:", "foo:bar"] {
+			assert!(s.parse::<super::Source>().is_err(), "{:?} should not have parsed", s);
+		}
+	}
+
+	#[test]
+	fn migrate_plaintext_files() {
+		let dir = tempfile::tempdir().unwrap();
+		let homedir = dir.path().join("homedir");
+		std::fs::create_dir(&homedir).unwrap();
+
+		let kek_path = dir.path().join("kek");
+		std::fs::write(&kek_path, [0x03_u8; super::KEY_LEN]).unwrap();
+
+		let preloaded_path = homedir.join("preloaded.key");
+		std::fs::write(&preloaded_path, b"preloaded").unwrap();
+
+		let kek = std::sync::Mutex::new(Some(super::Kek { source: super::Source::File(kek_path), key: None }));
+		let context = super::Context {
+			homedir_path: Some(&homedir),
+			preloaded_paths: &[preloaded_path.clone()],
+			kek: &kek,
+		};
+
+		// A plaintext file in the homedir is encrypted in place the first time it is read.
+		let path = homedir.join("managed.key");
+		std::fs::write(&path, b"managed").unwrap();
+		assert_eq!(context.read(&path).unwrap().unwrap(), b"managed");
+		assert!(std::fs::read(&path).unwrap().starts_with(super::MAGIC));
+		assert_eq!(context.read(&path).unwrap().unwrap(), b"managed");
+
+		// An encrypted file cannot be passed off as the file of another key.
+		let other_path = homedir.join("other.key");
+		std::fs::copy(&path, &other_path).unwrap();
+		assert!(context.read(&other_path).is_err());
+
+		// A preloaded key is never rewritten, even if it is in the homedir.
+		assert_eq!(context.read(&preloaded_path).unwrap().unwrap(), b"preloaded");
+		assert_eq!(std::fs::read(&preloaded_path).unwrap(), b"preloaded");
+
+		// Files outside the homedir are not encrypted.
+		let outside_path = dir.path().join("outside.key");
+		context.write(&outside_path, b"outside").unwrap();
+		assert_eq!(std::fs::read(&outside_path).unwrap(), b"outside");
+	}
+}
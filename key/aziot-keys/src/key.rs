@@ -14,18 +14,18 @@ pub(crate) unsafe extern "C" fn create_key_if_not_exists(
 
 		let location = crate::implementation::Location::of(id)?;
 
-		if load_inner(&location)?.is_none() {
-			let mut bytes = vec![0_u8; length];
-			openssl::rand::rand_bytes(&mut bytes)?;
+		if !exists(&location)? {
+			// Retired versions left behind by an interrupted delete must not be mistaken for older versions of the new key.
+			delete_retired_versions(&location)?;
 
-			let location = crate::implementation::Location::of(id)?;
-
-			create_inner(&location, &bytes)?;
-			if load_inner(&location)?.is_none() {
+			generate_inner(&location, length)?;
+			if !exists(&location)? {
 				return Err(crate::implementation::err_external("key created successfully but could not be found"));
 			}
 		}
 
+		crate::index::add(&location, id, crate::index::Kind::Key)?;
+
 		Ok(())
 	})
 }
@@ -53,11 +53,148 @@ pub(crate) unsafe extern "C" fn import_key(
 
 		let location = crate::implementation::Location::of(id)?;
 
-		create_inner(&location, bytes)?;
-		if load_inner(&location)?.is_none() {
+		match &location {
+			crate::implementation::Location::Filesystem(_) => match load_inner(&location)? {
+				// Importing different bytes over an existing key rotates it, so that ciphertexts created by the existing key can still be decrypted.
+				Some(existing_bytes) => {
+					if existing_bytes != bytes {
+						if crate::implementation::is_preloaded(id)? {
+							return Err(crate::implementation::err_invalid_parameter("id", "cannot overwrite preloaded key"));
+						}
+
+						rotate_inner(&location, bytes)?;
+					}
+				},
+
+				None => {
+					delete_retired_versions(&location)?;
+
+					create_inner(&location, bytes)?;
+				},
+			},
+
+			// Keys in a PKCS#11 token don't have versions, so importing different bytes over an existing key can't rotate it
+			// like it does for the filesystem. Replacing it instead would make every ciphertext created by it undecryptable.
+			crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+				match load_pkcs11::<pkcs11::HmacKey>(lib_path, uri)? {
+					Some(existing_key) => {
+						if !pkcs11_key_has_value(&existing_key, bytes)? {
+							return Err(crate::implementation::err_invalid_parameter("id", "cannot overwrite key in PKCS#11 token"));
+						}
+					},
+
+					None => create_inner(&location, bytes)?,
+				}
+			},
+		}
+
+		if !exists(&location)? {
 			return Err(crate::implementation::err_external("key created successfully but could not be found"));
 		}
 
+		crate::index::add(&location, id, crate::index::Kind::Key)?;
+
+		Ok(())
+	})
+}
+
+pub(crate) unsafe extern "C" fn delete_key(
+	id: *const std::os::raw::c_char,
+) -> crate::KEYGEN_ERROR {
+	crate::r#catch(|| {
+		let id = {
+			if id.is_null() {
+				return Err(crate::implementation::err_invalid_parameter("id", "expected non-NULL"));
+			}
+			let id = std::ffi::CStr::from_ptr(id);
+			let id = id.to_str().map_err(|err| crate::implementation::err_invalid_parameter("id", err))?;
+			id
+		};
+
+		let location = crate::implementation::Location::of_deletable(id)?;
+
+		delete_inner(&location)?;
+
+		crate::index::remove(&location, id)?;
+
+		Ok(())
+	})
+}
+
+pub(crate) unsafe extern "C" fn list_keys(
+	list: *mut std::os::raw::c_char,
+	list_len: *mut usize,
+) -> crate::KEYGEN_ERROR {
+	crate::r#catch(|| {
+		let mut entries = vec![];
+
+		for id in crate::implementation::preloaded_key_ids()? {
+			let location = crate::implementation::Location::of(&id)?;
+
+			match &location {
+				// Preloaded keys are not marked as symmetric keys or key pairs, so assume that any file that is not a PEM private key is a symmetric key.
+				crate::implementation::Location::Filesystem(_) => if let Some(key) = load_inner(&location)? {
+					if openssl::pkey::PKey::private_key_from_pem(&key).is_err() {
+						entries.push(crate::implementation::ListEntry {
+							id,
+							algorithm: algorithm(Some(key.len())),
+							backend: "preloaded",
+						});
+					}
+				},
+
+				crate::implementation::Location::Pkcs11 { lib_path, uri } => if let Some(key) = list_pkcs11(lib_path, uri)?.into_iter().next() {
+					entries.push(crate::implementation::ListEntry {
+						id,
+						algorithm: algorithm(key.value_len.map(|value_len| std::convert::TryInto::try_into(value_len).expect("CK_ULONG -> usize"))),
+						backend: "preloaded",
+					});
+				},
+			}
+		}
+
+		for (id, kind) in crate::index::load()? {
+			if kind != crate::index::Kind::Key {
+				continue;
+			}
+
+			// The index only describes the homedir, so skip its entries if keys are now created elsewhere.
+			let location = crate::implementation::Location::of(&id)?;
+			if let crate::implementation::Location::Pkcs11 { .. } = location {
+				continue;
+			}
+
+			if let Some(key) = load_inner(&location)? {
+				entries.push(crate::implementation::ListEntry {
+					id,
+					algorithm: algorithm(Some(key.len())),
+					backend: "filesystem",
+				});
+			}
+		}
+
+		if let Some((lib_path, base_slot)) = crate::implementation::pkcs11_base_slot()? {
+			for key in list_pkcs11(&lib_path, &base_slot)? {
+				let label = match key.label {
+					Some(label) => label,
+					None => continue,
+				};
+
+				// Preloaded keys in the same slot have already been listed.
+				if crate::implementation::is_preloaded(&label)? {
+					continue;
+				}
+
+				entries.push(crate::implementation::ListEntry {
+					id: label,
+					algorithm: algorithm(key.value_len.map(|value_len| std::convert::TryInto::try_into(value_len).expect("CK_ULONG -> usize"))),
+					backend: "pkcs11",
+				});
+			}
+		}
+
+		crate::implementation::write_list(&entries, list, list_len)?;
+
 		Ok(())
 	})
 }
@@ -68,6 +205,16 @@ pub(crate) unsafe fn sign(
 ) -> Result<(usize, Vec<u8>), crate::KEYGEN_ERROR> {
 	use hmac::{Mac, NewMac};
 
+	if let crate::implementation::Location::Pkcs11 { lib_path, uri } = location {
+		let key = match load_pkcs11::<pkcs11::HmacKey>(lib_path, uri)? {
+			Some(key) => key,
+			None => return Err(crate::implementation::err_invalid_parameter("id", "key not found")),
+		};
+
+		let signature = pkcs11_sign(&key, digest)?;
+		return Ok((signature.len(), signature));
+	}
+
 	let key = match load_inner(location)? {
 		Some(key) => key,
 		None => return Err(crate::implementation::err_invalid_parameter("id", "key not found")),
@@ -89,6 +236,18 @@ pub(crate) unsafe fn verify(
 ) -> Result<bool, crate::KEYGEN_ERROR> {
 	use hmac::{Mac, NewMac};
 
+	if let crate::implementation::Location::Pkcs11 { lib_path, uri } = location {
+		let key = match load_pkcs11::<pkcs11::HmacKey>(lib_path, uri)? {
+			Some(key) => key,
+			None => return Err(crate::implementation::err_invalid_parameter("id", "key not found")),
+		};
+
+		// The token can only compute the expected signature, so compare it in constant time here.
+		let expected_signature = pkcs11_sign(&key, digest)?;
+		let ok = expected_signature.len() == signature.len() && openssl::memcmp::eq(&expected_signature, signature);
+		return Ok(ok);
+	}
+
 	let key = match load_inner(location)? {
 		Some(key) => key,
 		None => return Err(crate::implementation::err_invalid_parameter("id", "key not found")),
@@ -104,11 +263,130 @@ pub(crate) unsafe fn verify(
 	Ok(ok)
 }
 
+pub(crate) unsafe extern "C" fn rotate_key(
+	id: *const std::os::raw::c_char,
+) -> crate::KEYGEN_ERROR {
+	crate::r#catch(|| {
+		let id = {
+			if id.is_null() {
+				return Err(crate::implementation::err_invalid_parameter("id", "expected non-NULL"));
+			}
+			let id = std::ffi::CStr::from_ptr(id);
+			let id = id.to_str().map_err(|err| crate::implementation::err_invalid_parameter("id", err))?;
+			id
+		};
+
+		if crate::implementation::is_preloaded(id)? {
+			return Err(crate::implementation::err_invalid_parameter("id", "cannot rotate preloaded key"));
+		}
+
+		let location = crate::implementation::Location::of(id)?;
+
+		// Rotating keeps the old version of the key around to decrypt existing ciphertexts, but keys in a PKCS#11 token don't have versions.
+		if let crate::implementation::Location::Pkcs11 { .. } = location {
+			return Err(crate::implementation::err_invalid_parameter("id", "cannot rotate key in PKCS#11 token"));
+		}
+
+		let key = match load_inner(&location)? {
+			Some(key) => key,
+			None => return Err(crate::implementation::err_invalid_parameter("id", "key not found")),
+		};
+
+		let mut bytes = vec![0_u8; key.len()];
+		openssl::rand::rand_bytes(&mut bytes)?;
+
+		rotate_inner(&location, &bytes)?;
+
+		Ok(())
+	})
+}
+
+pub(crate) unsafe extern "C" fn rewrap(
+	id: *const std::os::raw::c_char,
+	mechanism: crate::KEYGEN_ENCRYPT_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	ciphertext: *const std::os::raw::c_uchar,
+	ciphertext_len: usize,
+	new_ciphertext: *mut std::os::raw::c_uchar,
+	new_ciphertext_len: *mut usize,
+) -> crate::KEYGEN_ERROR {
+	crate::r#catch(|| {
+		let id = {
+			if id.is_null() {
+				return Err(crate::implementation::err_invalid_parameter("id", "expected non-NULL"));
+			}
+			let id = std::ffi::CStr::from_ptr(id);
+			let id = id.to_str().map_err(|err| crate::implementation::err_invalid_parameter("id", err))?;
+			id
+		};
+
+		let ciphertext =
+			if ciphertext.is_null() {
+				return Err(crate::implementation::err_invalid_parameter("ciphertext", "expected non-NULL"));
+			}
+			else {
+				std::slice::from_raw_parts(ciphertext, ciphertext_len)
+			};
+
+		let mut new_ciphertext_len_out =
+			std::ptr::NonNull::new(new_ciphertext_len)
+			.ok_or_else(|| crate::implementation::err_invalid_parameter("new_ciphertext_len", "expected non-NULL"))?;
+
+		let location = crate::implementation::Location::of(id)?;
+
+		let (expected_new_ciphertext_len, expected_new_ciphertext) = rewrap_inner(&location, mechanism, parameters, ciphertext)?;
+
+		let actual_new_ciphertext_len = *new_ciphertext_len_out.as_ref();
+
+		*new_ciphertext_len_out.as_mut() = expected_new_ciphertext_len;
+
+		if !new_ciphertext.is_null() {
+			if actual_new_ciphertext_len < expected_new_ciphertext_len {
+				return Err(crate::implementation::err_invalid_parameter("new_ciphertext", "insufficient size"));
+			}
+
+			let new_ciphertext_out = std::slice::from_raw_parts_mut(new_ciphertext, actual_new_ciphertext_len);
+			new_ciphertext_out[..expected_new_ciphertext_len].copy_from_slice(&expected_new_ciphertext);
+		}
+
+		Ok(())
+	})
+}
+
+unsafe fn rewrap_inner(
+	location: &crate::implementation::Location,
+	mechanism: crate::KEYGEN_ENCRYPT_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	ciphertext: &[u8],
+) -> Result<(usize, Vec<u8>), crate::KEYGEN_ERROR> {
+	let (_, plaintext) = decrypt(location, mechanism, parameters, ciphertext)?;
+	encrypt(location, mechanism, parameters, &plaintext)
+}
+
 // Ciphertext is formatted as:
 //
-// - Encryption scheme version (1 byte); v1 == 0x01_u8
+// - Encryption scheme version (1 byte); v1 == 0x01_u8, v2 == 0x02_u8, v3 == 0x03_u8, v4 == 0x04_u8
+// - v3 and v4 only: Version of the key that created the ciphertext (4 bytes, big-endian)
+// - v2 and v4 only: Nonce (12 bytes), generated by this library for every encrypt operation
 // - Tag (16 bytes) (16 bytes is the tag size for AES-256-GCM)
 // - Actual ciphertext
+//
+// v1 and v3 are produced by KEYGEN_ENCRYPT_MECHANISM_AEAD, where the caller supplies the IV.
+// v2 and v4 are produced by KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE.
+//
+// v1 and v2 predate key rotation, so they were always created by version 0 of the key. Ciphertexts created by version 0 are still v1 or v2,
+// so that they can be decrypted by implementations that do not know about key versions. Only ciphertexts created by a rotated key are v3 or v4.
+
+const CIPHERTEXT_VERSION_CALLER_IV: u8 = 0x01;
+const CIPHERTEXT_VERSION_GENERATED_NONCE: u8 = 0x02;
+const CIPHERTEXT_VERSION_KEY_VERSION_CALLER_IV: u8 = 0x03;
+const CIPHERTEXT_VERSION_KEY_VERSION_GENERATED_NONCE: u8 = 0x04;
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+const AES_256_KEY_LEN: usize = 32;
+const HMAC_SHA256_LEN: usize = 32;
 
 pub(crate) unsafe fn encrypt(
 	location: &crate::implementation::Location,
@@ -116,36 +394,59 @@ pub(crate) unsafe fn encrypt(
 	parameters: *const std::ffi::c_void,
 	plaintext: &[u8],
 ) -> Result<(usize, Vec<u8>), crate::KEYGEN_ERROR> {
-	let key = match load_inner(location)? {
+	let (key_version, key) = match load_aead_key(location, None)? {
 		Some(key) => key,
 		None => return Err(crate::implementation::err_invalid_parameter("id", "key not found")),
 	};
 
-	if mechanism != crate::KEYGEN_ENCRYPT_MECHANISM_AEAD {
-		return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value"));
-	}
+	let mut tag = vec![0_u8; TAG_LEN];
 
-	let (iv, aad) = {
-		if parameters.is_null() {
-			return Err(crate::implementation::err_invalid_parameter("parameters", "expected non-NULL"));
-		}
+	let result = match mechanism {
+		crate::KEYGEN_ENCRYPT_MECHANISM_AEAD => {
+			let (iv, aad) = aead_parameters(parameters)?;
 
-		let parameters = parameters as *const crate::KEYGEN_ENCRYPT_AEAD_PARAMETERS;
-		let parameters = &*parameters;
+			let ciphertext = key.encrypt(iv, aad, plaintext, &mut tag)?;
 
-		let iv = std::slice::from_raw_parts(parameters.iv, parameters.iv_len);
-		let aad = std::slice::from_raw_parts(parameters.aad, parameters.aad_len);
-		(iv, aad)
-	};
+			let mut result =
+				if key_version == 0 {
+					vec![CIPHERTEXT_VERSION_CALLER_IV]
+				}
+				else {
+					let mut result = vec![CIPHERTEXT_VERSION_KEY_VERSION_CALLER_IV];
+					result.extend_from_slice(&key_version.to_be_bytes());
+					result
+				};
+			result.extend_from_slice(&tag);
+			result.extend_from_slice(&ciphertext);
+			result
+		},
+
+		crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE => {
+			let aad = aead_generated_nonce_parameters(parameters)?;
 
-	let cipher = openssl::symm::Cipher::aes_256_gcm();
+			let mut nonce = [0_u8; NONCE_LEN];
+			openssl::rand::rand_bytes(&mut nonce)?;
 
-	let mut tag = vec![0_u8; 16];
-	let ciphertext = openssl::symm::encrypt_aead(cipher, &key, Some(iv), aad, plaintext, &mut tag[..])?;
+			let ciphertext = key.encrypt(&nonce, aad, plaintext, &mut tag)?;
+
+			let mut result =
+				if key_version == 0 {
+					vec![CIPHERTEXT_VERSION_GENERATED_NONCE]
+				}
+				else {
+					let mut result = vec![CIPHERTEXT_VERSION_KEY_VERSION_GENERATED_NONCE];
+					result.extend_from_slice(&key_version.to_be_bytes());
+					result
+				};
+			result.extend_from_slice(&nonce);
+			result.extend_from_slice(&tag);
+			result.extend_from_slice(&ciphertext);
+			result
+		},
+
+		_ => return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value")),
+	};
 
-	let mut result = vec![0x01_u8];
-	result.extend_from_slice(&tag);
-	result.extend_from_slice(&ciphertext);
 	Ok((result.len(), result))
 }
 
@@ -155,66 +456,841 @@ pub(crate) unsafe fn decrypt(
 	parameters: *const std::ffi::c_void,
 	ciphertext: &[u8],
 ) -> Result<(usize, Vec<u8>), crate::KEYGEN_ERROR> {
-	let key = match load_inner(location)? {
+	let (iv, aad) = match mechanism {
+		crate::KEYGEN_ENCRYPT_MECHANISM_AEAD => {
+			let (iv, aad) = aead_parameters(parameters)?;
+			(Some(iv), aad)
+		},
+
+		crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE => {
+			let aad = aead_generated_nonce_parameters(parameters)?;
+			(None, aad)
+		},
+
+		_ => return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value")),
+	};
+
+	let Ciphertext { key_version, nonce, tag, ciphertext } = Ciphertext::parse(ciphertext)?;
+
+	let iv = match (iv, nonce) {
+		(Some(iv), None) => iv,
+		(None, Some(nonce)) => nonce,
+		_ => return Err(crate::implementation::err_invalid_parameter("ciphertext", "not created with this mechanism")),
+	};
+
+	let (_, key) = match load_aead_key(location, Some(key_version))? {
 		Some(key) => key,
-		None => return Err(crate::implementation::err_invalid_parameter("id", "key not found")),
+		None => return Err(crate::implementation::err_invalid_parameter("ciphertext", "key version not found")),
 	};
 
-	if mechanism != crate::KEYGEN_ENCRYPT_MECHANISM_AEAD {
-		return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value"));
+	let plaintext = key.decrypt(iv, aad, ciphertext, tag)?;
+
+	Ok((plaintext.len(), plaintext))
+}
+
+/// The key that encrypts and decrypts ciphertexts.
+enum AeadKey {
+	Bytes(Vec<u8>),
+	Pkcs11(pkcs11::Object<pkcs11::AesKey>),
+}
+
+impl AeadKey {
+	fn encrypt(&self, iv: &[u8], aad: &[u8], plaintext: &[u8], tag: &mut [u8]) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+		match self {
+			AeadKey::Bytes(key) => {
+				let cipher = openssl::symm::Cipher::aes_256_gcm();
+				let ciphertext = openssl::symm::encrypt_aead(cipher, key, Some(iv), aad, plaintext, tag)?;
+				Ok(ciphertext)
+			},
+
+			AeadKey::Pkcs11(key) => {
+				// CKM_AES_GCM appends the tag to the ciphertext
+				let mut ciphertext = vec![0_u8; plaintext.len() + pkcs11::AES_GCM_TAG_LEN];
+				let ciphertext_len = key.encrypt(iv, aad, plaintext, &mut ciphertext).map_err(crate::implementation::err_external)?;
+				let ciphertext_len = std::convert::TryInto::try_into(ciphertext_len).expect("CK_ULONG -> usize");
+				ciphertext.truncate(ciphertext_len);
+
+				split_tag(ciphertext, tag)
+			},
+		}
 	}
 
-	let (iv, aad) = {
-		if parameters.is_null() {
-			return Err(crate::implementation::err_invalid_parameter("parameters", "expected non-NULL"));
+	fn decrypt(&self, iv: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+		match self {
+			AeadKey::Bytes(key) => {
+				let cipher = openssl::symm::Cipher::aes_256_gcm();
+				// With a well-formed key and IV, decryption only fails if the tag does not match.
+				let plaintext =
+					openssl::symm::decrypt_aead(cipher, key, Some(iv), aad, ciphertext, tag)
+					.map_err(|err| crate::implementation::err_invalid_parameter("ciphertext", err))?;
+				Ok(plaintext)
+			},
+
+			AeadKey::Pkcs11(key) => {
+				let ciphertext_with_tag = join_tag(ciphertext, tag);
+
+				// Some tokens check the output buffer against the length of the whole input, tag included.
+				let mut plaintext = vec![0_u8; ciphertext_with_tag.len()];
+				let plaintext_len = key.decrypt(iv, aad, &ciphertext_with_tag, &mut plaintext).map_err(|err| match err {
+					pkcs11::DecryptError::DecryptFailed(pkcs11_sys::CKR_ENCRYPTED_DATA_INVALID) =>
+						crate::implementation::err_invalid_parameter("ciphertext", err),
+					pkcs11::DecryptError::LoginFailed(_) |
+					pkcs11::DecryptError::SessionRecoveryFailed(_) =>
+						crate::implementation::err_backend_unavailable(err),
+					_ => crate::implementation::err_external(err),
+				})?;
+				let plaintext_len = std::convert::TryInto::try_into(plaintext_len).expect("CK_ULONG -> usize");
+				plaintext.truncate(plaintext_len);
+				Ok(plaintext)
+			},
 		}
+	}
+}
 
-		let parameters = parameters as *const crate::KEYGEN_ENCRYPT_AEAD_PARAMETERS;
-		let parameters = &*parameters;
+/// Splits the output of CKM_AES_GCM, which is the ciphertext followed by the tag, into the ciphertext and `tag`.
+fn split_tag(mut ciphertext_with_tag: Vec<u8>, tag: &mut [u8]) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+	let ciphertext_len =
+		ciphertext_with_tag.len().checked_sub(tag.len())
+		.ok_or_else(|| crate::implementation::err_external("ciphertext returned by PKCS#11 token is too short"))?;
+	tag.copy_from_slice(&ciphertext_with_tag[ciphertext_len..]);
+	ciphertext_with_tag.truncate(ciphertext_len);
+	Ok(ciphertext_with_tag)
+}
 
-		let iv = std::slice::from_raw_parts(parameters.iv, parameters.iv_len);
-		let aad = std::slice::from_raw_parts(parameters.aad, parameters.aad_len);
-		(iv, aad)
-	};
+/// The inverse of [`split_tag`], to build the input of CKM_AES_GCM decryption.
+fn join_tag(ciphertext: &[u8], tag: &[u8]) -> Vec<u8> {
+	let mut ciphertext_with_tag = ciphertext.to_owned();
+	ciphertext_with_tag.extend_from_slice(tag);
+	ciphertext_with_tag
+}
+
+struct Ciphertext<'a> {
+	key_version: u32,
+	nonce: Option<&'a [u8]>,
+	tag: &'a [u8],
+	ciphertext: &'a [u8],
+}
+
+impl<'a> Ciphertext<'a> {
+	fn parse(ciphertext: &'a [u8]) -> Result<Self, crate::KEYGEN_ERROR> {
+		fn take(ciphertext: &mut &[u8], len: usize) -> Result<(), crate::KEYGEN_ERROR> {
+			if ciphertext.len() < len {
+				return Err(crate::implementation::err_invalid_parameter("ciphertext", "too short"));
+			}
+
+			*ciphertext = &ciphertext[len..];
+			Ok(())
+		}
+
+		let mut rest = ciphertext;
+
+		take(&mut rest, 1)?;
+		let (has_key_version, has_nonce) = match ciphertext[0] {
+			CIPHERTEXT_VERSION_CALLER_IV => (false, false),
+			CIPHERTEXT_VERSION_GENERATED_NONCE => (false, true),
+			CIPHERTEXT_VERSION_KEY_VERSION_CALLER_IV => (true, false),
+			CIPHERTEXT_VERSION_KEY_VERSION_GENERATED_NONCE => (true, true),
+			_ => return Err(crate::implementation::err_invalid_parameter("ciphertext", "unrecognized version")),
+		};
+
+		let key_version =
+			if has_key_version {
+				let key_version = rest;
+				take(&mut rest, 4)?;
+				u32::from_be_bytes([key_version[0], key_version[1], key_version[2], key_version[3]])
+			}
+			else {
+				0
+			};
+
+		let nonce =
+			if has_nonce {
+				let nonce = rest;
+				take(&mut rest, NONCE_LEN)?;
+				Some(&nonce[..NONCE_LEN])
+			}
+			else {
+				None
+			};
+
+		let tag = rest;
+		take(&mut rest, TAG_LEN)?;
+		let tag = &tag[..TAG_LEN];
 
-	let cipher = openssl::symm::Cipher::aes_256_gcm();
+		Ok(Ciphertext {
+			key_version,
+			nonce,
+			tag,
+			ciphertext: rest,
+		})
+	}
+}
 
-	if ciphertext.len() < 1 + 16 {
-		return Err(crate::implementation::err_invalid_parameter("ciphertext", "expected non-NULL"));
+unsafe fn aead_parameters<'a>(parameters: *const std::ffi::c_void) -> Result<(&'a [u8], &'a [u8]), crate::KEYGEN_ERROR> {
+	if parameters.is_null() {
+		return Err(crate::implementation::err_invalid_parameter("parameters", "expected non-NULL"));
 	}
-	if ciphertext[0] != 0x01 {
-		return Err(crate::implementation::err_invalid_parameter("ciphertext", "expected non-NULL"));
+
+	let parameters = parameters as *const crate::KEYGEN_ENCRYPT_AEAD_PARAMETERS;
+	let parameters = &*parameters;
+
+	let iv = std::slice::from_raw_parts(parameters.iv, parameters.iv_len);
+	let aad = std::slice::from_raw_parts(parameters.aad, parameters.aad_len);
+	Ok((iv, aad))
+}
+
+unsafe fn aead_generated_nonce_parameters<'a>(parameters: *const std::ffi::c_void) -> Result<&'a [u8], crate::KEYGEN_ERROR> {
+	if parameters.is_null() {
+		return Err(crate::implementation::err_invalid_parameter("parameters", "expected non-NULL"));
 	}
 
-	let tag = &ciphertext[1..=16];
-	let ciphertext = &ciphertext[17..];
+	let parameters = parameters as *const crate::KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS;
+	let parameters = &*parameters;
 
-	let plaintext = openssl::symm::decrypt_aead(cipher, &key, Some(iv), aad, ciphertext, tag)?;
+	let aad = std::slice::from_raw_parts(parameters.aad, parameters.aad_len);
+	Ok(aad)
+}
 
-	Ok((plaintext.len(), plaintext))
+/// The algorithm of a symmetric key whose length in bytes is `len`, or is unknown if `len` is `None`.
+fn algorithm(len: Option<usize>) -> String {
+	match len {
+		Some(len) => format!("symmetric-{}", len * 8),
+		None => "symmetric".to_owned(),
+	}
+}
+
+// A key in a PKCS#11 token is stored as a CKK_GENERIC_SECRET object for HMAC, plus a CKK_AES object for AES-GCM if the key is 32 bytes long.
+// Both objects have the key's label. The CKK_AES object is created first, so that the key is only considered to exist once both objects do.
+//
+// A generated key gets two independent random values, since nothing needs the two objects to have the same value.
+
+fn exists(location: &crate::implementation::Location) -> Result<bool, crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(_) => Ok(load_inner(location)?.is_some()),
+
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => Ok(load_pkcs11::<pkcs11::HmacKey>(lib_path, uri)?.is_some()),
+	}
 }
 
 fn load_inner(location: &crate::implementation::Location) -> Result<Option<Vec<u8>>, crate::KEYGEN_ERROR> {
 	match location {
-		crate::implementation::Location::Filesystem(path) => match std::fs::read(path) {
-			Ok(key_bytes) => Ok(Some(key_bytes)),
-			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
-			Err(err) => Err(crate::implementation::err_external(err)),
-		},
+		crate::implementation::Location::Filesystem(path) => crate::kek::read(path),
 
 		crate::implementation::Location::Pkcs11 { .. } =>
-			Err(crate::implementation::err_external("PKCS#11 symmetric keys are not supported")),
+			Err(crate::implementation::err_external("the value of a key in a PKCS#11 token cannot be read")),
+	}
+}
+
+fn load_pkcs11<T>(lib_path: &std::path::Path, uri: &pkcs11::Uri) -> Result<Option<pkcs11::Object<T>>, crate::KEYGEN_ERROR> where T: pkcs11::SecretKey {
+	let pkcs11_session = pkcs11_session(lib_path, uri)?;
+
+	match pkcs11_session.get_secret_key(uri) {
+		Ok(key) => Ok(Some(key)),
+
+		Err(pkcs11::GetKeyError::KeyDoesNotExist) => Ok(None),
+
+		Err(err) => Err(crate::implementation::err_external(err)),
+	}
+}
+
+/// Lists the keys in the PKCS#11 token that match the given URI, by their HMAC objects.
+fn list_pkcs11(lib_path: &std::path::Path, uri: &pkcs11::Uri) -> Result<Vec<pkcs11::ObjectInfo>, crate::KEYGEN_ERROR> {
+	let pkcs11_session = pkcs11_session(lib_path, uri)?;
+
+	let template = pkcs11::ObjectTemplate {
+		class: Some(pkcs11_sys::CKO_SECRET_KEY),
+		key_type: Some(<pkcs11::HmacKey as pkcs11::SecretKey>::KEY_TYPE),
+		..pkcs11::ObjectTemplate::from_uri(uri)
+	};
+	let keys = pkcs11_session.list_objects(&template).map_err(crate::implementation::err_external)?;
+	Ok(keys)
+}
+
+fn generate_inner(location: &crate::implementation::Location, length: usize) -> Result<(), crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(path) => {
+			let mut bytes = vec![0_u8; length];
+			openssl::rand::rand_bytes(&mut bytes)?;
+
+			crate::kek::write(path, &bytes)
+		},
+
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+			let pkcs11_session = pkcs11_session(lib_path, uri)?;
+			let label = uri.object_label.as_deref();
+
+			if length == AES_256_KEY_LEN {
+				pkcs11_session.clone().generate_secret_key::<pkcs11::AesKey>(length, label).map_err(crate::implementation::err_external)?;
+			}
+			pkcs11_session.generate_secret_key::<pkcs11::HmacKey>(length, label).map_err(crate::implementation::err_external)?;
+
+			Ok(())
+		},
 	}
 }
 
 fn create_inner(location: &crate::implementation::Location, bytes: &[u8]) -> Result<(), crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(path) => crate::kek::write(path, bytes),
+
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+			let pkcs11_session = pkcs11_session(lib_path, uri)?;
+			let label = uri.object_label.as_deref();
+
+			if bytes.len() == AES_256_KEY_LEN {
+				pkcs11_session.clone().import_secret_key::<pkcs11::AesKey>(bytes, label).map_err(crate::implementation::err_external)?;
+			}
+			pkcs11_session.import_secret_key::<pkcs11::HmacKey>(bytes, label).map_err(crate::implementation::err_external)?;
+
+			Ok(())
+		},
+	}
+}
+
+fn delete_inner(location: &crate::implementation::Location) -> Result<(), crate::KEYGEN_ERROR> {
 	match location {
 		crate::implementation::Location::Filesystem(path) => {
-			std::fs::write(path, bytes).map_err(crate::implementation::err_external)?;
+			match std::fs::remove_file(path) {
+				Ok(()) => (),
+				Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => (),
+				Err(err) => return Err(crate::implementation::err_external(err)),
+			}
+
+			delete_retired_versions(location)?;
+
+			Ok(())
+		},
+
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+			let label = uri.object_label.as_ref().ok_or_else(|| crate::implementation::err_invalid_parameter("id", "PKCS#11 URI does not identify an object"))?;
+
+			let pkcs11_session = pkcs11_session(lib_path, uri)?;
+
+			pkcs11_session.delete_secret_key(label).map_err(crate::implementation::err_external)?;
+
+			Ok(())
+		},
+	}
+}
+
+fn pkcs11_session(lib_path: &std::path::Path, uri: &pkcs11::Uri) -> Result<std::sync::Arc<pkcs11::Session>, crate::KEYGEN_ERROR> {
+	let pkcs11_context = pkcs11::Context::load(lib_path.to_owned()).map_err(crate::implementation::err_backend_unavailable)?;
+	let pkcs11_slot = pkcs11_context.find_slot(uri).map_err(crate::implementation::err_backend_unavailable)?;
+	let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+	Ok(pkcs11_session)
+}
+
+fn pkcs11_sign(key: &pkcs11::Object<pkcs11::HmacKey>, message: &[u8]) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+	let mut signature = vec![0_u8; HMAC_SHA256_LEN];
+	let signature_len = key.sign(message, &mut signature).map_err(crate::implementation::err_external)?;
+	let signature_len = std::convert::TryInto::try_into(signature_len).expect("CK_ULONG -> usize");
+	signature.truncate(signature_len);
+	Ok(signature)
+}
+
+/// Checks whether the key in the PKCS#11 token has the given value.
+///
+/// The token won't reveal the key's value, so this compares the HMAC of a fixed message computed by the token
+/// against the HMAC computed with the given value.
+fn pkcs11_key_has_value(key: &pkcs11::Object<pkcs11::HmacKey>, bytes: &[u8]) -> Result<bool, crate::KEYGEN_ERROR> {
+	hmac_key_has_value(|message| pkcs11_sign(key, message), bytes)
+}
+
+/// The part of [`pkcs11_key_has_value`] that doesn't need the token. `sign` computes the HMAC-SHA256 of the given message with the key.
+fn hmac_key_has_value(sign: impl FnOnce(&[u8]) -> Result<Vec<u8>, crate::KEYGEN_ERROR>, bytes: &[u8]) -> Result<bool, crate::KEYGEN_ERROR> {
+	use hmac::{Mac, NewMac};
+
+	const MESSAGE: &[u8] = b"aziot-keys key comparison";
+
+	let actual = sign(MESSAGE)?;
+
+	let mut signer = hmac::Hmac::<sha2::Sha256>::new_varkey(bytes).map_err(crate::implementation::err_external)?;
+	signer.update(MESSAGE);
+	let ok = signer.verify(&actual).is_ok();
+	Ok(ok)
+}
+
+// Symmetric keys have versions, so that they can be rotated without losing the ability to decrypt ciphertexts created by older versions.
+//
+// The active version is stored at the key's path, like keys were stored before they had versions. Rotating a key first copies
+// the active version to `<path>.v<N>`, where N is the active version's number, and then writes the new version to the key's path.
+// So the retired versions are always `.v0` through `.v<N-1>`, and the number of the active version is the number of retired versions.
+//
+// If a rotation is interrupted after the copy, the active version's number goes up by one without its bytes changing.
+// This is harmless, because ciphertexts that were created with the old number find the same bytes in the retired copy.
+
+/// Loads the given version of the key for AES-GCM, or the active version if `version` is `None`.
+fn load_aead_key(location: &crate::implementation::Location, version: Option<u32>) -> Result<Option<(u32, AeadKey)>, crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(_) => {
+			let key = match version {
+				Some(version) => load_version(location, version)?.map(|key| (version, key)),
+				None => load_active_version(location)?,
+			};
+			Ok(key.map(|(version, key)| (version, AeadKey::Bytes(key))))
+		},
+
+		// Keys in a PKCS#11 token can't be rotated, so they only ever have version 0.
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+			if version.unwrap_or(0) != 0 {
+				return Ok(None);
+			}
+
+			let key = load_pkcs11::<pkcs11::AesKey>(lib_path, uri)?;
+			Ok(key.map(|key| (0, AeadKey::Pkcs11(key))))
+		},
+	}
+}
+
+fn load_active_version(location: &crate::implementation::Location) -> Result<Option<(u32, Vec<u8>)>, crate::KEYGEN_ERROR> {
+	let key = match load_inner(location)? {
+		Some(key) => key,
+		None => return Ok(None),
+	};
+
+	let version = active_version(location)?;
+
+	Ok(Some((version, key)))
+}
+
+fn load_version(location: &crate::implementation::Location, version: u32) -> Result<Option<Vec<u8>>, crate::KEYGEN_ERROR> {
+	let active_version = active_version(location)?;
+
+	if version == active_version {
+		return load_inner(location);
+	}
+
+	if version > active_version {
+		return Ok(None);
+	}
+
+	match location {
+		crate::implementation::Location::Filesystem(path) => crate::kek::read(&retired_version_path(path, version)),
+
+		crate::implementation::Location::Pkcs11 { .. } =>
+			Err(crate::implementation::err_external("keys in a PKCS#11 token do not have versions")),
+	}
+}
+
+fn active_version(location: &crate::implementation::Location) -> Result<u32, crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(path) => {
+			let mut version = 0_u32;
+			loop {
+				match std::fs::metadata(retired_version_path(path, version)) {
+					Ok(_) => version = version.checked_add(1).ok_or_else(|| crate::implementation::err_external("key has too many versions"))?,
+					Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(version),
+					Err(err) => return Err(crate::implementation::err_external(err)),
+				}
+			}
+		},
+
+		crate::implementation::Location::Pkcs11 { .. } => Ok(0),
+	}
+}
+
+fn rotate_inner(location: &crate::implementation::Location, bytes: &[u8]) -> Result<(), crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(path) => {
+			let version = active_version(location)?;
+
+			let key = crate::kek::read(path)?.ok_or_else(|| crate::implementation::err_external("key not found"))?;
+			crate::kek::write(&retired_version_path(path, version), &key)?;
+
+			crate::kek::write(path, bytes)?;
+
 			Ok(())
 		},
 
 		crate::implementation::Location::Pkcs11 { .. } =>
-			Err(crate::implementation::err_external("PKCS#11 symmetric keys are not supported")),
+			Err(crate::implementation::err_external("keys in a PKCS#11 token do not have versions")),
+	}
+}
+
+/// Deletes the retired versions of the key, if any.
+///
+/// They are deleted from newest to oldest, so that an interrupted deletion still leaves a contiguous range of versions behind.
+fn delete_retired_versions(location: &crate::implementation::Location) -> Result<(), crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(path) => {
+			let active_version = active_version(location)?;
+
+			for version in (0..active_version).rev() {
+				match std::fs::remove_file(retired_version_path(path, version)) {
+					Ok(()) => (),
+					Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => (),
+					Err(err) => return Err(crate::implementation::err_external(err)),
+				}
+			}
+
+			Ok(())
+		},
+
+		crate::implementation::Location::Pkcs11 { .. } => Ok(()),
+	}
+}
+
+fn retired_version_path(path: &std::path::Path, version: u32) -> std::path::PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(format!(".v{}", version));
+	path.into()
+}
+
+#[cfg(test)]
+mod tests {
+	fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+		use hmac::{Mac, NewMac};
+
+		let mut signer = hmac::Hmac::<sha2::Sha256>::new_varkey(key).unwrap();
+		signer.update(message);
+		signer.finalize().into_bytes().to_vec()
+	}
+
+	#[test]
+	fn hmac_sign_verify() {
+		let dir = tempfile::tempdir().unwrap();
+		let location = crate::implementation::Location::Filesystem(dir.path().join("key"));
+
+		let key = [0x01_u8; 32];
+		super::create_inner(&location, &key).unwrap();
+
+		let (signature_len, signature) = unsafe { super::sign(&location, b"message") }.unwrap();
+		assert_eq!(signature_len, super::HMAC_SHA256_LEN);
+		assert_eq!(signature, hmac_sha256(&key, b"message"));
+
+		assert!(unsafe { super::verify(&location, b"message", &signature) }.unwrap());
+		assert!(!unsafe { super::verify(&location, b"other message", &signature) }.unwrap());
+		assert!(!unsafe { super::verify(&location, b"message", &signature[1..]) }.unwrap());
+	}
+
+	#[test]
+	fn aes_gcm_round_trip() {
+		let key = super::AeadKey::Bytes(vec![0x02_u8; 32]);
+		let iv = [0x03_u8; super::NONCE_LEN];
+
+		let mut tag = [0_u8; super::TAG_LEN];
+		let ciphertext = key.encrypt(&iv, b"aad", b"plaintext", &mut tag).unwrap();
+		assert_ne!(ciphertext, b"plaintext");
+
+		let plaintext = key.decrypt(&iv, b"aad", &ciphertext, &tag).unwrap();
+		assert_eq!(plaintext, b"plaintext");
+
+		assert!(key.decrypt(&iv, b"other aad", &ciphertext, &tag).is_err());
+
+		let mut wrong_tag = tag;
+		wrong_tag[0] ^= 0x01;
+		assert!(key.decrypt(&iv, b"aad", &ciphertext, &wrong_tag).is_err());
+	}
+
+	#[test]
+	fn decrypt_tag_mismatch_is_invalid_parameter() {
+		let dir = tempfile::tempdir().unwrap();
+		let location = crate::implementation::Location::Filesystem(dir.path().join("key"));
+
+		super::create_inner(&location, &[0x06_u8; 32]).unwrap();
+
+		let aad = b"aad";
+		let parameters = crate::KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS {
+			aad: aad.as_ptr(),
+			aad_len: aad.len(),
+		};
+		let parameters: *const std::ffi::c_void = &parameters as *const _ as _;
+
+		let (_, mut ciphertext) =
+			unsafe { super::encrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, parameters, b"plaintext") }.unwrap();
+		let (_, plaintext) =
+			unsafe { super::decrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, parameters, &ciphertext) }.unwrap();
+		assert_eq!(plaintext, b"plaintext");
+
+		// Flip a bit of the ciphertext so that it no longer matches the tag
+		*ciphertext.last_mut().unwrap() ^= 0x01;
+		let err =
+			unsafe { super::decrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, parameters, &ciphertext) }.unwrap_err();
+		assert_eq!(err, crate::KEYGEN_ERROR_INVALID_PARAMETER);
+	}
+
+	fn generated_nonce_parameters(aad: &[u8]) -> crate::KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS {
+		crate::KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS {
+			aad: aad.as_ptr(),
+			aad_len: aad.len(),
+		}
+	}
+
+	fn key_version(ciphertext: &[u8]) -> u32 {
+		super::Ciphertext::parse(ciphertext).unwrap().key_version
+	}
+
+	#[test]
+	fn rotate_then_decrypt_old_ciphertext() {
+		let dir = tempfile::tempdir().unwrap();
+		let location = crate::implementation::Location::Filesystem(dir.path().join("key"));
+
+		super::create_inner(&location, &[0x08_u8; 32]).unwrap();
+
+		let parameters = generated_nonce_parameters(b"aad");
+		let parameters: *const std::ffi::c_void = &parameters as *const _ as _;
+		let mechanism = crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE;
+
+		let (_, old_ciphertext) = unsafe { super::encrypt(&location, mechanism, parameters, b"plaintext") }.unwrap();
+		assert_eq!(old_ciphertext[0], super::CIPHERTEXT_VERSION_GENERATED_NONCE);
+		assert_eq!(key_version(&old_ciphertext), 0);
+
+		super::rotate_inner(&location, &[0x09_u8; 32]).unwrap();
+		assert_eq!(super::active_version(&location).unwrap(), 1);
+		assert_eq!(super::load_version(&location, 0).unwrap().unwrap(), [0x08_u8; 32]);
+		assert_eq!(super::load_version(&location, 1).unwrap().unwrap(), [0x09_u8; 32]);
+		assert!(super::load_version(&location, 2).unwrap().is_none());
+
+		let (_, new_ciphertext) = unsafe { super::encrypt(&location, mechanism, parameters, b"plaintext") }.unwrap();
+		assert_eq!(new_ciphertext[0], super::CIPHERTEXT_VERSION_KEY_VERSION_GENERATED_NONCE);
+		assert_eq!(key_version(&new_ciphertext), 1);
+
+		for ciphertext in &[old_ciphertext, new_ciphertext] {
+			let (_, plaintext) = unsafe { super::decrypt(&location, mechanism, parameters, ciphertext) }.unwrap();
+			assert_eq!(plaintext, b"plaintext");
+		}
+	}
+
+	#[test]
+	fn decrypt_ciphertext_without_key_version() {
+		let dir = tempfile::tempdir().unwrap();
+		let location = crate::implementation::Location::Filesystem(dir.path().join("key"));
+
+		let key = [0x0a_u8; 32];
+		super::create_inner(&location, &key).unwrap();
+
+		let cipher = openssl::symm::Cipher::aes_256_gcm();
+		let iv = [0x0b_u8; super::NONCE_LEN];
+		let nonce = [0x0c_u8; super::NONCE_LEN];
+
+		// v1 and v2 ciphertexts, as created before keys had versions
+		let v1_ciphertext = {
+			let mut tag = [0_u8; super::TAG_LEN];
+			let ciphertext = openssl::symm::encrypt_aead(cipher, &key, Some(&iv), b"aad", b"plaintext", &mut tag).unwrap();
+			let mut result = vec![super::CIPHERTEXT_VERSION_CALLER_IV];
+			result.extend_from_slice(&tag);
+			result.extend_from_slice(&ciphertext);
+			result
+		};
+		let v2_ciphertext = {
+			let mut tag = [0_u8; super::TAG_LEN];
+			let ciphertext = openssl::symm::encrypt_aead(cipher, &key, Some(&nonce), b"aad", b"plaintext", &mut tag).unwrap();
+			let mut result = vec![super::CIPHERTEXT_VERSION_GENERATED_NONCE];
+			result.extend_from_slice(&nonce);
+			result.extend_from_slice(&tag);
+			result.extend_from_slice(&ciphertext);
+			result
+		};
+
+		let caller_iv_parameters = crate::KEYGEN_ENCRYPT_AEAD_PARAMETERS {
+			iv: iv.as_ptr(),
+			iv_len: iv.len(),
+			aad: b"aad".as_ptr(),
+			aad_len: 3,
+		};
+		let caller_iv_parameters: *const std::ffi::c_void = &caller_iv_parameters as *const _ as _;
+		let generated_nonce_parameters = generated_nonce_parameters(b"aad");
+		let generated_nonce_parameters: *const std::ffi::c_void = &generated_nonce_parameters as *const _ as _;
+
+		let decrypt_all = || {
+			let (_, plaintext) =
+				unsafe { super::decrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD, caller_iv_parameters, &v1_ciphertext) }.unwrap();
+			assert_eq!(plaintext, b"plaintext");
+
+			let (_, plaintext) =
+				unsafe { super::decrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, generated_nonce_parameters, &v2_ciphertext) }.unwrap();
+			assert_eq!(plaintext, b"plaintext");
+		};
+
+		decrypt_all();
+
+		// A key that has never been rotated still creates v1 and v2 ciphertexts.
+		let (_, ciphertext) =
+			unsafe { super::encrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD, caller_iv_parameters, b"plaintext") }.unwrap();
+		assert_eq!(ciphertext[0], super::CIPHERTEXT_VERSION_CALLER_IV);
+		assert_eq!(ciphertext, v1_ciphertext);
+		let (_, ciphertext) =
+			unsafe { super::encrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, generated_nonce_parameters, b"plaintext") }.unwrap();
+		assert_eq!(ciphertext[0], super::CIPHERTEXT_VERSION_GENERATED_NONCE);
+		assert_eq!(ciphertext.len(), v2_ciphertext.len());
+
+		// v1 and v2 ciphertexts were created by version 0 of the key, so they still decrypt after it is rotated.
+		super::rotate_inner(&location, &[0x0d_u8; 32]).unwrap();
+		decrypt_all();
+
+		// Ciphertexts created by the rotated key are v3 and v4.
+		let (_, ciphertext) =
+			unsafe { super::encrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD, caller_iv_parameters, b"plaintext") }.unwrap();
+		assert_eq!(ciphertext[0], super::CIPHERTEXT_VERSION_KEY_VERSION_CALLER_IV);
+		assert_eq!(key_version(&ciphertext), 1);
+		let (_, ciphertext) =
+			unsafe { super::encrypt(&location, crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE, generated_nonce_parameters, b"plaintext") }.unwrap();
+		assert_eq!(ciphertext[0], super::CIPHERTEXT_VERSION_KEY_VERSION_GENERATED_NONCE);
+		assert_eq!(key_version(&ciphertext), 1);
+	}
+
+	#[test]
+	fn interrupted_rotation() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("key");
+		let location = crate::implementation::Location::Filesystem(path.clone());
+
+		let key = [0x0e_u8; 32];
+		super::create_inner(&location, &key).unwrap();
+
+		let parameters = generated_nonce_parameters(b"aad");
+		let parameters: *const std::ffi::c_void = &parameters as *const _ as _;
+		let mechanism = crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE;
+
+		let (_, ciphertext_v0) = unsafe { super::encrypt(&location, mechanism, parameters, b"plaintext") }.unwrap();
+
+		// Rotation was interrupted after the active version was copied to .v0, but before the new version was written.
+		crate::kek::write(&super::retired_version_path(&path, 0), &key).unwrap();
+		assert_eq!(super::active_version(&location).unwrap(), 1);
+
+		let (_, ciphertext_v1) = unsafe { super::encrypt(&location, mechanism, parameters, b"plaintext") }.unwrap();
+		assert_eq!(key_version(&ciphertext_v1), 1);
+
+		// Rotating again picks up from the interrupted rotation.
+		super::rotate_inner(&location, &[0x0f_u8; 32]).unwrap();
+		assert_eq!(super::active_version(&location).unwrap(), 2);
+
+		for ciphertext in &[&ciphertext_v0, &ciphertext_v1] {
+			let (_, plaintext) = unsafe { super::decrypt(&location, mechanism, parameters, ciphertext) }.unwrap();
+			assert_eq!(plaintext, b"plaintext");
+		}
+
+		// Versions 0 and 1 have the same bytes.
+		assert_eq!(super::load_version(&location, 0).unwrap().unwrap(), key);
+		assert_eq!(super::load_version(&location, 1).unwrap().unwrap(), key);
+	}
+
+	#[test]
+	fn rewrap() {
+		let dir = tempfile::tempdir().unwrap();
+		let location = crate::implementation::Location::Filesystem(dir.path().join("key"));
+
+		super::create_inner(&location, &[0x10_u8; 32]).unwrap();
+
+		let parameters = generated_nonce_parameters(b"aad");
+		let parameters: *const std::ffi::c_void = &parameters as *const _ as _;
+		let mechanism = crate::KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE;
+
+		let (_, old_ciphertext) = unsafe { super::encrypt(&location, mechanism, parameters, b"plaintext") }.unwrap();
+
+		super::rotate_inner(&location, &[0x11_u8; 32]).unwrap();
+
+		let (new_ciphertext_len, new_ciphertext) = unsafe { super::rewrap_inner(&location, mechanism, parameters, &old_ciphertext) }.unwrap();
+		assert_eq!(new_ciphertext_len, new_ciphertext.len());
+		assert_eq!(key_version(&new_ciphertext), 1);
+
+		let (_, plaintext) = unsafe { super::decrypt(&location, mechanism, parameters, &new_ciphertext) }.unwrap();
+		assert_eq!(plaintext, b"plaintext");
+
+		// A ciphertext that was not created by any version of the key cannot be rewrapped.
+		let mut modified_ciphertext = old_ciphertext;
+		*modified_ciphertext.last_mut().unwrap() ^= 0x01;
+		let err = unsafe { super::rewrap_inner(&location, mechanism, parameters, &modified_ciphertext) }.unwrap_err();
+		assert_eq!(err, crate::KEYGEN_ERROR_INVALID_PARAMETER);
+	}
+
+	#[test]
+	fn split_tag_matches_openssl() {
+		let key = [0x04_u8; 32];
+		let iv = [0x05_u8; super::NONCE_LEN];
+		let cipher = openssl::symm::Cipher::aes_256_gcm();
+
+		let mut expected_tag = [0_u8; super::TAG_LEN];
+		let expected_ciphertext = openssl::symm::encrypt_aead(cipher, &key, Some(&iv), b"aad", b"plaintext", &mut expected_tag).unwrap();
+
+		// CKM_AES_GCM returns the ciphertext followed by the tag
+		let ciphertext_with_tag = super::join_tag(&expected_ciphertext, &expected_tag);
+		assert_eq!(ciphertext_with_tag.len(), expected_ciphertext.len() + super::TAG_LEN);
+
+		let mut tag = [0_u8; super::TAG_LEN];
+		let ciphertext = super::split_tag(ciphertext_with_tag, &mut tag).unwrap();
+		assert_eq!(ciphertext, expected_ciphertext);
+		assert_eq!(tag, expected_tag);
+
+		assert!(super::split_tag(vec![0_u8; super::TAG_LEN - 1], &mut tag).is_err());
+	}
+
+	#[test]
+	fn hmac_key_has_value() {
+		let key = [0x06_u8; 32];
+		let sign = |message: &[u8]| Ok(hmac_sha256(&key, message));
+
+		assert!(super::hmac_key_has_value(sign, &key).unwrap());
+		assert!(!super::hmac_key_has_value(sign, &[0x07_u8; 32]).unwrap());
+		assert!(!super::hmac_key_has_value(sign, &key[..16]).unwrap());
+	}
+
+	fn parse_ciphertext_err(ciphertext: &[u8]) -> crate::KEYGEN_ERROR {
+		match super::Ciphertext::parse(ciphertext) {
+			Ok(_) => panic!("{:?} was not rejected", ciphertext),
+			Err(err) => err,
+		}
+	}
+
+	#[test]
+	fn parse_ciphertext() {
+		let nonce = [0x10_u8; super::NONCE_LEN];
+		let tag = [0x11_u8; super::TAG_LEN];
+
+		let mut v2_ciphertext = vec![super::CIPHERTEXT_VERSION_GENERATED_NONCE];
+		v2_ciphertext.extend_from_slice(&nonce);
+		v2_ciphertext.extend_from_slice(&tag);
+		v2_ciphertext.extend_from_slice(b"ciphertext");
+
+		let ciphertext = super::Ciphertext::parse(&v2_ciphertext).unwrap();
+		assert_eq!(ciphertext.key_version, 0);
+		assert_eq!(ciphertext.nonce, Some(&nonce[..]));
+		assert_eq!(ciphertext.tag, tag);
+		assert_eq!(ciphertext.ciphertext, b"ciphertext");
+
+		let mut v4_ciphertext = vec![super::CIPHERTEXT_VERSION_KEY_VERSION_GENERATED_NONCE];
+		v4_ciphertext.extend_from_slice(&3_u32.to_be_bytes());
+		v4_ciphertext.extend_from_slice(&nonce);
+		v4_ciphertext.extend_from_slice(&tag);
+
+		// An empty plaintext has an empty ciphertext
+		let ciphertext = super::Ciphertext::parse(&v4_ciphertext).unwrap();
+		assert_eq!(ciphertext.key_version, 3);
+		assert_eq!(ciphertext.nonce, Some(&nonce[..]));
+		assert_eq!(ciphertext.tag, tag);
+		assert!(ciphertext.ciphertext.is_empty());
+	}
+
+	#[test]
+	fn parse_ciphertext_truncated() {
+		let mut v2_ciphertext = vec![super::CIPHERTEXT_VERSION_GENERATED_NONCE];
+		v2_ciphertext.extend_from_slice(&[0x12_u8; super::NONCE_LEN]);
+		v2_ciphertext.extend_from_slice(&[0x13_u8; super::TAG_LEN]);
+
+		let mut v4_ciphertext = vec![super::CIPHERTEXT_VERSION_KEY_VERSION_GENERATED_NONCE];
+		v4_ciphertext.extend_from_slice(&1_u32.to_be_bytes());
+		v4_ciphertext.extend_from_slice(&[0x12_u8; super::NONCE_LEN]);
+		v4_ciphertext.extend_from_slice(&[0x13_u8; super::TAG_LEN]);
+
+		for ciphertext in &[v2_ciphertext, v4_ciphertext] {
+			assert!(super::Ciphertext::parse(ciphertext).is_ok());
+
+			// Every prefix is missing some of the key version, nonce or tag
+			for len in 0..ciphertext.len() {
+				assert_eq!(parse_ciphertext_err(&ciphertext[..len]), crate::KEYGEN_ERROR_INVALID_PARAMETER);
+			}
+		}
+	}
+
+	#[test]
+	fn parse_ciphertext_unrecognized_version() {
+		for &version in &[0x00_u8, 0x05, 0xff] {
+			let mut ciphertext = vec![version];
+			ciphertext.extend_from_slice(&[0x14_u8; super::NONCE_LEN]);
+			ciphertext.extend_from_slice(&[0x15_u8; super::TAG_LEN]);
+			ciphertext.extend_from_slice(b"ciphertext");
+
+			assert_eq!(parse_ciphertext_err(&ciphertext), crate::KEYGEN_ERROR_INVALID_PARAMETER);
+		}
 	}
 }
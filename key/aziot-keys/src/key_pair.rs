@@ -1,6 +1,23 @@
+/// The `create_key_pair_if_not_exists` of `KEYGEN_FUNCTION_LIST_2_0_0_0`, which ignores unrecognized components of `preferred_algorithms`.
+pub(crate) unsafe extern "C" fn create_key_pair_if_not_exists_2_0_0_0(
+	id: *const std::os::raw::c_char,
+	preferred_algorithms: *const std::os::raw::c_char,
+) -> crate::KEYGEN_ERROR {
+	create_key_pair_if_not_exists_inner(id, preferred_algorithms, false)
+}
+
+/// The `create_key_pair_if_not_exists` of `KEYGEN_FUNCTION_LIST_2_1_0_0`, which rejects unrecognized components of `preferred_algorithms`.
 pub(crate) unsafe extern "C" fn create_key_pair_if_not_exists(
 	id: *const std::os::raw::c_char,
 	preferred_algorithms: *const std::os::raw::c_char,
+) -> crate::KEYGEN_ERROR {
+	create_key_pair_if_not_exists_inner(id, preferred_algorithms, true)
+}
+
+unsafe fn create_key_pair_if_not_exists_inner(
+	id: *const std::os::raw::c_char,
+	preferred_algorithms: *const std::os::raw::c_char,
+	reject_unrecognized_algorithms: bool,
 ) -> crate::KEYGEN_ERROR {
 	crate::r#catch(|| {
 		let id = {
@@ -12,17 +29,21 @@ pub(crate) unsafe extern "C" fn create_key_pair_if_not_exists(
 			id
 		};
 
-		let preferred_algorithms = PreferredAlgorithm::from_str(preferred_algorithms).map_err(|err| crate::implementation::err_invalid_parameter("preferred_algorithms", err))?;
+		let preferred_algorithms =
+			PreferredAlgorithm::from_str(preferred_algorithms, reject_unrecognized_algorithms)
+			.map_err(|err| crate::implementation::err_invalid_parameter("preferred_algorithms", err))?;
 
 		let location = crate::implementation::Location::of(id)?;
 
-		if load_inner(&location)?.is_none() {
+		if load_public_inner(&location)?.is_none() {
 			create_inner(&location, &preferred_algorithms)?;
-			if load_inner(&location)?.is_none() {
+			if load_public_inner(&location)?.is_none() {
 				return Err(crate::implementation::err_external("key created successfully but could not be found"));
 			}
 		}
 
+		crate::index::add(&location, id, crate::index::Kind::KeyPair)?;
+
 		Ok(())
 	})
 }
@@ -42,10 +63,116 @@ pub(crate) unsafe extern "C" fn load_key_pair(
 
 		let location = crate::implementation::Location::of(id)?;
 
-		if load_inner(&location)?.is_none() {
+		if load_public_inner(&location)?.is_none() {
 			return Err(crate::implementation::err_invalid_parameter("id", "not found"));
 		}
 
+		// Key pairs that were created before the index existed are added to it when they are next loaded.
+		crate::index::add(&location, id, crate::index::Kind::KeyPair)?;
+
+		Ok(())
+	})
+}
+
+pub(crate) unsafe extern "C" fn delete_key_pair(
+	id: *const std::os::raw::c_char,
+) -> crate::KEYGEN_ERROR {
+	crate::r#catch(|| {
+		let id = {
+			if id.is_null() {
+				return Err(crate::implementation::err_invalid_parameter("id", "expected non-NULL"));
+			}
+			let id = std::ffi::CStr::from_ptr(id);
+			let id = id.to_str().map_err(|err| crate::implementation::err_invalid_parameter("id", err))?;
+			id
+		};
+
+		let location = crate::implementation::Location::of_deletable(id)?;
+
+		delete_inner(&location)?;
+
+		crate::index::remove(&location, id)?;
+
+		Ok(())
+	})
+}
+
+pub(crate) unsafe extern "C" fn list_key_pairs(
+	list: *mut std::os::raw::c_char,
+	list_len: *mut usize,
+) -> crate::KEYGEN_ERROR {
+	crate::r#catch(|| {
+		let mut entries = vec![];
+
+		for id in crate::implementation::preloaded_key_ids()? {
+			let location = crate::implementation::Location::of(&id)?;
+
+			let key_pair = match location {
+				// Preloaded keys are not marked as symmetric keys or key pairs, so assume that any file that is not a PEM private key
+				// is a symmetric key. Those are listed by `list_keys` instead.
+				crate::implementation::Location::Filesystem(ref path) => match crate::kek::read(path)? {
+					Some(key) if openssl::pkey::PKey::private_key_from_pem(&key).is_ok() => load_public_inner(&location)?,
+					_ => None,
+				},
+
+				crate::implementation::Location::Pkcs11 { .. } => load_public_inner(&location)?,
+			};
+			if let Some(public_key) = key_pair {
+				entries.push(crate::implementation::ListEntry {
+					id,
+					algorithm: algorithm(&public_key)?,
+					backend: "preloaded",
+				});
+			}
+		}
+
+		for (id, kind) in crate::index::load()? {
+			if kind != crate::index::Kind::KeyPair {
+				continue;
+			}
+
+			// The index only describes the homedir, so skip its entries if key pairs are now created elsewhere.
+			let location = crate::implementation::Location::of(&id)?;
+			if let crate::implementation::Location::Pkcs11 { .. } = location {
+				continue;
+			}
+
+			if let Some(public_key) = load_public_inner(&location)? {
+				entries.push(crate::implementation::ListEntry {
+					id,
+					algorithm: algorithm(&public_key)?,
+					backend: "filesystem",
+				});
+			}
+		}
+
+		if let Some((lib_path, base_slot)) = crate::implementation::pkcs11_base_slot()? {
+			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_slot = pkcs11_context.find_slot(&base_slot).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, base_slot.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let labels = pkcs11_session.list_key_pairs().map_err(crate::implementation::err_external)?;
+
+			for label in labels {
+				// Preloaded keys in the same slot have already been listed.
+				if crate::implementation::is_preloaded(&label)? {
+					continue;
+				}
+
+				let mut uri = base_slot.clone();
+				uri.object_label = Some(label.clone());
+				let location = crate::implementation::Location::Pkcs11 { lib_path: lib_path.clone(), uri };
+				if let Some(public_key) = load_public_inner(&location)? {
+					entries.push(crate::implementation::ListEntry {
+						id: label,
+						algorithm: algorithm(&public_key)?,
+						backend: "pkcs11",
+					});
+				}
+			}
+		}
+
+		crate::implementation::write_list(&entries, list, list_len)?;
+
 		Ok(())
 	})
 }
@@ -70,7 +197,7 @@ pub(crate) unsafe extern "C" fn get_key_pair_parameter(
 
 		let location = crate::implementation::Location::of(id)?;
 
-		let (public_key, _) = load_inner(&location)?.ok_or_else(|| crate::implementation::err_invalid_parameter("id", "not found"))?;
+		let public_key = load_public_inner(&location)?.ok_or_else(|| crate::implementation::err_invalid_parameter("id", "not found"))?;
 
 		match r#type {
 			crate::KEYGEN_KEY_PAIR_PARAMETER_TYPE_ALGORITHM => {
@@ -93,6 +220,9 @@ pub(crate) unsafe extern "C" fn get_key_pair_parameter(
 						else if public_key.rsa().is_ok() {
 							crate::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_RSA
 						}
+						else if is_ed25519(&public_key) {
+							crate::KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_ED25519
+						}
 						else {
 							return Err(crate::implementation::err_invalid_parameter("id", "key is neither RSA nor EC"));
 						};
@@ -104,15 +234,17 @@ pub(crate) unsafe extern "C" fn get_key_pair_parameter(
 			},
 
 			crate::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_CURVE_OID => {
-				let ec_key =
+				let curve_nid =
 					if let Ok(ec_key) = public_key.ec_key() {
-						ec_key
+						ec_key.group().curve_name().ok_or_else(|| crate::implementation::err_invalid_parameter("type", "key does not have named curve"))?
+					}
+					else if is_ed25519(&public_key) {
+						openssl::nid::Nid::from_raw(public_key.id().as_raw())
 					}
 					else {
 						return Err(crate::implementation::err_invalid_parameter("type", "not an EC key"));
 					};
 
-				let curve_nid = ec_key.group().curve_name().ok_or_else(|| crate::implementation::err_invalid_parameter("type", "key does not have named curve"))?;
 				let curve = openssl2::EcCurve::from_nid(curve_nid).ok_or_else(|| crate::implementation::err_invalid_parameter("type", "key curve not recognized"))?;
 				let curve_oid = curve.as_oid_der();
 
@@ -135,19 +267,20 @@ pub(crate) unsafe extern "C" fn get_key_pair_parameter(
 			},
 
 			crate::KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT => {
-				let ec_key =
+				let point =
 					if let Ok(ec_key) = public_key.ec_key() {
-						ec_key
+						let curve = ec_key.group();
+						let point = ec_key.public_key();
+						let mut big_num_context = openssl::bn::BigNumContext::new()?;
+						point.to_bytes(curve, openssl::ec::PointConversionForm::COMPRESSED, &mut big_num_context)?
+					}
+					else if is_ed25519(&public_key) {
+						raw_public_key(&public_key)?
 					}
 					else {
 						return Err(crate::implementation::err_invalid_parameter("type", "not an EC key"));
 					};
 
-				let curve = ec_key.group();
-				let point = ec_key.public_key();
-				let mut big_num_context = openssl::bn::BigNumContext::new()?;
-				let point = point.to_bytes(curve, openssl::ec::PointConversionForm::COMPRESSED, &mut big_num_context)?;
-
 				let expected_value_len = point.len();
 				let actual_value_len = *value_len_out.as_ref();
 
@@ -229,12 +362,70 @@ pub(crate) unsafe extern "C" fn get_key_pair_parameter(
 	})
 }
 
+pub(crate) unsafe extern "C" fn derive_shared_secret(
+	id: *const std::os::raw::c_char,
+	peer_public_key: *const std::os::raw::c_uchar,
+	peer_public_key_len: usize,
+	shared_secret: *mut std::os::raw::c_uchar,
+	shared_secret_len: *mut usize,
+) -> crate::KEYGEN_ERROR {
+	crate::r#catch(|| {
+		let id = {
+			if id.is_null() {
+				return Err(crate::implementation::err_invalid_parameter("id", "expected non-NULL"));
+			}
+			let id = std::ffi::CStr::from_ptr(id);
+			let id = id.to_str().map_err(|err| crate::implementation::err_invalid_parameter("id", err))?;
+			id
+		};
+
+		let peer_public_key =
+			if peer_public_key.is_null() {
+				return Err(crate::implementation::err_invalid_parameter("peer_public_key", "expected non-NULL"));
+			}
+			else {
+				std::slice::from_raw_parts(peer_public_key, peer_public_key_len)
+			};
+
+		let mut shared_secret_len_out =
+			std::ptr::NonNull::new(shared_secret_len)
+			.ok_or_else(|| crate::implementation::err_invalid_parameter("shared_secret_len", "expected non-NULL"))?;
+
+		let location = crate::implementation::Location::of(id)?;
+
+		let expected_shared_secret = derive_shared_secret_inner(&location, peer_public_key)?;
+		let expected_shared_secret_len = expected_shared_secret.len();
+
+		let actual_shared_secret_len = *shared_secret_len_out.as_ref();
+
+		*shared_secret_len_out.as_mut() = expected_shared_secret_len;
+
+		if !shared_secret.is_null() {
+			if actual_shared_secret_len < expected_shared_secret_len {
+				return Err(crate::implementation::err_invalid_parameter("shared_secret", "insufficient size"));
+			}
+
+			let shared_secret_out = std::slice::from_raw_parts_mut(shared_secret, actual_shared_secret_len);
+			shared_secret_out[..expected_shared_secret_len].copy_from_slice(&expected_shared_secret);
+		}
+
+		Ok(())
+	})
+}
+
 pub(crate) unsafe fn sign(
 	location: &crate::implementation::Location,
 	mechanism: crate::KEYGEN_SIGN_MECHANISM,
 	parameters: *const std::ffi::c_void,
 	digest: &[u8],
 ) -> Result<(usize, Vec<u8>), crate::KEYGEN_ERROR> {
+	#[cfg(ossl111)]
+	{
+		if mechanism == crate::KEYGEN_SIGN_MECHANISM_EDDSA {
+			return sign_eddsa(location, parameters, digest);
+		}
+	}
+
 	let (_, private_key) = load_inner(location)?.ok_or_else(|| crate::implementation::err_invalid_parameter("id", "not found"))?;
 
 	let (signature_len, signature) = match (mechanism, private_key.ec_key(), private_key.rsa()) {
@@ -255,21 +446,7 @@ pub(crate) unsafe fn sign(
 		},
 
 		(crate::KEYGEN_SIGN_MECHANISM_RSA_PKCS1, _, Ok(rsa)) => {
-			let message_digest = {
-				if parameters.is_null() {
-					return Err(crate::implementation::err_invalid_parameter("parameters", "expected non-NULL"));
-				}
-
-				let parameters = parameters as *const crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST;
-				match *parameters {
-					crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA1 => openssl::hash::MessageDigest::sha1(),
-					crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA224 => openssl::hash::MessageDigest::sha224(),
-					crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA256 => openssl::hash::MessageDigest::sha256(),
-					crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA384 => openssl::hash::MessageDigest::sha384(),
-					crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA512 => openssl::hash::MessageDigest::sha512(),
-					_ => return Err(crate::implementation::err_invalid_parameter("parameters", "unrecognized message digest")),
-				}
-			};
+			let message_digest = rsa_pkcs1_message_digest(parameters)?;
 
 			// openssl crate doesn't expose a wrapper around RSA_sign, so call it directly
 
@@ -307,29 +484,55 @@ pub(crate) unsafe fn sign(
 			(signature_len, signature)
 		},
 
-		// crate::KEYGEN_SIGN_MECHANISM_RSA_PSS => {
-		// 	if parameters.is_null() {
-		// 		return Err(crate::implementation::err_invalid_parameter("parameters", "expected KEYGEN_SIGN_RSA_PSS_PARAMETERS"));
-		// 	}
+		(crate::KEYGEN_SIGN_MECHANISM_RSA_PSS, _, Ok(rsa)) => {
+			let (message_digest, salt_len) = rsa_pss_parameters(parameters, digest)?;
 
-		// 	let parameters = &*(parameters as *const crate::KEYGEN_SIGN_RSA_PSS_PARAMETERS);
+			let signature_len = rsa.size();
+			let signature_len =
+				std::convert::TryInto::try_into(signature_len)
+				.map_err(|err| crate::implementation::err_external(format!("RSA_size returned invalid value: {}", err)))?;
 
-		// 	let mask_generation_function = match parameters.mask_generation_function {
-		// 		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA1 => openssl::hash::MessageDigest::sha1(),
-		// 		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA224 => openssl::hash::MessageDigest::sha224(),
-		// 		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA256 => openssl::hash::MessageDigest::sha256(),
-		// 		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA384 => openssl::hash::MessageDigest::sha384(),
-		// 		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA512 => openssl::hash::MessageDigest::sha512(),
-		// 		_ => return Err(crate::implementation::err_invalid_parameter("mask_generation_function", "unrecognized value")),
-		// 	};
+			// openssl crate doesn't expose a wrapper around EVP_PKEY_sign, so call it directly.
+			//
+			// Sign via the EVP_PKEY instead of RSA_padding_add_PKCS1_PSS + RSA_private_encrypt, so that keys loaded through
+			// the PKCS#11 engine are signed with CKM_RSA_PKCS_PSS instead of requiring the token to support raw RSA.
 
-		// 	let salt_len = std::convert::TryInto::try_into(parameters.salt_len).map_err(|err| crate::implementation::err_invalid_parameter("salt_len", err))?;
-		// 	let salt_len = openssl::sign::RsaPssSaltlen::custom(salt_len);
+			let ctx =
+				openssl2::openssl_returns_nonnull(
+					openssl_sys::EVP_PKEY_CTX_new(foreign_types_shared::ForeignType::as_ptr(&private_key), std::ptr::null_mut()))?;
 
-		// 	signer.set_rsa_padding(openssl::rsa::Padding::PKCS1_PSS)?;
-		// 	signer.set_rsa_mgf1_md(mask_generation_function)?;
-		// 	signer.set_rsa_pss_saltlen(salt_len)?;
-		// },
+			let signature = (|| {
+				openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_sign_init(ctx))?;
+				openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_CTX_set_rsa_padding(ctx, openssl_sys::RSA_PKCS1_PSS_PADDING))?;
+				openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_CTX_set_signature_md(ctx, message_digest.as_ptr() as _))?;
+				openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_CTX_set_rsa_mgf1_md(ctx, message_digest.as_ptr() as _))?;
+				openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_CTX_set_rsa_pss_saltlen(ctx, salt_len))?;
+
+				let mut signature = vec![0_u8; signature_len];
+				let mut actual_signature_len = signature_len;
+
+				openssl2::openssl_returns_1(openssl_sys::EVP_PKEY_sign(
+					ctx,
+					signature.as_mut_ptr(), &mut actual_signature_len,
+					digest.as_ptr(), digest.len(),
+				))?;
+
+				if actual_signature_len > signature.len() {
+					// EVP_PKEY_sign scribbled past the end of the buffer. Crash as soon as possible.
+					std::process::abort();
+				}
+
+				signature.truncate(actual_signature_len);
+
+				Ok::<_, crate::KEYGEN_ERROR>(signature)
+			})();
+
+			openssl_sys::EVP_PKEY_CTX_free(ctx);
+
+			let signature = signature?;
+
+			(signature_len, signature)
+		},
 
 		_ => return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value")),
 	};
@@ -337,6 +540,397 @@ pub(crate) unsafe fn sign(
 	Ok((signature_len, signature))
 }
 
+/// Signs the whole `message` with an Ed25519 key pair.
+///
+/// Ed25519 keys in PKCS#11 cannot be loaded through the openssl engine, so they are signed with the PKCS#11 library directly.
+#[cfg(ossl111)]
+unsafe fn sign_eddsa(
+	location: &crate::implementation::Location,
+	parameters: *const std::ffi::c_void,
+	message: &[u8],
+) -> Result<(usize, Vec<u8>), crate::KEYGEN_ERROR> {
+	const SIGNATURE_LEN: usize = 64;
+
+	if !parameters.is_null() {
+		return Err(crate::implementation::err_invalid_parameter("parameters", "expected NULL"));
+	}
+
+	match location {
+		crate::implementation::Location::Filesystem(_) => {
+			let (_, private_key) = load_inner(location)?.ok_or_else(|| crate::implementation::err_invalid_parameter("id", "not found"))?;
+			if !is_ed25519(&private_key) {
+				return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value"));
+			}
+
+			let mut signer = openssl::sign::Signer::new_without_digest(&private_key)?;
+			let signature = signer.sign_oneshot_to_vec(message)?;
+
+			Ok((SIGNATURE_LEN, signature))
+		},
+
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_slot = pkcs11_context.find_slot(uri).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+
+			let private_key = match pkcs11_session.get_key_pair(uri) {
+				Ok(pkcs11::KeyPair::Ec(public_key, private_key)) if public_key.curve().ok() == Some(openssl2::EcCurve::Ed25519) => private_key,
+
+				Ok(_) => return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value")),
+
+				Err(pkcs11::GetKeyError::KeyDoesNotExist) => return Err(crate::implementation::err_invalid_parameter("id", "not found")),
+
+				Err(err) => return Err(crate::implementation::err_external(err)),
+			};
+
+			let mut signature = vec![0_u8; SIGNATURE_LEN];
+			let signature_len = private_key.sign_eddsa(message, &mut signature).map_err(crate::implementation::err_external)?;
+			let signature_len = std::convert::TryInto::try_into(signature_len).expect("CK_ULONG -> usize");
+			signature.truncate(signature_len);
+
+			Ok((SIGNATURE_LEN, signature))
+		},
+	}
+}
+
+pub(crate) unsafe fn verify(
+	location: &crate::implementation::Location,
+	mechanism: crate::KEYGEN_SIGN_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	digest: &[u8],
+	signature: &[u8],
+) -> Result<bool, crate::KEYGEN_ERROR> {
+	let public_key = load_public_inner(location)?.ok_or_else(|| crate::implementation::err_invalid_parameter("id", "not found"))?;
+
+	// Copy public_key's parameters into a new public key, so that keys loaded through the PKCS#11 engine
+	// are verified by openssl itself instead of being sent to the token.
+	let public_key_der = public_key.public_key_to_der()?;
+	let public_key = openssl::pkey::PKey::public_key_from_der(&public_key_der)?;
+
+	let ok = match (mechanism, public_key.ec_key(), public_key.rsa()) {
+		(crate::KEYGEN_SIGN_MECHANISM_ECDSA, Ok(ec_key), _) => {
+			let signature = match openssl::ecdsa::EcdsaSig::from_der(signature) {
+				Ok(signature) => signature,
+				Err(_) => return Ok(false),
+			};
+
+			signature.verify(digest, &ec_key)?
+		},
+
+		(crate::KEYGEN_SIGN_MECHANISM_RSA_PKCS1, _, Ok(_)) => {
+			let message_digest = rsa_pkcs1_message_digest(parameters)?;
+
+			verify_rsa(&public_key, openssl_sys::RSA_PKCS1_PADDING, message_digest, None, digest, signature)?
+		},
+
+		(crate::KEYGEN_SIGN_MECHANISM_RSA_PSS, _, Ok(_)) => {
+			let (message_digest, salt_len) = rsa_pss_parameters(parameters, digest)?;
+
+			verify_rsa(&public_key, openssl_sys::RSA_PKCS1_PSS_PADDING, message_digest, Some(salt_len), digest, signature)?
+		},
+
+		#[cfg(ossl111)]
+		(crate::KEYGEN_SIGN_MECHANISM_EDDSA, _, _) if is_ed25519(&public_key) => {
+			if !parameters.is_null() {
+				return Err(crate::implementation::err_invalid_parameter("parameters", "expected NULL"));
+			}
+
+			let mut verifier = openssl::sign::Verifier::new_without_digest(&public_key)?;
+			verifier.verify_oneshot(signature, digest)?
+		},
+
+		_ => return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value")),
+	};
+
+	Ok(ok)
+}
+
+unsafe fn rsa_pkcs1_message_digest(parameters: *const std::ffi::c_void) -> Result<openssl::hash::MessageDigest, crate::KEYGEN_ERROR> {
+	if parameters.is_null() {
+		return Err(crate::implementation::err_invalid_parameter("parameters", "expected non-NULL"));
+	}
+
+	let parameters = parameters as *const crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST;
+	match *parameters {
+		crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA1 => Ok(openssl::hash::MessageDigest::sha1()),
+		crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA224 => Ok(openssl::hash::MessageDigest::sha224()),
+		crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA256 => Ok(openssl::hash::MessageDigest::sha256()),
+		crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA384 => Ok(openssl::hash::MessageDigest::sha384()),
+		crate::KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA512 => Ok(openssl::hash::MessageDigest::sha512()),
+		_ => Err(crate::implementation::err_invalid_parameter("parameters", "unrecognized message digest")),
+	}
+}
+
+unsafe fn rsa_pss_parameters(
+	parameters: *const std::ffi::c_void,
+	digest: &[u8],
+) -> Result<(openssl::hash::MessageDigest, std::os::raw::c_int), crate::KEYGEN_ERROR> {
+	if parameters.is_null() {
+		return Err(crate::implementation::err_invalid_parameter("parameters", "expected non-NULL"));
+	}
+
+	let parameters = &*(parameters as *const crate::KEYGEN_SIGN_RSA_PSS_PARAMETERS);
+
+	// The digest and the mask generation function use the same hash algorithm.
+	let message_digest = match parameters.mask_generation_function {
+		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA1 => openssl::hash::MessageDigest::sha1(),
+		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA224 => openssl::hash::MessageDigest::sha224(),
+		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA256 => openssl::hash::MessageDigest::sha256(),
+		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA384 => openssl::hash::MessageDigest::sha384(),
+		crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA512 => openssl::hash::MessageDigest::sha512(),
+		_ => return Err(crate::implementation::err_invalid_parameter("parameters", "unrecognized mask generation function")),
+	};
+
+	if digest.len() != message_digest.size() {
+		return Err(crate::implementation::err_invalid_parameter("digest", "digest length does not match the mask generation function"));
+	}
+
+	let salt_len =
+		std::convert::TryInto::try_into(parameters.salt_len)
+		.map_err(|err| crate::implementation::err_invalid_parameter("parameters", err))?;
+
+	Ok((message_digest, salt_len))
+}
+
+unsafe fn verify_rsa(
+	public_key: &openssl::pkey::PKeyRef<openssl::pkey::Public>,
+	padding: std::os::raw::c_int,
+	message_digest: openssl::hash::MessageDigest,
+	salt_len: Option<std::os::raw::c_int>,
+	digest: &[u8],
+	signature: &[u8],
+) -> Result<bool, crate::KEYGEN_ERROR> {
+	// openssl crate doesn't expose a wrapper around EVP_PKEY_verify, so call it directly.
+
+	let ctx =
+		openssl2::openssl_returns_nonnull(
+			openssl_sys::EVP_PKEY_CTX_new(foreign_types_shared::ForeignTypeRef::as_ptr(public_key), std::ptr::null_mut()))?;
+
+	let ok = (|| {
+		openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_verify_init(ctx))?;
+		openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_CTX_set_rsa_padding(ctx, padding))?;
+		openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_CTX_set_signature_md(ctx, message_digest.as_ptr() as _))?;
+		if let Some(salt_len) = salt_len {
+			openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_CTX_set_rsa_mgf1_md(ctx, message_digest.as_ptr() as _))?;
+			openssl2::openssl_returns_positive(openssl_sys::EVP_PKEY_CTX_set_rsa_pss_saltlen(ctx, salt_len))?;
+		}
+
+		// EVP_PKEY_verify returns 1 if the signature is valid, 0 if it is invalid, and a negative value for any other error.
+		match openssl_sys::EVP_PKEY_verify(
+			ctx,
+			signature.as_ptr(), signature.len(),
+			digest.as_ptr(), digest.len(),
+		) {
+			1 => Ok::<_, crate::KEYGEN_ERROR>(true),
+
+			0 => {
+				// Discard the reason the signature was rejected, so that it doesn't show up in the next error.
+				let _ = openssl::error::ErrorStack::get();
+				Ok(false)
+			},
+
+			result => Err(openssl2::Error::SysReturnedUnexpected { expected: 1, actual: result, inner: openssl::error::ErrorStack::get() }.into()),
+		}
+	})();
+
+	openssl_sys::EVP_PKEY_CTX_free(ctx);
+
+	ok
+}
+
+pub(crate) unsafe fn encrypt(
+	location: &crate::implementation::Location,
+	mechanism: crate::KEYGEN_ENCRYPT_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	plaintext: &[u8],
+) -> Result<(usize, Vec<u8>), crate::KEYGEN_ERROR> {
+	if mechanism != crate::KEYGEN_ENCRYPT_MECHANISM_RSA_OAEP {
+		return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value"));
+	}
+
+	if !parameters.is_null() {
+		return Err(crate::implementation::err_invalid_parameter("parameters", "expected NULL"));
+	}
+
+	let (public_key, _) = load_inner(location)?.ok_or_else(|| crate::implementation::err_invalid_parameter("id", "not found"))?;
+
+	// As in `verify`, use a copy of the public key that is not tied to the PKCS#11 engine.
+	let public_key_der = public_key.public_key_to_der()?;
+	let public_key = openssl::pkey::PKey::public_key_from_der(&public_key_der)?;
+
+	if public_key.rsa().is_err() {
+		return Err(crate::implementation::err_invalid_parameter("mechanism", "mechanism cannot be used with this key pair"));
+	}
+
+	let mut encrypter = openssl::encrypt::Encrypter::new(&public_key)?;
+	encrypter.set_rsa_padding(openssl::rsa::Padding::PKCS1_OAEP)?;
+	encrypter.set_rsa_oaep_md(openssl::hash::MessageDigest::sha256())?;
+	encrypter.set_rsa_mgf1_md(openssl::hash::MessageDigest::sha256())?;
+
+	let mut ciphertext = vec![0_u8; encrypter.encrypt_len(plaintext)?];
+	let ciphertext_len = encrypter.encrypt(plaintext, &mut ciphertext)?;
+	ciphertext.truncate(ciphertext_len);
+
+	Ok((ciphertext.len(), ciphertext))
+}
+
+pub(crate) unsafe fn decrypt(
+	location: &crate::implementation::Location,
+	mechanism: crate::KEYGEN_ENCRYPT_MECHANISM,
+	parameters: *const std::ffi::c_void,
+	ciphertext: &[u8],
+) -> Result<(usize, Vec<u8>), crate::KEYGEN_ERROR> {
+	if mechanism != crate::KEYGEN_ENCRYPT_MECHANISM_RSA_OAEP {
+		return Err(crate::implementation::err_invalid_parameter("mechanism", "unrecognized value"));
+	}
+
+	if !parameters.is_null() {
+		return Err(crate::implementation::err_invalid_parameter("parameters", "expected NULL"));
+	}
+
+	let plaintext = match location {
+		crate::implementation::Location::Filesystem(_) => {
+			let (_, private_key) = load_inner(location)?.ok_or_else(|| crate::implementation::err_invalid_parameter("id", "not found"))?;
+
+			if private_key.rsa().is_err() {
+				return Err(crate::implementation::err_invalid_parameter("mechanism", "mechanism cannot be used with this key pair"));
+			}
+
+			let mut decrypter = openssl::encrypt::Decrypter::new(&private_key)?;
+			decrypter.set_rsa_padding(openssl::rsa::Padding::PKCS1_OAEP)?;
+			decrypter.set_rsa_oaep_md(openssl::hash::MessageDigest::sha256())?;
+			decrypter.set_rsa_mgf1_md(openssl::hash::MessageDigest::sha256())?;
+
+			let mut plaintext = vec![0_u8; decrypter.decrypt_len(ciphertext)?];
+			let plaintext_len = decrypter.decrypt(ciphertext, &mut plaintext)?;
+			plaintext.truncate(plaintext_len);
+			plaintext
+		},
+
+		// Decrypt with the PKCS#11 library directly instead of the openssl engine, because the engine would need the token
+		// to support raw RSA (CKM_RSA_X_509) to let openssl remove the OAEP padding itself.
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_slot = pkcs11_context.find_slot(uri).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+
+			let private_key = match pkcs11_session.get_key_pair(uri) {
+				Ok(pkcs11::KeyPair::Rsa(_, private_key)) => private_key,
+
+				Ok(pkcs11::KeyPair::Ec(_, _)) =>
+					return Err(crate::implementation::err_invalid_parameter("mechanism", "mechanism cannot be used with this key pair")),
+
+				Err(pkcs11::GetKeyError::KeyDoesNotExist) => return Err(crate::implementation::err_invalid_parameter("id", "not found")),
+
+				Err(err) => return Err(crate::implementation::err_external(err)),
+			};
+
+			let mechanism = pkcs11::RsaEncryptMechanism::Oaep(pkcs11_sys::CK_RSA_PKCS_OAEP_PARAMS {
+				hashAlg: pkcs11_sys::CKM_SHA256,
+				mgf: pkcs11_sys::CKG_MGF1_SHA256,
+				source: pkcs11_sys::CKZ_DATA_SPECIFIED,
+				pSourceData: std::ptr::null(),
+				ulSourceDataLen: 0,
+			});
+
+			// The plaintext is never longer than the ciphertext, which is as long as the RSA modulus.
+			let mut plaintext = vec![0_u8; ciphertext.len()];
+			let plaintext_len = private_key.decrypt(&mechanism, ciphertext, &mut plaintext).map_err(crate::implementation::err_external)?;
+			let plaintext_len = std::convert::TryInto::try_into(plaintext_len).expect("CK_ULONG -> usize");
+			plaintext.truncate(plaintext_len);
+			plaintext
+		},
+	};
+
+	Ok((plaintext.len(), plaintext))
+}
+
+fn derive_shared_secret_inner(location: &crate::implementation::Location, peer_public_key: &[u8]) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+	let (public_key, private_key) = load_inner(location)?.ok_or_else(|| crate::implementation::err_invalid_parameter("id", "not found"))?;
+
+	let ec_key = public_key.ec_key().map_err(|_| crate::implementation::err_invalid_parameter("id", "not an EC key pair"))?;
+
+	// Validate the peer public key here for both backends, so that an invalid point is reported as an invalid parameter
+	// instead of as an opaque error from the PKCS#11 library.
+	if peer_public_key.first() != Some(&0x04) {
+		return Err(crate::implementation::err_invalid_parameter("peer_public_key", "expected an uncompressed EC point"));
+	}
+	let peer_key = {
+		let mut big_num_context = openssl::bn::BigNumContext::new()?;
+		let point =
+			openssl::ec::EcPoint::from_bytes(ec_key.group(), peer_public_key, &mut big_num_context)
+			.map_err(|err| crate::implementation::err_invalid_parameter("peer_public_key", err))?;
+		let peer_key = openssl::ec::EcKey::from_public_key(ec_key.group(), &point)?;
+		peer_key.check_key().map_err(|err| crate::implementation::err_invalid_parameter("peer_public_key", err))?;
+		peer_key
+	};
+
+	match location {
+		crate::implementation::Location::Filesystem(_) => {
+			let peer_key = openssl::pkey::PKey::from_ec_key(peer_key)?;
+
+			let mut deriver = openssl::derive::Deriver::new(&private_key)?;
+			deriver.set_peer(&peer_key)?;
+			let shared_secret = deriver.derive_to_vec()?;
+			Ok(shared_secret)
+		},
+
+		// Derive with the PKCS#11 library directly instead of the openssl engine, since the engine does not implement ECDH.
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_slot = pkcs11_context.find_slot(uri).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+
+			let private_key = match pkcs11_session.get_key_pair(uri) {
+				Ok(pkcs11::KeyPair::Ec(_, private_key)) => private_key,
+
+				Ok(pkcs11::KeyPair::Rsa(_, _)) => return Err(crate::implementation::err_invalid_parameter("id", "not an EC key pair")),
+
+				Err(pkcs11::GetKeyError::KeyDoesNotExist) => return Err(crate::implementation::err_invalid_parameter("id", "not found")),
+
+				Err(err) => return Err(crate::implementation::err_external(err)),
+			};
+
+			let shared_secret = private_key.derive_shared_secret(peer_public_key).map_err(crate::implementation::err_external)?;
+			Ok(shared_secret)
+		},
+	}
+}
+
+/// Like `load_inner`, but only loads the public key.
+///
+/// Ed25519 key pairs in PKCS#11 cannot be loaded through the openssl engine, so their public key is built from the token's `CKA_EC_POINT` instead.
+fn load_public_inner(location: &crate::implementation::Location) -> Result<Option<openssl::pkey::PKey<openssl::pkey::Public>>, crate::KEYGEN_ERROR> {
+	#[cfg(ossl111)]
+	{
+		if let crate::implementation::Location::Pkcs11 { lib_path, uri } = location {
+			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_slot = pkcs11_context.find_slot(uri).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+
+			match pkcs11_session.get_public_key(uri) {
+				Ok(pkcs11::PublicKey::Ec(public_key)) => {
+					let curve = public_key.curve().map_err(crate::implementation::err_external)?;
+					if let openssl2::EcCurve::Ed25519 = curve {
+						let point = public_key.point().map_err(crate::implementation::err_external)?;
+						let public_key = openssl::pkey::PKey::public_key_from_raw_bytes(&point, openssl::pkey::Id::ED25519)?;
+						return Ok(Some(public_key));
+					}
+				},
+
+				Ok(pkcs11::PublicKey::Rsa(_)) => (),
+
+				Err(pkcs11::GetKeyError::KeyDoesNotExist) => return Ok(None),
+
+				Err(err) => return Err(crate::implementation::err_external(err)),
+			}
+		}
+	}
+
+	let public_key = load_inner(location)?.map(|(public_key, _)| public_key);
+	Ok(public_key)
+}
+
 fn load_inner(location: &crate::implementation::Location) ->
 	Result<
 		Option<(openssl::pkey::PKey<openssl::pkey::Public>, openssl::pkey::PKey<openssl::pkey::Private>)>,
@@ -344,8 +938,8 @@ fn load_inner(location: &crate::implementation::Location) ->
 	>
 {
 	match location {
-		crate::implementation::Location::Filesystem(path) => match std::fs::read(path) {
-			Ok(private_key_pem) => {
+		crate::implementation::Location::Filesystem(path) => match crate::kek::read(path)? {
+			Some(private_key_pem) => {
 				let private_key = openssl::pkey::PKey::private_key_from_pem(&private_key_pem)?;
 
 				// Copy private_key's public parameters into a new public key
@@ -355,19 +949,17 @@ fn load_inner(location: &crate::implementation::Location) ->
 				Ok(Some((public_key, private_key)))
 			},
 
-			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
-
-			Err(err) => Err(crate::implementation::err_external(err)),
+			None => Ok(None),
 		},
 
 		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
-			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_external)?;
-			let pkcs11_slot = pkcs11_context.find_slot(&uri.slot_identifier).map_err(crate::implementation::err_external)?;
-			let pkcs11_session = pkcs11_context.clone().open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_external)?;
+			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_slot = pkcs11_context.find_slot(uri).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_session = pkcs11_context.clone().open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
 
 			// Use PKCS#11 directly instead of the openssl engine, because PKCS#11 allows us to know the key doesn't exist,
 			// whereas openssl just returns `NULL` for all errors.
-			match pkcs11_session.get_key_pair(uri.object_label.as_ref().map(AsRef::as_ref)) {
+			match pkcs11_session.get_key_pair(uri) {
 				Ok(_) => (),
 
 				Err(pkcs11::GetKeyError::KeyDoesNotExist) => return Ok(None),
@@ -375,8 +967,9 @@ fn load_inner(location: &crate::implementation::Location) ->
 				Err(err) => return Err(crate::implementation::err_external(err)),
 			}
 
-			// PKCS#11 found the key pair, so now use the openssl engine
-			let key_id = uri.to_string();
+			// PKCS#11 found the key pair, so now use the openssl engine.
+			// The engine logs in with the PIN in the URI, so it must not be redacted.
+			let key_id = uri.to_unredacted_string();
 			let key_id = std::ffi::CString::new(key_id).map_err(|err| crate::implementation::err_invalid_parameter("id", err))?;
 
 			let mut engine = pkcs11_openssl_engine::load(pkcs11_context)?;
@@ -389,20 +982,70 @@ fn load_inner(location: &crate::implementation::Location) ->
 	}
 }
 
+fn algorithm(public_key: &openssl::pkey::PKeyRef<openssl::pkey::Public>) -> Result<String, crate::KEYGEN_ERROR> {
+	if let Ok(ec_key) = public_key.ec_key() {
+		let curve = ec_key.group().curve_name().and_then(openssl2::EcCurve::from_nid);
+		let algorithm = match curve {
+			Some(openssl2::EcCurve::NistP256) => "ec-p256",
+			Some(openssl2::EcCurve::NistP384) => "ec-p384",
+			Some(openssl2::EcCurve::NistP521) => "ec-p521",
+			_ => "ec",
+		};
+		Ok(algorithm.to_owned())
+	}
+	else if let Ok(rsa) = public_key.rsa() {
+		Ok(format!("rsa-{}", rsa.size() * 8))
+	}
+	else if is_ed25519(public_key) {
+		Ok("ed25519".to_owned())
+	}
+	else {
+		Err(crate::implementation::err_external("key is neither RSA nor EC"))
+	}
+}
+
+fn delete_inner(location: &crate::implementation::Location) -> Result<(), crate::KEYGEN_ERROR> {
+	match location {
+		crate::implementation::Location::Filesystem(path) => match std::fs::remove_file(path) {
+			Ok(()) => Ok(()),
+			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+			Err(err) => Err(crate::implementation::err_external(err)),
+		},
+
+		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
+			let label = uri.object_label.as_ref().ok_or_else(|| crate::implementation::err_invalid_parameter("id", "PKCS#11 URI does not identify an object"))?;
+
+			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_slot = pkcs11_context.find_slot(uri).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+
+			pkcs11_session.delete_key_pair(label).map_err(crate::implementation::err_external)?;
+
+			Ok(())
+		},
+	}
+}
+
 fn create_inner(location: &crate::implementation::Location, preferred_algorithms: &[PreferredAlgorithm]) -> Result<(), crate::KEYGEN_ERROR> {
 	match location {
 		crate::implementation::Location::Filesystem(path) => {
 			let preferred_algorithm = preferred_algorithms.iter().copied().next().ok_or_else(|| crate::implementation::err_invalid_parameter("preferred_algorithms", "none specified"))?;
 
 			let private_key = match preferred_algorithm {
-				PreferredAlgorithm::NistP256 => {
-					let mut group = openssl::ec::EcGroup::from_curve_name(openssl::nid::Nid::X9_62_PRIME256V1)?;
+				PreferredAlgorithm::Ec(curve) => {
+					let mut group = openssl::ec::EcGroup::from_curve_name(curve.as_nid())?;
 					group.set_asn1_flag(openssl::ec::Asn1Flag::NAMED_CURVE);
 					let ec_key = openssl::ec::EcKey::generate(&group)?;
 					let private_key = openssl::pkey::PKey::from_ec_key(ec_key)?;
 					private_key
 				},
 
+				#[cfg(ossl111)]
+				PreferredAlgorithm::Ed25519 => {
+					let private_key = openssl::pkey::PKey::generate_ed25519()?;
+					private_key
+				},
+
 				PreferredAlgorithm::Rsa2048 => {
 					let rsa = openssl::rsa::Rsa::generate(2048)?;
 					let private_key = openssl::pkey::PKey::from_rsa(rsa)?;
@@ -417,20 +1060,26 @@ fn create_inner(location: &crate::implementation::Location, preferred_algorithms
 			};
 
 			let private_key_pem = private_key.private_key_to_pem_pkcs8()?;
-			std::fs::write(&path, &private_key_pem).map_err(crate::implementation::err_external)?;
+			crate::kek::write(path, &private_key_pem)?;
 
 			Ok(())
 		},
 
 		crate::implementation::Location::Pkcs11 { lib_path, uri } => {
-			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_external)?;
-			let pkcs11_slot = pkcs11_context.find_slot(&uri.slot_identifier).map_err(crate::implementation::err_external)?;
-			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_external)?;
+			let pkcs11_context = pkcs11::Context::load(lib_path.clone()).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_slot = pkcs11_context.find_slot(uri).map_err(crate::implementation::err_backend_unavailable)?;
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, uri.pin.clone()).map_err(crate::implementation::err_backend_unavailable)?;
 
 			for preferred_algorithm in preferred_algorithms {
 				match preferred_algorithm {
-					PreferredAlgorithm::NistP256 =>
-						if pkcs11_session.clone().generate_ec_key_pair(openssl2::EcCurve::NistP256, uri.object_label.as_ref().map(AsRef::as_ref)).is_ok() {
+					PreferredAlgorithm::Ec(curve) =>
+						if pkcs11_session.clone().generate_ec_key_pair(*curve, uri.object_label.as_ref().map(AsRef::as_ref)).is_ok() {
+							return Ok(());
+						},
+
+					#[cfg(ossl111)]
+					PreferredAlgorithm::Ed25519 =>
+						if pkcs11_session.clone().generate_ec_key_pair(openssl2::EcCurve::Ed25519, uri.object_label.as_ref().map(AsRef::as_ref)).is_ok() {
 							return Ok(());
 						},
 
@@ -461,15 +1110,43 @@ fn create_inner(location: &crate::implementation::Location, preferred_algorithms
 	}
 }
 
+fn is_ed25519<T>(key: &openssl::pkey::PKeyRef<T>) -> bool {
+	#[cfg(ossl111)]
+	{
+		key.id() == openssl::pkey::Id::ED25519
+	}
+
+	#[cfg(not(ossl111))]
+	{
+		let _ = key;
+		false
+	}
+}
+
+#[cfg(ossl111)]
+fn raw_public_key(public_key: &openssl::pkey::PKeyRef<openssl::pkey::Public>) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+	Ok(public_key.raw_public_key()?)
+}
+
+#[cfg(not(ossl111))]
+fn raw_public_key(_public_key: &openssl::pkey::PKeyRef<openssl::pkey::Public>) -> Result<Vec<u8>, crate::KEYGEN_ERROR> {
+	unreachable!("is_ed25519 is always false without openssl 1.1.1")
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum PreferredAlgorithm {
-	NistP256,
+	Ec(openssl2::EcCurve),
+	#[cfg(ossl111)]
+	Ed25519,
 	Rsa2048,
 	Rsa4096,
 }
 
 impl PreferredAlgorithm {
-	unsafe fn from_str(s: *const std::os::raw::c_char) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+	/// Parses the `preferred_algorithms` parameter of `create_key_pair_if_not_exists`.
+	///
+	/// Components that are not recognized are an error if `reject_unrecognized` is set, and are skipped otherwise.
+	unsafe fn from_str(s: *const std::os::raw::c_char, reject_unrecognized: bool) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
 		fn add_if_not_exists<T>(v: &mut Vec<T>, element: T) where T: std::cmp::PartialEq {
 			if v.iter().any(|existing| existing == &element) {
 				return;
@@ -479,7 +1156,7 @@ impl PreferredAlgorithm {
 		}
 
 		if s.is_null() {
-			return Ok(vec![PreferredAlgorithm::NistP256, PreferredAlgorithm::Rsa2048]);
+			return Ok(vec![PreferredAlgorithm::Ec(openssl2::EcCurve::NistP256), PreferredAlgorithm::Rsa2048]);
 		}
 
 		let s = std::ffi::CStr::from_ptr(s);
@@ -488,19 +1165,147 @@ impl PreferredAlgorithm {
 		for component in s.split(':') {
 			match component {
 				"*" => {
-					add_if_not_exists(&mut result, PreferredAlgorithm::NistP256);
+					add_if_not_exists(&mut result, PreferredAlgorithm::Ec(openssl2::EcCurve::NistP256));
 					add_if_not_exists(&mut result, PreferredAlgorithm::Rsa2048);
 				},
 
-				"ec-p256" => add_if_not_exists(&mut result, PreferredAlgorithm::NistP256),
+				"ec-p256" => add_if_not_exists(&mut result, PreferredAlgorithm::Ec(openssl2::EcCurve::NistP256)),
+
+				"ec-p384" => add_if_not_exists(&mut result, PreferredAlgorithm::Ec(openssl2::EcCurve::NistP384)),
+
+				"ec-p521" => add_if_not_exists(&mut result, PreferredAlgorithm::Ec(openssl2::EcCurve::NistP521)),
+
+				#[cfg(ossl111)]
+				"ed25519" => add_if_not_exists(&mut result, PreferredAlgorithm::Ed25519),
+
+				// Ed25519 needs openssl 1.1.1. Without it, the algorithm is recognized but cannot be used, so it is skipped.
+				#[cfg(not(ossl111))]
+				"ed25519" => (),
 
 				"rsa-2048" => add_if_not_exists(&mut result, PreferredAlgorithm::Rsa2048),
 
 				"rsa-4096" => add_if_not_exists(&mut result, PreferredAlgorithm::Rsa4096),
 
-				_ => (),
+				component => if reject_unrecognized {
+					return Err(format!("unrecognized algorithm {:?}", component).into());
+				},
 			}
 		}
 		Ok(result)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	fn preferred_algorithms(s: &str) -> Result<Vec<super::PreferredAlgorithm>, Box<dyn std::error::Error>> {
+		let s = std::ffi::CString::new(s).unwrap();
+		unsafe { super::PreferredAlgorithm::from_str(s.as_ptr(), true) }
+	}
+
+	#[test]
+	fn parse_preferred_algorithms() {
+		assert_eq!(
+			preferred_algorithms("ec-p384:ec-p521:rsa-4096:ec-p384").unwrap(),
+			[
+				super::PreferredAlgorithm::Ec(openssl2::EcCurve::NistP384),
+				super::PreferredAlgorithm::Ec(openssl2::EcCurve::NistP521),
+				super::PreferredAlgorithm::Rsa4096,
+			],
+		);
+
+		assert_eq!(
+			preferred_algorithms("*").unwrap(),
+			[super::PreferredAlgorithm::Ec(openssl2::EcCurve::NistP256), super::PreferredAlgorithm::Rsa2048],
+		);
+
+		assert_eq!(
+			unsafe { super::PreferredAlgorithm::from_str(std::ptr::null(), true) }.unwrap(),
+			[super::PreferredAlgorithm::Ec(openssl2::EcCurve::NistP256), super::PreferredAlgorithm::Rsa2048],
+		);
+	}
+
+	#[test]
+	fn parse_preferred_algorithms_ed25519() {
+		let expected = vec![
+			#[cfg(ossl111)]
+			super::PreferredAlgorithm::Ed25519,
+			super::PreferredAlgorithm::Rsa2048,
+		];
+		assert_eq!(preferred_algorithms("ed25519:rsa-2048").unwrap(), expected);
+	}
+
+	#[test]
+	fn parse_preferred_algorithms_unrecognized() {
+		for &s in &["ec-p256:dsa", "", "ec-p256:", "EC-P256", "ed448"] {
+			assert!(preferred_algorithms(s).is_err(), "{:?} was not rejected", s);
+		}
+	}
+
+	#[test]
+	fn parse_preferred_algorithms_unrecognized_2_0_0_0() {
+		let s = std::ffi::CString::new("dsa:ec-p256:ed448").unwrap();
+		assert_eq!(
+			unsafe { super::PreferredAlgorithm::from_str(s.as_ptr(), false) }.unwrap(),
+			[super::PreferredAlgorithm::Ec(openssl2::EcCurve::NistP256)],
+		);
+	}
+
+	#[test]
+	fn rsa_pss_sign_verify() {
+		let dir = tempfile::tempdir().unwrap();
+		let location = crate::implementation::Location::Filesystem(dir.path().join("key"));
+
+		super::create_inner(&location, &[super::PreferredAlgorithm::Rsa2048]).unwrap();
+		let public_key = super::load_public_inner(&location).unwrap().unwrap();
+
+		let message = b"message";
+
+		for &(mask_generation_function, message_digest) in &[
+			(crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA1, openssl::hash::MessageDigest::sha1()),
+			(crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA256, openssl::hash::MessageDigest::sha256()),
+			(crate::KEYGEN_SIGN_RSA_PSS_MASK_GENERATION_FUNCTION_SHA512, openssl::hash::MessageDigest::sha512()),
+		] {
+			let digest = openssl::hash::hash(message_digest, message).unwrap();
+
+			for &salt_len in &[0, 20, 32] {
+				let parameters = crate::KEYGEN_SIGN_RSA_PSS_PARAMETERS { mask_generation_function, salt_len };
+				let parameters: *const std::ffi::c_void = &parameters as *const _ as _;
+
+				// Signed by libaziot-keys, verified by openssl
+				let (signature_len, signature) = unsafe { super::sign(&location, crate::KEYGEN_SIGN_MECHANISM_RSA_PSS, parameters, &digest) }.unwrap();
+				assert_eq!(signature_len, 256);
+				assert_eq!(signature.len(), 256);
+
+				let verify_with_openssl = |salt_len: usize| {
+					let mut verifier = openssl::sign::Verifier::new(message_digest, &public_key).unwrap();
+					verifier.set_rsa_padding(openssl::rsa::Padding::PKCS1_PSS).unwrap();
+					verifier.set_rsa_mgf1_md(message_digest).unwrap();
+					verifier.set_rsa_pss_saltlen(openssl::sign::RsaPssSaltlen::custom(std::convert::TryInto::try_into(salt_len).unwrap())).unwrap();
+					verifier.verify_oneshot(&signature, message).unwrap_or(false)
+				};
+				assert!(verify_with_openssl(salt_len));
+				assert!(!verify_with_openssl(salt_len + 1));
+
+				// Signed by openssl, verified by libaziot-keys
+				let private_key = openssl::pkey::PKey::private_key_from_pem(&std::fs::read(dir.path().join("key")).unwrap()).unwrap();
+				let mut signer = openssl::sign::Signer::new(message_digest, &private_key).unwrap();
+				signer.set_rsa_padding(openssl::rsa::Padding::PKCS1_PSS).unwrap();
+				signer.set_rsa_mgf1_md(message_digest).unwrap();
+				signer.set_rsa_pss_saltlen(openssl::sign::RsaPssSaltlen::custom(std::convert::TryInto::try_into(salt_len).unwrap())).unwrap();
+				let signature = signer.sign_oneshot_to_vec(message).unwrap();
+
+				assert!(unsafe { super::verify(&location, crate::KEYGEN_SIGN_MECHANISM_RSA_PSS, parameters, &digest, &signature) }.unwrap());
+
+				let mut wrong_signature = signature;
+				wrong_signature[0] ^= 0x01;
+				assert!(!unsafe { super::verify(&location, crate::KEYGEN_SIGN_MECHANISM_RSA_PSS, parameters, &digest, &wrong_signature) }.unwrap());
+			}
+
+			// The digest must have been computed with the hash algorithm of the mask generation function.
+			let parameters = crate::KEYGEN_SIGN_RSA_PSS_PARAMETERS { mask_generation_function, salt_len: 0 };
+			let parameters: *const std::ffi::c_void = &parameters as *const _ as _;
+			let err = unsafe { super::sign(&location, crate::KEYGEN_SIGN_MECHANISM_RSA_PSS, parameters, &digest[1..]) }.unwrap_err();
+			assert_eq!(err, crate::KEYGEN_ERROR_INVALID_PARAMETER);
+		}
+	}
+}
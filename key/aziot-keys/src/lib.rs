@@ -25,6 +25,7 @@
 //! All calls to [`KEYGEN_get_function_list`] or any function in [`KEYGEN_FUNCTION_LIST`] are serialized, ie a function will not be called
 //! while another function is running. However, it is not guaranteed that all function calls will be made from the same operating system thread.
 //! Thus, implementations do not need to worry about locking to prevent concurrent access, but should also not store data in thread-local storage.
+//! Implementations that do support concurrent calls can declare so with [`KEYGEN_CAPABILITY_THREAD_SAFE`], in which case callers may stop serializing them.
 
 // DEVNOTE:
 //
@@ -44,6 +45,8 @@
 
 
 
+mod index;
+mod kek;
 mod key;
 mod key_pair;
 mod implementation;
@@ -75,6 +78,14 @@ pub const KEYGEN_ERROR_INVALID_PARAMETER: KEYGEN_ERROR = KEYGEN_ERROR { inner: 2
 /// The library encountered an error with an external resource, such as an I/O error or RPC error.
 pub const KEYGEN_ERROR_EXTERNAL: KEYGEN_ERROR = KEYGEN_ERROR { inner: 3 };
 
+/// The library could not reach the backend that holds the key, such as a PKCS#11 token that could not be opened or logged in to.
+///
+/// Unlike `KEYGEN_ERROR_EXTERNAL`, the operation may succeed if retried later.
+///
+/// This is only returned if API version 2.1.0.0 was requested from `KEYGEN_get_function_list`. Any function that can return `KEYGEN_ERROR_EXTERNAL`
+/// can then also return this. Under API version 2.0.0.0, these errors are returned as `KEYGEN_ERROR_EXTERNAL` instead.
+pub const KEYGEN_ERROR_BACKEND_UNAVAILABLE: KEYGEN_ERROR = KEYGEN_ERROR { inner: 4 };
+
 
 /// Represents the version of the API exported by this library.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -84,6 +95,9 @@ pub struct KEYGEN_VERSION { inner: std::os::raw::c_uint }
 /// Version 2.0.0.0
 pub const KEYGEN_VERSION_2_0_0_0: KEYGEN_VERSION = KEYGEN_VERSION { inner: 0x02_00_00_00 };
 
+/// Version 2.1.0.0
+pub const KEYGEN_VERSION_2_1_0_0: KEYGEN_VERSION = KEYGEN_VERSION { inner: 0x02_01_00_00 };
+
 
 /// The base struct of all of function lists.
 #[derive(Debug)]
@@ -206,7 +220,8 @@ pub struct KEYGEN_FUNCTION_LIST_2_0_0_0 {
 
 	/// Verifies the signature of the given digest using the key identified by the specified `id`.
 	///
-	/// `mechanism` must be set to one of the `KEYGEN_SIGN_MECHANISM_*` constants.
+	/// `mechanism` must be set to one of the `KEYGEN_SIGN_MECHANISM_*` constants, and `parameters` must be set the same way as for `sign`.
+	/// The signature of a key pair is verified with its public key.
 	///
 	/// If the function returns `KEYGEN_SUCCESS`, then `ok` is set to 0 if the signature is invalid and non-zero if the signature is valid.
 	///
@@ -253,6 +268,298 @@ pub struct KEYGEN_FUNCTION_LIST_2_0_0_0 {
 #[no_mangle]
 pub extern "C" fn cbindgen_unused_KEYGEN_FUNCTION_LIST_2_0_0_0() -> KEYGEN_FUNCTION_LIST_2_0_0_0 { unimplemented!(); }
 
+/// The specific implementation of [`KEYGEN_FUNCTION_LIST`] for API version 2.1.0.0
+///
+/// This version contains all the functions of [`KEYGEN_FUNCTION_LIST_2_0_0_0`] in the same order and with the same semantics,
+/// followed by the functions that were added in this version.
+///
+/// The functions that were added in this version are optional. An implementation that does not support one of them sets it to `NULL`,
+/// so callers must check for `NULL` before calling them.
+#[derive(Debug)]
+#[repr(C)]
+pub struct KEYGEN_FUNCTION_LIST_2_1_0_0 {
+	/// The value of `base.version` must be [`KEYGEN_VERSION_2_1_0_0`].
+	pub base: KEYGEN_FUNCTION_LIST,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::set_parameter`]
+	pub set_parameter: unsafe extern "C" fn(
+		name: *const std::os::raw::c_char,
+		value: *const std::os::raw::c_char,
+	) -> KEYGEN_ERROR,
+
+	/// Create or load a key pair identified by the specified `id`.
+	///
+	/// This is the same as [`KEYGEN_FUNCTION_LIST_2_0_0_0::create_key_pair_if_not_exists`], except that the valid components of `preferred_algorithms`
+	/// are `"ec-p256"` for secp256r1, `"ec-p384"` for secp384r1, `"ec-p521"` for secp521r1, `"ed25519"` for Ed25519,
+	/// `"rsa-2048"` for 2048-bit RSA, `"rsa-4096"` for 4096-bit RSA, and `"*"`, and that a component that is not one of these
+	/// must be rejected instead of ignored.
+	///
+	/// If the implementation is unable to use a valid component's algorithm to generate a key pair, it should ignore that component and try the next one.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `id` is NULL.
+	///   - `preferred_algorithms` contains a component that is not one of the valid components.
+	///
+	/// - `KEYGEN_ERROR_EXTERNAL`
+	pub create_key_pair_if_not_exists: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		preferred_algorithms: *const std::os::raw::c_char,
+	) -> KEYGEN_ERROR,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::load_key_pair`]
+	pub load_key_pair: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+	) -> KEYGEN_ERROR,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::get_key_pair_parameter`]
+	pub get_key_pair_parameter: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		type_: KEYGEN_KEY_PAIR_PARAMETER_TYPE,
+		value: *mut std::os::raw::c_uchar,
+		value_len: *mut usize,
+	) -> KEYGEN_ERROR,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::create_key_if_not_exists`]
+	pub create_key_if_not_exists: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		length: usize,
+	) -> KEYGEN_ERROR,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::import_key`]
+	pub import_key: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		bytes: *const u8,
+		bytes_len: usize,
+	) -> KEYGEN_ERROR,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::sign`]
+	pub sign: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		mechanism: KEYGEN_SIGN_MECHANISM,
+		parameters: *const std::ffi::c_void,
+		digest: *const std::os::raw::c_uchar,
+		digest_len: usize,
+		signature: *mut std::os::raw::c_uchar,
+		signature_len: *mut usize,
+	) -> KEYGEN_ERROR,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::verify`]
+	pub verify: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		mechanism: KEYGEN_SIGN_MECHANISM,
+		parameters: *const std::ffi::c_void,
+		digest: *const std::os::raw::c_uchar,
+		digest_len: usize,
+		signature: *const std::os::raw::c_uchar,
+		signature_len: usize,
+		ok: *mut std::os::raw::c_int,
+	) -> KEYGEN_ERROR,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::encrypt`]
+	pub encrypt: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		mechanism: KEYGEN_ENCRYPT_MECHANISM,
+		parameters: *const std::ffi::c_void,
+		plaintext: *const std::os::raw::c_uchar,
+		plaintext_len: usize,
+		ciphertext: *mut std::os::raw::c_uchar,
+		ciphertext_len: *mut usize,
+	) -> KEYGEN_ERROR,
+
+	/// See [`KEYGEN_FUNCTION_LIST_2_0_0_0::decrypt`]
+	pub decrypt: unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		mechanism: KEYGEN_ENCRYPT_MECHANISM,
+		parameters: *const std::ffi::c_void,
+		ciphertext: *const std::os::raw::c_uchar,
+		ciphertext_len: usize,
+		plaintext: *mut std::os::raw::c_uchar,
+		plaintext_len: *mut usize,
+	) -> KEYGEN_ERROR,
+
+	/// Delete the key pair identified by the specified `id`.
+	///
+	/// Both the public and private components of the key pair are deleted.
+	/// It is not an error if the key pair does not exist.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `id` is NULL.
+	///   - `id` identifies a key pair that cannot be deleted, such as one that was preloaded into the implementation.
+	///
+	/// - `KEYGEN_ERROR_EXTERNAL`
+	pub delete_key_pair: Option<unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+	) -> KEYGEN_ERROR>,
+
+	/// Delete the key identified by the specified `id`.
+	///
+	/// It is not an error if the key does not exist.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `id` is NULL.
+	///   - `id` identifies a key that cannot be deleted, such as one that was preloaded into the implementation.
+	///
+	/// - `KEYGEN_ERROR_EXTERNAL`
+	pub delete_key: Option<unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+	) -> KEYGEN_ERROR>,
+
+	/// Lists the key pairs known to this implementation.
+	///
+	/// The list is written to `list` as a sequence of entries, one per key pair. Each entry is three NUL-terminated strings:
+	///
+	/// - The ID of the key pair.
+	/// - The algorithm of the key pair, such as `"ec-p256"` or `"rsa-2048"`.
+	/// - The backend that stores the key pair. The values are defined by the implementation. This implementation uses
+	///   `"filesystem"`, `"pkcs11"` and `"preloaded"`.
+	///
+	/// `list` may be `NULL`, in which case only `list_len` is set to the length required to hold the list. Thus callers can call this function
+	/// twice, first with `list` set to `NULL` to learn the required length, and then with `list` set to a buffer of that length.
+	///
+	/// Implementations may not be able to list key pairs that were created before they started keeping track of them, until those key pairs are loaded again with `load_key_pair` or `create_key_pair_if_not_exists`.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `list` is not `NULL` and `list_len` is too small to hold the list.
+	///   - `list_len` is `NULL`.
+	///
+	/// - `KEYGEN_ERROR_EXTERNAL`
+	pub list_key_pairs: Option<unsafe extern "C" fn(
+		list: *mut std::os::raw::c_char,
+		list_len: *mut usize,
+	) -> KEYGEN_ERROR>,
+
+	/// Lists the keys known to this implementation.
+	///
+	/// The list is written in the same format as for [`KEYGEN_FUNCTION_LIST_2_1_0_0::list_key_pairs`]. The algorithm of a key
+	/// is its length in bits, such as `"symmetric-256"`.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `list` is not `NULL` and `list_len` is too small to hold the list.
+	///   - `list_len` is `NULL`.
+	///
+	/// - `KEYGEN_ERROR_EXTERNAL`
+	pub list_keys: Option<unsafe extern "C" fn(
+		list: *mut std::os::raw::c_char,
+		list_len: *mut usize,
+	) -> KEYGEN_ERROR>,
+
+	/// Derives a shared secret via ECDH from the EC key pair identified by the specified `id` and the given peer public key.
+	///
+	/// `peer_public_key` must be a point on the same curve as the key pair, encoded in uncompressed form (`0x04 || X || Y`).
+	/// The shared secret is the X coordinate of the derived point. No KDF is applied to it.
+	///
+	/// `shared_secret` may be `NULL`, in which case only `shared_secret_len` is set to the length of the shared secret. Thus callers can call this function
+	/// twice, first with `shared_secret` set to `NULL` to learn the required length, and then with `shared_secret` set to a buffer of that length.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `id` is NULL.
+	///   - The key pair specified by `id` does not exist, or is not an EC key pair.
+	///   - `peer_public_key` is NULL, or is not a point on the curve of the key pair.
+	///   - `shared_secret` is not `NULL` and `shared_secret_len` is too small to hold the shared secret.
+	///   - `shared_secret_len` is `NULL`.
+	///
+	/// - `KEYGEN_ERROR_EXTERNAL`
+	pub derive_shared_secret: Option<unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		peer_public_key: *const std::os::raw::c_uchar,
+		peer_public_key_len: usize,
+		shared_secret: *mut std::os::raw::c_uchar,
+		shared_secret_len: *mut usize,
+	) -> KEYGEN_ERROR>,
+
+	/// Rotates the symmetric key identified by the specified `id`.
+	///
+	/// A new random key of the same length becomes the active version of the key, and the previous versions are retained.
+	/// Subsequent encrypt operations use the active version. Ciphertexts record the version that created them,
+	/// so decrypt operations continue to work with ciphertexts created by previous versions.
+	///
+	/// Importing different bytes over an existing key with `import_key` also rotates it, with the imported bytes as the new active version.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `id` is NULL.
+	///   - The key specified by `id` does not exist.
+	///   - `id` identifies a key that cannot be rotated, such as one that was preloaded into the implementation.
+	///
+	/// - `KEYGEN_ERROR_EXTERNAL`
+	pub rotate_key: Option<unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+	) -> KEYGEN_ERROR>,
+
+	/// Decrypts the given ciphertext with the symmetric key identified by the specified `id`, and encrypts the result with the active version of the key.
+	///
+	/// This is used to migrate ciphertexts created by previous versions of the key to its active version after it has been rotated.
+	/// `mechanism` and `parameters` are used for both the decrypt and the encrypt operation, and must be set the same way as for `encrypt`.
+	///
+	/// `new_ciphertext` may be `NULL`, in which case only `new_ciphertext_len` is set to the length of the new ciphertext. Thus callers can call this function
+	/// twice, first with `new_ciphertext` set to `NULL` to learn the required length, and then with `new_ciphertext` set to a buffer of that length.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `id` is NULL.
+	///   - The key specified by `id` does not exist.
+	///   - `mechanism` is not a valid encrypt mechanism for the key specified by `id`.
+	///   - `ciphertext` is NULL, or was not created by any version of the key.
+	///   - `new_ciphertext` is not `NULL` and `new_ciphertext_len` is too small to hold the new ciphertext.
+	///   - `new_ciphertext_len` is `NULL`.
+	///
+	/// - `KEYGEN_ERROR_EXTERNAL`
+	pub rewrap: Option<unsafe extern "C" fn(
+		id: *const std::os::raw::c_char,
+		mechanism: KEYGEN_ENCRYPT_MECHANISM,
+		parameters: *const std::ffi::c_void,
+		ciphertext: *const std::os::raw::c_uchar,
+		ciphertext_len: usize,
+		new_ciphertext: *mut std::os::raw::c_uchar,
+		new_ciphertext_len: *mut usize,
+	) -> KEYGEN_ERROR>,
+
+	/// Gets the capabilities of this implementation.
+	///
+	/// `capabilities` is set to a combination of the `KEYGEN_CAPABILITY_*` flags. Unrecognized flags must be ignored by the caller,
+	/// so that future versions of this API can add new capabilities.
+	///
+	/// If this function is `NULL`, the implementation has none of the capabilities.
+	///
+	/// # Errors
+	///
+	/// - `KEYGEN_ERROR_INVALID_PARAMETER`:
+	///   - `capabilities` is `NULL`.
+	pub get_capabilities: Option<unsafe extern "C" fn(
+		capabilities: *mut KEYGEN_CAPABILITIES,
+	) -> KEYGEN_ERROR>,
+}
+
+#[no_mangle]
+pub extern "C" fn cbindgen_unused_KEYGEN_FUNCTION_LIST_2_1_0_0() -> KEYGEN_FUNCTION_LIST_2_1_0_0 { unimplemented!(); }
+
+
+/// The capabilities of an implementation, returned by [`KEYGEN_FUNCTION_LIST_2_1_0_0::get_capabilities`].
+///
+/// This is a combination of the `KEYGEN_CAPABILITY_*` flags.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct KEYGEN_CAPABILITIES { inner: std::os::raw::c_uint }
+
+/// The functions of the implementation may be called concurrently from multiple threads.
+///
+/// Callers are not required to make use of this, so implementations that declare it must still work if their calls are serialized.
+pub const KEYGEN_CAPABILITY_THREAD_SAFE: KEYGEN_CAPABILITIES = KEYGEN_CAPABILITIES { inner: 0x01 };
+
 
 /// Get the list of functions for operations corresponding to the specified version.
 ///
@@ -285,7 +592,7 @@ pub struct KEYGEN_KEY_PAIR_PARAMETER_TYPE { inner: std::os::raw::c_uint }
 /// The value returned by `get_key_pair_parameter` will be one of the `KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_*` constants.
 pub const KEYGEN_KEY_PAIR_PARAMETER_TYPE_ALGORITHM: KEYGEN_KEY_PAIR_PARAMETER_TYPE = KEYGEN_KEY_PAIR_PARAMETER_TYPE { inner: 1 };
 
-/// Used as the parameter type with `get_key_pair_parameter` to get the curve OID of an EC key.
+/// Used as the parameter type with `get_key_pair_parameter` to get the curve OID of an EC or Ed25519 key.
 ///
 /// The value returned by `get_key_pair_parameter` will be a byte buffer containing a DER-encoded OID.
 pub const KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_CURVE_OID: KEYGEN_KEY_PAIR_PARAMETER_TYPE = KEYGEN_KEY_PAIR_PARAMETER_TYPE { inner: 2 };
@@ -293,6 +600,8 @@ pub const KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_CURVE_OID: KEYGEN_KEY_PAIR_PARAMETER
 /// Used as the parameter type with `get_key_pair_parameter` to get the point of an EC key.
 ///
 /// The value returned by `get_key_pair_parameter` will be a byte buffer containing a DER-encoded octet string in RFC 5490 format.
+///
+/// For an Ed25519 key, the value is the 32-byte public key as defined in RFC 8032.
 pub const KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_POINT: KEYGEN_KEY_PAIR_PARAMETER_TYPE = KEYGEN_KEY_PAIR_PARAMETER_TYPE { inner: 3 };
 
 /// Used as the parameter type with `get_key_pair_parameter` to get the modulus of an RSA key.
@@ -314,6 +623,9 @@ pub const KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_EC: KEYGEN_KEY_PAIR_PARAMETER_ALGO
 
 pub const KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_RSA: KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM = KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM { inner: 2 };
 
+/// Ed25519. The curve OID and point can be read with the `KEYGEN_KEY_PAIR_PARAMETER_TYPE_EC_*` parameter types.
+pub const KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM_ED25519: KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM = KEYGEN_KEY_PAIR_PARAMETER_ALGORITHM { inner: 3 };
+
 
 /// Represents the mechanism used for a sign operation.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -332,6 +644,12 @@ pub const KEYGEN_SIGN_MECHANISM_RSA_PSS: KEYGEN_SIGN_MECHANISM = KEYGEN_SIGN_MEC
 /// HMAC-SHA256
 pub const KEYGEN_SIGN_MECHANISM_HMAC_SHA256: KEYGEN_SIGN_MECHANISM = KEYGEN_SIGN_MECHANISM { inner: 4 };
 
+/// EdDSA with Ed25519 (pure Ed25519 as defined in RFC 8032)
+///
+/// Only valid for Ed25519 key pairs. EdDSA cannot sign pre-hashed digests, so the `digest` given to `sign` and `verify` must be the whole message.
+/// The parameters must be NULL.
+pub const KEYGEN_SIGN_MECHANISM_EDDSA: KEYGEN_SIGN_MECHANISM = KEYGEN_SIGN_MECHANISM { inner: 5 };
+
 
 /// Represents the hash algorithm used to create the message digest given to a RSA PKCS1 sign operation.
 ///
@@ -362,6 +680,8 @@ pub const KEYGEN_RSA_PKCS1_MESSAGE_DIGEST_SHA512: KEYGEN_RSA_PKCS1_MESSAGE_DIGES
 
 
 /// Holds parameters for a sign operation with the [`KEYGEN_SIGN_MECHANISM_RSA_PSS`] mechanism.
+///
+/// The digest given to the sign operation must have been computed with the same hash algorithm as the one used by the mask generation function.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub struct KEYGEN_SIGN_RSA_PSS_PARAMETERS {
@@ -406,6 +726,16 @@ pub struct KEYGEN_ENCRYPT_MECHANISM { inner: std::os::raw::c_uint }
 /// AEAD (eg AES-256-GCM)
 pub const KEYGEN_ENCRYPT_MECHANISM_AEAD: KEYGEN_ENCRYPT_MECHANISM = KEYGEN_ENCRYPT_MECHANISM { inner: 1 };
 
+/// RSA-OAEP with SHA-256 as the hash function of both the label and MGF1, and an empty label.
+///
+/// Only valid for RSA key pairs. Encrypts with the public key and decrypts with the private key. The parameters must be NULL.
+pub const KEYGEN_ENCRYPT_MECHANISM_RSA_OAEP: KEYGEN_ENCRYPT_MECHANISM = KEYGEN_ENCRYPT_MECHANISM { inner: 2 };
+
+/// AEAD (eg AES-256-GCM) with a random nonce generated by the library for every encrypt operation.
+///
+/// The nonce is stored in the ciphertext, so decrypt only needs the same AAD that was used to encrypt.
+pub const KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE: KEYGEN_ENCRYPT_MECHANISM = KEYGEN_ENCRYPT_MECHANISM { inner: 3 };
+
 
 /// Holds parameters for an encrypt operation with the [`KEYGEN_ENCRYPT_MECHANISM_AEAD`] mechanism.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -420,6 +750,17 @@ pub struct KEYGEN_ENCRYPT_AEAD_PARAMETERS {
 #[no_mangle]
 pub extern "C" fn cbindgen_unused_KEYGEN_ENCRYPT_AEAD_PARAMETERS() -> KEYGEN_ENCRYPT_AEAD_PARAMETERS { unimplemented!(); }
 
+/// Holds parameters for an encrypt operation with the [`KEYGEN_ENCRYPT_MECHANISM_AEAD_GENERATED_NONCE`] mechanism.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS {
+	pub aad: *const std::os::raw::c_uchar,
+	pub aad_len: usize,
+}
+
+#[no_mangle]
+pub extern "C" fn cbindgen_unused_KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS() -> KEYGEN_ENCRYPT_AEAD_GENERATED_NONCE_PARAMETERS { unimplemented!(); }
+
 
 /// Catches the error, if any, and returns it. Otherwise returns [`KEYGEN_SUCCESS`].
 fn r#catch(f: impl FnOnce() -> Result<(), KEYGEN_ERROR>) -> KEYGEN_ERROR {
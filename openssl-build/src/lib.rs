@@ -1,6 +1,6 @@
 #![deny(rust_2018_idioms, warnings)]
 
-/// Emits `ossl110` and `ossl111` cfgs based on the version of openssl.
+/// Emits `ossl110`, `ossl111` and `ossl300` cfgs based on the version of openssl.
 pub fn define_version_number_cfg() {
 	let openssl_version = std::env::var("DEP_OPENSSL_VERSION_NUMBER").expect("DEP_OPENSSL_VERSION_NUMBER must have been set by openssl-sys");
 	let openssl_version = u64::from_str_radix(&openssl_version, 16).expect("DEP_OPENSSL_VERSION_NUMBER must have been set to a valid integer");
@@ -13,6 +13,10 @@ pub fn define_version_number_cfg() {
 		if openssl_version >= 0x01_01_01_00_0 {
 			println!("cargo:rustc-cfg=ossl111");
 		}
+
+		if openssl_version >= 0x03_00_00_00_0 {
+			println!("cargo:rustc-cfg=ossl300");
+		}
 	}
 }
 
@@ -4,16 +4,39 @@
 pub struct EVP_PKEY_METHOD([u8; 0]);
 
 pub const EVP_PKEY_FLAG_AUTOARGLEN: std::os::raw::c_int = 0x0002;
+pub const EVP_PKEY_FLAG_SIGCTX_CUSTOM: std::os::raw::c_int = 0x0004;
 
 extern "C" {
 	pub fn EVP_PKEY_CTX_get0_pkey(
 		ctx: *mut openssl_sys::EVP_PKEY_CTX,
 	) -> *mut openssl_sys::EVP_PKEY;
 
+	#[cfg(ossl300)]
+	pub fn EVP_PKEY_get_ex_data(
+		key: *const openssl_sys::EVP_PKEY,
+		idx: std::os::raw::c_int,
+	) -> *mut std::ffi::c_void;
+	#[cfg(ossl300)]
+	pub fn EVP_PKEY_set_ex_data(
+		key: *mut openssl_sys::EVP_PKEY,
+		idx: std::os::raw::c_int,
+		arg: *mut std::ffi::c_void,
+	) -> std::os::raw::c_int;
+
 	pub fn EVP_PKEY_CTX_get_signature_md_f(
 		ctx: *mut openssl_sys::EVP_PKEY_CTX,
 		pmd: *mut *const openssl_sys::EVP_MD,
 	) -> std::os::raw::c_int;
+
+	#[cfg(all(ossl111, not(ossl300)))]
+	pub fn EVP_MD_CTX_pkey_ctx(
+		ctx: *const openssl_sys::EVP_MD_CTX,
+	) -> *mut openssl_sys::EVP_PKEY_CTX;
+	#[cfg(ossl300)]
+	#[link_name = "EVP_MD_CTX_get_pkey_ctx"]
+	pub fn EVP_MD_CTX_pkey_ctx(
+		ctx: *const openssl_sys::EVP_MD_CTX,
+	) -> *mut openssl_sys::EVP_PKEY_CTX;
 }
 
 extern "C" {
@@ -29,6 +52,29 @@ extern "C" {
 		flags: std::os::raw::c_int,
 	) -> *mut EVP_PKEY_METHOD;
 
+	#[cfg(ossl111)]
+	pub fn EVP_PKEY_meth_get_digestsign(
+		pmeth: *const EVP_PKEY_METHOD,
+		digestsign: *mut Option<unsafe extern "C" fn(
+			ctx: *mut openssl_sys::EVP_MD_CTX,
+			sig: *mut std::os::raw::c_uchar,
+			siglen: *mut usize,
+			tbs: *const std::os::raw::c_uchar,
+			tbslen: usize,
+		) -> std::os::raw::c_int>,
+	);
+	#[cfg(ossl111)]
+	pub fn EVP_PKEY_meth_set_digestsign(
+		pmeth: *mut EVP_PKEY_METHOD,
+		digestsign: Option<unsafe extern "C" fn(
+			ctx: *mut openssl_sys::EVP_MD_CTX,
+			sig: *mut std::os::raw::c_uchar,
+			siglen: *mut usize,
+			tbs: *const std::os::raw::c_uchar,
+			tbslen: usize,
+		) -> std::os::raw::c_int>,
+	);
+
 	#[cfg(ossl110)]
 	pub fn EVP_PKEY_meth_get_sign(
 		pmeth: *const EVP_PKEY_METHOD,
@@ -187,6 +187,23 @@ pub fn foreign_type_into_ptr<T>(value: T) -> *mut <T as foreign_types_shared::Fo
 	result
 }
 
+/// The message digest to use when signing certificates and CSRs with the given key.
+///
+/// This is SHA-256 for every key type except Ed25519, which signs the whole message and so must not be given a digest.
+pub fn signature_message_digest<T>(key: &openssl::pkey::PKeyRef<T>) -> openssl::hash::MessageDigest {
+	#[cfg(ossl111)]
+	{
+		if key.id() == openssl::pkey::Id::ED25519 {
+			return openssl::hash::MessageDigest::null();
+		}
+	}
+
+	#[cfg(not(ossl111))]
+	let _ = key;
+
+	openssl::hash::MessageDigest::sha256()
+}
+
 /// This trait defines the getter and setter for this type's ex data.
 pub trait ExDataAccessors {
 	const GET_FN: unsafe extern "C" fn(this: *const Self, idx: std::os::raw::c_int) -> *mut std::ffi::c_void;
@@ -216,6 +233,14 @@ impl ExDataAccessors for openssl_sys::ENGINE {
 		openssl_sys2::ENGINE_set_ex_data;
 }
 
+#[cfg(ossl300)]
+impl ExDataAccessors for openssl_sys::EVP_PKEY {
+	const GET_FN: unsafe extern "C" fn(this: *const Self, idx: std::os::raw::c_int) -> *mut std::ffi::c_void =
+		openssl_sys2::EVP_PKEY_get_ex_data;
+	const SET_FN: unsafe extern "C" fn(this: *mut Self, idx: std::os::raw::c_int, arg: *mut std::ffi::c_void) -> std::os::raw::c_int =
+		openssl_sys2::EVP_PKEY_set_ex_data;
+}
+
 impl ExDataAccessors for openssl_sys::RSA {
 	const GET_FN: unsafe extern "C" fn(this: *const Self, idx: std::os::raw::c_int) -> *mut std::ffi::c_void =
 		openssl_sys2::RSA_get_ex_data;
@@ -225,7 +250,7 @@ impl ExDataAccessors for openssl_sys::RSA {
 
 /// The kinds of EC curves supported for key generation.
 #[cfg_attr(not(ossl111), allow(clippy::pub_enum_variant_names))] // "All variants start with Nist"
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum EcCurve {
 	/// ed25519
 	///
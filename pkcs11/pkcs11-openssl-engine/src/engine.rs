@@ -96,10 +96,10 @@ unsafe extern "C" fn engine_load_privkey(
 		let key_id: pkcs11::Uri = key_id.parse()?;
 
 		let context = engine.context.clone();
-		let slot_id = context.find_slot(&key_id.slot_identifier)?;
-		let session = context.open_session(slot_id, key_id.pin)?;
+		let slot_id = context.find_slot(&key_id)?;
+		let session = context.open_session(slot_id, key_id.pin.clone())?;
 
-		let key_pair = session.get_key_pair(key_id.object_label.as_ref().map(AsRef::as_ref))?;
+		let key_pair = session.get_key_pair(&key_id)?;
 		let openssl_key_raw = match key_pair {
 			pkcs11::KeyPair::Ec(public_key, private_key) => {
 				let parameters = public_key.parameters()?;
@@ -173,10 +173,10 @@ unsafe extern "C" fn engine_load_pubkey(
 		let key_id: pkcs11::Uri = key_id.parse()?;
 
 		let context = engine.context.clone();
-		let slot_id = context.find_slot(&key_id.slot_identifier)?;
-		let session = context.open_session(slot_id, key_id.pin)?;
+		let slot_id = context.find_slot(&key_id)?;
+		let session = context.open_session(slot_id, key_id.pin.clone())?;
 
-		let public_key = session.get_public_key(key_id.object_label.as_ref().map(AsRef::as_ref))?;
+		let public_key = session.get_public_key(&key_id)?;
 		match public_key {
 			pkcs11::PublicKey::Ec(public_key) => {
 				let parameters = public_key.parameters()?;
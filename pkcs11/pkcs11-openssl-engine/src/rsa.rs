@@ -160,7 +160,7 @@ unsafe extern "C" fn pkcs11_evp_rsa_sign(
 			openssl2::openssl_returns_positive(openssl_sys2::EVP_PKEY_CTX_get_rsa_pss_saltlen_f(ctx, &mut rsa_pss_salt_len))?;
 
 			let rsa_pss_salt_len = match rsa_pss_salt_len {
-				rsa_pss_salt_len if rsa_pss_salt_len > 0 => rsa_pss_salt_len,
+				rsa_pss_salt_len if rsa_pss_salt_len >= 0 => rsa_pss_salt_len,
 				-1 => openssl_sys::EVP_MD_size(signature_md),
 				rsa_pss_salt_len => return Err(format!("invalid rsa_pss_salt_len 0x{:08x}", rsa_pss_salt_len).into()),
 			};
@@ -99,9 +99,12 @@ pub type CK_ATTRIBUTE_PTR_CONST = *const CK_ATTRIBUTE_IN;
 define_enum!(CK_ATTRIBUTE_TYPE {
 	CKA_CLASS = 0x0000_0000,
 	CKA_DECRYPT = 0x0000_0105,
+	CKA_DERIVE = 0x0000_010c,
 	CKA_EC_PARAMS = 0x0000_0180,
 	CKA_EC_POINT = 0x0000_0181,
 	CKA_ENCRYPT = 0x0000_0104,
+	CKA_EXTRACTABLE = 0x0000_0162,
+	CKA_ID = 0x0000_0102,
 	CKA_KEY_TYPE = 0x0000_0100,
 	CKA_LABEL = 0x0000_0003,
 	CKA_MODULUS = 0x0000_0120,
@@ -111,13 +114,15 @@ define_enum!(CK_ATTRIBUTE_TYPE {
 	CKA_SENSITIVE = 0x0000_0103,
 	CKA_SIGN = 0x0000_0108,
 	CKA_TOKEN = 0x0000_0001,
+	CKA_VALUE = 0x0000_0011,
+	CKA_VALUE_LEN = 0x0000_0161,
 	CKA_VERIFY = 0x0000_010a,
 });
 
 
 // CK_BBOOL
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(transparent)]
 pub struct CK_BBOOL(u8);
 
@@ -138,6 +143,26 @@ pub type CK_BYTE_PTR_CONST = *const CK_BYTE;
 pub type CK_CHAR = CK_BYTE;
 
 
+// CK_EC_KDF_TYPE
+
+define_enum!(CK_EC_KDF_TYPE {
+	CKD_NULL = 0x0000_0001,
+});
+
+
+// CK_ECDH1_DERIVE_PARAMS
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct CK_ECDH1_DERIVE_PARAMS {
+	pub kdf: CK_EC_KDF_TYPE,
+	pub ulSharedDataLen: CK_ULONG,
+	pub pSharedData: CK_BYTE_PTR_CONST,
+	pub ulPublicDataLen: CK_ULONG,
+	pub pPublicData: CK_BYTE_PTR_CONST,
+}
+
+
 // CK_FLAGS
 
 #[derive(Clone, Copy, Debug)]
@@ -193,25 +218,27 @@ pub struct CK_FUNCTION_LIST {
 	_unused1: [Option<unsafe extern "C" fn()>; 1],
 
 	pub C_GetSlotList: Option<CK_C_GetSlotList>,
-
-	_unused2: [Option<unsafe extern "C" fn()>; 1],
-
+	pub C_GetSlotInfo: Option<CK_C_GetSlotInfo>,
 	pub C_GetTokenInfo: Option<CK_C_GetTokenInfo>,
 
-	_unused3: [Option<unsafe extern "C" fn()>; 5],
+	_unused2: [Option<unsafe extern "C" fn()>; 5],
 
 	pub C_OpenSession: Option<CK_C_OpenSession>,
 	pub C_CloseSession: Option<CK_C_CloseSession>,
 
-	_unused4: [Option<unsafe extern "C" fn()>; 1],
+	_unused3: [Option<unsafe extern "C" fn()>; 1],
 
 	pub C_GetSessionInfo: Option<CK_C_GetSessionInfo>,
 
-	_unused5: [Option<unsafe extern "C" fn()>; 2],
+	_unused4: [Option<unsafe extern "C" fn()>; 2],
 
 	pub C_Login: Option<CK_C_Login>,
 
-	_unused6: [Option<unsafe extern "C" fn()>; 3],
+	_unused5: [Option<unsafe extern "C" fn()>; 1],
+
+	pub C_CreateObject: Option<CK_C_CreateObject>,
+
+	_unused6: [Option<unsafe extern "C" fn()>; 1],
 
 	pub C_DestroyObject: Option<CK_C_DestroyObject>,
 
@@ -227,22 +254,46 @@ pub struct CK_FUNCTION_LIST {
 	pub C_EncryptInit: Option<CK_C_EncryptInit>,
 	pub C_Encrypt: Option<CK_C_Encrypt>,
 
-	_unused9: [Option<unsafe extern "C" fn()>; 11],
+	_unused9: [Option<unsafe extern "C" fn()>; 2],
+
+	pub C_DecryptInit: Option<CK_C_DecryptInit>,
+	pub C_Decrypt: Option<CK_C_Decrypt>,
+
+	_unused10: [Option<unsafe extern "C" fn()>; 7],
 
 	pub C_SignInit: Option<CK_C_SignInit>,
 	pub C_Sign: Option<CK_C_Sign>,
 
-	_unused10: [Option<unsafe extern "C" fn()>; 15],
+	_unused11: [Option<unsafe extern "C" fn()>; 14],
 
+	pub C_GenerateKey: Option<CK_C_GenerateKey>,
 	pub C_GenerateKeyPair: Option<CK_C_GenerateKeyPair>,
 
-	_unused11: [Option<unsafe extern "C" fn()>; 8],
+	_unused12: [Option<unsafe extern "C" fn()>; 2],
+
+	pub C_DeriveKey: Option<CK_C_DeriveKey>,
+
+	_unused13: [Option<unsafe extern "C" fn()>; 5],
 }
 
 pub type CK_FUNCTION_LIST_PTR_CONST = *const CK_FUNCTION_LIST;
 pub type CK_FUNCTION_LIST_PTR_PTR = *mut CK_FUNCTION_LIST_PTR_CONST;
 
 
+// CK_GCM_PARAMS
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct CK_GCM_PARAMS {
+	pub pIv: CK_BYTE_PTR_CONST,
+	pub ulIvLen: CK_ULONG,
+	pub ulIvBits: CK_ULONG,
+	pub pAAD: CK_BYTE_PTR_CONST,
+	pub ulAADLen: CK_ULONG,
+	pub ulTagBits: CK_ULONG,
+}
+
+
 // CK_INFO
 
 #[derive(Debug)]
@@ -292,7 +343,10 @@ pub type CK_C_INITIALIZE_ARGS_PTR = *const CK_C_INITIALIZE_ARGS;
 // CK_KEY_TYPE
 
 define_enum!(CK_KEY_TYPE {
+	CKK_AES = 0x0000_001f,
 	CKK_EC = 0x0000_0003,
+	CKK_EC_EDWARDS = 0x0000_0040,
+	CKK_GENERIC_SECRET = 0x0000_0010,
 	CKK_RSA = 0x0000_0000,
 });
 
@@ -313,15 +367,23 @@ pub type CK_MECHANISM_PTR_CONST = *const CK_MECHANISM_IN;
 // CK_MECHANISM_TYPE
 
 define_enum!(CK_MECHANISM_TYPE {
+	CKM_AES_GCM = 0x0000_1087,
+	CKM_AES_KEY_GEN = 0x0000_1080,
+	CKM_EC_EDWARDS_KEY_PAIR_GEN = 0x0000_1055,
 	CKM_EC_KEY_PAIR_GEN = 0x0000_1040,
+	CKM_ECDH1_DERIVE = 0x0000_1050,
 	CKM_ECDSA = 0x0000_1041,
+	CKM_EDDSA = 0x0000_1057,
+	CKM_GENERIC_SECRET_KEY_GEN = 0x0000_0350,
 	CKM_RSA_PKCS = 0x0000_0001,
 	CKM_RSA_PKCS_KEY_PAIR_GEN = 0x0000_0000,
+	CKM_RSA_PKCS_OAEP = 0x0000_0009,
 	CKM_RSA_X9_31 = 0x0000_000b,
 	CKM_RSA_PKCS_PSS = 0x0000_000d,
 	CKM_SHA_1 = 0x0000_0220,
 	CKM_SHA224 = 0x0000_0255,
 	CKM_SHA256 = 0x0000_0250,
+	CKM_SHA256_HMAC = 0x0000_0251,
 	CKM_SHA384 = 0x0000_0260,
 	CKM_SHA512 = 0x0000_0270,
 });
@@ -337,8 +399,11 @@ pub struct CK_NOTIFICATION(CK_ULONG);
 // CK_OBJECT_CLASS
 
 define_enum!(CK_OBJECT_CLASS {
+	CKO_DATA = 0x0000_0000,
+	CKO_CERTIFICATE = 0x0000_0001,
 	CKO_PUBLIC_KEY = 0x0000_0002,
 	CKO_PRIVATE_KEY = 0x0000_0003,
+	CKO_SECRET_KEY = 0x0000_0004,
 });
 
 
@@ -364,6 +429,26 @@ define_enum!(CK_RSA_PKCS_MGF_TYPE {
 });
 
 
+// CK_RSA_PKCS_OAEP_PARAMS
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct CK_RSA_PKCS_OAEP_PARAMS {
+	pub hashAlg: CK_MECHANISM_TYPE,
+	pub mgf: CK_RSA_PKCS_MGF_TYPE,
+	pub source: CK_RSA_PKCS_OAEP_SOURCE_TYPE,
+	pub pSourceData: CK_VOID_PTR_CONST,
+	pub ulSourceDataLen: CK_ULONG,
+}
+
+
+// CK_RSA_PKCS_OAEP_SOURCE_TYPE
+
+define_enum!(CK_RSA_PKCS_OAEP_SOURCE_TYPE {
+	CKZ_DATA_SPECIFIED = 0x0000_0001,
+});
+
+
 // CK_RSA_PKCS_PSS_PARAMS
 
 #[derive(Debug)]
@@ -380,6 +465,7 @@ pub struct CK_RSA_PKCS_PSS_PARAMS {
 define_enum!(CK_RV {
 	CKR_ACTION_PROHIBITED = 0x0000_001b,
 	CKR_ARGUMENTS_BAD = 0x0000_0007,
+	CKR_ATTRIBUTE_SENSITIVE = 0x0000_0011,
 	CKR_ATTRIBUTE_TYPE_INVALID = 0x0000_0012,
 	CKR_ATTRIBUTE_VALUE_INVALID = 0x0000_0013,
 
@@ -393,6 +479,8 @@ define_enum!(CK_RV {
 	CKR_DEVICE_MEMORY = 0x0000_0031,
 	CKR_DEVICE_REMOVED = 0x0000_0032,
 
+	CKR_ENCRYPTED_DATA_INVALID = 0x0000_0040,
+
 	CKR_FUNCTION_FAILED = 0x0000_0006,
 	CKR_FUNCTION_NOT_SUPPORTED = 0x0000_0054,
 
@@ -484,6 +572,21 @@ impl std::str::FromStr for CK_SLOT_ID {
 pub type CK_SLOT_ID_PTR = *mut CK_SLOT_ID;
 
 
+// CK_SLOT_INFO
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct CK_SLOT_INFO {
+	pub slotDescription: [CK_UTF8CHAR; 64],
+	pub manufacturerID: [CK_UTF8CHAR; 32],
+	pub flags: CK_ULONG,
+	pub hardwareVersion: CK_VERSION,
+	pub firmwareVersion: CK_VERSION,
+}
+
+pub type CK_SLOT_INFO_PTR = *mut CK_SLOT_INFO;
+
+
 // CK_STATE
 
 define_enum!(CK_STATE {
@@ -576,6 +679,32 @@ pub type CK_VOID_PTR_PTR = *mut CK_VOID_PTR;
 pub type CK_C_CloseSession = unsafe extern "C" fn(
 	hSession: CK_SESSION_HANDLE,
 ) -> CK_RV;
+pub type CK_C_CreateObject = unsafe extern "C" fn(
+	hSession: CK_SESSION_HANDLE,
+	pTemplate: CK_ATTRIBUTE_PTR_CONST,
+	ulCount: CK_ULONG,
+	phObject: CK_OBJECT_HANDLE_PTR,
+) -> CK_RV;
+pub type CK_C_Decrypt = unsafe extern "C" fn(
+	hSession: CK_SESSION_HANDLE,
+	pEncryptedData: CK_BYTE_PTR_CONST,
+	ulEncryptedDataLen: CK_ULONG,
+	pData: CK_BYTE_PTR,
+	pulDataLen: CK_ULONG_PTR,
+) -> CK_RV;
+pub type CK_C_DecryptInit = unsafe extern "C" fn(
+	hSession: CK_SESSION_HANDLE,
+	pMechanism: CK_MECHANISM_PTR_CONST,
+	hKey: CK_OBJECT_HANDLE,
+) -> CK_RV;
+pub type CK_C_DeriveKey = unsafe extern "C" fn(
+	hSession: CK_SESSION_HANDLE,
+	pMechanism: CK_MECHANISM_PTR_CONST,
+	hBaseKey: CK_OBJECT_HANDLE,
+	pTemplate: CK_ATTRIBUTE_PTR_CONST,
+	ulAttributeCount: CK_ULONG,
+	phKey: CK_OBJECT_HANDLE_PTR,
+) -> CK_RV;
 pub type CK_C_DestroyObject = unsafe extern "C" fn(
 	hSession: CK_SESSION_HANDLE,
 	hObject: CK_OBJECT_HANDLE,
@@ -609,6 +738,13 @@ pub type CK_C_FindObjectsInit = unsafe extern "C" fn(
 	pTemplate: CK_ATTRIBUTE_PTR_CONST,
 	ulCount: CK_ULONG,
 ) -> CK_RV;
+pub type CK_C_GenerateKey = unsafe extern "C" fn(
+	hSession: CK_SESSION_HANDLE,
+	pMechanism: CK_MECHANISM_PTR_CONST,
+	pTemplate: CK_ATTRIBUTE_PTR_CONST,
+	ulCount: CK_ULONG,
+	phKey: CK_OBJECT_HANDLE_PTR,
+) -> CK_RV;
 pub type CK_C_GenerateKeyPair = unsafe extern "C" fn(
 	hSession: CK_SESSION_HANDLE,
 	pMechanism: CK_MECHANISM_PTR_CONST,
@@ -640,6 +776,10 @@ pub type CK_C_GetSlotList = unsafe extern "C" fn(
 	pSlotList: CK_SLOT_ID_PTR,
 	pulCount: CK_ULONG_PTR,
 ) -> CK_RV;
+pub type CK_C_GetSlotInfo = unsafe extern "C" fn(
+	slotID: CK_SLOT_ID,
+	pInfo: CK_SLOT_INFO_PTR,
+) -> CK_RV;
 pub type CK_C_GetTokenInfo = unsafe extern "C" fn(
 	slotID: CK_SLOT_ID,
 	pInfo: CK_TOKEN_INFO_PTR,
@@ -47,12 +47,72 @@ async fn main() -> Result<(), Error> {
 				&GenerateCertKind::Client { ca_cert, ca_key },
 			)?,
 
+		Command::EncryptDecrypt { key, plaintext } => {
+			let key: pkcs11::Uri = key.parse()?;
+
+			let pkcs11_context = load_pkcs11_context(pkcs11_lib_path)?;
+
+			let pkcs11_slot = pkcs11_context.find_slot(&key)?;
+
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, key.pin.clone())?;
+
+			let (public_key_handle, private_key_handle) = match pkcs11_session.get_key_pair(&key)? {
+				pkcs11::KeyPair::Rsa(public_key_handle, private_key_handle) => (public_key_handle, private_key_handle),
+				pkcs11::KeyPair::Ec(_, _) => return Err("encrypt-decrypt needs an RSA key pair".into()),
+			};
+
+			let public_key = public_key_handle.parameters()?;
+			let plaintext = plaintext.as_bytes();
+
+			for (name, mechanism) in &[
+				("RSA-PKCS1", pkcs11::RsaEncryptMechanism::Pkcs1),
+				("RSA-OAEP", pkcs11::RsaEncryptMechanism::Oaep(pkcs11_sys::CK_RSA_PKCS_OAEP_PARAMS {
+					hashAlg: pkcs11_sys::CKM_SHA256,
+					mgf: pkcs11_sys::CKG_MGF1_SHA256,
+					source: pkcs11_sys::CKZ_DATA_SPECIFIED,
+					pSourceData: std::ptr::null(),
+					ulSourceDataLen: 0,
+				})),
+			] {
+				let mut ciphertext = vec![0_u8; std::convert::TryInto::try_into(public_key.size())?];
+				let ciphertext_len = public_key_handle.encrypt(mechanism, plaintext, &mut ciphertext)?;
+				ciphertext.truncate(std::convert::TryInto::try_into(ciphertext_len)?);
+
+				let decrypted = decrypt(&private_key_handle, mechanism, &ciphertext)?;
+				if decrypted != plaintext {
+					return Err(format!("{} plaintext does not round-trip through the token: {:?}", name, decrypted).into());
+				}
+
+				// Also decrypt a ciphertext made by openssl, to check that the token uses the same padding as openssl.
+				let public_key = openssl::pkey::PKey::from_rsa(public_key.clone())?;
+				let mut encrypter = openssl::encrypt::Encrypter::new(&public_key)?;
+				if let pkcs11::RsaEncryptMechanism::Oaep(_) = mechanism {
+					encrypter.set_rsa_padding(openssl::rsa::Padding::PKCS1_OAEP)?;
+					encrypter.set_rsa_oaep_md(openssl::hash::MessageDigest::sha256())?;
+					encrypter.set_rsa_mgf1_md(openssl::hash::MessageDigest::sha256())?;
+				}
+				else {
+					encrypter.set_rsa_padding(openssl::rsa::Padding::PKCS1)?;
+				}
+				let mut ciphertext = vec![0_u8; encrypter.encrypt_len(plaintext)?];
+				let ciphertext_len = encrypter.encrypt(plaintext, &mut ciphertext)?;
+				ciphertext.truncate(ciphertext_len);
+
+				let decrypted = decrypt(&private_key_handle, mechanism, &ciphertext)?;
+				if decrypted != plaintext {
+					return Err(format!("{} plaintext encrypted by openssl does not round-trip through the token: {:?}", name, decrypted).into());
+				}
+
+				println!("Encrypted and decrypted with {}", name);
+			}
+		},
+
 		Command::GenerateKeyPair { key, r#type } => {
 			let key: pkcs11::Uri = key.parse()?;
 
 			let pkcs11_context = load_pkcs11_context(pkcs11_lib_path)?;
 
-			let pkcs11_slot = pkcs11_context.find_slot(&key.slot_identifier)?;
+			let pkcs11_slot = pkcs11_context.find_slot(&key)?;
 
 			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, key.pin)?;
 
@@ -86,6 +146,34 @@ async fn main() -> Result<(), Error> {
 				&GenerateCertKind::Server { hostname: "example.com", ca_cert, ca_key },
 			)?,
 
+		Command::DeleteObject { object } => {
+			let object: pkcs11::Uri = object.parse()?;
+
+			let pkcs11_context = load_pkcs11_context(pkcs11_lib_path)?;
+
+			let pkcs11_slot = pkcs11_context.find_slot(&object)?;
+
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, object.pin.clone())?;
+
+			let object_info = pkcs11_session.destroy_object(&pkcs11::ObjectTemplate::from_uri(&object))?;
+			println!("Deleted {}", Displayable(object_info));
+		},
+
+		Command::ListObjects { token } => {
+			let token: pkcs11::Uri = token.parse()?;
+
+			let pkcs11_context = load_pkcs11_context(pkcs11_lib_path)?;
+
+			let pkcs11_slot = pkcs11_context.find_slot(&token)?;
+
+			let pkcs11_session = pkcs11_context.open_session(pkcs11_slot, token.pin.clone())?;
+
+			let objects = pkcs11_session.list_objects(&pkcs11::ObjectTemplate::from_uri(&token))?;
+			for object_info in objects {
+				println!("{}", Displayable(object_info));
+			}
+		},
+
 		Command::Load { keys } => {
 			let pkcs11_context = load_pkcs11_context(pkcs11_lib_path)?;
 
@@ -188,6 +276,18 @@ fn load_private_key(
 	Ok(key)
 }
 
+fn decrypt(
+	private_key_handle: &pkcs11::Object<openssl::rsa::Rsa<openssl::pkey::Private>>,
+	mechanism: &pkcs11::RsaEncryptMechanism,
+	ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+	// The plaintext is never longer than the ciphertext, which is as long as the RSA modulus.
+	let mut plaintext = vec![0_u8; ciphertext.len()];
+	let plaintext_len = private_key_handle.decrypt(mechanism, ciphertext, &mut plaintext)?;
+	plaintext.truncate(std::convert::TryInto::try_into(plaintext_len)?);
+	Ok(plaintext)
+}
+
 fn generate_cert(
 	pkcs11_lib_path: std::path::PathBuf,
 	key: String,
@@ -364,12 +464,23 @@ enum Command {
 		subject: String,
 	},
 
+	/// Encrypt a plaintext with an RSA key pair in the HSM and decrypt it again.
+	EncryptDecrypt {
+		/// The ID of the key pair, in PKCS#11 URI format.
+		#[structopt(long)]
+		key: String,
+
+		/// The plaintext to encrypt.
+		#[structopt(long)]
+		plaintext: String,
+	},
+
 	/// Generate a key pair in the HSM.
 	GenerateKeyPair {
 		/// The ID of the token where the key pair will be stored, in a PKCS#11 URI format.
 		///
 		/// Must have either a `token` (label) or `slot-id` (slot ID) component to identify the slot,
-		/// and a `pin-value` (user PIN) component.
+		/// and a `pin-value` (user PIN) or `pin-source` (`file:` URI or `credential:<name>` to read the user PIN from) component.
 		#[structopt(long)]
 		key: String,
 
@@ -401,6 +512,24 @@ enum Command {
 		subject: String,
 	},
 
+	/// Delete an object from the HSM.
+	DeleteObject {
+		/// The ID of the object to delete, in PKCS#11 URI format.
+		///
+		/// Must match exactly one object. If several objects have the same label, use the `id` component to choose one of them.
+		#[structopt(long)]
+		object: String,
+	},
+
+	/// List the objects in the HSM.
+	ListObjects {
+		/// The ID of the token whose objects will be listed, in PKCS#11 URI format.
+		///
+		/// If the URI has `object`, `id` or `type` components, only objects that match them are listed.
+		#[structopt(long)]
+		token: String,
+	},
+
 	/// Load one or more public keys from the HSM.
 	Load {
 		/// One or more IDs of public keys, each in PKCS#11 URI format. Each argument to the command is one key ID.
@@ -499,3 +628,47 @@ impl<T> std::fmt::Display for Displayable<openssl::rsa::Rsa<T>> where T: openssl
 		Ok(())
 	}
 }
+
+impl std::fmt::Display for Displayable<pkcs11::ObjectInfo> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let pkcs11::ObjectInfo { class, key_type, label, id, modulus_bits, ec_params, value_len, sensitive, extractable } = &self.0;
+
+		write!(f, "class = {}", class)?;
+
+		if let Some(key_type) = key_type {
+			write!(f, ", key type = {}", key_type)?;
+		}
+
+		write!(f, ", label = {:?}", label.as_deref().unwrap_or_default())?;
+
+		f.write_str(", id = 0x")?;
+		for b in id.as_deref().unwrap_or_default() {
+			write!(f, "{:02x}", b)?;
+		}
+
+		if let Some(modulus_bits) = modulus_bits {
+			write!(f, ", modulus bits = {}", modulus_bits)?;
+		}
+
+		if let Some(ec_params) = ec_params {
+			match openssl2::EcCurve::from_oid_der(ec_params) {
+				Some(curve) => write!(f, ", curve = {}", curve.as_nid().long_name()?)?,
+				None => write!(f, ", curve = <unknown>")?,
+			}
+		}
+
+		if let Some(value_len) = value_len {
+			write!(f, ", value length = {}", value_len)?;
+		}
+
+		if let Some(sensitive) = sensitive {
+			write!(f, ", sensitive = {}", sensitive)?;
+		}
+
+		if let Some(extractable) = extractable {
+			write!(f, ", extractable = {}", extractable)?;
+		}
+
+		Ok(())
+	}
+}
@@ -21,9 +21,13 @@ pub struct Context {
 	sessions: std::sync::Mutex<std::collections::BTreeMap<pkcs11_sys::CK_SLOT_ID, std::sync::Weak<crate::Session>>>,
 
 	// Ensure this comes after anything else that might be using the library, like `sessions` above, so that it's dropped after them.
-	_library: crate::dl::Library,
+	_library: dl_common::Library,
 
 	pub(crate) C_CloseSession: pkcs11_sys::CK_C_CloseSession,
+	pub(crate) C_CreateObject: pkcs11_sys::CK_C_CreateObject,
+	pub(crate) C_Decrypt: pkcs11_sys::CK_C_Decrypt,
+	pub(crate) C_DecryptInit: pkcs11_sys::CK_C_DecryptInit,
+	pub(crate) C_DeriveKey: pkcs11_sys::CK_C_DeriveKey,
 	pub(crate) C_DestroyObject: pkcs11_sys::CK_C_DestroyObject,
 	pub(crate) C_Encrypt: pkcs11_sys::CK_C_Encrypt,
 	pub(crate) C_EncryptInit: pkcs11_sys::CK_C_EncryptInit,
@@ -31,9 +35,11 @@ pub struct Context {
 	pub(crate) C_FindObjects: pkcs11_sys::CK_C_FindObjects,
 	pub(crate) C_FindObjectsFinal: pkcs11_sys::CK_C_FindObjectsFinal,
 	pub(crate) C_FindObjectsInit: pkcs11_sys::CK_C_FindObjectsInit,
+	pub(crate) C_GenerateKey: pkcs11_sys::CK_C_GenerateKey,
 	pub(crate) C_GenerateKeyPair: pkcs11_sys::CK_C_GenerateKeyPair,
 	pub(crate) C_GetAttributeValue: pkcs11_sys::CK_C_GetAttributeValue,
 	pub(crate) C_GetSessionInfo: pkcs11_sys::CK_C_GetSessionInfo,
+	C_GetSlotInfo: pkcs11_sys::CK_C_GetSlotInfo,
 	C_GetSlotList: pkcs11_sys::CK_C_GetSlotList,
 	C_GetTokenInfo: pkcs11_sys::CK_C_GetTokenInfo,
 	C_GetInfo: Option<pkcs11_sys::CK_C_GetInfo>,
@@ -55,7 +61,7 @@ impl Context {
 
 	fn load_inner(lib_path: &std::path::Path) -> Result<Self, LoadContextError> {
 		unsafe {
-			let library = crate::dl::Library::load(lib_path).map_err(LoadContextError::LoadLibrary)?;
+			let library = dl_common::Library::load(lib_path, libc::RTLD_LAZY | libc::RTLD_LOCAL).map_err(LoadContextError::LoadLibrary)?;
 
 			let C_GetFunctionList: pkcs11_sys::CK_C_GetFunctionList =
 				*library.symbol(std::ffi::CStr::from_bytes_with_nul(b"C_GetFunctionList\0").unwrap())
@@ -84,6 +90,10 @@ impl Context {
 			}
 
 			let C_CloseSession = (*function_list).C_CloseSession.ok_or(LoadContextError::MissingFunction("C_CloseSession"))?;
+			let C_CreateObject = (*function_list).C_CreateObject.ok_or(LoadContextError::MissingFunction("C_CreateObject"))?;
+			let C_Decrypt = (*function_list).C_Decrypt.ok_or(LoadContextError::MissingFunction("C_Decrypt"))?;
+			let C_DecryptInit = (*function_list).C_DecryptInit.ok_or(LoadContextError::MissingFunction("C_DecryptInit"))?;
+			let C_DeriveKey = (*function_list).C_DeriveKey.ok_or(LoadContextError::MissingFunction("C_DeriveKey"))?;
 			let C_DestroyObject = (*function_list).C_DestroyObject.ok_or(LoadContextError::MissingFunction("C_DestroyObject"))?;
 			let C_Encrypt = (*function_list).C_Encrypt.ok_or(LoadContextError::MissingFunction("C_Encrypt"))?;
 			let C_EncryptInit = (*function_list).C_EncryptInit.ok_or(LoadContextError::MissingFunction("C_EncryptInit"))?;
@@ -91,10 +101,12 @@ impl Context {
 			let C_FindObjects = (*function_list).C_FindObjects.ok_or(LoadContextError::MissingFunction("C_FindObjects"))?;
 			let C_FindObjectsFinal = (*function_list).C_FindObjectsFinal.ok_or(LoadContextError::MissingFunction("C_FindObjectsFinal"))?;
 			let C_FindObjectsInit = (*function_list).C_FindObjectsInit.ok_or(LoadContextError::MissingFunction("C_FindObjectsInit"))?;
+			let C_GenerateKey = (*function_list).C_GenerateKey.ok_or(LoadContextError::MissingFunction("C_GenerateKey"))?;
 			let C_GenerateKeyPair = (*function_list).C_GenerateKeyPair.ok_or(LoadContextError::MissingFunction("C_GenerateKeyPair"))?;
 			let C_GetAttributeValue = (*function_list).C_GetAttributeValue.ok_or(LoadContextError::MissingFunction("C_GetAttributeValue"))?;
 			let C_GetInfo = (*function_list).C_GetInfo;
 			let C_GetSessionInfo = (*function_list).C_GetSessionInfo.ok_or(LoadContextError::MissingFunction("C_GetSessionInfo"))?;
+			let C_GetSlotInfo = (*function_list).C_GetSlotInfo.ok_or(LoadContextError::MissingFunction("C_GetSlotInfo"))?;
 			let C_GetSlotList = (*function_list).C_GetSlotList.ok_or(LoadContextError::MissingFunction("C_GetSlotList"))?;
 			let C_GetTokenInfo = (*function_list).C_GetTokenInfo.ok_or(LoadContextError::MissingFunction("C_GetTokenInfo"))?;
 			let C_Login = (*function_list).C_Login.ok_or(LoadContextError::MissingFunction("C_Login"))?;
@@ -123,6 +135,10 @@ impl Context {
 				_library: library,
 
 				C_CloseSession,
+				C_CreateObject,
+				C_Decrypt,
+				C_DecryptInit,
+				C_DeriveKey,
 				C_DestroyObject,
 				C_Encrypt,
 				C_EncryptInit,
@@ -130,10 +146,12 @@ impl Context {
 				C_FindObjects,
 				C_FindObjectsFinal,
 				C_FindObjectsInit,
+				C_GenerateKey,
 				C_GenerateKeyPair,
 				C_GetAttributeValue,
 				C_GetInfo,
 				C_GetSessionInfo,
+				C_GetSlotInfo,
 				C_GetSlotList,
 				C_GetTokenInfo,
 				C_Login,
@@ -286,67 +304,165 @@ impl std::error::Error for ListSlotsError {
 }
 
 impl Context {
-	/// Finds a slot that matches the criteria set by the given identifier.
+	/// Finds a slot that matches the slot, token and library attributes of the given URI.
+	///
+	/// If the URI identifies the slot by its ID, that slot is returned as long as it also matches the other attributes.
+	/// Otherwise the first slot with an initialized token whose label and other attributes match is returned.
 	pub fn find_slot(
 		&self,
-		identifier: &crate::UriSlotIdentifier,
+		uri: &crate::Uri,
 	) -> Result<pkcs11_sys::CK_SLOT_ID, FindSlotError> {
-		match identifier {
+		if let Some((key, _)) = uri.unrecognized_path_attributes.first() {
+			return Err(FindSlotError::UnrecognizedPathAttribute(key.clone()));
+		}
+
+		if uri.library_description.is_some() || uri.library_manufacturer.is_some() || uri.library_version.is_some() {
+			// If the library can't report its information, there is no way to know whether it matches.
+			let info = self.info().ok_or(FindSlotError::NoMatchingSlotFound)?;
+			if
+				!padded_field_matches(&info.libraryDescription, uri.library_description.as_deref()) ||
+				!padded_field_matches(&info.manufacturerID, uri.library_manufacturer.as_deref()) ||
+				uri.library_version.map_or(false, |version| version.major != info.libraryVersion.major || version.minor != info.libraryVersion.minor)
+			{
+				return Err(FindSlotError::NoMatchingSlotFound);
+			}
+		}
+
+		let slot_matches = |slot_id, token_label: Option<&str>| -> Result<bool, FindSlotError> {
+			if uri.slot_description.is_some() || uri.slot_manufacturer.is_some() {
+				let slot_info = self.slot_info(slot_id).map_err(FindSlotError::GetSlotInfo)?;
+				if
+					!padded_field_matches(&slot_info.slotDescription, uri.slot_description.as_deref()) ||
+					!padded_field_matches(&slot_info.manufacturerID, uri.slot_manufacturer.as_deref())
+				{
+					return Ok(false);
+				}
+			}
+
+			if token_label.is_some() || uri.token_manufacturer.is_some() || uri.token_model.is_some() || uri.token_serial.is_some() {
+				let token_info = self.token_info(slot_id).map_err(FindSlotError::GetTokenInfo)?;
+				if token_label.is_some() && !token_info.flags.has(pkcs11_sys::CKF_TOKEN_INITIALIZED) {
+					return Ok(false);
+				}
+
+				if
+					!padded_field_matches(&token_info.label, token_label) ||
+					!padded_field_matches(&token_info.manufacturerID, uri.token_manufacturer.as_deref()) ||
+					!padded_field_matches(&token_info.model, uri.token_model.as_deref()) ||
+					!padded_field_matches(&token_info.serialNumber, uri.token_serial.as_deref())
+				{
+					return Ok(false);
+				}
+			}
+
+			Ok(true)
+		};
+
+		match &uri.slot_identifier {
 			crate::UriSlotIdentifier::Label(label) => {
-				let mut slot = None;
 				for context_slot in self.slots().map_err(FindSlotError::ListSlots)? {
-					let token_info = self.token_info(context_slot).map_err(FindSlotError::GetTokenInfo)?;
-					if !token_info.flags.has(pkcs11_sys::CKF_TOKEN_INITIALIZED) {
-						continue;
+					if slot_matches(context_slot, Some(label))? {
+						return Ok(context_slot);
 					}
-
-					let slot_label = String::from_utf8_lossy(&token_info.label);
-					// Labels are always 32 bytes, so shorter labels are padded with trailing whitespace which the URI parameter will not have.
-					let slot_label = slot_label.trim();
-					if slot_label != label {
-						continue;
-					}
-
-					slot = Some(context_slot);
-					break;
 				}
 
-				Ok(slot.ok_or(FindSlotError::NoMatchingSlotFound)?)
+				Err(FindSlotError::NoMatchingSlotFound)
 			},
 
-			crate::UriSlotIdentifier::SlotId(slot_id) => Ok(*slot_id),
+			crate::UriSlotIdentifier::SlotId(slot_id) =>
+				if slot_matches(*slot_id, uri.token_label.as_deref())? {
+					Ok(*slot_id)
+				}
+				else {
+					Err(FindSlotError::NoMatchingSlotFound)
+				},
 		}
 	}
 }
 
+/// Whether a fixed-length field of a `CK_*_INFO` struct matches the given URI attribute. A `None` attribute matches any value.
+fn padded_field_matches(field: &[u8], expected: Option<&str>) -> bool {
+	expected.map_or(true, |expected| {
+		let field = String::from_utf8_lossy(field);
+		// These fields are padded with trailing whitespace which the URI attribute will not have.
+		let field = field.trim();
+		field == expected
+	})
+}
+
 /// An error from finding a slot from its identifier.
 #[derive(Debug)]
 pub enum FindSlotError {
+	GetSlotInfo(GetSlotInfoError),
 	GetTokenInfo(crate::GetTokenInfoError),
 	ListSlots(ListSlotsError),
 	NoMatchingSlotFound,
+	UnrecognizedPathAttribute(String),
 }
 
 impl std::fmt::Display for FindSlotError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
+			FindSlotError::GetSlotInfo(_) => f.write_str("could not get slot info"),
 			FindSlotError::GetTokenInfo(_) => f.write_str("could not get token info"),
 			FindSlotError::ListSlots(_) => f.write_str("could not list slots"),
-			FindSlotError::NoMatchingSlotFound => f.write_str("could not find a slot with a matching label"),
+			FindSlotError::NoMatchingSlotFound => f.write_str("could not find a slot that matches the URI"),
+			FindSlotError::UnrecognizedPathAttribute(key) => write!(f, "URI has unrecognized path attribute [{}] so it does not match any slot", key),
 		}
 	}
 }
 
 impl std::error::Error for FindSlotError {
+	#[allow(clippy::match_same_arms)]
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
+			FindSlotError::GetSlotInfo(inner) => Some(inner),
 			FindSlotError::GetTokenInfo(inner) => Some(inner),
 			FindSlotError::ListSlots(inner) => Some(inner),
 			FindSlotError::NoMatchingSlotFound => None,
+			FindSlotError::UnrecognizedPathAttribute(_) => None,
 		}
 	}
 }
 
+impl Context {
+	/// Get the info of this slot.
+	pub fn slot_info(&self, slot_id: pkcs11_sys::CK_SLOT_ID) -> Result<pkcs11_sys::CK_SLOT_INFO, GetSlotInfoError> {
+		unsafe {
+			let mut info = std::mem::MaybeUninit::uninit();
+
+			let result =
+				(self.C_GetSlotInfo)(
+					slot_id,
+					info.as_mut_ptr(),
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(GetSlotInfoError::GetSlotInfo(result));
+			}
+
+			let info = info.assume_init();
+			Ok(info)
+		}
+	}
+}
+
+/// An error from getting a slot's info.
+#[derive(Debug)]
+pub enum GetSlotInfoError {
+	GetSlotInfo(pkcs11_sys::CK_RV),
+}
+
+impl std::fmt::Display for GetSlotInfoError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			GetSlotInfoError::GetSlotInfo(result) => write!(f, "C_GetSlotInfo failed with {}", result),
+		}
+	}
+}
+
+impl std::error::Error for GetSlotInfoError {
+}
+
 impl Context {
 	/// Get the info of the token in this slot.
 	pub fn token_info(&self, slot_id: pkcs11_sys::CK_SLOT_ID) -> Result<pkcs11_sys::CK_TOKEN_INFO, GetTokenInfoError> {
@@ -401,10 +517,14 @@ impl Context {
 	/// it will be in the R/W Public Session state and will never be able to transition to the R/W User Functions state.
 	/// If you need the session to be in the R/W User Functions state, either first close all other sessions for this slot and
 	/// then open a new one with the PIN, or make sure to always supply a PIN for any sessions opened against this slot.
+	///
+	/// If an operation on the session fails because the session was lost, such as with `CKR_SESSION_HANDLE_INVALID` after the token was reset,
+	/// the session is opened again and logged in to with the same PIN, and the operation is retried once. If that fails,
+	/// the operation's error has a `SessionRecoveryFailed` variant with a [`crate::RecoverSessionError`].
 	pub fn open_session(
 		self: std::sync::Arc<Self>,
 		slot_id: pkcs11_sys::CK_SLOT_ID,
-		pin: Option<String>,
+		pin: Option<crate::Pin>,
 	) -> Result<std::sync::Arc<crate::Session>, OpenSessionError> {
 		let this = self.clone();
 
@@ -418,8 +538,15 @@ impl Context {
 	fn open_session_inner(
 		self: std::sync::Arc<Self>,
 		slot_id: pkcs11_sys::CK_SLOT_ID,
-		pin: Option<String>,
+		pin: Option<crate::Pin>,
 	) -> Result<crate::Session, OpenSessionError> {
+		let handle = self.open_session_handle(slot_id)?;
+		let session = crate::Session::new(self, slot_id, handle, pin);
+		Ok(session)
+	}
+
+	/// Opens a new session against the given slot. This is also used to replace a session that was lost.
+	pub(crate) fn open_session_handle(&self, slot_id: pkcs11_sys::CK_SLOT_ID) -> Result<pkcs11_sys::CK_SESSION_HANDLE, OpenSessionError> {
 		unsafe {
 			let mut handle = pkcs11_sys::CK_INVALID_SESSION_HANDLE;
 			let result =
@@ -436,9 +563,8 @@ impl Context {
 			if handle == pkcs11_sys::CK_INVALID_SESSION_HANDLE {
 				return Err(OpenSessionError::OpenSessionFailed("C_OpenSession succeeded but session handle is still CK_INVALID_HANDLE".into()));
 			}
-			let session = crate::Session::new(self, handle, pin);
 
-			Ok(session)
+			Ok(handle)
 		}
 	}
 }
@@ -15,30 +15,80 @@
 mod context;
 pub use context::{
 	Context,
-	GetTokenInfoError, LoadContextError, ListSlotsError, OpenSessionError,
+	FindSlotError, GetSlotInfoError, GetTokenInfoError, LoadContextError, ListSlotsError, OpenSessionError,
 };
 
-mod dl;
-
 mod object;
 pub use object::{
 	Object,
-	EncryptError, GetKeyParametersError, SignError,
-	RsaSignMechanism,
+	DecryptError, DeriveError, EncryptError, GetKeyParametersError, SignError,
+	RsaEncryptMechanism, RsaSignMechanism,
+	AesKey, HmacKey, SecretKey, AES_GCM_TAG_LEN,
 };
 
+mod pin;
+pub use pin::Pin;
+
 mod session;
 pub use session::{
-	KeyPair, PublicKey, Session,
-	FindObjectsError, GenerateKeyPairError, GetKeyError, LoginError,
+	KeyPair, ObjectInfo, ObjectTemplate, PublicKey, Session,
+	DeleteKeyError, DestroyObjectError, FindObjectsError, GenerateKeyError, GenerateKeyPairError, GetKeyError, ImportKeyError, ListKeyPairsError, ListObjectsError, LoginError, RecoverSessionError,
 };
 
 
+/// A PKCS#11 URI, as defined by RFC 7512.
+///
+/// Every attribute narrows down the slots or objects that the URI matches. Attributes that are `None` match anything.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Uri {
 	pub slot_identifier: UriSlotIdentifier,
+
+	/// The `token` attribute, ie the label of the token, if the URI also has a `slot-id` attribute.
+	///
+	/// The slot is then identified by its ID, but it still only matches if its token has this label.
+	/// A `token` attribute without `slot-id` is in `slot_identifier` instead.
+	pub token_label: Option<String>,
+	/// The `manufacturer` attribute, ie the manufacturer ID of the token.
+	pub token_manufacturer: Option<String>,
+	/// The `model` attribute.
+	pub token_model: Option<String>,
+	/// The `serial` attribute.
+	pub token_serial: Option<String>,
+
+	/// The `slot-description` attribute.
+	pub slot_description: Option<String>,
+	/// The `slot-manufacturer` attribute.
+	pub slot_manufacturer: Option<String>,
+
+	/// The `library-description` attribute.
+	pub library_description: Option<String>,
+	/// The `library-manufacturer` attribute.
+	pub library_manufacturer: Option<String>,
+	/// The `library-version` attribute.
+	pub library_version: Option<UriLibraryVersion>,
+
+	/// The `object` attribute, ie the label of the object.
 	pub object_label: Option<String>,
-	pub pin: Option<String>,
+	/// The `id` attribute, ie the `CKA_ID` of the object.
+	pub object_id: Option<Vec<u8>>,
+	/// The `type` attribute.
+	pub object_type: Option<UriObjectType>,
+
+	/// The `pin-value` or `pin-source` query attribute.
+	///
+	/// The `pin-source` attribute can be a `file:` URI or `credential:<name>` for a systemd credential.
+	/// `pin-value` is redacted when the URI is displayed or debug-printed.
+	pub pin: Option<Pin>,
+	/// The `module-name` query attribute.
+	pub module_name: Option<String>,
+	/// The `module-path` query attribute.
+	pub module_path: Option<String>,
+
+	/// Path attributes that are not recognized, including vendor-specific ones, as key-value pairs.
+	///
+	/// RFC 7512 requires that a URI with unrecognized path attributes does not match anything,
+	/// so [`Context::find_slot`] fails for such URIs. They are kept so that the URI can be displayed as it was parsed.
+	pub unrecognized_path_attributes: Vec<(String, String)>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -47,93 +97,211 @@ pub enum UriSlotIdentifier {
 	SlotId(pkcs11_sys::CK_SLOT_ID),
 }
 
-impl std::fmt::Display for Uri {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// The value of the `library-version` attribute of a [`Uri`]. A version without a minor number has a minor number of 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UriLibraryVersion {
+	pub major: u8,
+	pub minor: u8,
+}
+
+/// The value of the `type` attribute of a [`Uri`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UriObjectType {
+	Certificate,
+	Data,
+	PrivateKey,
+	PublicKey,
+	SecretKey,
+}
+
+impl UriObjectType {
+	fn as_str(self) -> &'static str {
+		match self {
+			UriObjectType::Certificate => "cert",
+			UriObjectType::Data => "data",
+			UriObjectType::PrivateKey => "private",
+			UriObjectType::PublicKey => "public",
+			UriObjectType::SecretKey => "secret-key",
+		}
+	}
+}
+
+impl Uri {
+	/// Serializes the URI like its `Display` impl, except that `pin-value` is not redacted.
+	///
+	/// This is only for handing the URI to something that parses it again to log in, such as the openssl engine.
+	/// It must not be logged.
+	pub fn to_unredacted_string(&self) -> String {
+		struct Unredacted<'a>(&'a Uri);
+
+		impl std::fmt::Display for Unredacted<'_> {
+			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+				self.0.fmt_inner(f, false)
+			}
+		}
+
+		Unredacted(self).to_string()
+	}
+
+	fn fmt_inner(&self, f: &mut std::fmt::Formatter<'_>, redact_pin: bool) -> std::fmt::Result {
+		// Ref https://tools.ietf.org/html/rfc7512#section-2.3
+		//
+		// Characters that RFC 7512 allows in path and query attribute values (pk11-pchar and pk11-qchar) are written as they are.
+		// Everything else, including the `;`, `?` and `&` separators and `%` itself, is percent-encoded.
+		const RES_AVAIL: &percent_encoding::AsciiSet =
+			&percent_encoding::NON_ALPHANUMERIC
+			// unreserved
+			.remove(b'-').remove(b'.').remove(b'_').remove(b'~')
+			// pk11-res-avail
+			.remove(b':').remove(b'[').remove(b']').remove(b'@').remove(b'!').remove(b'$').remove(b'\'')
+			.remove(b'(').remove(b')').remove(b'*').remove(b'+').remove(b',').remove(b'=');
+		const PCHAR_ENCODE_SET: &percent_encoding::AsciiSet = &RES_AVAIL.remove(b'&');
+		const QCHAR_ENCODE_SET: &percent_encoding::AsciiSet = &RES_AVAIL.remove(b'/').remove(b'?').remove(b'|');
+		// Attribute names are split from their values at the first `=`, so it must not appear in them.
+		const ATTRIBUTE_NAME_ENCODE_SET: &percent_encoding::AsciiSet = &PCHAR_ENCODE_SET.add(b'=');
+
+		fn write_encoded(f: &mut std::fmt::Formatter<'_>, value: &str, encode_set: &'static percent_encoding::AsciiSet) -> std::fmt::Result {
+			let value = percent_encoding::utf8_percent_encode(value, encode_set);
+			for s in value {
+				write!(f, "{}", s)?;
+			}
+			Ok(())
+		}
+
+		fn write_path_attribute(f: &mut std::fmt::Formatter<'_>, key: &str, value: Option<&str>) -> std::fmt::Result {
+			if let Some(value) = value {
+				write!(f, ";{}=", key)?;
+				write_encoded(f, value, PCHAR_ENCODE_SET)?;
+			}
+			Ok(())
+		}
+
+		fn write_query_attribute(f: &mut std::fmt::Formatter<'_>, first: &mut bool, key: &str, value: Option<&str>) -> std::fmt::Result {
+			if let Some(value) = value {
+				write!(f, "{}{}=", if *first { '?' } else { '&' }, key)?;
+				write_encoded(f, value, QCHAR_ENCODE_SET)?;
+				*first = false;
+			}
+			Ok(())
+		}
+
 		write!(f, "pkcs11:")?;
 
 		match &self.slot_identifier {
 			UriSlotIdentifier::Label(token_label) => {
 				write!(f, "token=")?;
-				let value = percent_encoding::utf8_percent_encode(&*token_label, percent_encoding::NON_ALPHANUMERIC);
-				for s in value {
-					write!(f, "{}", s)?;
-				}
+				write_encoded(f, token_label, PCHAR_ENCODE_SET)?;
 			},
 
 			UriSlotIdentifier::SlotId(slot_id) => {
-				write!(f, "slot-id=")?;
-				let slot_id = slot_id.0.to_string();
-				let value = percent_encoding::utf8_percent_encode(&slot_id, percent_encoding::NON_ALPHANUMERIC);
-				for s in value {
-					write!(f, "{}", s)?;
-				}
+				write!(f, "slot-id={}", slot_id.0)?;
+				write_path_attribute(f, "token", self.token_label.as_deref())?;
 			},
 		}
 
-		if let Some(object_label) = &self.object_label {
-			write!(f, ";object=")?;
-			let value = percent_encoding::utf8_percent_encode(&*object_label, percent_encoding::NON_ALPHANUMERIC);
-			for s in value {
-				write!(f, "{}", s)?;
+		write_path_attribute(f, "manufacturer", self.token_manufacturer.as_deref())?;
+		write_path_attribute(f, "model", self.token_model.as_deref())?;
+		write_path_attribute(f, "serial", self.token_serial.as_deref())?;
+		write_path_attribute(f, "slot-description", self.slot_description.as_deref())?;
+		write_path_attribute(f, "slot-manufacturer", self.slot_manufacturer.as_deref())?;
+		write_path_attribute(f, "library-description", self.library_description.as_deref())?;
+		write_path_attribute(f, "library-manufacturer", self.library_manufacturer.as_deref())?;
+		if let Some(UriLibraryVersion { major, minor }) = self.library_version {
+			write!(f, ";library-version={}.{}", major, minor)?;
+		}
+		write_path_attribute(f, "object", self.object_label.as_deref())?;
+		if let Some(object_id) = &self.object_id {
+			// RFC 7512 recommends that id values are always fully percent-encoded, since they are usually not printable.
+			write!(f, ";id=")?;
+			for b in object_id {
+				write!(f, "%{:02X}", b)?;
 			}
 		}
+		write_path_attribute(f, "type", self.object_type.map(UriObjectType::as_str))?;
+		for (key, value) in &self.unrecognized_path_attributes {
+			write!(f, ";")?;
+			write_encoded(f, key, ATTRIBUTE_NAME_ENCODE_SET)?;
+			write!(f, "=")?;
+			write_encoded(f, value, PCHAR_ENCODE_SET)?;
+		}
 
-		if let Some(pin) = &self.pin {
-			write!(f, "?pin-value=")?;
-			let value = percent_encoding::utf8_percent_encode(&*pin, percent_encoding::NON_ALPHANUMERIC);
-			for s in value {
-				write!(f, "{}", s)?;
-			}
+		let mut first = true;
+		match &self.pin {
+			Some(Pin::Value(_)) if redact_pin => {
+				write!(f, "?pin-value=<redacted>")?;
+				first = false;
+			},
+			Some(Pin::Value(pin)) => write_query_attribute(f, &mut first, "pin-value", Some(pin))?,
+			Some(pin) => write_query_attribute(f, &mut first, "pin-source", pin.source().as_deref())?,
+			None => (),
 		}
+		write_query_attribute(f, &mut first, "module-name", self.module_name.as_deref())?;
+		write_query_attribute(f, &mut first, "module-path", self.module_path.as_deref())?;
 
 		Ok(())
 	}
 }
 
+impl std::fmt::Display for Uri {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.fmt_inner(f, true)
+	}
+}
+
 impl std::str::FromStr for Uri {
 	type Err = ParsePkcs11UriError;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
 		// Ref https://tools.ietf.org/html/rfc7512#section-2.3
 		//
-		// Only object-label, slot-id, token-label and pin-value are parsed from the URL. If both slot-id and token are provided, token is ignored.
+		// All path and query attributes defined by the RFC are parsed. One of slot-id and token is required. If both are provided, the slot is identified by slot-id
+		// and token is kept as an additional attribute that the slot's token must match.
+		//
+		// Per the RFC, path attributes must not be repeated, unrecognized path attributes (including vendor-specific ones) make the URI
+		// match nothing, and unrecognized query attributes are ignored.
 
-		enum PathComponentKey {
-			Object,
-			SlotId,
-			Token,
-		}
+		fn split_key_value_pair(s: &str) -> (std::borrow::Cow<'_, [u8]>, std::borrow::Cow<'_, [u8]>) {
+			let mut parts = s.splitn(2, '=');
+
+			let key = parts.next().expect("str::splitn() yields at least one str");
+			let key = percent_encoding::percent_decode(key.as_bytes()).into();
 
-		enum QueryComponentKey {
-			PinValue,
+			let value = parts.next().unwrap_or_default();
+			let value = percent_encoding::percent_decode(value.as_bytes()).into();
+
+			(key, value)
 		}
 
-		fn parse_key_value_pair<'a, F, T>(
-			s: &'a str,
-			mut key_discriminant: F,
-		) -> Result<Option<(T, std::borrow::Cow<'a, str>)>, ParsePkcs11UriError> where F: FnMut(&[u8]) -> Option<T> {
-			let mut parts = s.splitn(2, '=');
+		fn into_string(key: &[u8], value: std::borrow::Cow<'_, [u8]>) -> Result<String, ParsePkcs11UriError> {
+			String::from_utf8(value.into_owned()).map_err(|err| ParsePkcs11UriError::InvalidUtf8(key.to_owned(), err.into()))
+		}
 
-			let key = parts.next().expect("str::splitn() yields at least one str");
-			let key = percent_encoding::percent_decode(key.as_bytes());
-			let key: std::borrow::Cow<'a, _> = key.into();
-			if let Some(typed_key) = key_discriminant(&*key) {
-				let value = parts.next().unwrap_or_default();
-				let value = percent_encoding::percent_decode(value.as_bytes());
-				match value.decode_utf8() {
-					Ok(value) => Ok(Some((typed_key, value))),
-					Err(err) => Err(ParsePkcs11UriError::InvalidUtf8(key.into_owned(), err.into())),
-				}
-			}
-			else {
-				Ok(None)
+		fn set_once<T>(attribute: &mut Option<T>, key: &[u8], value: T) -> Result<(), ParsePkcs11UriError> {
+			if attribute.is_some() {
+				return Err(ParsePkcs11UriError::DuplicateAttribute(String::from_utf8_lossy(key).into_owned()));
 			}
+
+			*attribute = Some(value);
+			Ok(())
 		}
 
 		let mut object_label = None;
+		let mut object_id = None;
+		let mut object_type = None;
 		let mut token_label = None;
+		let mut token_manufacturer = None;
+		let mut token_model = None;
+		let mut token_serial = None;
 		let mut slot_id = None;
+		let mut slot_description = None;
+		let mut slot_manufacturer = None;
+		let mut library_description = None;
+		let mut library_manufacturer = None;
+		let mut library_version = None;
+		let mut unrecognized_path_attributes: Vec<(String, String)> = vec![];
 		let mut pin = None;
+		let mut module_name = None;
+		let mut module_path = None;
 
 		let s =
 			if s.starts_with("pkcs11:") {
@@ -143,77 +311,150 @@ impl std::str::FromStr for Uri {
 				return Err(ParsePkcs11UriError::InvalidScheme);
 			};
 
-		let mut url_parts = s.split('?');
+		let mut url_parts = s.splitn(2, '?');
 
-		let path = url_parts.next().expect("str::split() yields at least one str");
-		let path_components = path.split(';');
+		let path = url_parts.next().expect("str::splitn() yields at least one str");
+		let path_components = path.split(';').filter(|path_component| !path_component.is_empty());
 		for path_component in path_components {
-			let key_value_pair = parse_key_value_pair(path_component, |key| match key {
-				b"object" => Some(PathComponentKey::Object),
-				b"slot-id" => Some(PathComponentKey::SlotId),
-				b"token" => Some(PathComponentKey::Token),
-				_ => None,
-			})?;
-			if let Some((key, value)) = key_value_pair {
-				match key {
-					PathComponentKey::Object => {
-						object_label = Some(value.into_owned());
-					},
-					PathComponentKey::SlotId => {
-						let value = value.parse::<pkcs11_sys::CK_SLOT_ID>().map_err(|err| ParsePkcs11UriError::MalformedSlotId(value.into_owned(), err))?;
-						slot_id = Some(value);
-					},
-					PathComponentKey::Token => {
-						token_label = Some(value.into_owned());
-					},
-				}
+			let (key, value) = split_key_value_pair(path_component);
+			match &*key {
+				b"id" => set_once(&mut object_id, &key, value.into_owned())?,
+
+				b"library-description" => set_once(&mut library_description, &key, into_string(&key, value)?)?,
+
+				b"library-manufacturer" => set_once(&mut library_manufacturer, &key, into_string(&key, value)?)?,
+
+				b"library-version" => {
+					let value = into_string(&key, value)?;
+					let mut parts = value.splitn(2, '.');
+					let major = parts.next().expect("str::splitn() yields at least one str").parse();
+					let minor = parts.next().map_or(Ok(0), str::parse);
+					let (major, minor) = match (major, minor) {
+						(Ok(major), Ok(minor)) => (major, minor),
+						_ => return Err(ParsePkcs11UriError::MalformedLibraryVersion(value)),
+					};
+					set_once(&mut library_version, &key, UriLibraryVersion { major, minor })?;
+				},
+
+				b"manufacturer" => set_once(&mut token_manufacturer, &key, into_string(&key, value)?)?,
+
+				b"model" => set_once(&mut token_model, &key, into_string(&key, value)?)?,
+
+				b"object" => set_once(&mut object_label, &key, into_string(&key, value)?)?,
+
+				b"serial" => set_once(&mut token_serial, &key, into_string(&key, value)?)?,
+
+				b"slot-description" => set_once(&mut slot_description, &key, into_string(&key, value)?)?,
+
+				b"slot-id" => {
+					let value = into_string(&key, value)?;
+					let value = value.parse::<pkcs11_sys::CK_SLOT_ID>().map_err(|err| ParsePkcs11UriError::MalformedSlotId(value, err))?;
+					set_once(&mut slot_id, &key, value)?;
+				},
+
+				b"slot-manufacturer" => set_once(&mut slot_manufacturer, &key, into_string(&key, value)?)?,
+
+				b"token" => set_once(&mut token_label, &key, into_string(&key, value)?)?,
+
+				b"type" => {
+					let value = match &*value {
+						b"cert" => UriObjectType::Certificate,
+						b"data" => UriObjectType::Data,
+						b"private" => UriObjectType::PrivateKey,
+						b"public" => UriObjectType::PublicKey,
+						b"secret-key" => UriObjectType::SecretKey,
+						_ => return Err(ParsePkcs11UriError::InvalidObjectType(String::from_utf8_lossy(&value).into_owned())),
+					};
+					set_once(&mut object_type, &key, value)?;
+				},
+
+				_ => {
+					let value = into_string(&key, value)?;
+					let key = into_string(&key, key.clone())?;
+					if unrecognized_path_attributes.iter().any(|(existing_key, _)| *existing_key == key) {
+						return Err(ParsePkcs11UriError::DuplicateAttribute(key));
+					}
+					unrecognized_path_attributes.push((key, value));
+				},
 			}
 		}
 
 		let query = url_parts.next().unwrap_or_default();
-		let query_components = query.split('&');
+		let query_components = query.split('&').filter(|query_component| !query_component.is_empty());
 		for query_component in query_components {
-			let key_value_pair = parse_key_value_pair(query_component, |key| match key {
-				b"pin-value" => Some(QueryComponentKey::PinValue),
-				_ => None,
-			})?;
-			if let Some((key, value)) = key_value_pair {
-				match key {
-					QueryComponentKey::PinValue => {
-						pin = Some(value.into_owned());
-					},
-				}
+			let (key, value) = split_key_value_pair(query_component);
+			match &*key {
+				b"module-name" => module_name = Some(into_string(&key, value)?),
+				b"module-path" => module_path = Some(into_string(&key, value)?),
+				b"pin-source" => {
+					let value = into_string(&key, value)?;
+					let value = Pin::from_source(&value).map_err(|err| ParsePkcs11UriError::MalformedPinSource(value, err))?;
+					if pin.replace(value).is_some() {
+						return Err(ParsePkcs11UriError::MultiplePins);
+					}
+				},
+				b"pin-value" => {
+					let value = Pin::Value(into_string(&key, value)?);
+					if pin.replace(value).is_some() {
+						return Err(ParsePkcs11UriError::MultiplePins);
+					}
+				},
+				_ => (),
 			}
 		}
 
-		let slot_identifier = match (token_label, slot_id) {
-			(_, Some(slot_id)) => UriSlotIdentifier::SlotId(slot_id),
-			(Some(token_label), _) => UriSlotIdentifier::Label(token_label),
+		let (slot_identifier, token_label) = match (token_label, slot_id) {
+			(token_label, Some(slot_id)) => (UriSlotIdentifier::SlotId(slot_id), token_label),
+			(Some(token_label), None) => (UriSlotIdentifier::Label(token_label), None),
 			(None, None) => return Err(ParsePkcs11UriError::NeitherSlotIdNorTokenSpecified),
 		};
 
 		Ok(Uri {
 			slot_identifier,
+			token_label,
+			token_manufacturer,
+			token_model,
+			token_serial,
+			slot_description,
+			slot_manufacturer,
+			library_description,
+			library_manufacturer,
+			library_version,
 			object_label,
+			object_id,
+			object_type,
 			pin,
+			module_name,
+			module_path,
+			unrecognized_path_attributes,
 		})
 	}
 }
 
 #[derive(Debug)]
 pub enum ParsePkcs11UriError {
+	DuplicateAttribute(String),
+	InvalidObjectType(String),
 	InvalidScheme,
 	InvalidUtf8(Vec<u8>, Box<dyn std::error::Error>),
+	MalformedLibraryVersion(String),
+	MalformedPinSource(String, Box<dyn std::error::Error>),
 	MalformedSlotId(String, <pkcs11_sys::CK_SLOT_ID as std::str::FromStr>::Err),
+	MultiplePins,
 	NeitherSlotIdNorTokenSpecified,
 }
 
 impl std::fmt::Display for ParsePkcs11UriError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
+			ParsePkcs11UriError::DuplicateAttribute(key) => write!(f, "URI has more than one [{}] path component", key),
+			ParsePkcs11UriError::InvalidObjectType(value) => write!(f, "type path component has invalid value [{}]", value),
 			ParsePkcs11UriError::InvalidScheme => f.write_str("URI does not have pkcs11 scheme"),
 			ParsePkcs11UriError::InvalidUtf8(key, _) => write!(f, "URI component with key [{:?}] is not valid UTF-8", key),
-			ParsePkcs11UriError::MalformedSlotId(value, _) => write!(f, "pin-value path component has malformed value [{}]", value),
+			ParsePkcs11UriError::MalformedLibraryVersion(value) => write!(f, "library-version path component has malformed value [{}]", value),
+			ParsePkcs11UriError::MalformedPinSource(value, _) => write!(f, "pin-source query component has malformed value [{}]", value),
+			ParsePkcs11UriError::MalformedSlotId(value, _) => write!(f, "slot-id path component has malformed value [{}]", value),
+			ParsePkcs11UriError::MultiplePins => f.write_str("URI has more than one [pin-value] or [pin-source] query component"),
 			ParsePkcs11UriError::NeitherSlotIdNorTokenSpecified => f.write_str("URI has neither [slot-id] nor [token] components"),
 		}
 	}
@@ -223,9 +464,14 @@ impl std::error::Error for ParsePkcs11UriError {
 	#[allow(clippy::match_same_arms)]
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
+			ParsePkcs11UriError::DuplicateAttribute(_) => None,
+			ParsePkcs11UriError::InvalidObjectType(_) => None,
 			ParsePkcs11UriError::InvalidScheme => None,
 			ParsePkcs11UriError::InvalidUtf8(_, inner) => Some(&**inner),
+			ParsePkcs11UriError::MalformedLibraryVersion(_) => None,
+			ParsePkcs11UriError::MalformedPinSource(_, inner) => Some(&**inner),
 			ParsePkcs11UriError::MalformedSlotId(_, inner) => Some(inner),
+			ParsePkcs11UriError::MultiplePins => None,
 			ParsePkcs11UriError::NeitherSlotIdNorTokenSpecified => None,
 		}
 	}
@@ -297,6 +543,105 @@ mod tests {
 		let _ = "pkcs11:".parse::<super::Uri>().expect_err("expect URI with neither label nor slot ID to fail to parse");
 	}
 
+	#[test]
+	fn parse_pkcs11_uri_rfc7512_attributes() {
+		// Examples from https://tools.ietf.org/html/rfc7512#section-3
+
+		let uri: super::Uri =
+			"pkcs11:token=The%20Software%20PKCS%2311%20Softtoken;manufacturer=Snake%20Oil,%20Inc.;model=1.0;object=my-certificate;type=cert;id=%69%95%3E%5C%F4%BD%EC%91;serial=?pin-source=file:/etc/token_pin"
+			.parse().unwrap();
+		assert_eq!(uri.slot_identifier, super::UriSlotIdentifier::Label("The Software PKCS#11 Softtoken".to_owned()));
+		assert_eq!(uri.token_manufacturer.as_deref(), Some("Snake Oil, Inc."));
+		assert_eq!(uri.token_model.as_deref(), Some("1.0"));
+		assert_eq!(uri.token_serial.as_deref(), Some(""));
+		assert_eq!(uri.object_label.as_deref(), Some("my-certificate"));
+		assert_eq!(uri.object_type, Some(super::UriObjectType::Certificate));
+		assert_eq!(uri.object_id.as_deref(), Some(&b"\x69\x95\x3e\x5c\xf4\xbd\xec\x91"[..]));
+		assert_eq!(uri.pin, Some(super::Pin::File("/etc/token_pin".into())));
+
+		let uri: super::Uri =
+			"pkcs11:slot-id=1;slot-description=Sun%20Metaslot;slot-manufacturer=Snake%20Oil,%20Inc.;library-manufacturer=Snake%20Oil,%20Inc.;library-description=Soft%20Token%20Library;library-version=1.23?module-name=mypkcs11&module-path=/usr/lib/libmypkcs11.so.1"
+			.parse().unwrap();
+		assert_eq!(uri.slot_identifier, super::UriSlotIdentifier::SlotId(pkcs11_sys::CK_SLOT_ID(1)));
+		assert_eq!(uri.slot_description.as_deref(), Some("Sun Metaslot"));
+		assert_eq!(uri.slot_manufacturer.as_deref(), Some("Snake Oil, Inc."));
+		assert_eq!(uri.library_manufacturer.as_deref(), Some("Snake Oil, Inc."));
+		assert_eq!(uri.library_description.as_deref(), Some("Soft Token Library"));
+		assert_eq!(uri.library_version, Some(super::UriLibraryVersion { major: 1, minor: 23 }));
+		assert_eq!(uri.module_name.as_deref(), Some("mypkcs11"));
+		assert_eq!(uri.module_path.as_deref(), Some("/usr/lib/libmypkcs11.so.1"));
+
+		let uri: super::Uri = "pkcs11:token=foo;library-version=7".parse().unwrap();
+		assert_eq!(uri.library_version, Some(super::UriLibraryVersion { major: 7, minor: 0 }));
+
+		// Unrecognized path attributes are kept, unrecognized query attributes are ignored
+		let uri: super::Uri = "pkcs11:token=foo;x-vendor-attr=bar%20baz?x-other=quux&pin-value=1234".parse().unwrap();
+		assert_eq!(uri.unrecognized_path_attributes, vec![("x-vendor-attr".to_owned(), "bar baz".to_owned())]);
+		assert_eq!(uri.pin, Some(super::Pin::Value("1234".to_owned())));
+
+		let _ = "pkcs11:token=foo;token=bar".parse::<super::Uri>().expect_err("expect URI with repeated path attribute to fail to parse");
+		let _ = "pkcs11:token=foo;x-a=1;x-a=2".parse::<super::Uri>().expect_err("expect URI with repeated path attribute to fail to parse");
+		let _ = "pkcs11:token=foo;type=key".parse::<super::Uri>().expect_err("expect URI with invalid type to fail to parse");
+		let _ = "pkcs11:token=foo;library-version=1.2.3".parse::<super::Uri>().expect_err("expect URI with malformed library-version to fail to parse");
+		let _ = "pkcs11:token=foo;library-version=".parse::<super::Uri>().expect_err("expect URI with malformed library-version to fail to parse");
+	}
+
+	#[test]
+	fn parse_pkcs11_uri_pin_source() {
+		let uri: super::Uri = "pkcs11:token=foo?pin-source=file:///etc/aziot/keyd/pin".parse().unwrap();
+		assert_eq!(uri.pin, Some(super::Pin::File("/etc/aziot/keyd/pin".into())));
+
+		let uri: super::Uri = "pkcs11:token=foo?pin-source=credential:aziot-keyd-pin".parse().unwrap();
+		assert_eq!(uri.pin, Some(super::Pin::Credential("aziot-keyd-pin".to_owned())));
+
+		let _ = "pkcs11:token=foo?pin-source=credential:".parse::<super::Uri>().expect_err("expect URI with empty credential name to fail to parse");
+		let _ = "pkcs11:token=foo?pin-source=credential:../pin".parse::<super::Uri>().expect_err("expect URI with invalid credential name to fail to parse");
+		let _ = "pkcs11:token=foo?pin-source=%7C/usr/bin/get-pin".parse::<super::Uri>().expect_err("expect URI with unsupported pin-source to fail to parse");
+		let _ =
+			"pkcs11:token=foo?pin-value=1234&pin-source=credential:aziot-keyd-pin".parse::<super::Uri>()
+			.expect_err("expect URI with both pin-value and pin-source to fail to parse");
+	}
+
+	#[test]
+	fn redact_pkcs11_uri_pin() {
+		let uri: super::Uri = "pkcs11:token=foo?pin-value=1234".parse().unwrap();
+		assert_eq!(uri.to_string(), "pkcs11:token=foo?pin-value=<redacted>");
+		assert!(!format!("{:?}", uri).contains("1234"));
+
+		let unredacted = uri.to_unredacted_string();
+		assert_eq!(unredacted, "pkcs11:token=foo?pin-value=1234");
+		assert_eq!(unredacted.parse::<super::Uri>().unwrap(), uri);
+	}
+
+	#[test]
+	fn display_pkcs11_uri() {
+		for &uri_string in &[
+			"pkcs11:token=foo%20bar",
+			"pkcs11:slot-id=1;object=baz?pin-source=credential:pin",
+			"pkcs11:slot-id=1;token=foo%20bar;object=baz",
+			"pkcs11:token=a%3Bb%3Fc&d;object=e%25f?module-path=/g%26h?i|j",
+			"pkcs11:token=The%20Software%20PKCS%2311%20Softtoken;manufacturer=Snake%20Oil,%20Inc.;model=1.0;serial=;object=my-certificate;id=%69%95%3E%5C%F4%BD%EC%91;type=cert?pin-source=file:///etc/token_pin",
+			"pkcs11:slot-id=2;slot-description=Sun%20Metaslot;library-version=1.23;type=private;x-vendor-attr=1?module-path=/usr/lib/libmypkcs11.so",
+		] {
+			let uri: super::Uri = uri_string.parse().unwrap();
+			assert_eq!(uri.to_string(), uri_string);
+		}
+	}
+
+	#[test]
+	fn object_template_from_pkcs11_uri() {
+		let uri: super::Uri = "pkcs11:token=foo;object=bar;id=%01%02;type=private".parse().unwrap();
+		assert_eq!(super::ObjectTemplate::from_uri(&uri), super::ObjectTemplate {
+			class: Some(pkcs11_sys::CKO_PRIVATE_KEY),
+			key_type: None,
+			label: Some("bar".to_owned()),
+			id: Some(vec![0x01, 0x02]),
+		});
+
+		let uri: super::Uri = "pkcs11:token=foo".parse().unwrap();
+		assert_eq!(super::ObjectTemplate::from_uri(&uri), Default::default());
+	}
+
 	fn parse_pkcs11_uri_inner(
 		uri_string: &str,
 		slot_id: Option<pkcs11_sys::CK_ULONG>,
@@ -313,18 +658,21 @@ mod tests {
 			// One of slot ID or token label is required
 			(None, None, Err(_)) => return,
 
-			// If slot ID is given, it is used, even if token label is given
+			// If slot ID is given, it is used, and the token label is kept alongside it if given
 			(
 				Some(expected_slot_id),
-				_,
-				Ok(super::Uri { slot_identifier: super::UriSlotIdentifier::SlotId(pkcs11_sys::CK_SLOT_ID(actual_slot_id)), .. }),
-			) => assert_eq!(expected_slot_id, *actual_slot_id),
+				expected_token_label,
+				Ok(super::Uri { slot_identifier: super::UriSlotIdentifier::SlotId(pkcs11_sys::CK_SLOT_ID(actual_slot_id)), token_label: actual_token_label, .. }),
+			) => {
+				assert_eq!(expected_slot_id, *actual_slot_id);
+				assert_eq!(expected_token_label, actual_token_label.as_deref());
+			},
 
 			// If slot ID is not given and token label is, then token label is used
 			(
 				None,
 				Some(expected_token_label),
-				Ok(super::Uri { slot_identifier: super::UriSlotIdentifier::Label(actual_token_label), .. }),
+				Ok(super::Uri { slot_identifier: super::UriSlotIdentifier::Label(actual_token_label), token_label: None, .. }),
 			) => assert_eq!(expected_token_label, actual_token_label),
 
 			(slot_id, token_label, uri) =>
@@ -348,7 +696,7 @@ mod tests {
 		match (pin, &uri) {
 			(
 				Some(expected_pin),
-				super::Uri { pin: Some(actual_pin), .. },
+				super::Uri { pin: Some(super::Pin::Value(actual_pin)), .. },
 			) => assert_eq!(expected_pin, actual_pin),
 
 			(None, super::Uri { pin: None, .. }) => (),
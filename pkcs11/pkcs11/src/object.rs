@@ -1,7 +1,14 @@
 /// A reference to an object stored in a slot.
 pub struct Object<T> {
 	session: std::sync::Arc<crate::Session>,
-	handle: pkcs11_sys::CK_OBJECT_HANDLE,
+
+	/// The handle and the generation of the session it was found in. See [`crate::Session::generation`].
+	handle: std::sync::Mutex<(pkcs11_sys::CK_OBJECT_HANDLE, u64)>,
+
+	/// How to find this object again if the session is lost. `None` if the object can't be found again,
+	/// in which case the original handle continues to be used.
+	lookup: Option<ObjectLookup>,
+
 	_key: std::marker::PhantomData<T>,
 }
 
@@ -9,40 +16,116 @@ impl<T> Object<T> {
 	pub(crate) fn new(
 		session: std::sync::Arc<crate::Session>,
 		handle: pkcs11_sys::CK_OBJECT_HANDLE,
+		lookup: Option<ObjectLookup>,
 	) -> Self {
+		let generation = session.generation();
+
 		Object {
 			session,
-			handle,
+			handle: std::sync::Mutex::new((handle, generation)),
+			lookup,
 			_key: Default::default(),
 		}
 	}
+
+	/// Get the handle of this object in the current session, finding the object again if the session has been opened again since the handle was found.
+	fn handle(&self) -> Result<pkcs11_sys::CK_OBJECT_HANDLE, crate::RecoverSessionError> {
+		let mut handle = self.handle.lock().expect("object handle mutex is poisoned");
+
+		let generation = self.session.generation();
+		if handle.1 != generation {
+			if let Some(lookup) = &self.lookup {
+				handle.0 = unsafe { lookup.find(&self.session) }.map_err(|err| crate::RecoverSessionError::FindObjectFailed(Box::new(err)))?;
+			}
+			handle.1 = generation;
+		}
+
+		Ok(handle.0)
+	}
+
+	/// Runs the given operation with the handle of this object, recovering the session and retrying the operation once if the session was lost.
+	fn with_recovery<U, E>(&self, mut f: impl FnMut(pkcs11_sys::CK_OBJECT_HANDLE) -> Result<U, E>) -> Result<U, E> where E: crate::session::RecoverableError {
+		self.session.with_recovery(|| {
+			let handle = self.handle().map_err(E::recover_session_failed)?;
+			f(handle)
+		})
+	}
+}
+
+/// The attributes that an object was found with, so that it can be found again in a new session.
+pub(crate) struct ObjectLookup {
+	pub(crate) class: pkcs11_sys::CK_OBJECT_CLASS,
+	pub(crate) key_type: Option<pkcs11_sys::CK_KEY_TYPE>,
+	pub(crate) label: Option<String>,
+	pub(crate) id: Option<Vec<u8>>,
+}
+
+impl ObjectLookup {
+	pub(crate) fn new(class: pkcs11_sys::CK_OBJECT_CLASS, uri: &crate::Uri) -> Self {
+		ObjectLookup {
+			class,
+			key_type: None,
+			label: uri.object_label.clone(),
+			id: uri.object_id.clone(),
+		}
+	}
+
+	pub(crate) unsafe fn find(&self, session: &crate::Session) -> Result<pkcs11_sys::CK_OBJECT_HANDLE, crate::GetKeyError> {
+		session.get_key_inner(self.class, self.key_type, self.label.as_deref(), self.id.as_deref())
+	}
 }
 
 impl Object<openssl::ec::EcKey<openssl::pkey::Public>> {
 	/// Get the EC parameters of this EC public key object.
+	///
+	/// This fails for Ed25519 keys, since openssl cannot represent them as `EC_KEY`. Use [`Object::curve`] and [`Object::point`] instead.
 	pub fn parameters(&self) -> Result<openssl::ec::EcKey<openssl::pkey::Public>, GetKeyParametersError> {
-		unsafe {
+		let curve = self.curve()?;
+		let curve = curve.as_nid();
+		let mut group = openssl::ec::EcGroup::from_curve_name(curve).map_err(GetKeyParametersError::ConvertToOpenssl)?;
+
+		group.set_asn1_flag(openssl::ec::Asn1Flag::NAMED_CURVE);
+
+		let point = self.point()?;
+		let mut big_num_context = openssl::bn::BigNumContext::new().map_err(GetKeyParametersError::ConvertToOpenssl)?;
+		let point = openssl::ec::EcPoint::from_bytes(&group, &point, &mut big_num_context).map_err(GetKeyParametersError::ConvertToOpenssl)?;
+
+		let parameters = openssl::ec::EcKey::<openssl::pkey::Public>::from_public_key(
+			&group,
+			&point,
+		).map_err(GetKeyParametersError::ConvertToOpenssl)?;
+
+		Ok(parameters)
+	}
+
+	/// Get the curve of this EC public key object.
+	pub fn curve(&self) -> Result<openssl2::EcCurve, GetKeyParametersError> {
+		self.with_recovery(|handle| unsafe {
 			let curve = get_attribute_value_byte_buf(
 				&self.session,
-				self,
+				handle,
 				pkcs11_sys::CKA_EC_PARAMS,
 				self.session.context.C_GetAttributeValue,
-			)?;
+			).map_err(GetKeyParametersError::GetAttributeValueFailed)?;
 			let curve = openssl2::EcCurve::from_oid_der(&curve).ok_or_else(|| GetKeyParametersError::UnrecognizedEcCurve(curve))?;
-			let curve = curve.as_nid();
-			let mut group = openssl::ec::EcGroup::from_curve_name(curve).map_err(GetKeyParametersError::ConvertToOpenssl)?;
-
-			group.set_asn1_flag(openssl::ec::Asn1Flag::NAMED_CURVE);
+			Ok(curve)
+		})
+	}
 
+	/// Get the point of this EC public key object.
+	///
+	/// For NIST curves this is the point in the RFC 5480 format. For Ed25519 this is the 32-byte public key.
+	pub fn point(&self) -> Result<Vec<u8>, GetKeyParametersError> {
+		self.with_recovery(|handle| unsafe {
 			// CKA_EC_POINT returns a DER encoded octet string representing the point.
 			//
 			// The octet string is in the RFC 5480 format which is exactly what EC_POINT_oct2point expected, so we just need to strip the DER type and length prefix.
 			let point = get_attribute_value_byte_buf(
 				&self.session,
-				self,
+				handle,
 				pkcs11_sys::CKA_EC_POINT,
 				self.session.context.C_GetAttributeValue,
-			)?;
+			).map_err(GetKeyParametersError::GetAttributeValueFailed)?;
 			let point =
 				openssl_sys2::d2i_ASN1_OCTET_STRING(
 					std::ptr::null_mut(),
@@ -53,16 +136,8 @@ impl Object<openssl::ec::EcKey<openssl::pkey::Public>> {
 				return Err(GetKeyParametersError::MalformedEcPoint(openssl::error::ErrorStack::get()));
 			}
 			let point: openssl::asn1::Asn1String = foreign_types_shared::ForeignType::from_ptr(point);
-			let mut big_num_context = openssl::bn::BigNumContext::new().map_err(GetKeyParametersError::ConvertToOpenssl)?;
-			let point = openssl::ec::EcPoint::from_bytes(&group, point.as_slice(), &mut big_num_context).map_err(GetKeyParametersError::ConvertToOpenssl)?;
-
-			let parameters = openssl::ec::EcKey::<openssl::pkey::Public>::from_public_key(
-				&group,
-				&point,
-			).map_err(GetKeyParametersError::ConvertToOpenssl)?;
-
-			Ok(parameters)
-		}
+			Ok(point.as_slice().to_owned())
+		})
 	}
 }
 
@@ -72,15 +147,33 @@ pub enum GetKeyParametersError {
 	ConvertToOpenssl(openssl::error::ErrorStack),
 	GetAttributeValueFailed(pkcs11_sys::CK_RV),
 	MalformedEcPoint(openssl::error::ErrorStack),
+	SessionRecoveryFailed(crate::RecoverSessionError),
 	UnrecognizedEcCurve(Vec<u8>),
 }
 
+impl crate::session::RecoverableError for GetKeyParametersError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			GetKeyParametersError::GetAttributeValueFailed(result) => crate::session::session_lost(*result),
+			GetKeyParametersError::ConvertToOpenssl(_) |
+			GetKeyParametersError::MalformedEcPoint(_) |
+			GetKeyParametersError::SessionRecoveryFailed(_) |
+			GetKeyParametersError::UnrecognizedEcCurve(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: crate::RecoverSessionError) -> Self {
+		GetKeyParametersError::SessionRecoveryFailed(err)
+	}
+}
+
 impl std::fmt::Display for GetKeyParametersError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
 			GetKeyParametersError::ConvertToOpenssl(_) => write!(f, "could not convert components to openssl types"),
 			GetKeyParametersError::GetAttributeValueFailed(result) => write!(f, "C_GetAttributeValue failed with {}", result),
 			GetKeyParametersError::MalformedEcPoint(_) => write!(f, "could not parse the DER-encoded EC point"),
+			GetKeyParametersError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
 			GetKeyParametersError::UnrecognizedEcCurve(curve) => write!(f, "the EC point is using an unknown curve: {:?}", curve),
 		}
 	}
@@ -89,21 +182,21 @@ impl std::fmt::Display for GetKeyParametersError {
 impl Object<openssl::rsa::Rsa<openssl::pkey::Public>> {
 	/// Get the RSA parameters of this RSA public key object.
 	pub fn parameters(&self) -> Result<openssl::rsa::Rsa<openssl::pkey::Public>, GetKeyParametersError> {
-		unsafe {
+		self.with_recovery(|handle| unsafe {
 			let modulus = get_attribute_value_byte_buf(
 				&self.session,
-				self,
+				handle,
 				pkcs11_sys::CKA_MODULUS,
 				self.session.context.C_GetAttributeValue,
-			)?;
+			).map_err(GetKeyParametersError::GetAttributeValueFailed)?;
 			let modulus = openssl::bn::BigNum::from_slice(&modulus).map_err(GetKeyParametersError::ConvertToOpenssl)?;
 
 			let public_exponent = get_attribute_value_byte_buf(
 				&self.session,
-				self,
+				handle,
 				pkcs11_sys::CKA_PUBLIC_EXPONENT,
 				self.session.context.C_GetAttributeValue,
-			)?;
+			).map_err(GetKeyParametersError::GetAttributeValueFailed)?;
 			let public_exponent = openssl::bn::BigNum::from_slice(&public_exponent).map_err(GetKeyParametersError::ConvertToOpenssl)?;
 
 			let parameters = openssl::rsa::Rsa::<openssl::pkey::Public>::from_public_components(
@@ -111,7 +204,7 @@ impl Object<openssl::rsa::Rsa<openssl::pkey::Public>> {
 				public_exponent,
 			).map_err(GetKeyParametersError::ConvertToOpenssl)?;
 			Ok(parameters)
-		}
+		})
 	}
 }
 
@@ -122,6 +215,7 @@ impl std::error::Error for GetKeyParametersError {
 			GetKeyParametersError::ConvertToOpenssl(inner) => Some(inner),
 			GetKeyParametersError::GetAttributeValueFailed(_) => None,
 			GetKeyParametersError::MalformedEcPoint(inner) => Some(inner),
+			GetKeyParametersError::SessionRecoveryFailed(inner) => Some(inner),
 			GetKeyParametersError::UnrecognizedEcCurve(_) => None,
 		}
 	}
@@ -130,20 +224,31 @@ impl std::error::Error for GetKeyParametersError {
 impl Object<openssl::ec::EcKey<openssl::pkey::Private>> {
 	/// Use this key to sign the given digest and store the result into the given signature buffer.
 	pub fn sign(&self, digest: &[u8], signature: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, SignError> {
-		unsafe {
+		self.sign_inner(pkcs11_sys::CKM_ECDSA, digest, signature)
+	}
+
+	/// Use this Ed25519 key to sign the given message without pre-hashing it and store the result into the given signature buffer.
+	pub fn sign_eddsa(&self, message: &[u8], signature: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, SignError> {
+		self.sign_inner(pkcs11_sys::CKM_EDDSA, message, signature)
+	}
+}
+
+impl<T> Object<T> {
+	fn sign_inner(&self, mechanism: pkcs11_sys::CK_MECHANISM_TYPE, digest: &[u8], signature: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, SignError> {
+		self.with_recovery(|handle| unsafe {
 			// Signing with the private key needs login
 			self.session.login().map_err(SignError::LoginFailed)?;
 
 			let mechanism = pkcs11_sys::CK_MECHANISM_IN {
-				mechanism: pkcs11_sys::CKM_ECDSA,
+				mechanism,
 				pParameter: std::ptr::null(),
 				ulParameterLen: 0,
 			};
 			let result =
 				(self.session.context.C_SignInit)(
-					self.session.handle,
+					self.session.handle(),
 					&mechanism,
-					self.handle,
+					handle,
 				);
 			if result != pkcs11_sys::CKR_OK {
 				return Err(SignError::SignInitFailed(result));
@@ -154,7 +259,7 @@ impl Object<openssl::ec::EcKey<openssl::pkey::Private>> {
 
 			let result =
 				(self.session.context.C_Sign)(
-					self.session.handle,
+					self.session.handle(),
 					digest.as_ptr(),
 					std::convert::TryInto::try_into(digest.len()).expect("usize -> CK_ULONG"),
 					signature.as_mut_ptr(),
@@ -166,6 +271,147 @@ impl Object<openssl::ec::EcKey<openssl::pkey::Private>> {
 			assert!(signature_len <= original_signature_len);
 
 			Ok(signature_len)
+		})
+	}
+}
+
+impl Object<openssl::ec::EcKey<openssl::pkey::Private>> {
+	/// Use this key to derive a shared secret with the given peer public key via ECDH.
+	///
+	/// `peer_public_key` is the peer's EC point in uncompressed form. The shared secret is the raw X coordinate of the derived point.
+	pub fn derive_shared_secret(&self, peer_public_key: &[u8]) -> Result<Vec<u8>, DeriveError> {
+		self.with_recovery(|handle| unsafe {
+			// Deriving with the private key needs login
+			self.session.login().map_err(DeriveError::LoginFailed)?;
+
+			let parameters = pkcs11_sys::CK_ECDH1_DERIVE_PARAMS {
+				kdf: pkcs11_sys::CKD_NULL,
+				ulSharedDataLen: 0,
+				pSharedData: std::ptr::null(),
+				ulPublicDataLen: std::convert::TryInto::try_into(peer_public_key.len()).expect("usize -> CK_ULONG"),
+				pPublicData: peer_public_key.as_ptr(),
+			};
+			let mechanism = pkcs11_sys::CK_MECHANISM_IN {
+				mechanism: pkcs11_sys::CKM_ECDH1_DERIVE,
+				pParameter: &parameters as *const _ as _,
+				ulParameterLen: std::convert::TryInto::try_into(std::mem::size_of_val(&parameters)).expect("usize -> CK_ULONG"),
+			};
+
+			let class = pkcs11_sys::CKO_SECRET_KEY;
+			let key_type = pkcs11_sys::CKK_GENERIC_SECRET;
+			let r#true = pkcs11_sys::CK_TRUE;
+			let r#false = pkcs11_sys::CK_FALSE;
+
+			// The derived secret is a session object that can be extracted, so that its value can be read and returned to the caller.
+			let template = [
+				pkcs11_sys::CK_ATTRIBUTE_IN {
+					r#type: pkcs11_sys::CKA_CLASS,
+					pValue: &class as *const _ as _,
+					ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&class)).expect("usize -> CK_ULONG"),
+				},
+				pkcs11_sys::CK_ATTRIBUTE_IN {
+					r#type: pkcs11_sys::CKA_KEY_TYPE,
+					pValue: &key_type as *const _ as _,
+					ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&key_type)).expect("usize -> CK_ULONG"),
+				},
+				pkcs11_sys::CK_ATTRIBUTE_IN {
+					r#type: pkcs11_sys::CKA_TOKEN,
+					pValue: &r#false as *const _ as _,
+					ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&r#false)).expect("usize -> CK_ULONG"),
+				},
+				pkcs11_sys::CK_ATTRIBUTE_IN {
+					r#type: pkcs11_sys::CKA_SENSITIVE,
+					pValue: &r#false as *const _ as _,
+					ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&r#false)).expect("usize -> CK_ULONG"),
+				},
+				pkcs11_sys::CK_ATTRIBUTE_IN {
+					r#type: pkcs11_sys::CKA_EXTRACTABLE,
+					pValue: &r#true as *const _ as _,
+					ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&r#true)).expect("usize -> CK_ULONG"),
+				},
+			];
+
+			let mut shared_secret_handle = pkcs11_sys::CK_INVALID_OBJECT_HANDLE;
+
+			let result =
+				(self.session.context.C_DeriveKey)(
+					self.session.handle(),
+					&mechanism,
+					handle,
+					template.as_ptr(),
+					std::convert::TryInto::try_into(template.len()).expect("usize -> CK_ULONG"),
+					&mut shared_secret_handle,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(DeriveError::DeriveKeyFailed(result));
+			}
+			if shared_secret_handle == pkcs11_sys::CK_INVALID_OBJECT_HANDLE {
+				return Err(DeriveError::DeriveKeyDidNotReturnHandle);
+			}
+
+			let value = get_attribute_value_byte_buf(
+				&self.session,
+				shared_secret_handle,
+				pkcs11_sys::CKA_VALUE,
+				self.session.context.C_GetAttributeValue,
+			).map_err(GetKeyParametersError::GetAttributeValueFailed);
+
+			// The secret is a session object so it would be destroyed when the session is closed anyway,
+			// but don't leave it around for longer than necessary.
+			let _ = (self.session.context.C_DestroyObject)(self.session.handle(), shared_secret_handle);
+
+			let value = value.map_err(DeriveError::GetValueFailed)?;
+			Ok(value)
+		})
+	}
+}
+
+#[derive(Debug)]
+pub enum DeriveError {
+	DeriveKeyDidNotReturnHandle,
+	DeriveKeyFailed(pkcs11_sys::CK_RV),
+	GetValueFailed(GetKeyParametersError),
+	LoginFailed(crate::LoginError),
+	SessionRecoveryFailed(crate::RecoverSessionError),
+}
+
+impl crate::session::RecoverableError for DeriveError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			DeriveError::DeriveKeyFailed(result) => crate::session::session_lost(*result),
+			DeriveError::GetValueFailed(inner) => inner.session_lost(),
+			DeriveError::LoginFailed(inner) => inner.session_lost(),
+			DeriveError::DeriveKeyDidNotReturnHandle |
+			DeriveError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: crate::RecoverSessionError) -> Self {
+		DeriveError::SessionRecoveryFailed(err)
+	}
+}
+
+impl std::fmt::Display for DeriveError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DeriveError::DeriveKeyDidNotReturnHandle => f.write_str("C_DeriveKey succeeded but did not return a handle"),
+			DeriveError::DeriveKeyFailed(result) => write!(f, "C_DeriveKey failed with {}", result),
+			DeriveError::GetValueFailed(_) => f.write_str("could not get the value of the derived key"),
+			DeriveError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			DeriveError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
+		}
+	}
+}
+
+impl std::error::Error for DeriveError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			DeriveError::DeriveKeyDidNotReturnHandle => None,
+			DeriveError::DeriveKeyFailed(_) => None,
+			DeriveError::GetValueFailed(inner) => Some(inner),
+			DeriveError::LoginFailed(inner) => Some(inner),
+			DeriveError::SessionRecoveryFailed(inner) => Some(inner),
 		}
 	}
 }
@@ -178,7 +424,7 @@ pub enum RsaSignMechanism {
 impl Object<openssl::rsa::Rsa<openssl::pkey::Private>> {
 	/// Use this key to sign the given digest with the given mechanism type and store the result into the given signature buffer.
 	pub fn sign(&self, mechanism: &RsaSignMechanism, digest: &[u8], signature: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, SignError> {
-		unsafe {
+		self.with_recovery(|handle| unsafe {
 			// Signing with the private key needs login
 			self.session.login().map_err(SignError::LoginFailed)?;
 
@@ -197,9 +443,9 @@ impl Object<openssl::rsa::Rsa<openssl::pkey::Private>> {
 			};
 			let result =
 				(self.session.context.C_SignInit)(
-					self.session.handle,
+					self.session.handle(),
 					&mechanism,
-					self.handle,
+					handle,
 				);
 			if result != pkcs11_sys::CKR_OK {
 				return Err(SignError::SignInitFailed(result));
@@ -210,7 +456,7 @@ impl Object<openssl::rsa::Rsa<openssl::pkey::Private>> {
 
 			let result =
 				(self.session.context.C_Sign)(
-					self.session.handle,
+					self.session.handle(),
 					digest.as_ptr(),
 					std::convert::TryInto::try_into(digest.len()).expect("usize -> CK_ULONG"),
 					signature.as_mut_ptr(),
@@ -222,7 +468,7 @@ impl Object<openssl::rsa::Rsa<openssl::pkey::Private>> {
 			assert!(signature_len <= original_signature_len);
 
 			Ok(signature_len)
-		}
+		})
 	}
 }
 
@@ -230,14 +476,31 @@ impl Object<openssl::rsa::Rsa<openssl::pkey::Private>> {
 #[allow(clippy::pub_enum_variant_names)]
 pub enum SignError {
 	LoginFailed(crate::LoginError),
+	SessionRecoveryFailed(crate::RecoverSessionError),
 	SignInitFailed(pkcs11_sys::CK_RV),
 	SignFailed(pkcs11_sys::CK_RV),
 }
 
+impl crate::session::RecoverableError for SignError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			SignError::LoginFailed(inner) => inner.session_lost(),
+			SignError::SessionRecoveryFailed(_) => None,
+			SignError::SignInitFailed(result) |
+			SignError::SignFailed(result) => crate::session::session_lost(*result),
+		}
+	}
+
+	fn recover_session_failed(err: crate::RecoverSessionError) -> Self {
+		SignError::SessionRecoveryFailed(err)
+	}
+}
+
 impl std::fmt::Display for SignError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
 			SignError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			SignError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
 			SignError::SignInitFailed(result) => write!(f, "C_SignInit failed with {}", result),
 			SignError::SignFailed(result) => write!(f, "C_Sign failed with {}", result),
 		}
@@ -249,26 +512,46 @@ impl std::error::Error for SignError {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
 			SignError::LoginFailed(inner) => Some(inner),
+			SignError::SessionRecoveryFailed(inner) => Some(inner),
 			SignError::SignInitFailed(_) => None,
 			SignError::SignFailed(_) => None,
 		}
 	}
 }
 
-impl Object<openssl::rsa::Rsa<openssl::pkey::Public>> {
-	/// Use this key to encrypt the given plaintext and store the result into the given ciphertext buffer.
-	pub fn encrypt(&self, mechanism: pkcs11_sys::CK_MECHANISM_TYPE, plaintext: &[u8], ciphertext: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, EncryptError> {
-		unsafe {
-			let mechanism = pkcs11_sys::CK_MECHANISM_IN {
-				mechanism,
+pub enum RsaEncryptMechanism {
+	Pkcs1,
+	Oaep(pkcs11_sys::CK_RSA_PKCS_OAEP_PARAMS),
+}
+
+impl RsaEncryptMechanism {
+	fn as_mechanism(&self) -> pkcs11_sys::CK_MECHANISM_IN {
+		match self {
+			RsaEncryptMechanism::Pkcs1 => pkcs11_sys::CK_MECHANISM_IN {
+				mechanism: pkcs11_sys::CKM_RSA_PKCS,
 				pParameter: std::ptr::null(),
 				ulParameterLen: 0,
-			};
+			},
+
+			RsaEncryptMechanism::Oaep(parameter) => pkcs11_sys::CK_MECHANISM_IN {
+				mechanism: pkcs11_sys::CKM_RSA_PKCS_OAEP,
+				pParameter: parameter as *const _ as _,
+				ulParameterLen: std::convert::TryInto::try_into(std::mem::size_of_val(parameter)).expect("usize -> CK_ULONG"),
+			},
+		}
+	}
+}
+
+impl Object<openssl::rsa::Rsa<openssl::pkey::Public>> {
+	/// Use this key to encrypt the given plaintext with the given mechanism and store the result into the given ciphertext buffer.
+	pub fn encrypt(&self, mechanism: &RsaEncryptMechanism, plaintext: &[u8], ciphertext: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, EncryptError> {
+		self.with_recovery(|handle| unsafe {
+			let mechanism = mechanism.as_mechanism();
 			let result =
 				(self.session.context.C_EncryptInit)(
-					self.session.handle,
+					self.session.handle(),
 					&mechanism,
-					self.handle,
+					handle,
 				);
 			if result != pkcs11_sys::CKR_OK {
 				return Err(EncryptError::EncryptInitFailed(result));
@@ -279,7 +562,7 @@ impl Object<openssl::rsa::Rsa<openssl::pkey::Public>> {
 
 			let result =
 				(self.session.context.C_Encrypt)(
-					self.session.handle,
+					self.session.handle(),
 					plaintext.as_ptr(),
 					std::convert::TryInto::try_into(plaintext.len()).expect("usize -> CK_ULONG"),
 					ciphertext.as_mut_ptr(),
@@ -291,7 +574,7 @@ impl Object<openssl::rsa::Rsa<openssl::pkey::Public>> {
 			assert!(ciphertext_len <= original_ciphertext_len);
 
 			Ok(ciphertext_len)
-		}
+		})
 	}
 }
 
@@ -299,6 +582,23 @@ impl Object<openssl::rsa::Rsa<openssl::pkey::Public>> {
 pub enum EncryptError {
 	EncryptInitFailed(pkcs11_sys::CK_RV),
 	EncryptFailed(pkcs11_sys::CK_RV),
+	LoginFailed(crate::LoginError),
+	SessionRecoveryFailed(crate::RecoverSessionError),
+}
+
+impl crate::session::RecoverableError for EncryptError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			EncryptError::EncryptInitFailed(result) |
+			EncryptError::EncryptFailed(result) => crate::session::session_lost(*result),
+			EncryptError::LoginFailed(inner) => inner.session_lost(),
+			EncryptError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: crate::RecoverSessionError) -> Self {
+		EncryptError::SessionRecoveryFailed(err)
+	}
 }
 
 impl std::fmt::Display for EncryptError {
@@ -306,20 +606,262 @@ impl std::fmt::Display for EncryptError {
 		match self {
 			EncryptError::EncryptInitFailed(result) => write!(f, "C_EncryptInit failed with {}", result),
 			EncryptError::EncryptFailed(result) => write!(f, "C_Encrypt failed with {}", result),
+			EncryptError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			EncryptError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
 		}
 	}
 }
 
 impl std::error::Error for EncryptError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			EncryptError::EncryptInitFailed(_) => None,
+			EncryptError::EncryptFailed(_) => None,
+			EncryptError::LoginFailed(inner) => Some(inner),
+			EncryptError::SessionRecoveryFailed(inner) => Some(inner),
+		}
+	}
+}
+
+impl Object<openssl::rsa::Rsa<openssl::pkey::Private>> {
+	/// Use this key to decrypt the given ciphertext with the given mechanism and store the result into the given plaintext buffer.
+	pub fn decrypt(&self, mechanism: &RsaEncryptMechanism, ciphertext: &[u8], plaintext: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, DecryptError> {
+		self.with_recovery(|handle| unsafe {
+			// Decrypting with the private key needs login
+			self.session.login().map_err(DecryptError::LoginFailed)?;
+
+			let mechanism = mechanism.as_mechanism();
+			let result =
+				(self.session.context.C_DecryptInit)(
+					self.session.handle(),
+					&mechanism,
+					handle,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(DecryptError::DecryptInitFailed(result));
+			}
+
+			let original_plaintext_len = std::convert::TryInto::try_into(plaintext.len()).expect("usize -> CK_ULONG");
+			let mut plaintext_len = original_plaintext_len;
+
+			let result =
+				(self.session.context.C_Decrypt)(
+					self.session.handle(),
+					ciphertext.as_ptr(),
+					std::convert::TryInto::try_into(ciphertext.len()).expect("usize -> CK_ULONG"),
+					plaintext.as_mut_ptr(),
+					&mut plaintext_len,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(DecryptError::DecryptFailed(result));
+			}
+			assert!(plaintext_len <= original_plaintext_len);
+
+			Ok(plaintext_len)
+		})
+	}
+}
+
+#[derive(Debug)]
+pub enum DecryptError {
+	DecryptFailed(pkcs11_sys::CK_RV),
+	DecryptInitFailed(pkcs11_sys::CK_RV),
+	LoginFailed(crate::LoginError),
+	SessionRecoveryFailed(crate::RecoverSessionError),
+}
+
+impl crate::session::RecoverableError for DecryptError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			DecryptError::DecryptFailed(result) |
+			DecryptError::DecryptInitFailed(result) => crate::session::session_lost(*result),
+			DecryptError::LoginFailed(inner) => inner.session_lost(),
+			DecryptError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: crate::RecoverSessionError) -> Self {
+		DecryptError::SessionRecoveryFailed(err)
+	}
+}
+
+impl std::fmt::Display for DecryptError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DecryptError::DecryptFailed(result) => write!(f, "C_Decrypt failed with {}", result),
+			DecryptError::DecryptInitFailed(result) => write!(f, "C_DecryptInit failed with {}", result),
+			DecryptError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			DecryptError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
+		}
+	}
+}
+
+impl std::error::Error for DecryptError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			DecryptError::DecryptFailed(_) => None,
+			DecryptError::DecryptInitFailed(_) => None,
+			DecryptError::LoginFailed(inner) => Some(inner),
+			DecryptError::SessionRecoveryFailed(inner) => Some(inner),
+		}
+	}
+}
+
+/// A type of secret key object. See [`crate::Session::generate_secret_key`]
+pub trait SecretKey {
+	/// The `CKA_KEY_TYPE` of objects of this type.
+	const KEY_TYPE: pkcs11_sys::CK_KEY_TYPE;
+
+	/// The mechanism that generates objects of this type.
+	const GENERATE_MECHANISM: pkcs11_sys::CK_MECHANISM_TYPE;
+
+	/// The attributes that are set to `CK_TRUE` on objects of this type, to allow using them for their operations.
+	const USAGE_ATTRIBUTES: &'static [pkcs11_sys::CK_ATTRIBUTE_TYPE];
+}
+
+/// An AES key, used for AES-GCM.
+pub enum AesKey {}
+
+impl SecretKey for AesKey {
+	const KEY_TYPE: pkcs11_sys::CK_KEY_TYPE = pkcs11_sys::CKK_AES;
+	const GENERATE_MECHANISM: pkcs11_sys::CK_MECHANISM_TYPE = pkcs11_sys::CKM_AES_KEY_GEN;
+	const USAGE_ATTRIBUTES: &'static [pkcs11_sys::CK_ATTRIBUTE_TYPE] = &[pkcs11_sys::CKA_DECRYPT, pkcs11_sys::CKA_ENCRYPT];
+}
+
+/// A generic secret key, used for HMAC-SHA256.
+pub enum HmacKey {}
+
+impl SecretKey for HmacKey {
+	const KEY_TYPE: pkcs11_sys::CK_KEY_TYPE = pkcs11_sys::CKK_GENERIC_SECRET;
+	const GENERATE_MECHANISM: pkcs11_sys::CK_MECHANISM_TYPE = pkcs11_sys::CKM_GENERIC_SECRET_KEY_GEN;
+	const USAGE_ATTRIBUTES: &'static [pkcs11_sys::CK_ATTRIBUTE_TYPE] = &[pkcs11_sys::CKA_SIGN, pkcs11_sys::CKA_VERIFY];
+}
+
+impl Object<HmacKey> {
+	/// Use this key to compute the HMAC-SHA256 of the given message and store the result into the given signature buffer.
+	pub fn sign(&self, message: &[u8], signature: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, SignError> {
+		self.sign_inner(pkcs11_sys::CKM_SHA256_HMAC, message, signature)
+	}
+}
+
+/// The length of the tag that [`Object::<AesKey>::encrypt`] appends to the ciphertext.
+pub const AES_GCM_TAG_LEN: usize = 16;
+
+impl Object<AesKey> {
+	/// Use this key to encrypt the given plaintext with AES-GCM and store the result into the given ciphertext buffer.
+	///
+	/// As with `CKM_AES_GCM` itself, the ciphertext is followed by the tag, so the buffer must be [`AES_GCM_TAG_LEN`] bytes longer than the plaintext.
+	pub fn encrypt(&self, iv: &[u8], aad: &[u8], plaintext: &[u8], ciphertext: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, EncryptError> {
+		self.with_recovery(|handle| unsafe {
+			// Secret keys are private objects, so using them needs login
+			self.session.login().map_err(EncryptError::LoginFailed)?;
+
+			let parameters = aes_gcm_parameters(iv, aad);
+			let mechanism = pkcs11_sys::CK_MECHANISM_IN {
+				mechanism: pkcs11_sys::CKM_AES_GCM,
+				pParameter: &parameters as *const _ as _,
+				ulParameterLen: std::convert::TryInto::try_into(std::mem::size_of_val(&parameters)).expect("usize -> CK_ULONG"),
+			};
+			let result =
+				(self.session.context.C_EncryptInit)(
+					self.session.handle(),
+					&mechanism,
+					handle,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(EncryptError::EncryptInitFailed(result));
+			}
+
+			let original_ciphertext_len = std::convert::TryInto::try_into(ciphertext.len()).expect("usize -> CK_ULONG");
+			let mut ciphertext_len = original_ciphertext_len;
+
+			let result =
+				(self.session.context.C_Encrypt)(
+					self.session.handle(),
+					plaintext.as_ptr(),
+					std::convert::TryInto::try_into(plaintext.len()).expect("usize -> CK_ULONG"),
+					ciphertext.as_mut_ptr(),
+					&mut ciphertext_len,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(EncryptError::EncryptFailed(result));
+			}
+			assert!(ciphertext_len <= original_ciphertext_len);
+
+			Ok(ciphertext_len)
+		})
+	}
+
+	/// Use this key to decrypt the given ciphertext with AES-GCM and store the result into the given plaintext buffer.
+	///
+	/// The ciphertext must be followed by the tag, as returned by [`Object::<AesKey>::encrypt`]. If the tag does not match,
+	/// this fails with `DecryptError::DecryptFailed(CKR_ENCRYPTED_DATA_INVALID)`.
+	pub fn decrypt(&self, iv: &[u8], aad: &[u8], ciphertext: &[u8], plaintext: &mut [u8]) -> Result<pkcs11_sys::CK_ULONG, DecryptError> {
+		self.with_recovery(|handle| unsafe {
+			// Secret keys are private objects, so using them needs login
+			self.session.login().map_err(DecryptError::LoginFailed)?;
+
+			let parameters = aes_gcm_parameters(iv, aad);
+			let mechanism = pkcs11_sys::CK_MECHANISM_IN {
+				mechanism: pkcs11_sys::CKM_AES_GCM,
+				pParameter: &parameters as *const _ as _,
+				ulParameterLen: std::convert::TryInto::try_into(std::mem::size_of_val(&parameters)).expect("usize -> CK_ULONG"),
+			};
+			let result =
+				(self.session.context.C_DecryptInit)(
+					self.session.handle(),
+					&mechanism,
+					handle,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(DecryptError::DecryptInitFailed(result));
+			}
+
+			let original_plaintext_len = std::convert::TryInto::try_into(plaintext.len()).expect("usize -> CK_ULONG");
+			let mut plaintext_len = original_plaintext_len;
+
+			let result =
+				(self.session.context.C_Decrypt)(
+					self.session.handle(),
+					ciphertext.as_ptr(),
+					std::convert::TryInto::try_into(ciphertext.len()).expect("usize -> CK_ULONG"),
+					plaintext.as_mut_ptr(),
+					&mut plaintext_len,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(DecryptError::DecryptFailed(result));
+			}
+			assert!(plaintext_len <= original_plaintext_len);
+
+			Ok(plaintext_len)
+		})
+	}
+}
+
+fn aes_gcm_parameters(iv: &[u8], aad: &[u8]) -> pkcs11_sys::CK_GCM_PARAMS {
+	let iv_len = std::convert::TryInto::try_into(iv.len()).expect("usize -> CK_ULONG");
+
+	pkcs11_sys::CK_GCM_PARAMS {
+		pIv: iv.as_ptr(),
+		ulIvLen: iv_len,
+		ulIvBits: iv_len * 8,
+		pAAD: aad.as_ptr(),
+		ulAADLen: std::convert::TryInto::try_into(aad.len()).expect("usize -> CK_ULONG"),
+		ulTagBits: std::convert::TryInto::try_into(AES_GCM_TAG_LEN * 8).expect("usize -> CK_ULONG"),
+	}
 }
 
 /// Query an attribute value as a byte buffer of arbitrary length.
-unsafe fn get_attribute_value_byte_buf<T>(
+///
+/// Fails with the result of `C_GetAttributeValue` if it is not `CKR_OK`.
+pub(crate) unsafe fn get_attribute_value_byte_buf(
 	session: &crate::Session,
-	object: &Object<T>,
+	object_handle: pkcs11_sys::CK_OBJECT_HANDLE,
 	r#type: pkcs11_sys::CK_ATTRIBUTE_TYPE,
 	C_GetAttributeValue: pkcs11_sys::CK_C_GetAttributeValue,
-) -> Result<Vec<u8>, GetKeyParametersError> {
+) -> Result<Vec<u8>, pkcs11_sys::CK_RV> {
 	// Per the docs of C_GetAttributeValue, it is legal to call it with pValue == NULL and ulValueLen == 0.
 	// In this case it will set ulValueLen to the size of buffer it needs and return CKR_OK.
 
@@ -331,13 +873,13 @@ unsafe fn get_attribute_value_byte_buf<T>(
 
 	let result =
 		C_GetAttributeValue(
-			session.handle,
-			object.handle,
+			session.handle(),
+			object_handle,
 			&mut attribute,
 			1,
 		);
 	if result != pkcs11_sys::CKR_OK {
-		return Err(GetKeyParametersError::GetAttributeValueFailed(result));
+		return Err(result);
 	}
 
 	let mut buf = vec![0_u8; std::convert::TryInto::try_into(attribute.ulValueLen).expect("CK_ULONG -> usize")];
@@ -345,13 +887,13 @@ unsafe fn get_attribute_value_byte_buf<T>(
 
 	let result =
 		C_GetAttributeValue(
-			session.handle,
-			object.handle,
+			session.handle(),
+			object_handle,
 			&mut attribute,
 			1,
 		);
 	if result != pkcs11_sys::CKR_OK {
-		return Err(GetKeyParametersError::GetAttributeValueFailed(result));
+		return Err(result);
 	}
 
 	Ok(buf)
@@ -0,0 +1,88 @@
+/// The user PIN of a token, or where to read it from.
+///
+/// PINs from a source are read every time a session logs in, so they are never held in memory for longer than the login takes,
+/// and a PIN that is changed at its source is picked up without restarting the process.
+#[derive(Clone, PartialEq)]
+pub enum Pin {
+	/// The PIN itself, from the `pin-value` attribute of a [`crate::Uri`].
+	Value(String),
+
+	/// A file that contains the PIN, from a `pin-source=file:...` attribute. A single trailing newline in the file is ignored.
+	File(std::path::PathBuf),
+
+	/// A systemd credential that contains the PIN, from a `pin-source=credential:<name>` attribute.
+	/// It is read from the directory in the `CREDENTIALS_DIRECTORY` env var. A single trailing newline in the credential is ignored.
+	Credential(String),
+}
+
+impl Pin {
+	/// Parses the value of a `pin-source` attribute.
+	pub(crate) fn from_source(s: &str) -> Result<Self, Box<dyn std::error::Error>> {
+		let scheme_end_index = s.find(':').ok_or("missing scheme")?;
+		let scheme = &s[..scheme_end_index];
+		let rest = &s[(scheme_end_index + 1)..];
+
+		match scheme {
+			"file" => {
+				let uri: url::Url = s.parse()?;
+				let path = uri.to_file_path().map_err(|()| "cannot convert to file path")?;
+				Ok(Pin::File(path))
+			},
+
+			"credential" => {
+				if rest.is_empty() || rest.contains('/') {
+					return Err("invalid credential name".into());
+				}
+
+				Ok(Pin::Credential(rest.to_owned()))
+			},
+
+			_ => Err("unrecognized scheme".into())
+		}
+	}
+
+	/// The value of the `pin-source` attribute that this PIN was parsed from, or `None` if it is a `pin-value`.
+	pub(crate) fn source(&self) -> Option<String> {
+		match self {
+			Pin::Value(_) => None,
+			Pin::File(path) => Some(url::Url::from_file_path(path).map_or_else(|()| format!("file:{}", path.display()), Into::into)),
+			Pin::Credential(name) => Some(format!("credential:{}", name)),
+		}
+	}
+
+	/// Gets the PIN, reading it from its source if necessary.
+	pub(crate) fn read(&self) -> std::io::Result<std::borrow::Cow<'_, [u8]>> {
+		let path = match self {
+			Pin::Value(pin) => return Ok(pin.as_bytes().into()),
+
+			Pin::File(path) => std::borrow::Cow::Borrowed(&**path),
+
+			Pin::Credential(name) => {
+				let credentials_directory =
+					std::env::var_os("CREDENTIALS_DIRECTORY")
+					.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "PIN is a credential but CREDENTIALS_DIRECTORY is not set"))?;
+				std::borrow::Cow::Owned(std::path::Path::new(&credentials_directory).join(name))
+			},
+		};
+
+		let mut pin = std::fs::read(path)?;
+		if pin.last() == Some(&b'\n') {
+			pin.pop();
+			if pin.last() == Some(&b'\r') {
+				pin.pop();
+			}
+		}
+
+		Ok(pin.into())
+	}
+}
+
+impl std::fmt::Debug for Pin {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Pin::Value(_) => f.write_str("Value(<redacted>)"),
+			Pin::File(path) => f.debug_tuple("File").field(path).finish(),
+			Pin::Credential(name) => f.debug_tuple("Credential").field(name).finish(),
+		}
+	}
+}
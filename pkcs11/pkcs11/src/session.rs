@@ -1,24 +1,159 @@
 pub struct Session {
 	pub(crate) context: std::sync::Arc<crate::Context>,
-	pub(crate) handle: pkcs11_sys::CK_SESSION_HANDLE,
-	pin: Option<String>,
+	slot_id: pkcs11_sys::CK_SLOT_ID,
+
+	/// The handle changes if the session is lost and has to be opened again. See [`Session::with_recovery`].
+	handle: std::sync::Mutex<SessionHandle>,
+
+	pin: Option<crate::Pin>,
 }
 
 impl Session {
 	pub(crate) fn new(
 		context: std::sync::Arc<crate::Context>,
+		slot_id: pkcs11_sys::CK_SLOT_ID,
 		handle: pkcs11_sys::CK_SESSION_HANDLE,
-		pin: Option<String>,
+		pin: Option<crate::Pin>,
 	) -> Self {
 		Session {
 			context,
-			handle,
+			slot_id,
+			handle: std::sync::Mutex::new(SessionHandle {
+				handle,
+				generation: 0,
+			}),
 			pin,
 		}
 	}
+
+	pub(crate) fn handle(&self) -> pkcs11_sys::CK_SESSION_HANDLE {
+		self.handle.lock().expect("session handle mutex is poisoned").handle
+	}
+
+	/// The number of times this session has been opened again after being lost.
+	///
+	/// This goes up every time an operation recovers from a lost session, so callers can compare it before and after an operation
+	/// to tell whether the operation had to recover. Objects use it to tell whether their handles were found in an older session.
+	pub fn generation(&self) -> u64 {
+		self.handle.lock().expect("session handle mutex is poisoned").generation
+	}
+}
+
+struct SessionHandle {
+	handle: pkcs11_sys::CK_SESSION_HANDLE,
+	generation: u64,
+}
+
+/// Returns the given return value of a PKCS#11 function if it means that the session can no longer be used,
+/// such as because the token was removed and reinserted, or the HSM was reset.
+pub(crate) fn session_lost(result: pkcs11_sys::CK_RV) -> Option<pkcs11_sys::CK_RV> {
+	match result {
+		pkcs11_sys::CKR_DEVICE_REMOVED |
+		pkcs11_sys::CKR_SESSION_CLOSED |
+		pkcs11_sys::CKR_SESSION_HANDLE_INVALID |
+		pkcs11_sys::CKR_TOKEN_NOT_PRESENT |
+		pkcs11_sys::CKR_USER_NOT_LOGGED_IN => Some(result),
+
+		_ => None,
+	}
+}
+
+/// An error from an operation that [`Session::with_recovery`] can retry.
+pub(crate) trait RecoverableError {
+	/// If this error means the session was lost, the return value of the PKCS#11 function that failed.
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV>;
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self;
+}
+
+impl Session {
+	/// Runs the given operation. If it fails because the session was lost, opens the session again, logs in to it, and runs the operation once more.
+	pub(crate) fn with_recovery<T, E>(&self, f: impl FnMut() -> Result<T, E>) -> Result<T, E> where E: RecoverableError {
+		let generation = self.generation();
+		retry_if_session_lost(f, |result| self.recover(result, generation))
+	}
+
+	/// Replaces the session handle with a new one against the same slot and logs in to it.
+	///
+	/// `result` is the return value that showed the session was lost. It is only used to report errors.
+	/// `generation` is the generation of the session that was lost. If the session has already been opened again since then,
+	/// for example by an operation on another thread, it is not opened again.
+	fn recover(&self, result: pkcs11_sys::CK_RV, generation: u64) -> Result<(), RecoverSessionError> {
+		unsafe {
+			{
+				let mut handle = self.handle.lock().expect("session handle mutex is poisoned");
+
+				if handle.generation == generation {
+					// The old handle is most likely invalid already, so there's nothing to do if closing it fails.
+					let _ = (self.context.C_CloseSession)(handle.handle);
+					handle.handle = pkcs11_sys::CK_INVALID_SESSION_HANDLE;
+
+					handle.handle = self.context.open_session_handle(self.slot_id).map_err(|err| RecoverSessionError::OpenSessionFailed(result, err))?;
+					handle.generation += 1;
+				}
+			}
+
+			self.login().map_err(|err| RecoverSessionError::LoginFailed(result, err))?;
+
+			Ok(())
+		}
+	}
+}
+
+/// Runs `f`. If it fails because the session was lost, runs `recover` and then runs `f` once more.
+fn retry_if_session_lost<T, E>(
+	mut f: impl FnMut() -> Result<T, E>,
+	recover: impl FnOnce(pkcs11_sys::CK_RV) -> Result<(), RecoverSessionError>,
+) -> Result<T, E> where E: RecoverableError {
+	match f() {
+		Err(err) => match err.session_lost() {
+			Some(result) => {
+				recover(result).map_err(E::recover_session_failed)?;
+				f()
+			},
+
+			None => Err(err),
+		},
+
+		result => result,
+	}
+}
+
+/// An error from opening a session again after it was lost.
+///
+/// Where available, the variant contains the return value of the PKCS#11 function that showed the session was lost.
+#[derive(Debug)]
+pub enum RecoverSessionError {
+	FindObjectFailed(Box<GetKeyError>),
+	LoginFailed(pkcs11_sys::CK_RV, LoginError),
+	OpenSessionFailed(pkcs11_sys::CK_RV, crate::OpenSessionError),
+}
+
+impl std::fmt::Display for RecoverSessionError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			RecoverSessionError::FindObjectFailed(_) =>
+				f.write_str("session was lost and was opened again, but the object could not be found in the new session"),
+			RecoverSessionError::LoginFailed(result, _) =>
+				write!(f, "session was lost ({}) and was opened again, but could not log in to the token", result),
+			RecoverSessionError::OpenSessionFailed(result, _) =>
+				write!(f, "session was lost ({}) and could not be opened again", result),
+		}
+	}
+}
+
+impl std::error::Error for RecoverSessionError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			RecoverSessionError::FindObjectFailed(inner) => Some(&**inner),
+			RecoverSessionError::LoginFailed(_, inner) => Some(inner),
+			RecoverSessionError::OpenSessionFailed(_, inner) => Some(inner),
+		}
+	}
 }
 
 pub enum KeyPair {
+	/// An EC key pair. This also includes Ed25519 key pairs, since PKCS#11 describes them with the same `CKA_EC_PARAMS` and `CKA_EC_POINT` attributes.
 	Ec(
 		crate::Object<openssl::ec::EcKey<openssl::pkey::Public>>,
 		crate::Object<openssl::ec::EcKey<openssl::pkey::Private>>,
@@ -35,75 +170,80 @@ pub enum PublicKey {
 }
 
 impl Session {
-	/// Get a public key in the current session with the given label.
-	pub fn get_public_key(self: std::sync::Arc<Self>, label: Option<&str>) -> Result<PublicKey, GetKeyError> {
-		unsafe {
-			let public_key_handle = self.get_key_inner(pkcs11_sys::CKO_PUBLIC_KEY, label)?;
+	/// Get a public key in the current session that matches the object attributes of the given URI.
+	///
+	/// If the URI has a `type` attribute, it must be `public` or `private`. In the latter case, the public key of the key pair is returned.
+	/// If more than one key matches, this fails with [`GetKeyError::AmbiguousKey`]. Set the URI's `id` to choose one of them.
+	pub fn get_public_key(self: std::sync::Arc<Self>, uri: &crate::Uri) -> Result<PublicKey, GetKeyError> {
+		check_key_pair_object_type(uri)?;
+
+		self.with_recovery(|| unsafe {
+			let public_key_lookup = crate::object::ObjectLookup::new(pkcs11_sys::CKO_PUBLIC_KEY, uri);
+			let public_key_handle = public_key_lookup.find(&self)?;
 			let public_key_mechanism_type = self.get_key_mechanism_type(public_key_handle)?;
 
 			match public_key_mechanism_type {
-				pkcs11_sys::CKK_EC => Ok(PublicKey::Ec(crate::Object::new(self, public_key_handle))),
-				pkcs11_sys::CKK_RSA => Ok(PublicKey::Rsa(crate::Object::new(self, public_key_handle))),
+				pkcs11_sys::CKK_EC | pkcs11_sys::CKK_EC_EDWARDS => Ok(PublicKey::Ec(crate::Object::new(self.clone(), public_key_handle, Some(public_key_lookup)))),
+				pkcs11_sys::CKK_RSA => Ok(PublicKey::Rsa(crate::Object::new(self.clone(), public_key_handle, Some(public_key_lookup)))),
 				_ => Err(GetKeyError::MismatchedMechanismType),
 			}
-		}
+		})
 	}
 
-	/// Get a key pair in the current session with the given label.
-	pub fn get_key_pair(self: std::sync::Arc<Self>, label: Option<&str>) -> Result<KeyPair, GetKeyError> {
-		unsafe {
+	/// Get a key pair in the current session that matches the object attributes of the given URI.
+	///
+	/// If the URI has a `type` attribute, it must be `public` or `private`. Either way, both halves of the key pair are looked up.
+	/// If more than one key matches, this fails with [`GetKeyError::AmbiguousKey`]. Set the URI's `id` to choose one of them.
+	pub fn get_key_pair(self: std::sync::Arc<Self>, uri: &crate::Uri) -> Result<KeyPair, GetKeyError> {
+		check_key_pair_object_type(uri)?;
+
+		self.with_recovery(|| unsafe {
 			// Private key access needs login
 			self.login().map_err(GetKeyError::LoginFailed)?;
 
-			let public_key_handle = self.get_key_inner(pkcs11_sys::CKO_PUBLIC_KEY, label)?;
+			let public_key_lookup = crate::object::ObjectLookup::new(pkcs11_sys::CKO_PUBLIC_KEY, uri);
+			let public_key_handle = public_key_lookup.find(&self)?;
 			let public_key_mechanism_type = self.get_key_mechanism_type(public_key_handle)?;
-			let private_key_handle = self.get_key_inner(pkcs11_sys::CKO_PRIVATE_KEY, label)?;
+			let private_key_lookup = crate::object::ObjectLookup::new(pkcs11_sys::CKO_PRIVATE_KEY, uri);
+			let private_key_handle = private_key_lookup.find(&self)?;
 			let private_key_mechanism_type = self.get_key_mechanism_type(private_key_handle)?;
 
 			match (public_key_mechanism_type, private_key_mechanism_type) {
-				(pkcs11_sys::CKK_EC, pkcs11_sys::CKK_EC) => Ok(KeyPair::Ec(
-					crate::Object::new(self.clone(), public_key_handle),
-					crate::Object::new(self, private_key_handle),
+				(pkcs11_sys::CKK_EC, pkcs11_sys::CKK_EC) |
+				(pkcs11_sys::CKK_EC_EDWARDS, pkcs11_sys::CKK_EC_EDWARDS) => Ok(KeyPair::Ec(
+					crate::Object::new(self.clone(), public_key_handle, Some(public_key_lookup)),
+					crate::Object::new(self.clone(), private_key_handle, Some(private_key_lookup)),
 				)),
 
 				(pkcs11_sys::CKK_RSA, pkcs11_sys::CKK_RSA) => Ok(KeyPair::Rsa(
-					crate::Object::new(self.clone(), public_key_handle),
-					crate::Object::new(self, private_key_handle),
+					crate::Object::new(self.clone(), public_key_handle, Some(public_key_lookup)),
+					crate::Object::new(self.clone(), private_key_handle, Some(private_key_lookup)),
 				)),
 
 				_ => Err(GetKeyError::MismatchedMechanismType),
 			}
-		}
+		})
 	}
 
-	unsafe fn get_key_inner(
+	pub(crate) unsafe fn get_key_inner(
 		&self,
 		class: pkcs11_sys::CK_OBJECT_CLASS,
+		key_type: Option<pkcs11_sys::CK_KEY_TYPE>,
 		label: Option<&str>,
+		id: Option<&[u8]>,
 	) -> Result<pkcs11_sys::CK_OBJECT_HANDLE, GetKeyError> {
-		let mut templates = vec![
-			pkcs11_sys::CK_ATTRIBUTE_IN {
-				r#type: pkcs11_sys::CKA_CLASS,
-				pValue: &class as *const _ as _,
-				ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&class)).expect("usize -> CK_ULONG"),
-			},
-		];
-		if let Some(label) = label {
-			templates.push(pkcs11_sys::CK_ATTRIBUTE_IN {
-				r#type: pkcs11_sys::CKA_LABEL,
-				pValue: label.as_ptr() as *const _ as _,
-				ulValueLen: std::convert::TryInto::try_into(label.len()).expect("usize -> CK_ULONG"),
-			});
-		}
-		let key_handle = {
-			let mut find_objects = FindObjects::new(self, &templates).map_err(GetKeyError::FindObjectsFailed)?;
-			match find_objects.next() {
-				Some(key_handle) => key_handle.map_err(GetKeyError::FindObjectsFailed)?,
-				None => return Err(GetKeyError::KeyDoesNotExist),
-			}
+		let templates = find_objects_template(Some(&class), key_type.as_ref(), label, id);
+		let key_handles: Vec<_> = {
+			let find_objects = FindObjects::new(self, &templates).map_err(GetKeyError::FindObjectsFailed)?;
+			find_objects.collect::<Result<_, _>>().map_err(GetKeyError::FindObjectsFailed)?
 		};
 
-		Ok(key_handle)
+		// Picking one of several matches would make the key that gets used depend on the order the token happens to return them in.
+		match key_handles[..] {
+			[] => Err(GetKeyError::KeyDoesNotExist),
+			[key_handle] => Ok(key_handle),
+			_ => Err(GetKeyError::AmbiguousKey(key_handles.len())),
+		}
 	}
 
 	unsafe fn get_key_mechanism_type(
@@ -119,7 +259,7 @@ impl Session {
 		};
 		let result =
 			(self.context.C_GetAttributeValue)(
-				self.handle,
+				self.handle(),
 				key_handle,
 				&mut attribute,
 				1,
@@ -132,24 +272,179 @@ impl Session {
 	}
 }
 
+/// A key pair can be identified by a URI that names either of its halves, but not by one that names some other type of object.
+fn check_key_pair_object_type(uri: &crate::Uri) -> Result<(), GetKeyError> {
+	match uri.object_type.unwrap_or(crate::UriObjectType::PrivateKey) {
+		crate::UriObjectType::PrivateKey | crate::UriObjectType::PublicKey => Ok(()),
+		crate::UriObjectType::Certificate | crate::UriObjectType::Data | crate::UriObjectType::SecretKey => Err(GetKeyError::KeyDoesNotExist),
+	}
+}
+
+impl Session {
+	/// List the labels of the key pairs in the current session.
+	///
+	/// Key pairs are identified by their private key objects. Objects that do not have a label are ignored.
+	pub fn list_key_pairs(&self) -> Result<Vec<String>, ListKeyPairsError> {
+		self.with_recovery(|| unsafe {
+			// Private key access needs login
+			self.login().map_err(ListKeyPairsError::LoginFailed)?;
+
+			let class = pkcs11_sys::CKO_PRIVATE_KEY;
+			let templates = [
+				pkcs11_sys::CK_ATTRIBUTE_IN {
+					r#type: pkcs11_sys::CKA_CLASS,
+					pValue: &class as *const _ as _,
+					ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&class)).expect("usize -> CK_ULONG"),
+				},
+			];
+
+			let key_handles: Vec<_> = {
+				let find_objects = FindObjects::new(self, &templates).map_err(ListKeyPairsError::FindObjectsFailed)?;
+				find_objects.collect::<Result<_, _>>().map_err(ListKeyPairsError::FindObjectsFailed)?
+			};
+
+			let mut labels = vec![];
+
+			for key_handle in key_handles {
+				// Per the docs of C_GetAttributeValue, it is legal to call it with pValue == NULL and ulValueLen == 0.
+				// In this case it will set ulValueLen to the size of buffer it needs and return CKR_OK.
+
+				let mut attribute = pkcs11_sys::CK_ATTRIBUTE {
+					r#type: pkcs11_sys::CKA_LABEL,
+					pValue: std::ptr::null_mut(),
+					ulValueLen: 0,
+				};
+				let result =
+					(self.context.C_GetAttributeValue)(
+						self.handle(),
+						key_handle,
+						&mut attribute,
+						1,
+					);
+				if result != pkcs11_sys::CKR_OK {
+					return Err(ListKeyPairsError::GetLabelFailed(result));
+				}
+
+				if attribute.ulValueLen == 0 {
+					continue;
+				}
+
+				let mut label = vec![0_u8; std::convert::TryInto::try_into(attribute.ulValueLen).expect("CK_ULONG -> usize")];
+				attribute.pValue = label.as_mut_ptr() as _;
+				let result =
+					(self.context.C_GetAttributeValue)(
+						self.handle(),
+						key_handle,
+						&mut attribute,
+						1,
+					);
+				if result != pkcs11_sys::CKR_OK {
+					return Err(ListKeyPairsError::GetLabelFailed(result));
+				}
+
+				let label = String::from_utf8(label).map_err(ListKeyPairsError::MalformedLabel)?;
+				labels.push(label);
+			}
+
+			labels.sort();
+			labels.dedup();
+
+			Ok(labels)
+		})
+	}
+}
+
+/// An error from listing key pairs.
+#[derive(Debug)]
+pub enum ListKeyPairsError {
+	FindObjectsFailed(FindObjectsError),
+	GetLabelFailed(pkcs11_sys::CK_RV),
+	LoginFailed(LoginError),
+	MalformedLabel(std::string::FromUtf8Error),
+	SessionRecoveryFailed(RecoverSessionError),
+}
+
+impl RecoverableError for ListKeyPairsError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			ListKeyPairsError::FindObjectsFailed(inner) => inner.session_lost(),
+			ListKeyPairsError::GetLabelFailed(result) => session_lost(*result),
+			ListKeyPairsError::LoginFailed(inner) => inner.session_lost(),
+			ListKeyPairsError::MalformedLabel(_) |
+			ListKeyPairsError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self {
+		ListKeyPairsError::SessionRecoveryFailed(err)
+	}
+}
+
+impl std::fmt::Display for ListKeyPairsError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ListKeyPairsError::FindObjectsFailed(_) => f.write_str("could not find objects"),
+			ListKeyPairsError::GetLabelFailed(result) => write!(f, "C_GetAttributeValue(CKA_LABEL) failed with {}", result),
+			ListKeyPairsError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			ListKeyPairsError::MalformedLabel(_) => f.write_str("object label is not valid UTF-8"),
+			ListKeyPairsError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
+		}
+	}
+}
+
+impl std::error::Error for ListKeyPairsError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ListKeyPairsError::FindObjectsFailed(inner) => Some(inner),
+			ListKeyPairsError::GetLabelFailed(_) => None,
+			ListKeyPairsError::LoginFailed(inner) => Some(inner),
+			ListKeyPairsError::MalformedLabel(inner) => Some(inner),
+			ListKeyPairsError::SessionRecoveryFailed(inner) => Some(inner),
+		}
+	}
+}
+
 /// An error from getting a key.
 #[derive(Debug)]
 pub enum GetKeyError {
+	AmbiguousKey(usize),
 	FindObjectsFailed(FindObjectsError),
 	GetKeyTypeFailed(pkcs11_sys::CK_RV),
 	KeyDoesNotExist,
 	LoginFailed(LoginError),
 	MismatchedMechanismType,
+	SessionRecoveryFailed(RecoverSessionError),
+}
+
+impl RecoverableError for GetKeyError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			GetKeyError::FindObjectsFailed(inner) => inner.session_lost(),
+			GetKeyError::GetKeyTypeFailed(result) => session_lost(*result),
+			GetKeyError::LoginFailed(inner) => inner.session_lost(),
+			GetKeyError::AmbiguousKey(_) |
+			GetKeyError::KeyDoesNotExist |
+			GetKeyError::MismatchedMechanismType |
+			GetKeyError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self {
+		GetKeyError::SessionRecoveryFailed(err)
+	}
 }
 
 impl std::fmt::Display for GetKeyError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
+			GetKeyError::AmbiguousKey(num_keys) => write!(f, "found {} keys that match, specify an ID to choose one of them", num_keys),
 			GetKeyError::FindObjectsFailed(_) => f.write_str("could not find objects"),
 			GetKeyError::GetKeyTypeFailed(result) => write!(f, "C_GetAttributeValue(CKA_KEY_TYPE) failed with {}", result),
 			GetKeyError::KeyDoesNotExist => f.write_str("did not find any keys in the slot"),
 			GetKeyError::LoginFailed(_) => f.write_str("could not log in to the token"),
 			GetKeyError::MismatchedMechanismType => f.write_str("public and private keys have different mechanisms"),
+			GetKeyError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
 		}
 	}
 }
@@ -158,15 +453,58 @@ impl std::error::Error for GetKeyError {
 	#[allow(clippy::match_same_arms)]
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
+			GetKeyError::AmbiguousKey(_) => None,
 			GetKeyError::FindObjectsFailed(inner) => Some(inner),
 			GetKeyError::GetKeyTypeFailed(_) => None,
 			GetKeyError::KeyDoesNotExist => None,
 			GetKeyError::LoginFailed(inner) => Some(inner),
 			GetKeyError::MismatchedMechanismType => None,
+			GetKeyError::SessionRecoveryFailed(inner) => Some(inner),
 		}
 	}
 }
 
+/// The attributes to pass to `C_FindObjectsInit` to find objects with the given attributes. Attributes that are `None` match any value.
+///
+/// The returned attributes point into the arguments, so they are valid for as long as the arguments are.
+fn find_objects_template(
+	class: Option<&pkcs11_sys::CK_OBJECT_CLASS>,
+	key_type: Option<&pkcs11_sys::CK_KEY_TYPE>,
+	label: Option<&str>,
+	id: Option<&[u8]>,
+) -> Vec<pkcs11_sys::CK_ATTRIBUTE_IN> {
+	let mut templates = vec![];
+	if let Some(class) = class {
+		templates.push(pkcs11_sys::CK_ATTRIBUTE_IN {
+			r#type: pkcs11_sys::CKA_CLASS,
+			pValue: class as *const _ as _,
+			ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(class)).expect("usize -> CK_ULONG"),
+		});
+	}
+	if let Some(key_type) = key_type {
+		templates.push(pkcs11_sys::CK_ATTRIBUTE_IN {
+			r#type: pkcs11_sys::CKA_KEY_TYPE,
+			pValue: key_type as *const _ as _,
+			ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(key_type)).expect("usize -> CK_ULONG"),
+		});
+	}
+	if let Some(label) = label {
+		templates.push(pkcs11_sys::CK_ATTRIBUTE_IN {
+			r#type: pkcs11_sys::CKA_LABEL,
+			pValue: label.as_ptr() as *const _ as _,
+			ulValueLen: std::convert::TryInto::try_into(label.len()).expect("usize -> CK_ULONG"),
+		});
+	}
+	if let Some(id) = id {
+		templates.push(pkcs11_sys::CK_ATTRIBUTE_IN {
+			r#type: pkcs11_sys::CKA_ID,
+			pValue: id.as_ptr() as *const _ as _,
+			ulValueLen: std::convert::TryInto::try_into(id.len()).expect("usize -> CK_ULONG"),
+		});
+	}
+	templates
+}
+
 struct FindObjects<'session> {
 	session: &'session Session,
 }
@@ -178,7 +516,7 @@ impl<'session> FindObjects<'session> {
 	) -> Result<Self, FindObjectsError> {
 		let result =
 			(session.context.C_FindObjectsInit)(
-				session.handle,
+				session.handle(),
 				templates.as_ptr(),
 				std::convert::TryInto::try_into(templates.len()).expect("usize -> CK_ULONG"),
 			);
@@ -201,19 +539,19 @@ impl<'session> Iterator for FindObjects<'session> {
 			let mut num_objects = 0;
 			let result =
 				(self.session.context.C_FindObjects)(
-					self.session.handle,
+					self.session.handle(),
 					&mut object_handle,
 					1,
 					&mut num_objects,
 				);
 			if result != pkcs11_sys::CKR_OK {
-				return Some(Err(FindObjectsError::FindObjectsFailed(format!("C_FindObjects failed with {}", result).into())));
+				return Some(Err(FindObjectsError::FindObjectsFailed(result)));
 			}
 			match num_objects {
 				0 => None,
 				1 if object_handle != pkcs11_sys::CK_INVALID_OBJECT_HANDLE => Some(Ok(object_handle)),
-				1 => Some(Err(FindObjectsError::FindObjectsFailed("C_FindObjects found 1 object but object handle is still CK_INVALID_HANDLE".into()))),
-				num_objects => Some(Err(FindObjectsError::FindObjectsFailed(format!("C_FindObjects found {} objects", num_objects).into()))),
+				1 => Some(Err(FindObjectsError::UnexpectedResult("C_FindObjects found 1 object but object handle is still CK_INVALID_HANDLE".into()))),
+				num_objects => Some(Err(FindObjectsError::UnexpectedResult(format!("C_FindObjects found {} objects", num_objects).into()))),
 			}
 		}
 	}
@@ -222,7 +560,7 @@ impl<'session> Iterator for FindObjects<'session> {
 impl<'session> Drop for FindObjects<'session> {
 	fn drop(&mut self) {
 		unsafe {
-			let _ = (self.session.context.C_FindObjectsFinal)(self.session.handle);
+			let _ = (self.session.context.C_FindObjectsFinal)(self.session.handle());
 		}
 	}
 }
@@ -230,15 +568,27 @@ impl<'session> Drop for FindObjects<'session> {
 /// An error from finding an object.
 #[derive(Debug)]
 pub enum FindObjectsError {
-	FindObjectsFailed(std::borrow::Cow<'static, str>),
+	FindObjectsFailed(pkcs11_sys::CK_RV),
 	FindObjectsInitFailed(pkcs11_sys::CK_RV),
+	UnexpectedResult(std::borrow::Cow<'static, str>),
+}
+
+impl FindObjectsError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			FindObjectsError::FindObjectsFailed(result) |
+			FindObjectsError::FindObjectsInitFailed(result) => session_lost(*result),
+			FindObjectsError::UnexpectedResult(_) => None,
+		}
+	}
 }
 
 impl std::fmt::Display for FindObjectsError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
-			FindObjectsError::FindObjectsFailed(message) => f.write_str(message),
+			FindObjectsError::FindObjectsFailed(result) => write!(f, "C_FindObjects failed with {}", result),
 			FindObjectsError::FindObjectsInitFailed(result) => write!(f, "C_FindObjectsInit failed with {}", result),
+			FindObjectsError::UnexpectedResult(message) => f.write_str(message),
 		}
 	}
 }
@@ -253,7 +603,7 @@ impl Session {
 		curve: openssl2::EcCurve,
 		label: Option<&str>,
 	) -> Result<(crate::Object<openssl::ec::EcKey<openssl::pkey::Public>>, crate::Object<openssl::ec::EcKey<openssl::pkey::Private>>), GenerateKeyPairError> {
-		unsafe {
+		self.with_recovery(|| unsafe {
 			let oid = curve.as_oid_der();
 
 			let public_key_template = vec![
@@ -264,15 +614,30 @@ impl Session {
 				},
 			];
 
-			let private_key_template = vec![];
+			let r#true = pkcs11_sys::CK_TRUE;
+
+			let (mechanism, private_key_template) = match curve {
+				// Ed25519 keys are generated with the dedicated Edwards mechanism from PKCS#11 v3.0, and cannot be used for ECDH.
+				#[cfg(ossl111)]
+				openssl2::EcCurve::Ed25519 => (pkcs11_sys::CKM_EC_EDWARDS_KEY_PAIR_GEN, vec![]),
+
+				// Allow the private key to be used for ECDH.
+				openssl2::EcCurve::NistP256 | openssl2::EcCurve::NistP384 | openssl2::EcCurve::NistP521 => (pkcs11_sys::CKM_EC_KEY_PAIR_GEN, vec![
+					pkcs11_sys::CK_ATTRIBUTE_IN {
+						r#type: pkcs11_sys::CKA_DERIVE,
+						pValue: &r#true as *const _ as _,
+						ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&r#true)).expect("usize -> CK_ULONG"),
+					},
+				]),
+			};
 
-			self.generate_key_pair_inner(
-				pkcs11_sys::CKM_EC_KEY_PAIR_GEN,
+			self.clone().generate_key_pair_inner(
+				mechanism,
 				public_key_template,
 				private_key_template,
 				label,
 			)
-		}
+		})
 	}
 
 	/// Generate an RSA key pair in the current session with the given modulus size, exponent and label.
@@ -282,7 +647,7 @@ impl Session {
 		exponent: &openssl::bn::BigNumRef,
 		label: Option<&str>,
 	) -> Result<(crate::Object<openssl::rsa::Rsa<openssl::pkey::Public>>, crate::Object<openssl::rsa::Rsa<openssl::pkey::Private>>), GenerateKeyPairError> {
-		unsafe {
+		self.with_recovery(|| unsafe {
 			let exponent = exponent.to_vec();
 
 			let public_key_template = vec![
@@ -300,13 +665,13 @@ impl Session {
 
 			let private_key_template = vec![];
 
-			self.generate_key_pair_inner(
+			self.clone().generate_key_pair_inner(
 				pkcs11_sys::CKM_RSA_PKCS_KEY_PAIR_GEN,
 				public_key_template,
 				private_key_template,
 				label,
 			)
-		}
+		})
 	}
 
 	unsafe fn generate_key_pair_inner<TPublic, TPrivate>(
@@ -322,20 +687,7 @@ impl Session {
 		// If label is set, delete any existing objects with that label first
 		if let Some(label) = label {
 			for &class in &[pkcs11_sys::CKO_PUBLIC_KEY, pkcs11_sys::CKO_PRIVATE_KEY] {
-				match self.get_key_inner(class, Some(label)) {
-					Ok(key_handle) => {
-						let result =
-							(self.context.C_DestroyObject)(
-								self.handle,
-								key_handle,
-							);
-						if result != pkcs11_sys::CKR_OK {
-							return Err(GenerateKeyPairError::DeleteExistingKey(result));
-						}
-					},
-					Err(GetKeyError::KeyDoesNotExist) => (),
-					Err(err) => return Err(GenerateKeyPairError::GetExistingKey(err)),
-				}
+				self.destroy_keys(class, None, label, pkcs11_sys::CK_INVALID_OBJECT_HANDLE).map_err(GenerateKeyPairError::DeleteExistingKey)?;
 			}
 		}
 
@@ -425,7 +777,7 @@ impl Session {
 
 		let result =
 			(self.context.C_GenerateKeyPair)(
-				self.handle,
+				self.handle(),
 				&mechanism,
 				public_key_template.as_ptr() as _,
 				std::convert::TryInto::try_into(public_key_template.len()).expect("usize -> CK_ULONG"),
@@ -444,9 +796,17 @@ impl Session {
 			return Err(GenerateKeyPairError::GenerateKeyPairDidNotReturnHandle("private"));
 		}
 
+		// Keys without a label can't be found again if the session is lost, so they keep using their original handles.
+		let lookup = |class| label.map(|label| crate::object::ObjectLookup {
+			class,
+			key_type: None,
+			label: Some(label.to_owned()),
+			id: None,
+		});
+
 		Ok((
-			crate::Object::new(self.clone(), public_key_handle),
-			crate::Object::new(self, private_key_handle),
+			crate::Object::new(self.clone(), public_key_handle, lookup(pkcs11_sys::CKO_PUBLIC_KEY)),
+			crate::Object::new(self, private_key_handle, lookup(pkcs11_sys::CKO_PRIVATE_KEY)),
 		))
 	}
 }
@@ -455,22 +815,38 @@ impl Session {
 #[derive(Debug)]
 #[allow(clippy::pub_enum_variant_names)]
 pub enum GenerateKeyPairError {
-	DeleteExistingKey(pkcs11_sys::CK_RV),
+	DeleteExistingKey(DeleteKeyError),
 	GenerateKeyPairDidNotReturnHandle(&'static str),
 	GenerateKeyPairFailed(pkcs11_sys::CK_RV),
-	GetExistingKey(GetKeyError),
 	LoginFailed(crate::LoginError),
+	SessionRecoveryFailed(RecoverSessionError),
+}
+
+impl RecoverableError for GenerateKeyPairError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			GenerateKeyPairError::DeleteExistingKey(inner) => inner.session_lost(),
+			GenerateKeyPairError::GenerateKeyPairFailed(result) => session_lost(*result),
+			GenerateKeyPairError::LoginFailed(inner) => inner.session_lost(),
+			GenerateKeyPairError::GenerateKeyPairDidNotReturnHandle(_) |
+			GenerateKeyPairError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self {
+		GenerateKeyPairError::SessionRecoveryFailed(err)
+	}
 }
 
 impl std::fmt::Display for GenerateKeyPairError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
-			GenerateKeyPairError::DeleteExistingKey(result) => write!(f, "C_DestroyObject failed with {}", result),
+			GenerateKeyPairError::DeleteExistingKey(_) => f.write_str("could not delete existing key"),
 			GenerateKeyPairError::GenerateKeyPairDidNotReturnHandle(kind) =>
 				write!(f, "could not generate key pair: C_GenerateKeyPair succeeded but {} key handle is still CK_INVALID_HANDLE", kind),
 			GenerateKeyPairError::GenerateKeyPairFailed(result) => write!(f, "could not generate key pair: C_GenerateKeyPair failed with {}", result),
-			GenerateKeyPairError::GetExistingKey(_) => write!(f, "could not get existing key object"),
 			GenerateKeyPairError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			GenerateKeyPairError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
 		}
 	}
 }
@@ -479,11 +855,754 @@ impl std::error::Error for GenerateKeyPairError {
 	#[allow(clippy::match_same_arms)]
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
-			GenerateKeyPairError::DeleteExistingKey(_) => None,
+			GenerateKeyPairError::DeleteExistingKey(inner) => Some(inner),
 			GenerateKeyPairError::GenerateKeyPairDidNotReturnHandle(_) => None,
 			GenerateKeyPairError::GenerateKeyPairFailed(_) => None,
-			GenerateKeyPairError::GetExistingKey(inner) => Some(inner),
 			GenerateKeyPairError::LoginFailed(inner) => Some(inner),
+			GenerateKeyPairError::SessionRecoveryFailed(inner) => Some(inner),
+		}
+	}
+}
+
+impl Session {
+	/// Delete the public and private keys in the current session with the given label.
+	///
+	/// Keys that do not exist are ignored, so it is not an error if the key pair does not exist.
+	pub fn delete_key_pair(&self, label: &str) -> Result<(), DeleteKeyError> {
+		self.with_recovery(|| unsafe {
+			// Deleting private keys needs login
+			self.login().map_err(DeleteKeyError::LoginFailed)?;
+
+			for &class in &[pkcs11_sys::CKO_PUBLIC_KEY, pkcs11_sys::CKO_PRIVATE_KEY] {
+				self.destroy_keys(class, None, label, pkcs11_sys::CK_INVALID_OBJECT_HANDLE)?;
+			}
+
+			Ok(())
+		})
+	}
+}
+
+/// An error from deleting a key.
+#[derive(Debug)]
+pub enum DeleteKeyError {
+	DestroyObjectFailed(pkcs11_sys::CK_RV),
+	GetKeyFailed(GetKeyError),
+	LoginFailed(crate::LoginError),
+	SessionRecoveryFailed(RecoverSessionError),
+}
+
+impl RecoverableError for DeleteKeyError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			DeleteKeyError::DestroyObjectFailed(result) => session_lost(*result),
+			DeleteKeyError::GetKeyFailed(inner) => inner.session_lost(),
+			DeleteKeyError::LoginFailed(inner) => inner.session_lost(),
+			DeleteKeyError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self {
+		DeleteKeyError::SessionRecoveryFailed(err)
+	}
+}
+
+impl std::fmt::Display for DeleteKeyError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DeleteKeyError::DestroyObjectFailed(result) => write!(f, "C_DestroyObject failed with {}", result),
+			DeleteKeyError::GetKeyFailed(_) => f.write_str("could not get key object"),
+			DeleteKeyError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			DeleteKeyError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
+		}
+	}
+}
+
+impl std::error::Error for DeleteKeyError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			DeleteKeyError::DestroyObjectFailed(_) => None,
+			DeleteKeyError::GetKeyFailed(inner) => Some(inner),
+			DeleteKeyError::LoginFailed(inner) => Some(inner),
+			DeleteKeyError::SessionRecoveryFailed(inner) => Some(inner),
+		}
+	}
+}
+
+impl Session {
+	/// Get a secret key of the given type in the current session that matches the object attributes of the given URI.
+	///
+	/// If the URI has a `type` attribute, it must be `secret-key`.
+	/// If more than one key matches, this fails with [`GetKeyError::AmbiguousKey`]. Set the URI's `id` to choose one of them.
+	pub fn get_secret_key<T>(self: std::sync::Arc<Self>, uri: &crate::Uri) -> Result<crate::Object<T>, GetKeyError> where T: crate::SecretKey {
+		match uri.object_type {
+			None | Some(crate::UriObjectType::SecretKey) => (),
+			Some(_) => return Err(GetKeyError::KeyDoesNotExist),
+		}
+
+		self.with_recovery(|| unsafe {
+			// Secret keys are private objects, so finding them needs login
+			self.login().map_err(GetKeyError::LoginFailed)?;
+
+			let lookup = crate::object::ObjectLookup {
+				key_type: Some(T::KEY_TYPE),
+				..crate::object::ObjectLookup::new(pkcs11_sys::CKO_SECRET_KEY, uri)
+			};
+			let handle = lookup.find(&self)?;
+
+			Ok(crate::Object::new(self.clone(), handle, Some(lookup)))
+		})
+	}
+
+	/// Generate a secret key of the given type and length in bytes in the current session, with the given label.
+	///
+	/// Any existing secret key of the same type with the same label is deleted once the new key has been generated. The key is generated
+	/// as a sensitive, non-extractable token object, so its value never leaves the token.
+	pub fn generate_secret_key<T>(self: std::sync::Arc<Self>, len: usize, label: Option<&str>) -> Result<crate::Object<T>, GenerateKeyError> where T: crate::SecretKey {
+		self.with_recovery(|| unsafe {
+			// Deleting existing keys and generating new ones needs login
+			self.login().map_err(GenerateKeyError::LoginFailed)?;
+
+			let mechanism = pkcs11_sys::CK_MECHANISM_IN {
+				mechanism: T::GENERATE_MECHANISM,
+				pParameter: std::ptr::null(),
+				ulParameterLen: 0,
+			};
+
+			let len: pkcs11_sys::CK_ULONG = std::convert::TryInto::try_into(len).expect("usize -> CK_ULONG");
+
+			let mut template = secret_key_template::<T>(label);
+			template.push(pkcs11_sys::CK_ATTRIBUTE_IN {
+				r#type: pkcs11_sys::CKA_VALUE_LEN,
+				pValue: &len as *const _ as _,
+				ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&len)).expect("usize -> CK_ULONG"),
+			});
+
+			let mut handle = pkcs11_sys::CK_INVALID_OBJECT_HANDLE;
+
+			let result =
+				(self.context.C_GenerateKey)(
+					self.handle(),
+					&mechanism,
+					template.as_ptr(),
+					std::convert::TryInto::try_into(template.len()).expect("usize -> CK_ULONG"),
+					&mut handle,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(GenerateKeyError::GenerateKeyFailed(result));
+			}
+			if handle == pkcs11_sys::CK_INVALID_OBJECT_HANDLE {
+				return Err(GenerateKeyError::GenerateKeyDidNotReturnHandle);
+			}
+
+			if let Some(label) = label {
+				self.destroy_keys(pkcs11_sys::CKO_SECRET_KEY, Some(T::KEY_TYPE), label, handle).map_err(GenerateKeyError::DeleteExistingKey)?;
+			}
+
+			Ok(crate::Object::new(self.clone(), handle, secret_key_lookup::<T>(label)))
+		})
+	}
+
+	/// Import the given bytes as a secret key of the given type in the current session, with the given label.
+	///
+	/// Any existing secret key of the same type with the same label is deleted once the new key has been created.
+	/// The key is stored as a sensitive, non-extractable token object.
+	pub fn import_secret_key<T>(self: std::sync::Arc<Self>, bytes: &[u8], label: Option<&str>) -> Result<crate::Object<T>, ImportKeyError> where T: crate::SecretKey {
+		self.with_recovery(|| unsafe {
+			// Deleting existing keys and creating new ones needs login
+			self.login().map_err(ImportKeyError::LoginFailed)?;
+
+			let class = pkcs11_sys::CKO_SECRET_KEY;
+
+			let mut template = secret_key_template::<T>(label);
+			template.push(pkcs11_sys::CK_ATTRIBUTE_IN {
+				r#type: pkcs11_sys::CKA_CLASS,
+				pValue: &class as *const _ as _,
+				ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&class)).expect("usize -> CK_ULONG"),
+			});
+			template.push(pkcs11_sys::CK_ATTRIBUTE_IN {
+				r#type: pkcs11_sys::CKA_VALUE,
+				pValue: bytes.as_ptr() as _,
+				ulValueLen: std::convert::TryInto::try_into(bytes.len()).expect("usize -> CK_ULONG"),
+			});
+
+			let mut handle = pkcs11_sys::CK_INVALID_OBJECT_HANDLE;
+
+			let result =
+				(self.context.C_CreateObject)(
+					self.handle(),
+					template.as_ptr(),
+					std::convert::TryInto::try_into(template.len()).expect("usize -> CK_ULONG"),
+					&mut handle,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(ImportKeyError::CreateObjectFailed(result));
+			}
+			if handle == pkcs11_sys::CK_INVALID_OBJECT_HANDLE {
+				return Err(ImportKeyError::CreateObjectDidNotReturnHandle);
+			}
+
+			if let Some(label) = label {
+				self.destroy_keys(pkcs11_sys::CKO_SECRET_KEY, Some(T::KEY_TYPE), label, handle).map_err(ImportKeyError::DeleteExistingKey)?;
+			}
+
+			Ok(crate::Object::new(self.clone(), handle, secret_key_lookup::<T>(label)))
+		})
+	}
+
+	/// Delete the secret keys of every type in the current session with the given label.
+	///
+	/// Keys that do not exist are ignored, so it is not an error if no such keys exist.
+	pub fn delete_secret_key(&self, label: &str) -> Result<(), DeleteKeyError> {
+		self.with_recovery(|| unsafe {
+			// Deleting private objects needs login
+			self.login().map_err(DeleteKeyError::LoginFailed)?;
+
+			for &key_type in &[pkcs11_sys::CKK_AES, pkcs11_sys::CKK_GENERIC_SECRET] {
+				self.destroy_keys(pkcs11_sys::CKO_SECRET_KEY, Some(key_type), label, pkcs11_sys::CK_INVALID_OBJECT_HANDLE)?;
+			}
+
+			Ok(())
+		})
+	}
+
+	/// Destroy every key of the given class and type with the given label, except the one with the handle `keep`.
+	/// A `key_type` of `None` matches keys of any type.
+	///
+	/// Replacing a key creates the new object before calling this, so that the label never stops referring to a key.
+	unsafe fn destroy_keys(
+		&self,
+		class: pkcs11_sys::CK_OBJECT_CLASS,
+		key_type: Option<pkcs11_sys::CK_KEY_TYPE>,
+		label: &str,
+		keep: pkcs11_sys::CK_OBJECT_HANDLE,
+	) -> Result<(), DeleteKeyError> {
+		let templates = find_objects_template(Some(&class), key_type.as_ref(), Some(label), None);
+
+		let key_handles: Vec<_> = {
+			let find_objects = FindObjects::new(self, &templates).map_err(|err| DeleteKeyError::GetKeyFailed(GetKeyError::FindObjectsFailed(err)))?;
+			find_objects.collect::<Result<_, _>>().map_err(|err| DeleteKeyError::GetKeyFailed(GetKeyError::FindObjectsFailed(err)))?
+		};
+
+		for key_handle in key_handles {
+			if key_handle == keep {
+				continue;
+			}
+
+			let result =
+				(self.context.C_DestroyObject)(
+					self.handle(),
+					key_handle,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(DeleteKeyError::DestroyObjectFailed(result));
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// The attributes that generated and imported secret keys of the given type have in common.
+///
+/// The returned attributes point into `label` and into statics, so they are valid for as long as `label` is.
+fn secret_key_template<T>(label: Option<&str>) -> Vec<pkcs11_sys::CK_ATTRIBUTE_IN> where T: crate::SecretKey {
+	static TRUE: pkcs11_sys::CK_BBOOL = pkcs11_sys::CK_TRUE;
+	static FALSE: pkcs11_sys::CK_BBOOL = pkcs11_sys::CK_FALSE;
+
+	let bool_size = std::convert::TryInto::try_into(std::mem::size_of_val(&TRUE)).expect("usize -> CK_ULONG");
+	let attribute = |r#type, value: &'static pkcs11_sys::CK_BBOOL| pkcs11_sys::CK_ATTRIBUTE_IN {
+		r#type,
+		pValue: value as *const _ as _,
+		ulValueLen: bool_size,
+	};
+
+	let mut template = vec![
+		pkcs11_sys::CK_ATTRIBUTE_IN {
+			r#type: pkcs11_sys::CKA_KEY_TYPE,
+			pValue: &T::KEY_TYPE as *const _ as _,
+			ulValueLen: std::convert::TryInto::try_into(std::mem::size_of_val(&T::KEY_TYPE)).expect("usize -> CK_ULONG"),
+		},
+		attribute(pkcs11_sys::CKA_EXTRACTABLE, &FALSE),
+		attribute(pkcs11_sys::CKA_PRIVATE, &TRUE),
+		attribute(pkcs11_sys::CKA_SENSITIVE, &TRUE),
+		attribute(pkcs11_sys::CKA_TOKEN, &TRUE),
+	];
+	for &r#type in T::USAGE_ATTRIBUTES {
+		template.push(attribute(r#type, &TRUE));
+	}
+	if let Some(label) = label {
+		template.push(pkcs11_sys::CK_ATTRIBUTE_IN {
+			r#type: pkcs11_sys::CKA_LABEL,
+			pValue: label.as_ptr() as _,
+			ulValueLen: std::convert::TryInto::try_into(label.len()).expect("usize -> CK_ULONG"),
+		});
+	}
+
+	template
+}
+
+/// Keys without a label can't be found again if the session is lost, so they keep using their original handles.
+fn secret_key_lookup<T>(label: Option<&str>) -> Option<crate::object::ObjectLookup> where T: crate::SecretKey {
+	label.map(|label| crate::object::ObjectLookup {
+		class: pkcs11_sys::CKO_SECRET_KEY,
+		key_type: Some(T::KEY_TYPE),
+		label: Some(label.to_owned()),
+		id: None,
+	})
+}
+
+/// An error from generating a secret key.
+#[derive(Debug)]
+#[allow(clippy::pub_enum_variant_names)]
+pub enum GenerateKeyError {
+	DeleteExistingKey(DeleteKeyError),
+	GenerateKeyDidNotReturnHandle,
+	GenerateKeyFailed(pkcs11_sys::CK_RV),
+	LoginFailed(LoginError),
+	SessionRecoveryFailed(RecoverSessionError),
+}
+
+impl RecoverableError for GenerateKeyError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			GenerateKeyError::DeleteExistingKey(inner) => inner.session_lost(),
+			GenerateKeyError::GenerateKeyFailed(result) => session_lost(*result),
+			GenerateKeyError::LoginFailed(inner) => inner.session_lost(),
+			GenerateKeyError::GenerateKeyDidNotReturnHandle |
+			GenerateKeyError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self {
+		GenerateKeyError::SessionRecoveryFailed(err)
+	}
+}
+
+impl std::fmt::Display for GenerateKeyError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			GenerateKeyError::DeleteExistingKey(_) => f.write_str("could not delete existing key"),
+			GenerateKeyError::GenerateKeyDidNotReturnHandle =>
+				f.write_str("could not generate key: C_GenerateKey succeeded but key handle is still CK_INVALID_HANDLE"),
+			GenerateKeyError::GenerateKeyFailed(result) => write!(f, "could not generate key: C_GenerateKey failed with {}", result),
+			GenerateKeyError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			GenerateKeyError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
+		}
+	}
+}
+
+impl std::error::Error for GenerateKeyError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			GenerateKeyError::DeleteExistingKey(inner) => Some(inner),
+			GenerateKeyError::GenerateKeyDidNotReturnHandle => None,
+			GenerateKeyError::GenerateKeyFailed(_) => None,
+			GenerateKeyError::LoginFailed(inner) => Some(inner),
+			GenerateKeyError::SessionRecoveryFailed(inner) => Some(inner),
+		}
+	}
+}
+
+/// An error from importing a secret key.
+#[derive(Debug)]
+pub enum ImportKeyError {
+	CreateObjectDidNotReturnHandle,
+	CreateObjectFailed(pkcs11_sys::CK_RV),
+	DeleteExistingKey(DeleteKeyError),
+	LoginFailed(LoginError),
+	SessionRecoveryFailed(RecoverSessionError),
+}
+
+impl RecoverableError for ImportKeyError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			ImportKeyError::CreateObjectFailed(result) => session_lost(*result),
+			ImportKeyError::DeleteExistingKey(inner) => inner.session_lost(),
+			ImportKeyError::LoginFailed(inner) => inner.session_lost(),
+			ImportKeyError::CreateObjectDidNotReturnHandle |
+			ImportKeyError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self {
+		ImportKeyError::SessionRecoveryFailed(err)
+	}
+}
+
+impl std::fmt::Display for ImportKeyError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ImportKeyError::CreateObjectDidNotReturnHandle =>
+				f.write_str("could not import key: C_CreateObject succeeded but object handle is still CK_INVALID_HANDLE"),
+			ImportKeyError::CreateObjectFailed(result) => write!(f, "could not import key: C_CreateObject failed with {}", result),
+			ImportKeyError::DeleteExistingKey(_) => f.write_str("could not delete existing key"),
+			ImportKeyError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			ImportKeyError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
+		}
+	}
+}
+
+impl std::error::Error for ImportKeyError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ImportKeyError::CreateObjectDidNotReturnHandle => None,
+			ImportKeyError::CreateObjectFailed(_) => None,
+			ImportKeyError::DeleteExistingKey(inner) => Some(inner),
+			ImportKeyError::LoginFailed(inner) => Some(inner),
+			ImportKeyError::SessionRecoveryFailed(inner) => Some(inner),
+		}
+	}
+}
+
+/// The attributes that objects must have to be listed by [`Session::list_objects`] or destroyed by [`Session::destroy_object`].
+///
+/// Attributes that are `None` match any value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjectTemplate {
+	pub class: Option<pkcs11_sys::CK_OBJECT_CLASS>,
+	pub key_type: Option<pkcs11_sys::CK_KEY_TYPE>,
+	pub label: Option<String>,
+	pub id: Option<Vec<u8>>,
+}
+
+impl ObjectTemplate {
+	/// The template that matches the objects identified by the `type`, `object` and `id` attributes of the given URI.
+	pub fn from_uri(uri: &crate::Uri) -> Self {
+		let class = uri.object_type.map(|object_type| match object_type {
+			crate::UriObjectType::Certificate => pkcs11_sys::CKO_CERTIFICATE,
+			crate::UriObjectType::Data => pkcs11_sys::CKO_DATA,
+			crate::UriObjectType::PrivateKey => pkcs11_sys::CKO_PRIVATE_KEY,
+			crate::UriObjectType::PublicKey => pkcs11_sys::CKO_PUBLIC_KEY,
+			crate::UriObjectType::SecretKey => pkcs11_sys::CKO_SECRET_KEY,
+		});
+
+		ObjectTemplate {
+			class,
+			key_type: None,
+			label: uri.object_label.clone(),
+			id: uri.object_id.clone(),
+		}
+	}
+}
+
+/// An object listed by [`Session::list_objects`], with its common attributes.
+///
+/// Attributes that the object does not have, or that the token will not reveal, are `None`.
+#[derive(Clone, Debug)]
+pub struct ObjectInfo {
+	pub class: pkcs11_sys::CK_OBJECT_CLASS,
+	pub key_type: Option<pkcs11_sys::CK_KEY_TYPE>,
+	pub label: Option<String>,
+	pub id: Option<Vec<u8>>,
+
+	/// The size of the modulus of an RSA key, in bits.
+	pub modulus_bits: Option<pkcs11_sys::CK_ULONG>,
+
+	/// The DER-encoded curve of an EC key. See [`openssl2::EcCurve::from_oid_der`].
+	pub ec_params: Option<Vec<u8>>,
+
+	/// The length of the value of a secret key, in bytes.
+	pub value_len: Option<pkcs11_sys::CK_ULONG>,
+
+	pub sensitive: Option<bool>,
+	pub extractable: Option<bool>,
+}
+
+impl Session {
+	/// List the objects in the current session that match the given template.
+	///
+	/// Private objects are only visible after login, so this logs in to the token first.
+	pub fn list_objects(&self, template: &ObjectTemplate) -> Result<Vec<ObjectInfo>, ListObjectsError> {
+		self.with_recovery(|| unsafe {
+			self.login().map_err(ListObjectsError::LoginFailed)?;
+
+			let objects = self.list_objects_inner(template)?;
+			Ok(objects.into_iter().map(|(_, object)| object).collect())
+		})
+	}
+
+	/// Destroy the object in the current session that matches the given template, and return its attributes.
+	///
+	/// Several objects can have the same label. If the template matches more than one object, nothing is destroyed
+	/// and this fails with [`DestroyObjectError::AmbiguousObject`]. Set the template's `id` to choose one of them.
+	pub fn destroy_object(&self, template: &ObjectTemplate) -> Result<ObjectInfo, DestroyObjectError> {
+		self.with_recovery(|| unsafe {
+			// Destroying private objects needs login
+			self.login().map_err(DestroyObjectError::LoginFailed)?;
+
+			let mut objects = self.list_objects_inner(template).map_err(DestroyObjectError::ListObjectsFailed)?;
+			let (object_handle, object) = match objects.len() {
+				0 => return Err(DestroyObjectError::ObjectDoesNotExist),
+				1 => objects.remove(0),
+				num_objects => return Err(DestroyObjectError::AmbiguousObject(num_objects)),
+			};
+
+			let result =
+				(self.context.C_DestroyObject)(
+					self.handle(),
+					object_handle,
+				);
+			if result != pkcs11_sys::CKR_OK {
+				return Err(DestroyObjectError::DestroyObjectFailed(result));
+			}
+
+			Ok(object)
+		})
+	}
+
+	unsafe fn list_objects_inner(&self, template: &ObjectTemplate) -> Result<Vec<(pkcs11_sys::CK_OBJECT_HANDLE, ObjectInfo)>, ListObjectsError> {
+		let templates = find_objects_template(template.class.as_ref(), template.key_type.as_ref(), template.label.as_deref(), template.id.as_deref());
+
+		let object_handles: Vec<_> = {
+			let find_objects = FindObjects::new(self, &templates).map_err(ListObjectsError::FindObjectsFailed)?;
+			find_objects.collect::<Result<_, _>>().map_err(ListObjectsError::FindObjectsFailed)?
+		};
+
+		let mut objects = Vec::with_capacity(object_handles.len());
+
+		for object_handle in object_handles {
+			let class =
+				self.get_attribute_value_fixed(object_handle, pkcs11_sys::CKA_CLASS, pkcs11_sys::CKO_DATA)?
+				.ok_or(ListObjectsError::GetAttributeValueFailed(pkcs11_sys::CKA_CLASS, pkcs11_sys::CKR_ATTRIBUTE_TYPE_INVALID))?;
+
+			let (key_type, modulus_bits, ec_params, value_len, sensitive, extractable) =
+				if class == pkcs11_sys::CKO_PUBLIC_KEY || class == pkcs11_sys::CKO_PRIVATE_KEY || class == pkcs11_sys::CKO_SECRET_KEY {
+					let key_type = self.get_attribute_value_fixed(object_handle, pkcs11_sys::CKA_KEY_TYPE, pkcs11_sys::CKK_EC)?;
+
+					let modulus_bits =
+						if key_type == Some(pkcs11_sys::CKK_RSA) {
+							self.get_modulus_bits(object_handle)?
+						}
+						else {
+							None
+						};
+
+					let ec_params =
+						if key_type == Some(pkcs11_sys::CKK_EC) || key_type == Some(pkcs11_sys::CKK_EC_EDWARDS) {
+							self.get_optional_attribute_value_byte_buf(object_handle, pkcs11_sys::CKA_EC_PARAMS)?
+						}
+						else {
+							None
+						};
+
+					// Public keys don't have these attributes.
+					let (sensitive, extractable) =
+						if class == pkcs11_sys::CKO_PUBLIC_KEY {
+							(None, None)
+						}
+						else {
+							(
+								self.get_attribute_value_fixed(object_handle, pkcs11_sys::CKA_SENSITIVE, pkcs11_sys::CK_FALSE)?.map(|value| value != pkcs11_sys::CK_FALSE),
+								self.get_attribute_value_fixed(object_handle, pkcs11_sys::CKA_EXTRACTABLE, pkcs11_sys::CK_FALSE)?.map(|value| value != pkcs11_sys::CK_FALSE),
+							)
+						};
+
+					let value_len =
+						if class == pkcs11_sys::CKO_SECRET_KEY {
+							self.get_attribute_value_fixed(object_handle, pkcs11_sys::CKA_VALUE_LEN, 0)?
+						}
+						else {
+							None
+						};
+
+					(key_type, modulus_bits, ec_params, value_len, sensitive, extractable)
+				}
+				else {
+					(None, None, None, None, None, None)
+				};
+
+			let label = self.get_optional_attribute_value_byte_buf(object_handle, pkcs11_sys::CKA_LABEL)?;
+			let label = label.map(String::from_utf8).transpose().map_err(ListObjectsError::MalformedLabel)?;
+
+			let id = self.get_optional_attribute_value_byte_buf(object_handle, pkcs11_sys::CKA_ID)?;
+
+			objects.push((object_handle, ObjectInfo {
+				class,
+				key_type,
+				label,
+				id,
+				modulus_bits,
+				ec_params,
+				value_len,
+				sensitive,
+				extractable,
+			}));
+		}
+
+		Ok(objects)
+	}
+
+	/// RSA public keys have `CKA_MODULUS_BITS`, but private keys usually don't, so count the bits of `CKA_MODULUS` for those.
+	unsafe fn get_modulus_bits(&self, object_handle: pkcs11_sys::CK_OBJECT_HANDLE) -> Result<Option<pkcs11_sys::CK_ULONG>, ListObjectsError> {
+		if let Some(modulus_bits) = self.get_attribute_value_fixed(object_handle, pkcs11_sys::CKA_MODULUS_BITS, 0)? {
+			return Ok(Some(modulus_bits));
+		}
+
+		let modulus = match self.get_optional_attribute_value_byte_buf(object_handle, pkcs11_sys::CKA_MODULUS)? {
+			Some(modulus) => modulus,
+			None => return Ok(None),
+		};
+		let modulus = openssl::bn::BigNum::from_slice(&modulus).map_err(|_| ListObjectsError::MalformedAttributeValue(pkcs11_sys::CKA_MODULUS))?;
+		Ok(Some(std::convert::TryInto::try_into(modulus.num_bits()).expect("i32 -> CK_ULONG")))
+	}
+
+	/// Get the value of an attribute that has a fixed size, like `CK_ULONG` or `CK_BBOOL`.
+	/// `value` is the buffer that the value is read into.
+	///
+	/// Returns `None` if the object does not have the attribute or the token will not reveal its value.
+	unsafe fn get_attribute_value_fixed<T>(
+		&self,
+		object_handle: pkcs11_sys::CK_OBJECT_HANDLE,
+		r#type: pkcs11_sys::CK_ATTRIBUTE_TYPE,
+		mut value: T,
+	) -> Result<Option<T>, ListObjectsError> {
+		let value_size = std::convert::TryInto::try_into(std::mem::size_of_val(&value)).expect("usize -> CK_ULONG");
+		let mut attribute = pkcs11_sys::CK_ATTRIBUTE {
+			r#type,
+			pValue: &mut value as *mut _ as _,
+			ulValueLen: value_size,
+		};
+		let result =
+			(self.context.C_GetAttributeValue)(
+				self.handle(),
+				object_handle,
+				&mut attribute,
+				1,
+			);
+		match result {
+			pkcs11_sys::CKR_OK if attribute.ulValueLen == value_size => Ok(Some(value)),
+			pkcs11_sys::CKR_OK => Err(ListObjectsError::MalformedAttributeValue(r#type)),
+			pkcs11_sys::CKR_ATTRIBUTE_SENSITIVE | pkcs11_sys::CKR_ATTRIBUTE_TYPE_INVALID => Ok(None),
+			result => Err(ListObjectsError::GetAttributeValueFailed(r#type, result)),
+		}
+	}
+
+	/// Get the value of an attribute that has a variable size, like `CKA_LABEL`.
+	///
+	/// Returns `None` if the object does not have the attribute, the token will not reveal its value, or its value is empty.
+	unsafe fn get_optional_attribute_value_byte_buf(
+		&self,
+		object_handle: pkcs11_sys::CK_OBJECT_HANDLE,
+		r#type: pkcs11_sys::CK_ATTRIBUTE_TYPE,
+	) -> Result<Option<Vec<u8>>, ListObjectsError> {
+		match crate::object::get_attribute_value_byte_buf(self, object_handle, r#type, self.context.C_GetAttributeValue) {
+			Ok(value) if value.is_empty() => Ok(None),
+			Ok(value) => Ok(Some(value)),
+			Err(pkcs11_sys::CKR_ATTRIBUTE_SENSITIVE) |
+			Err(pkcs11_sys::CKR_ATTRIBUTE_TYPE_INVALID) => Ok(None),
+			Err(result) => Err(ListObjectsError::GetAttributeValueFailed(r#type, result)),
+		}
+	}
+}
+
+/// An error from listing objects.
+#[derive(Debug)]
+pub enum ListObjectsError {
+	FindObjectsFailed(FindObjectsError),
+	GetAttributeValueFailed(pkcs11_sys::CK_ATTRIBUTE_TYPE, pkcs11_sys::CK_RV),
+	LoginFailed(LoginError),
+	MalformedAttributeValue(pkcs11_sys::CK_ATTRIBUTE_TYPE),
+	MalformedLabel(std::string::FromUtf8Error),
+	SessionRecoveryFailed(RecoverSessionError),
+}
+
+impl RecoverableError for ListObjectsError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			ListObjectsError::FindObjectsFailed(inner) => inner.session_lost(),
+			ListObjectsError::GetAttributeValueFailed(_, result) => session_lost(*result),
+			ListObjectsError::LoginFailed(inner) => inner.session_lost(),
+			ListObjectsError::MalformedAttributeValue(_) |
+			ListObjectsError::MalformedLabel(_) |
+			ListObjectsError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self {
+		ListObjectsError::SessionRecoveryFailed(err)
+	}
+}
+
+impl std::fmt::Display for ListObjectsError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ListObjectsError::FindObjectsFailed(_) => f.write_str("could not find objects"),
+			ListObjectsError::GetAttributeValueFailed(r#type, result) => write!(f, "C_GetAttributeValue({}) failed with {}", r#type, result),
+			ListObjectsError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			ListObjectsError::MalformedAttributeValue(r#type) => write!(f, "C_GetAttributeValue({}) returned a malformed value", r#type),
+			ListObjectsError::MalformedLabel(_) => f.write_str("object label is not valid UTF-8"),
+			ListObjectsError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
+		}
+	}
+}
+
+impl std::error::Error for ListObjectsError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ListObjectsError::FindObjectsFailed(inner) => Some(inner),
+			ListObjectsError::GetAttributeValueFailed(_, _) => None,
+			ListObjectsError::LoginFailed(inner) => Some(inner),
+			ListObjectsError::MalformedAttributeValue(_) => None,
+			ListObjectsError::MalformedLabel(inner) => Some(inner),
+			ListObjectsError::SessionRecoveryFailed(inner) => Some(inner),
+		}
+	}
+}
+
+/// An error from destroying an object.
+#[derive(Debug)]
+pub enum DestroyObjectError {
+	AmbiguousObject(usize),
+	DestroyObjectFailed(pkcs11_sys::CK_RV),
+	ListObjectsFailed(ListObjectsError),
+	LoginFailed(LoginError),
+	ObjectDoesNotExist,
+	SessionRecoveryFailed(RecoverSessionError),
+}
+
+impl RecoverableError for DestroyObjectError {
+	fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			DestroyObjectError::DestroyObjectFailed(result) => session_lost(*result),
+			DestroyObjectError::ListObjectsFailed(inner) => inner.session_lost(),
+			DestroyObjectError::LoginFailed(inner) => inner.session_lost(),
+			DestroyObjectError::AmbiguousObject(_) |
+			DestroyObjectError::ObjectDoesNotExist |
+			DestroyObjectError::SessionRecoveryFailed(_) => None,
+		}
+	}
+
+	fn recover_session_failed(err: RecoverSessionError) -> Self {
+		DestroyObjectError::SessionRecoveryFailed(err)
+	}
+}
+
+impl std::fmt::Display for DestroyObjectError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DestroyObjectError::AmbiguousObject(num_objects) => write!(f, "found {} objects that match, specify an ID to choose one of them", num_objects),
+			DestroyObjectError::DestroyObjectFailed(result) => write!(f, "C_DestroyObject failed with {}", result),
+			DestroyObjectError::ListObjectsFailed(_) => f.write_str("could not list objects"),
+			DestroyObjectError::LoginFailed(_) => f.write_str("could not log in to the token"),
+			DestroyObjectError::ObjectDoesNotExist => f.write_str("did not find any objects that match"),
+			DestroyObjectError::SessionRecoveryFailed(_) => f.write_str("could not recover the session"),
+		}
+	}
+}
+
+impl std::error::Error for DestroyObjectError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			DestroyObjectError::AmbiguousObject(_) => None,
+			DestroyObjectError::DestroyObjectFailed(_) => None,
+			DestroyObjectError::ListObjectsFailed(inner) => Some(inner),
+			DestroyObjectError::LoginFailed(inner) => Some(inner),
+			DestroyObjectError::ObjectDoesNotExist => None,
+			DestroyObjectError::SessionRecoveryFailed(inner) => Some(inner),
 		}
 	}
 }
@@ -493,7 +1612,7 @@ impl Session {
 		let mut session_info = std::mem::MaybeUninit::uninit();
 		let result =
 			(self.context.C_GetSessionInfo)(
-				self.handle,
+				self.handle(),
 				session_info.as_mut_ptr(),
 			);
 		if result != pkcs11_sys::CKR_OK {
@@ -510,9 +1629,13 @@ impl Session {
 		}
 
 		if let Some(pin) = &self.pin {
+			// The PIN is read from its source every time instead of once when the session is opened, so that it isn't held in memory
+			// and so that a changed PIN is picked up.
+			let pin = pin.read().map_err(LoginError::ReadPinFailed)?;
+
 			let result =
 				(self.context.C_Login)(
-					self.handle,
+					self.handle(),
 					pkcs11_sys::CKU_USER,
 					pin.as_ptr() as _,
 					std::convert::TryInto::try_into(pin.len()).expect("usize -> CK_ULONG"),
@@ -537,6 +1660,17 @@ impl Session {
 pub enum LoginError {
 	GetSessionInfoFailed(pkcs11_sys::CK_RV),
 	LoginFailed(pkcs11_sys::CK_RV),
+	ReadPinFailed(std::io::Error),
+}
+
+impl LoginError {
+	pub(crate) fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+		match self {
+			LoginError::GetSessionInfoFailed(result) |
+			LoginError::LoginFailed(result) => session_lost(*result),
+			LoginError::ReadPinFailed(_) => None,
+		}
+	}
 }
 
 impl std::fmt::Display for LoginError {
@@ -544,17 +1678,100 @@ impl std::fmt::Display for LoginError {
 		match self {
 			LoginError::GetSessionInfoFailed(result) => write!(f, "C_GetSessionInfo failed with {}", result),
 			LoginError::LoginFailed(result) => write!(f, "C_Login failed with {}", result),
+			LoginError::ReadPinFailed(_) => f.write_str("could not read PIN"),
 		}
 	}
 }
 
 impl std::error::Error for LoginError {
+	#[allow(clippy::match_same_arms)]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			LoginError::GetSessionInfoFailed(_) => None,
+			LoginError::LoginFailed(_) => None,
+			LoginError::ReadPinFailed(inner) => Some(inner),
+		}
+	}
 }
 
 impl Drop for Session {
 	fn drop(&mut self) {
 		unsafe {
-			let _ = (self.context.C_CloseSession)(self.handle);
+			let _ = (self.context.C_CloseSession)(self.handle());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[derive(Debug)]
+	enum FakeError {
+		SessionLost,
+		Other,
+		RecoverSessionFailed,
+	}
+
+	impl super::RecoverableError for FakeError {
+		fn session_lost(&self) -> Option<pkcs11_sys::CK_RV> {
+			match self {
+				FakeError::SessionLost => Some(pkcs11_sys::CKR_SESSION_HANDLE_INVALID),
+				FakeError::Other | FakeError::RecoverSessionFailed => None,
+			}
+		}
+
+		fn recover_session_failed(_: super::RecoverSessionError) -> Self {
+			FakeError::RecoverSessionFailed
 		}
 	}
+
+	/// Runs `retry_if_session_lost` with an operation that returns the given results in order,
+	/// and returns its result along with the number of times the operation and the recovery were run.
+	fn run(results: Vec<Result<(), FakeError>>, recover_succeeds: bool) -> (Result<(), FakeError>, usize, usize) {
+		let mut results = results.into_iter();
+		let mut attempts = 0;
+		let mut recoveries = 0;
+
+		let result = super::retry_if_session_lost(
+			|| {
+				attempts += 1;
+				results.next().expect("operation was run too many times")
+			},
+			|result| {
+				assert_eq!(result, pkcs11_sys::CKR_SESSION_HANDLE_INVALID);
+				recoveries += 1;
+				if recover_succeeds {
+					Ok(())
+				}
+				else {
+					Err(super::RecoverSessionError::FindObjectFailed(Box::new(super::GetKeyError::KeyDoesNotExist)))
+				}
+			},
+		);
+
+		(result, attempts, recoveries)
+	}
+
+	#[test]
+	fn retry_if_session_lost() {
+		let (result, attempts, recoveries) = run(vec![Ok(())], true);
+		assert!(result.is_ok());
+		assert_eq!((attempts, recoveries), (1, 0));
+
+		let (result, attempts, recoveries) = run(vec![Err(FakeError::Other)], true);
+		assert!(matches!(result, Err(FakeError::Other)));
+		assert_eq!((attempts, recoveries), (1, 0));
+
+		let (result, attempts, recoveries) = run(vec![Err(FakeError::SessionLost), Ok(())], true);
+		assert!(result.is_ok());
+		assert_eq!((attempts, recoveries), (2, 1));
+
+		// The operation is only retried once, even if the session is lost again.
+		let (result, attempts, recoveries) = run(vec![Err(FakeError::SessionLost), Err(FakeError::SessionLost)], true);
+		assert!(matches!(result, Err(FakeError::SessionLost)));
+		assert_eq!((attempts, recoveries), (2, 1));
+
+		let (result, attempts, recoveries) = run(vec![Err(FakeError::SessionLost)], false);
+		assert!(matches!(result, Err(FakeError::RecoverSessionFailed)));
+		assert_eq!((attempts, recoveries), (1, 1));
+	}
 }